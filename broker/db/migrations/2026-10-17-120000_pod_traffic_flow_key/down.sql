@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_pod_traffic_flow_key;
+CREATE INDEX IF NOT EXISTS idx_pod_traffic_dedup
+  ON pod_traffic (pod_ip, pod_port, traffic_type, traffic_in_out_ip, traffic_in_out_port, decision);
+ALTER TABLE pod_traffic
+  DROP COLUMN IF EXISTS seen_count,
+  DROP COLUMN IF EXISTS last_seen;
@@ -0,0 +1,49 @@
+-- Give pod_traffic a real flow identity so ingest can dedup with one
+-- INSERT ... ON CONFLICT instead of a SELECT per event (two for UDP).
+-- The key is the content tuple create_pod_traffic_batch already
+-- collapses on (add.rs::traffic_content_key); uuid/time_stamp differ on
+-- every eBPF emit and stay out of it. time_stamp keeps meaning "first
+-- seen"; repeats now bump last_seen and seen_count on the existing row.
+ALTER TABLE pod_traffic
+  ADD COLUMN last_seen TIMESTAMP,
+  ADD COLUMN seen_count BIGINT NOT NULL DEFAULT 1;
+
+-- Rows written before this migration can already hold duplicates of a
+-- key: the old UDP lookup matched on either port rather than both, and
+-- concurrent batches could both miss the SELECT and insert. Fold every
+-- group into its earliest row (carrying the group's latest time_stamp
+-- and size), then drop the rest so the unique index can build.
+-- PARTITION BY groups NULLs together, matching NULLS NOT DISTINCT below.
+CREATE TEMPORARY TABLE pod_traffic_flow_groups ON COMMIT DROP AS
+SELECT uuid,
+       ROW_NUMBER() OVER (flow ORDER BY time_stamp, uuid) AS rn,
+       MAX(time_stamp) OVER flow AS group_last_seen,
+       COUNT(*) OVER flow AS group_count
+  FROM pod_traffic
+WINDOW flow AS (PARTITION BY pod_ip, pod_port, ip_protocol, traffic_type,
+                             traffic_in_out_ip, traffic_in_out_port, decision);
+
+UPDATE pod_traffic t
+   SET last_seen = g.group_last_seen,
+       seen_count = g.group_count
+  FROM pod_traffic_flow_groups g
+ WHERE t.uuid = g.uuid AND g.rn = 1;
+
+DELETE FROM pod_traffic t
+ USING pod_traffic_flow_groups g
+ WHERE t.uuid = g.uuid AND g.rn > 1;
+
+ALTER TABLE pod_traffic
+  ALTER COLUMN last_seen SET NOT NULL,
+  ALTER COLUMN last_seen SET DEFAULT (timezone('utc', now()));
+
+-- NULLS NOT DISTINCT (PostgreSQL 15+) so a flow with e.g. a NULL
+-- decision still conflicts with its repeats instead of inserting a new
+-- row each time. The old non-unique dedup index only served the
+-- per-event SELECT this replaces, so it goes.
+CREATE UNIQUE INDEX idx_pod_traffic_flow_key
+  ON pod_traffic (pod_ip, pod_port, ip_protocol, traffic_type,
+                  traffic_in_out_ip, traffic_in_out_port, decision)
+  NULLS NOT DISTINCT;
+
+DROP INDEX IF EXISTS idx_pod_traffic_dedup;
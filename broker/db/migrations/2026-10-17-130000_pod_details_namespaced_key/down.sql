@@ -0,0 +1,20 @@
+-- Going back to a pod_name key needs one row per name: keep the live,
+-- most recently updated row of each name and drop the rest.
+DELETE FROM pod_details d
+ USING (
+   SELECT pod_namespace, pod_name,
+          ROW_NUMBER() OVER (PARTITION BY pod_name
+                             ORDER BY is_dead ASC, time_stamp DESC) AS rn
+     FROM pod_details
+ ) ranked
+ WHERE d.pod_namespace = ranked.pod_namespace
+   AND d.pod_name = ranked.pod_name
+   AND ranked.rn > 1;
+
+DROP INDEX IF EXISTS idx_pod_details_pod_name;
+ALTER TABLE pod_details DROP CONSTRAINT pod_details_pkey;
+ALTER TABLE pod_details ADD PRIMARY KEY (pod_name);
+ALTER TABLE pod_details
+  ALTER COLUMN pod_namespace DROP NOT NULL,
+  ALTER COLUMN pod_namespace DROP DEFAULT;
+UPDATE pod_details SET pod_namespace = NULL WHERE pod_namespace = '';
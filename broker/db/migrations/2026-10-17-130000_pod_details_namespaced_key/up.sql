@@ -0,0 +1,22 @@
+-- pod_details was keyed on pod_name alone, but pod names are only
+-- unique within a namespace: prod/web-1 and staging/web-1 overwrote
+-- each other's row on every /pod/spec upsert, and /pod/mark_dead for
+-- one could flag the other. Key the table on (pod_namespace, pod_name).
+--
+-- A primary-key column must be NOT NULL. Rows from controllers that
+-- predate pod_namespace have NULL there; they become '' ("namespace
+-- unknown"), which consumers already treat like a missing namespace.
+UPDATE pod_details SET pod_namespace = '' WHERE pod_namespace IS NULL;
+
+ALTER TABLE pod_details
+  ALTER COLUMN pod_namespace SET DEFAULT '',
+  ALTER COLUMN pod_namespace SET NOT NULL;
+
+ALTER TABLE pod_details DROP CONSTRAINT pod_details_pkey;
+ALTER TABLE pod_details ADD PRIMARY KEY (pod_namespace, pod_name);
+
+-- The legacy name-only routes (/pod/name/{name}, /pod/traffic/{name},
+-- /pod/syscalls/{name}) still look up by pod_name first to detect a
+-- name that exists in several namespaces; the new PK leads with
+-- namespace, so it can't serve that.
+CREATE INDEX IF NOT EXISTS idx_pod_details_pod_name ON pod_details (pod_name);
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS pod_syscall_observations;
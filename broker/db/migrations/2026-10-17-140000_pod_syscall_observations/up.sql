@@ -0,0 +1,27 @@
+-- pod_syscalls holds one comma-joined string per pod, overwritten on
+-- every change, so it can say which syscalls a pod has made but not
+-- when each one first showed up. pod_syscall_observations keeps one
+-- row per (pod, arch, syscall) with the first and last time it was
+-- reported and how many times it was seen, which is what "what did
+-- this pod start calling after the last deploy?" needs.
+CREATE TABLE IF NOT EXISTS pod_syscall_observations (
+  pod_namespace VARCHAR NOT NULL DEFAULT '',
+  pod_name VARCHAR NOT NULL,
+  arch VARCHAR NOT NULL,
+  syscall VARCHAR NOT NULL,
+  first_seen TIMESTAMP NOT NULL,
+  last_seen TIMESTAMP NOT NULL,
+  count BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY (pod_namespace, pod_name, arch, syscall)
+);
+
+-- Seed from the existing sets. pod_syscalls.time_stamp is when the
+-- pod's row was first written, the best first_seen available; the
+-- legacy table never counted, so count starts at 0.
+INSERT INTO pod_syscall_observations
+  (pod_namespace, pod_name, arch, syscall, first_seen, last_seen, count)
+SELECT s.pod_namespace, s.pod_name, s.arch, c.syscall, s.time_stamp, s.time_stamp, 0
+  FROM pod_syscalls s
+ CROSS JOIN LATERAL unnest(string_to_array(s.syscalls, ',')) AS c(syscall)
+ WHERE c.syscall <> ''
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_audit_verdicts_hits;
+DROP INDEX IF EXISTS idx_audit_verdicts_key;
+ALTER TABLE audit_verdicts
+  DROP COLUMN IF EXISTS hits,
+  DROP COLUMN IF EXISTS first_observed_at;
+ALTER TABLE audit_verdicts RENAME COLUMN last_observed_at TO observed_at;
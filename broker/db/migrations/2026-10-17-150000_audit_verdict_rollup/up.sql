@@ -0,0 +1,53 @@
+-- Roll repeated audit verdicts up into one row per distinct verdict.
+-- evaluate_and_persist used to insert a row per evaluation, so a
+-- steady flow produced a fresh row every time it passed ingest dedup
+-- and only retention kept the table bounded. The row is now keyed on
+-- what was decided about which flow; repeats bump hits and
+-- last_observed_at instead of inserting.
+--
+-- observed_at becomes last_observed_at: the time indexes, the
+-- /audit/verdicts ordering and retention all mean "most recent
+-- sighting", and RENAME carries those indexes over unchanged.
+ALTER TABLE audit_verdicts RENAME COLUMN observed_at TO last_observed_at;
+ALTER TABLE audit_verdicts
+  ADD COLUMN first_observed_at TIMESTAMP,
+  ADD COLUMN hits BIGINT NOT NULL DEFAULT 1;
+
+-- Fold existing duplicates into the newest row of each group, which
+-- carries the most recent reason. PARTITION BY groups NULL pods
+-- together, matching NULLS NOT DISTINCT below.
+CREATE TEMPORARY TABLE audit_verdict_groups ON COMMIT DROP AS
+SELECT id,
+       ROW_NUMBER() OVER (verdict_key ORDER BY last_observed_at DESC, id DESC) AS rn,
+       MIN(last_observed_at) OVER verdict_key AS group_first,
+       MAX(last_observed_at) OVER verdict_key AS group_last,
+       COUNT(*) OVER verdict_key AS group_hits
+  FROM audit_verdicts
+WINDOW verdict_key AS (PARTITION BY policy_uid, direction, src_namespace, src_pod,
+                                    dst_namespace, dst_pod, dst_port, protocol, verdict);
+
+UPDATE audit_verdicts v
+   SET first_observed_at = g.group_first,
+       last_observed_at = g.group_last,
+       hits = g.group_hits
+  FROM audit_verdict_groups g
+ WHERE v.id = g.id AND g.rn = 1;
+
+DELETE FROM audit_verdicts v
+ USING audit_verdict_groups g
+ WHERE v.id = g.id AND g.rn > 1;
+
+ALTER TABLE audit_verdicts
+  ALTER COLUMN first_observed_at SET NOT NULL,
+  ALTER COLUMN first_observed_at SET DEFAULT CURRENT_TIMESTAMP;
+
+-- NULLS NOT DISTINCT (PostgreSQL 15+): flows with an unresolved pod
+-- (external peers) must still conflict with their repeats.
+CREATE UNIQUE INDEX idx_audit_verdicts_key
+  ON audit_verdicts (policy_uid, direction, src_namespace, src_pod,
+                     dst_namespace, dst_pod, dst_port, protocol, verdict)
+  NULLS NOT DISTINCT;
+
+-- ?sort=hits: "which denies fire most".
+CREATE INDEX idx_audit_verdicts_hits
+  ON audit_verdicts (hits DESC, id DESC);
@@ -0,0 +1,24 @@
+-- Durable hand-off from ingest to the audit evaluator. With
+-- AUDIT_OUTBOX_ENABLED the broker writes one row here per newly
+-- inserted pod_traffic flow, in the same transaction as the flow
+-- itself, and a dispatcher deletes it once the evaluator has answered.
+-- A flow that arrives while the evaluator is down waits here (retried
+-- with backoff) instead of being shed from the in-memory queue.
+--
+-- Rows follow their flow: pruning pod_traffic by retention drops any
+-- evaluation still pending for it.
+CREATE TABLE IF NOT EXISTS audit_outbox (
+  id BIGSERIAL PRIMARY KEY,
+  traffic_uuid VARCHAR NOT NULL REFERENCES pod_traffic (uuid) ON DELETE CASCADE,
+  attempts INTEGER NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMP NOT NULL DEFAULT (timezone('utc', now())),
+  created_at TIMESTAMP NOT NULL DEFAULT (timezone('utc', now())),
+  last_error VARCHAR
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_audit_outbox_traffic_uuid
+  ON audit_outbox (traffic_uuid);
+
+-- The dispatcher's claim query: due rows, oldest first.
+CREATE INDEX IF NOT EXISTS idx_audit_outbox_due
+  ON audit_outbox (next_attempt_at, id);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_pod_traffic_namespace_time;
+DROP TABLE IF EXISTS audit_replay_jobs;
@@ -0,0 +1,25 @@
+-- Audit replay jobs (POST /audit/replay). A job re-sends a namespace's
+-- stored pod_traffic through the audit evaluator so a newly created
+-- AuditNetworkPolicy gets verdicts for flows seen before it existed.
+-- The row is the job's progress record: the broker that runs it bumps
+-- processed/failed and updated_at as it goes, and any replica can
+-- answer GET /audit/replay/{id} from it.
+CREATE TABLE IF NOT EXISTS audit_replay_jobs (
+  id VARCHAR PRIMARY KEY,
+  namespace VARCHAR NOT NULL,
+  policy VARCHAR,
+  since TIMESTAMP NOT NULL,
+  until TIMESTAMP NOT NULL,
+  state VARCHAR NOT NULL,
+  total BIGINT NOT NULL DEFAULT 0,
+  processed BIGINT NOT NULL DEFAULT 0,
+  failed BIGINT NOT NULL DEFAULT 0,
+  error VARCHAR,
+  created_at TIMESTAMP NOT NULL DEFAULT (timezone('utc', now())),
+  updated_at TIMESTAMP NOT NULL DEFAULT (timezone('utc', now())),
+  finished_at TIMESTAMP
+);
+
+-- The replay walks one namespace's flows in (time_stamp, uuid) order.
+CREATE INDEX IF NOT EXISTS idx_pod_traffic_namespace_time
+  ON pod_traffic (pod_namespace, time_stamp, uuid);
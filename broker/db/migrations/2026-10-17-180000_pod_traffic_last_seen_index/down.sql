@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_pod_traffic_last_seen;
@@ -0,0 +1,9 @@
+-- pod_traffic retention prunes on last_seen, not time_stamp: time_stamp
+-- is a flow's first-seen time since the flow-key migration, so pruning
+-- on it deleted flows that were still active, and their next event
+-- re-inserted them as new (re-running audit evaluation, the traffic
+-- stream and DROP alerts). The oldest-first batch scan in
+-- retention.rs::run_pod_traffic_batch matches this index, as do the
+-- last_seen windows of /graph and the workload reads.
+CREATE INDEX IF NOT EXISTS idx_pod_traffic_last_seen
+  ON pod_traffic (last_seen, uuid);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_pod_traffic_seq;
+ALTER TABLE pod_traffic DROP COLUMN IF EXISTS seq;
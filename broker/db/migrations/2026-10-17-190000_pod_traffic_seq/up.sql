@@ -0,0 +1,9 @@
+-- Insert order for /stream/traffic resume. The stream's event ids were
+-- (time_stamp, uuid), but time_stamp is the controller's capture time:
+-- a batch that reaches the broker late commits rows behind a cursor a
+-- client already holds, and its reconnect never replayed them. seq is
+-- taken from a sequence at insert, so it follows commit order up to
+-- the length of one ingest transaction.
+ALTER TABLE pod_traffic ADD COLUMN seq BIGSERIAL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_pod_traffic_seq ON pod_traffic (seq);
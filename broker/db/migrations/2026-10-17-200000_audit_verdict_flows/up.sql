@@ -0,0 +1,18 @@
+-- Which flows have been counted into which verdict row. A verdict's
+-- hits used to grow by one on every evaluation, so POST /audit/replay
+-- over flows that were already evaluated (live, or by an earlier
+-- replay) counted them again, as did an outbox retry after a broker
+-- restart. A flow now adds a hit only when its row here is new.
+--
+-- Rows follow both sides: pruning a verdict or its flow drops them.
+-- Flows evaluated before this migration have no rows, so the first
+-- replay over them still counts them once more.
+CREATE TABLE IF NOT EXISTS audit_verdict_flows (
+  verdict_id BIGINT NOT NULL REFERENCES audit_verdicts (id) ON DELETE CASCADE,
+  traffic_uuid VARCHAR NOT NULL REFERENCES pod_traffic (uuid) ON DELETE CASCADE,
+  PRIMARY KEY (verdict_id, traffic_uuid)
+);
+
+-- pod_traffic retention deletes by uuid; the cascade looks rows up here.
+CREATE INDEX IF NOT EXISTS idx_audit_verdict_flows_traffic_uuid
+  ON audit_verdict_flows (traffic_uuid);
@@ -0,0 +1,19 @@
+-- Going back to a pod_name key needs one row per name: keep the most
+-- recently first-recorded row of each name and drop the rest.
+DELETE FROM pod_syscalls s
+ USING (
+   SELECT pod_name, pod_namespace,
+          ROW_NUMBER() OVER (PARTITION BY pod_name
+                             ORDER BY time_stamp DESC) AS rn
+     FROM pod_syscalls
+ ) ranked
+ WHERE s.pod_name = ranked.pod_name
+   AND s.pod_namespace = ranked.pod_namespace
+   AND ranked.rn > 1;
+
+ALTER TABLE pod_syscalls DROP CONSTRAINT pod_syscalls_pkey;
+ALTER TABLE pod_syscalls ADD PRIMARY KEY (pod_name);
+CREATE INDEX IF NOT EXISTS idx_pod_syscalls_pod_name ON pod_syscalls (pod_name);
+ALTER TABLE pod_syscalls
+  ALTER COLUMN pod_namespace DROP NOT NULL,
+  ALTER COLUMN pod_namespace DROP DEFAULT;
@@ -0,0 +1,20 @@
+-- pod_syscalls was keyed on pod_name alone, so prod/web-1 and
+-- staging/web-1 shared one row: each report overwrote the other pod's
+-- syscall set and moved the row to its own namespace. Key the table on
+-- (pod_name, pod_namespace), as pod_details already is.
+--
+-- A primary-key column must be NOT NULL. Rows from controllers that
+-- predate pod_namespace have NULL there; they become '' ("namespace
+-- unknown"), which the namespaced syscall reads already return.
+UPDATE pod_syscalls SET pod_namespace = '' WHERE pod_namespace IS NULL;
+
+ALTER TABLE pod_syscalls
+  ALTER COLUMN pod_namespace SET DEFAULT '',
+  ALTER COLUMN pod_namespace SET NOT NULL;
+
+ALTER TABLE pod_syscalls DROP CONSTRAINT pod_syscalls_pkey;
+ALTER TABLE pod_syscalls ADD PRIMARY KEY (pod_name, pod_namespace);
+
+-- The new key leads with pod_name, so it serves the name-only lookups
+-- this index was added for.
+DROP INDEX IF EXISTS idx_pod_syscalls_pod_name;
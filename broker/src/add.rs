@@ -1,5 +1,8 @@
-use crate::{schema, AuditClient, PodDetail, PodInputSyscalls, PodSyscalls, PodTraffic, SvcDetail};
-use actix_web::{post, web, Error, HttpResponse};
+use crate::{
+    schema, Alerter, AuditClient, Error, EventHub, PodDetail, PodInputSyscalls,
+    PodSyscallObservation, PodSyscalls, PodTraffic, SvcDetail,
+};
+use actix_web::{post, web, HttpResponse};
 use diesel::pg::PgConnection;
 use diesel::r2d2::{self, ConnectionManager};
 use std::clone::Clone;
@@ -10,10 +13,20 @@ use tracing::{debug, info};
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 type DbError = Box<dyn std::error::Error + Send + Sync>;
 
+/// Store a batch of flows reported by the controller.
+#[utoipa::path(
+    tag = "ingest",
+    request_body = Vec<PodTraffic>,
+    responses(
+        (status = 200, description = "Number of new flows stored", body = usize),
+    )
+)]
 #[post("/pod/traffic/batch")]
 pub async fn add_pods_batch(
     pool: web::Data<DbPool>,
     audit: web::Data<AuditClient>,
+    alerts: web::Data<Alerter>,
+    events: web::Data<EventHub>,
     form: web::Json<Vec<PodTraffic>>,
 ) -> Result<HttpResponse, Error> {
     let received = form.len();
@@ -28,12 +41,12 @@ pub async fn add_pods_batch(
     // batches are >90% duplicate; the wasted audit traffic was
     // pinning the evaluator semaphore and starving real audit work.
     let pool_for_insert = pool.clone();
-    let inserted: Vec<PodTraffic> = web::block(move || {
+    let outbox = audit.outbox_enabled();
+    let (inserted, seqs) = web::block(move || {
         let mut conn = pool_for_insert.get()?;
-        create_pod_traffic_batch(&mut conn, form)
+        create_pod_traffic_batch(&mut conn, form, outbox)
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
 
     info!(
         "Inserted {} new network traffic events ({} duplicates filtered)",
@@ -41,16 +54,23 @@ pub async fn add_pods_batch(
         received - inserted.len()
     );
 
+    // Committed: live /stream/traffic subscribers see the new flows now.
+    events.publish_traffic(&inserted, &seqs);
+
     // Enqueue new flows for best-effort audit eval. try_enqueue never blocks
     // the ingest hot path and never back-pressures capture: a backed-up
     // evaluator sheds load (the bounded queue drops the overflow and counts it)
     // instead of accumulating unbounded waiting tasks. The dispatcher drains the
-    // queue under a concurrency cap.
-    if audit.enabled() {
+    // queue under a concurrency cap. With the outbox on, the new flows were
+    // already queued durably inside the insert transaction.
+    if audit.enabled() && !outbox {
         for event in inserted.iter().cloned() {
             audit.try_enqueue(event);
         }
     }
+    // New DROP flows go to the alert webhooks, through the same kind of
+    // non-blocking bounded queue.
+    alerts.notify_traffic(&inserted);
 
     // Wire format unchanged: respond with the count of newly-inserted
     // rows (a usize JSON-encoded as a number). The controllers caller
@@ -60,9 +80,10 @@ pub async fn add_pods_batch(
 }
 
 /// The content columns that identify a duplicate `PodTraffic` event —
-/// the same set `get_row` dedups on. `uuid` and `time_stamp` differ on
-/// every eBPF emit by design and are intentionally excluded so that
-/// repeated emits of the same flow collapse to one row.
+/// the columns of the `idx_pod_traffic_flow_key` unique index the
+/// upsert conflicts on. `uuid` and `time_stamp` differ on every eBPF
+/// emit by design and are intentionally excluded so that repeated emits
+/// of the same flow collapse to one row.
 type TrafficContentKey = (
     Option<String>,
     Option<String>,
@@ -85,135 +106,137 @@ fn traffic_content_key(e: &PodTraffic) -> TrafficContentKey {
     )
 }
 
+/// Bind parameters per row in the pod_traffic upsert (one per column).
+const TRAFFIC_INSERT_BINDS: usize = 13;
+/// PostgreSQL rejects a statement with more than 65535 bind parameters.
+/// Controllers post at most ~1000 events, so this only splits a batch
+/// from some other writer — into several INSERTs in one transaction.
+const MAX_TRAFFIC_ROWS_PER_INSERT: usize = u16::MAX as usize / TRAFFIC_INSERT_BINDS;
+// A full controller post (1000 pending events) must stay one INSERT.
+const _: () = assert!(MAX_TRAFFIC_ROWS_PER_INSERT >= 1000);
+
+/// Fold repeats of the same flow within one batch into a single row.
+/// eBPF re-emits a flow every cycle and a batch accumulates over
+/// BATCH_TIMEOUT, so repeats commonly arrive together — and ON CONFLICT
+/// DO UPDATE errors if one statement touches the same row twice. The
+/// first emit's uuid is kept, with the earliest `time_stamp`, the latest
+/// as `last_seen`, and `seen_count` counting every emit.
+///
+/// The result is sorted by content key so concurrent batches lock
+/// overlapping flow-key rows in the same order and can't deadlock.
+fn collapse_traffic_batch(batch: Vec<PodTraffic>) -> Vec<PodTraffic> {
+    let mut by_key: std::collections::BTreeMap<TrafficContentKey, PodTraffic> =
+        std::collections::BTreeMap::new();
+    for mut event in batch {
+        match by_key.entry(traffic_content_key(&event)) {
+            std::collections::btree_map::Entry::Occupied(mut e) => {
+                let row = e.get_mut();
+                row.time_stamp = row.time_stamp.min(event.time_stamp);
+                row.last_seen = row.last_seen.max(event.time_stamp);
+                row.seen_count += 1;
+            }
+            std::collections::btree_map::Entry::Vacant(e) => {
+                event.last_seen = event.time_stamp;
+                event.seen_count = 1;
+                e.insert(event);
+            }
+        }
+    }
+    by_key.into_values().collect()
+}
+
 fn create_pod_traffic_batch(
     conn: &mut PgConnection,
     batch: web::Json<Vec<PodTraffic>>,
-) -> Result<Vec<PodTraffic>, DbError> {
+    outbox: bool,
+) -> Result<(Vec<PodTraffic>, Vec<i64>), DbError> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::{Bool, Timestamp};
+    use diesel::upsert::excluded;
     use schema::pod_traffic::dsl::*;
 
     if batch.is_empty() {
-        return Ok(Vec::new());
+        return Ok((Vec::new(), Vec::new()));
     }
 
     debug!("Processing batch of {} network traffic events", batch.len());
 
-    // Filter out duplicates by checking each event against existing records.
-    // The returned vec is what the HTTP handler uses to drive audit
-    // forwarding — only events that were genuinely new should hit the
-    // evaluator, so the dedup decision lives here as the single source
-    // of truth.
-    let mut events_to_insert = Vec::new();
-    // Collapse byte-identical events within THIS batch before the
-    // per-event DB check. eBPF re-emits the same flow every cycle and a
-    // batch accumulates over BATCH_TIMEOUT, so identical events commonly
-    // arrive together. Without this, both pass get_row (neither is
-    // committed yet), double-insert into pod_traffic, AND double-fire
-    // the audit evaluator — inflating verdict/flow counts. Key on the
-    // same content columns get_row dedups on; uuid/time_stamp differ per
-    // event by design and are excluded.
-    let mut seen_in_batch = std::collections::HashSet::new();
-    for event in batch.iter() {
-        if !seen_in_batch.insert(traffic_content_key(event)) {
-            debug!(
-                "Skipping in-batch duplicate traffic event for pod: {:?}",
-                event.pod_name
-            );
-            continue;
+    let rows = collapse_traffic_batch(batch.into_inner());
+
+    // One upsert per batch instead of a SELECT per event: a flow already
+    // in pod_traffic conflicts on idx_pod_traffic_flow_key and only has
+    // last_seen / seen_count bumped. `xmax = 0` in RETURNING is true only
+    // for rows this statement inserted (an updated row carries the
+    // updating transaction's xmax), and that subset is what the handler
+    // forwards to the audit evaluator — repeats of a known flow never
+    // re-fire it. The dedup decision still lives here as the single
+    // source of truth. With `outbox` set the new rows are also queued
+    // in audit_outbox before the transaction commits, so a flow is
+    // never stored without its pending evaluation (or vice versa). Each
+    // new row's `seq` comes back alongside it for the /stream/traffic
+    // event ids.
+    let (inserted, seqs) = conn.transaction(|conn| {
+        let mut inserted = Vec::new();
+        let mut seqs = Vec::new();
+        for chunk in rows.chunks(MAX_TRAFFIC_ROWS_PER_INSERT) {
+            let returned: Vec<(PodTraffic, bool, i64)> = diesel::insert_into(pod_traffic)
+                .values(chunk)
+                .on_conflict((
+                    pod_ip,
+                    pod_port,
+                    ip_protocol,
+                    traffic_type,
+                    traffic_in_out_ip,
+                    traffic_in_out_port,
+                    decision,
+                ))
+                .do_update()
+                .set((
+                    last_seen.eq(sql::<Timestamp>(
+                        "GREATEST(pod_traffic.last_seen, EXCLUDED.last_seen)",
+                    )),
+                    seen_count.eq(seen_count + excluded(seen_count)),
+                ))
+                .returning((PodTraffic::as_returning(), sql::<Bool>("xmax = 0"), seq))
+                .get_results(conn)?;
+            for (row, is_new, row_seq) in returned {
+                if is_new {
+                    inserted.push(row);
+                    seqs.push(row_seq);
+                }
+            }
         }
-        if event.get_row(conn)?.is_none() {
-            events_to_insert.push(event.clone());
-        } else {
-            debug!(
-                "Skipping duplicate traffic event for pod: {:?}",
-                event.pod_name
-            );
+        if outbox {
+            for chunk in inserted.chunks(MAX_TRAFFIC_ROWS_PER_INSERT) {
+                crate::audit_outbox::enqueue(conn, chunk)?;
+            }
         }
-    }
-
-    if events_to_insert.is_empty() {
-        debug!("All events in batch were duplicates, nothing to insert");
-        return Ok(events_to_insert);
-    }
+        Ok::<_, diesel::result::Error>((inserted, seqs))
+    })?;
 
     debug!(
-        "Inserting {} new network traffic events (filtered {} duplicates)",
-        events_to_insert.len(),
-        batch.len() - events_to_insert.len()
+        "Upserted {} distinct flows, {} new",
+        rows.len(),
+        inserted.len()
     );
-
-    // Bulk insert only the new events
-    diesel::insert_into(pod_traffic)
-        .values(&events_to_insert)
-        .execute(conn)?;
-
-    debug!(
-        "Successfully inserted {} network traffic events",
-        events_to_insert.len()
-    );
-    Ok(events_to_insert)
-}
-
-impl PodTraffic {
-    pub fn get_row(&self, conn: &mut PgConnection) -> Result<Option<PodTraffic>, DbError> {
-        use schema::pod_traffic::dsl::*;
-        if self.ip_protocol.eq(&Some("UDP".to_string())) {
-            let out: Option<PodTraffic> = pod_traffic
-                .filter(pod_ip.eq(&self.pod_ip))
-                .filter(traffic_type.eq(&self.traffic_type))
-                .filter(traffic_in_out_ip.eq(&self.traffic_in_out_ip))
-                .filter(traffic_in_out_port.eq(&self.traffic_in_out_port))
-                .filter(decision.eq(&self.decision))
-                .first::<PodTraffic>(conn)
-                .optional()?;
-            if out.is_none() {
-                let second: Option<PodTraffic> = pod_traffic
-                    .filter(pod_ip.eq(&self.pod_ip))
-                    .filter(pod_port.eq(&self.pod_port))
-                    .filter(traffic_type.eq(&self.traffic_type))
-                    .filter(traffic_in_out_ip.eq(&self.traffic_in_out_ip))
-                    .filter(decision.eq(&self.decision))
-                    .first::<PodTraffic>(conn)
-                    .optional()?;
-                return Ok(second);
-            }
-            return Ok(out);
-        }
-
-        // Single-line structured log — the previous "\n"-joined
-        // multi-line format broke log-aggregator parsing (each line
-        // looked like a separate logger emit) and the field name
-        // "pod_trafic_type" was a typo. Use tracing's structured
-        // fields so operators querying by pod_ip / decision get
-        // clean filters in their log backend.
-        debug!(
-            pod_ip = ?self.pod_ip,
-            pod_port = ?self.pod_port,
-            traffic_type = ?self.traffic_type,
-            traffic_in_out_ip = ?self.traffic_in_out_ip,
-            traffic_in_out_port = ?self.traffic_in_out_port,
-            decision = ?self.decision,
-            "checking pod_traffic for existing row",
-        );
-        let row = pod_traffic
-            .filter(pod_ip.eq(&self.pod_ip))
-            .filter(pod_port.eq(&self.pod_port))
-            .filter(traffic_type.eq(&self.traffic_type))
-            .filter(traffic_in_out_ip.eq(&self.traffic_in_out_ip))
-            .filter(traffic_in_out_port.eq(&self.traffic_in_out_port))
-            .filter(decision.eq(&self.decision))
-            .first::<PodTraffic>(conn)
-            .optional()?;
-        Ok(row)
-    }
+    Ok((inserted, seqs))
 }
 
+/// Store or update a pod.
+#[utoipa::path(
+    tag = "ingest",
+    request_body = PodDetail,
+    responses(
+        (status = 200, body = PodDetail),
+    )
+)]
 #[post("/pod/spec")]
 pub async fn add_pod_details(
     pool: web::Data<DbPool>,
     form: web::Json<PodDetail>,
 ) -> Result<HttpResponse, Error> {
     // Defense-in-depth: reject empty/whitespace-only pod_name before
-    // it reaches the diesel upsert. pod_name is the table PK and the
+    // it reaches the diesel upsert. pod_name is part of the table PK and the
     // CRD validator would never produce an empty value, but the
     // broker accepts external POSTs (future tool, hand-rolled curl,
     // misbehaving controller) and an empty PK row creates a sentinel
@@ -231,8 +254,7 @@ pub async fn add_pod_details(
         let mut conn = pool.get()?;
         upsert_pod_details(&mut conn, form)
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
     Ok(HttpResponse::Ok().json(pods))
 }
 
@@ -252,9 +274,12 @@ pub fn upsert_pod_details(
         "storing the pod details {:?} into pod_details table",
         w.pod_name,
     );
+    // Conflict on the (pod_namespace, pod_name) PK: same-named pods in
+    // different namespaces are different rows, while a StatefulSet pod
+    // restarting under its old name still replaces its row in place.
     diesel::insert_into(pod_details)
         .values(&*w)
-        .on_conflict(pod_name)
+        .on_conflict((pod_namespace, pod_name))
         .do_update()
         .set(&*w)
         .execute(conn)?;
@@ -268,57 +293,75 @@ pub fn upsert_pod_details(
 
 /// Mark-dead request body. `pod_name` is required for backward
 /// compatibility with controllers that haven't been updated yet;
-/// `pod_ip` is preferred when set because it acts as a sanity check
-/// against the row's current pod_ip.
+/// `pod_namespace` and `pod_ip` are preferred when set.
 ///
-/// pod_details PK is pod_name (one row per pod_name), but a pod that
-/// restarts updates the SAME row with a new pod_ip via on_conflict
-/// upsert. If the reconciler holds a stale view of the row
+/// pod_details is keyed on (pod_namespace, pod_name), so the namespace
+/// picks the row when the same name exists in several namespaces. A
+/// pod that restarts updates the SAME row with a new pod_ip via
+/// on_conflict upsert. If the reconciler holds a stale view of the row
 /// (pod_ip=old) and posts mark_dead during the race window between
-/// restart and reconciler refresh, the precise (name, ip) filter
-/// won't match the broker's current (name, new_ip) row → no
-/// mark-dead, the live restarted pod stays alive. Without pod_ip the
-/// name-only filter would mark the live row dead, requiring an
-/// upsert from the watcher to restore is_dead=false.
-#[derive(Debug, serde::Deserialize)]
+/// restart and reconciler refresh, the precise ip filter won't match
+/// the broker's current (new_ip) row → no mark-dead, the live
+/// restarted pod stays alive. Without pod_ip the name-only filter
+/// would mark the live row dead, requiring an upsert from the watcher
+/// to restore is_dead=false.
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
 pub struct MarkDeadRequest {
     pub pod_name: String,
     #[serde(default)]
+    pub pod_namespace: Option<String>,
+    #[serde(default)]
     pub pod_ip: Option<String>,
 }
 
+/// Mark a pod as dead.
+#[utoipa::path(
+    tag = "ingest",
+    request_body = MarkDeadRequest,
+    responses(
+        (status = 200, description = "Number of pods marked", body = usize),
+    )
+)]
 #[post("/pod/mark_dead")]
 pub async fn mark_pod_dead(
     pool: web::Data<DbPool>,
     form: web::Json<MarkDeadRequest>,
 ) -> Result<HttpResponse, Error> {
     debug!("Marking pod {} as dead", form.pod_name);
-    let MarkDeadRequest { pod_name, pod_ip } = form.into_inner();
+    let MarkDeadRequest {
+        pod_name,
+        pod_namespace,
+        pod_ip,
+    } = form.into_inner();
     let result = web::block(move || {
         let mut conn = pool.get()?;
-        mark_pod_as_dead(&mut conn, &pod_name, pod_ip.as_deref())
+        mark_pod_as_dead(
+            &mut conn,
+            &pod_name,
+            pod_namespace.as_deref(),
+            pod_ip.as_deref(),
+        )
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
 
     Ok(HttpResponse::Ok().json(result))
 }
 
-/// Mark the pod_details row(s) dead. Prefer the precise (pod_ip)
-/// filter; fall back to name-only for legacy callers.
+/// Mark the pod_details row(s) dead. Prefer the precise filters
+/// (pod_namespace, pod_ip); fall back to name-only for legacy callers.
 ///
-/// pod_details PK is pod_name (one row per pod_name). The precise
-/// (pod_name, pod_ip) filter acts as a sanity check — if the
-/// reconciler holds a stale view, the precise filter won't match
-/// the broker's current row, leaving the (now-restarted, live)
-/// pod alone. The legacy name-only fallback unconditionally marks
-/// the (single) row dead, which is fine for actually-gone pods but
-/// briefly mis-flags a restart during the race window between the
-/// new instance's upsert and the reconciler refresh — until the
+/// The namespace narrows to the one row the PK allows. The pod_ip
+/// filter acts as a sanity check — if the reconciler holds a stale
+/// view, it won't match the broker's current row, leaving the
+/// (now-restarted, live) pod alone. The legacy name-only fallback
+/// marks the row dead unconditionally, which is fine for actually-gone
+/// pods but briefly mis-flags a restart during the race window between
+/// the new instance's upsert and the reconciler refresh — until the
 /// next watcher upsert restores is_dead=false.
 fn mark_pod_as_dead(
     conn: &mut PgConnection,
     pod: &str,
+    namespace: Option<&str>,
     ip: Option<&str>,
 ) -> Result<usize, DbError> {
     use schema::pod_details::dsl::*;
@@ -338,46 +381,51 @@ fn mark_pod_as_dead(
         return Ok(0);
     }
 
-    // Normalise the pod_ip arg: trim whitespace, then treat empty as
+    // Normalise the optional args: trim whitespace, then treat empty as
     // None. A degenerate caller (a future tool sending pod_ip="") would
     // otherwise hit the precise-filter path with `WHERE pod_ip = ''`
     // — silently matches no rows, returns 0, gives the caller a
-    // false success. Falling back to the legacy name-only path is
-    // less precise but visible (it logs the warn) and at least
-    // marks the matched-by-name rows dead.
+    // false success. Falling back to the less precise path is visible
+    // (it logs the warn) and at least marks the matched rows dead.
+    let namespace = namespace.map(str::trim).filter(|s| !s.is_empty());
     let ip = ip.map(str::trim).filter(|s| !s.is_empty());
 
-    let updated = match ip {
-        Some(precise_ip) => {
-            // Filter by (pod_name, pod_ip). pod_name is the PK so the
-            // row is unique; adding pod_ip is a sanity check that
-            // prevents marking the wrong row dead when the reconciler
-            // and broker have racing views of the pod's current IP.
-            diesel::update(pod_details)
-                .filter(pod_name.eq(pod))
-                .filter(pod_ip.eq(precise_ip))
-                .set(is_dead.eq(true))
-                .execute(conn)?
-        }
-        None => {
-            // Legacy path. Logged at warn so operators can see when a
-            // controller hasn't been updated to send pod_ip yet. With
-            // pod_name as PK this still updates only one row — but
-            // without the pod_ip sanity check we risk marking a
-            // racing-restart's live row dead until the next watcher
-            // upsert refreshes is_dead.
+    if namespace.is_none() && ip.is_none() {
+        // Legacy path. Logged at warn so operators can see when a
+        // controller hasn't been updated to send pod_namespace/pod_ip
+        // yet. With neither, a name living in several namespaces can't
+        // be resolved — marking every one of them dead would take live
+        // pods down with the gone one, so refuse instead.
+        let matches: i64 = pod_details
+            .filter(pod_name.eq(pod))
+            .count()
+            .get_result(conn)?;
+        if matches > 1 {
             tracing::warn!(
                 pod = %pod,
-                "mark_pod_dead called without pod_ip — falling back to name-only filter; no IP sanity check against a racing restart"
+                rows = matches,
+                "mark_pod_dead called without pod_namespace or pod_ip for a name in several namespaces; no-op"
             );
-            diesel::update(pod_details)
-                .filter(pod_name.eq(pod))
-                .set(is_dead.eq(true))
-                .execute(conn)?
+            return Ok(0);
         }
-    };
+        tracing::warn!(
+            pod = %pod,
+            "mark_pod_dead called without pod_ip — falling back to name-only filter; no IP sanity check against a racing restart"
+        );
+    }
 
-    info!(pod = %pod, ip = ?ip, rows = updated, "Marked pod row(s) as dead");
+    let mut update = diesel::update(pod_details)
+        .filter(pod_name.eq(pod))
+        .into_boxed();
+    if let Some(ns) = namespace {
+        update = update.filter(pod_namespace.eq(ns));
+    }
+    if let Some(precise_ip) = ip {
+        update = update.filter(pod_ip.eq(precise_ip));
+    }
+    let updated = update.set(is_dead.eq(true)).execute(conn)?;
+
+    info!(pod = %pod, namespace = ?namespace, ip = ?ip, rows = updated, "Marked pod row(s) as dead");
     Ok(updated)
 }
 
@@ -393,6 +441,14 @@ pub(crate) fn is_routable_svc_ip(s: &str) -> bool {
     !s.is_empty() && s != "None"
 }
 
+/// Store or update a service.
+#[utoipa::path(
+    tag = "ingest",
+    request_body = SvcDetail,
+    responses(
+        (status = 200, body = SvcDetail),
+    )
+)]
 #[post("/svc/spec")]
 pub async fn add_svc_details(
     pool: web::Data<DbPool>,
@@ -418,8 +474,7 @@ pub async fn add_svc_details(
         let mut conn = pool.get()?;
         upsert_svc_details(&mut conn, form)
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
     Ok(HttpResponse::Ok().json(pods))
 }
 
@@ -450,26 +505,14 @@ pub fn upsert_svc_details(
     Ok(w.0)
 }
 
-impl PodInputSyscalls {
-    pub fn get_row(&self, conn: &mut PgConnection) -> Result<Option<PodSyscalls>, DbError> {
-        use schema::pod_syscalls::dsl::*;
-
-        debug!(
-            "pod_name: {:?}, pod_namespace: {:?}, syscalls: {:?}, arch: {:?}",
-            &self.pod_name, &self.pod_namespace, &self.syscalls, &self.arch
-        );
-
-        let row = pod_syscalls
-            .filter(pod_name.eq(&self.pod_name))
-            .filter(pod_namespace.eq(&self.pod_namespace))
-            .filter(arch.eq(&self.arch))
-            .first::<PodSyscalls>(conn)
-            .optional()?;
-
-        Ok(row)
-    }
-}
-
+/// Store syscalls reported by the controller.
+#[utoipa::path(
+    tag = "ingest",
+    request_body = Vec<PodInputSyscalls>,
+    responses(
+        (status = 200),
+    )
+)]
 #[post("/pod/syscalls")]
 pub async fn add_pods_syscalls(
     pool: web::Data<DbPool>,
@@ -480,8 +523,7 @@ pub async fn add_pods_syscalls(
         let mut conn = pool.get()?;
         create_pod_syscalls(&mut conn, form)
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
 
     Ok(HttpResponse::Ok().json(()))
 }
@@ -490,14 +532,13 @@ pub fn create_pod_syscalls(
     conn: &mut PgConnection,
     w: web::Json<Vec<PodInputSyscalls>>,
 ) -> Result<(), DbError> {
-    use schema::pod_syscalls::dsl::*;
-
     conn.transaction(|conn| {
+        let mut observations = Vec::new();
         for pod_syscall in w.iter() {
             // Skip entries with empty/whitespace pod_name — same
             // defense as the /pod/spec guard (commit 66090aed) and
             // the symmetric one in mark_pod_as_dead (7eb9bf00).
-            // pod_name is the table PK; an empty value would create
+            // pod_name leads the table PK; an empty value would create
             // a sentinel row that subsequent batches' "is there
             // already a syscall row for X?" lookups could collide
             // with. Skip per-entry rather than failing the whole
@@ -512,39 +553,163 @@ pub fn create_pod_syscalls(
             }
             debug!("storing pod_syscalls entry for {:?}", pod_syscall.pod_name);
 
-            let existing_row = pod_syscall.get_row(conn)?;
-            let new_syscall_number = pod_syscall.syscalls.join(",");
-
-            if let Some(mut row) = existing_row {
-                row.syscalls = new_syscall_number;
-
-                diesel::update(pod_syscalls.filter(pod_name.eq(&row.pod_name)))
-                    .set(syscalls.eq(row.syscalls.clone()))
-                    .execute(conn)?;
+            let namespace = if pod_syscall.pod_namespace.is_empty() {
+                resolve_pod_namespace(conn, &pod_syscall.pod_name)?
             } else {
-                let new_pod_syscall = PodSyscalls {
-                    syscalls: new_syscall_number,
-                    pod_name: pod_syscall.pod_name.clone(),
-                    pod_namespace: pod_syscall.pod_namespace.clone(),
-                    arch: pod_syscall.arch.clone(),
-                    time_stamp: pod_syscall.time_stamp,
-                };
-
-                diesel::insert_into(pod_syscalls)
-                    .values(&new_pod_syscall)
-                    .execute(conn)?;
-            }
+                pod_syscall.pod_namespace.clone()
+            };
+            upsert_pod_syscalls_row(conn, pod_syscall, &namespace)?;
+            observations.extend(syscall_observations(pod_syscall, &namespace));
 
             debug!(
                 "Success: pod {:?} processed in pod_syscalls table",
                 pod_syscall.pod_name
             );
         }
+        upsert_syscall_observations(conn, collapse_observations(observations))
+    })
+}
 
-        Ok(())
+/// Namespace for a report from a controller that didn't send one: the
+/// pod's namespace in pod_details when exactly one namespace has a pod
+/// of that name, '' otherwise.
+fn resolve_pod_namespace(conn: &mut PgConnection, name: &str) -> Result<String, DbError> {
+    use schema::pod_details::dsl::*;
+    let mut namespaces = pod_details
+        .filter(pod_name.eq(name))
+        .select(pod_namespace)
+        .limit(2)
+        .load::<String>(conn)?;
+    Ok(match namespaces.len() {
+        1 => namespaces.remove(0),
+        _ => String::new(),
     })
 }
 
+/// The legacy one-row-per-pod set, keyed on (pod_name, pod_namespace)
+/// so same-named pods in different namespaces keep separate rows. A pod
+/// first reported with namespace '' and later with its real one has a
+/// row under each, as in pod_syscall_observations.
+fn upsert_pod_syscalls_row(
+    conn: &mut PgConnection,
+    report: &PodInputSyscalls,
+    namespace: &str,
+) -> Result<(), DbError> {
+    use schema::pod_syscalls::dsl::*;
+    let row = PodSyscalls {
+        syscalls: report.syscalls.join(","),
+        pod_name: report.pod_name.clone(),
+        pod_namespace: namespace.to_string(),
+        arch: report.arch.clone(),
+        time_stamp: report.time_stamp,
+    };
+    // time_stamp stays the first-recorded time; readers filter on it.
+    diesel::insert_into(pod_syscalls)
+        .values(&row)
+        .on_conflict((pod_name, pod_namespace))
+        .do_update()
+        .set((syscalls.eq(&row.syscalls), arch.eq(&row.arch)))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Binds per pod_syscall_observations row, one per column.
+const OBSERVATION_INSERT_BINDS: usize = 7;
+/// Same 65535-bind ceiling as MAX_TRAFFIC_ROWS_PER_INSERT.
+const MAX_OBSERVATION_ROWS_PER_INSERT: usize = u16::MAX as usize / OBSERVATION_INSERT_BINDS;
+
+/// Observation rows for one report. A controller that sends `counts`
+/// lists only the syscalls seen this interval there, while `syscalls`
+/// is the pod's whole set — stamping that set would mark every syscall
+/// as seen just now, so only counted syscalls are recorded. Older
+/// controllers send just the set, when it changes; each member is
+/// recorded with a count of 0.
+fn syscall_observations(report: &PodInputSyscalls, namespace: &str) -> Vec<PodSyscallObservation> {
+    let row = |name: &str, n: i64| PodSyscallObservation {
+        pod_namespace: namespace.to_string(),
+        pod_name: report.pod_name.clone(),
+        arch: report.arch.clone(),
+        syscall: name.to_string(),
+        first_seen: report.time_stamp,
+        last_seen: report.time_stamp,
+        count: n,
+    };
+    if report.counts.is_empty() {
+        report
+            .syscalls
+            .iter()
+            .filter(|name| !name.is_empty())
+            .map(|name| row(name, 0))
+            .collect()
+    } else {
+        report
+            .counts
+            .iter()
+            .filter(|(name, n)| !name.is_empty() && **n > 0)
+            .map(|(name, n)| row(name, *n))
+            .collect()
+    }
+}
+
+/// Fold rows for the same (namespace, pod, arch, syscall) into one —
+/// ON CONFLICT DO UPDATE errors if one statement touches a row twice —
+/// and return them in key order so concurrent batches lock rows in the
+/// same order.
+fn collapse_observations(rows: Vec<PodSyscallObservation>) -> Vec<PodSyscallObservation> {
+    let mut by_key: std::collections::BTreeMap<
+        (String, String, String, String),
+        PodSyscallObservation,
+    > = std::collections::BTreeMap::new();
+    for obs in rows {
+        let key = (
+            obs.pod_namespace.clone(),
+            obs.pod_name.clone(),
+            obs.arch.clone(),
+            obs.syscall.clone(),
+        );
+        match by_key.entry(key) {
+            std::collections::btree_map::Entry::Occupied(mut e) => {
+                let row = e.get_mut();
+                row.first_seen = row.first_seen.min(obs.first_seen);
+                row.last_seen = row.last_seen.max(obs.last_seen);
+                row.count = row.count.saturating_add(obs.count);
+            }
+            std::collections::btree_map::Entry::Vacant(e) => {
+                e.insert(obs);
+            }
+        }
+    }
+    by_key.into_values().collect()
+}
+
+fn upsert_syscall_observations(
+    conn: &mut PgConnection,
+    rows: Vec<PodSyscallObservation>,
+) -> Result<(), DbError> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::Timestamp;
+    use diesel::upsert::excluded;
+    use schema::pod_syscall_observations::dsl::*;
+
+    for chunk in rows.chunks(MAX_OBSERVATION_ROWS_PER_INSERT) {
+        diesel::insert_into(pod_syscall_observations)
+            .values(chunk)
+            .on_conflict((pod_namespace, pod_name, arch, syscall))
+            .do_update()
+            .set((
+                first_seen.eq(sql::<Timestamp>(
+                    "LEAST(pod_syscall_observations.first_seen, EXCLUDED.first_seen)",
+                )),
+                last_seen.eq(sql::<Timestamp>(
+                    "GREATEST(pod_syscall_observations.last_seen, EXCLUDED.last_seen)",
+                )),
+                count.eq(count + excluded(count)),
+            ))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -576,6 +741,34 @@ mod tests {
         assert_eq!(got.pod_ip.as_deref(), Some("10.42.3.5"));
     }
 
+    #[test]
+    fn mark_dead_request_with_namespace() {
+        // Controllers that know the pod's namespace send it so a name
+        // shared across namespaces marks only the right row.
+        let json = r#"{"pod_name":"web-1","pod_namespace":"prod","pod_ip":"10.42.3.5"}"#;
+        let got: MarkDeadRequest = serde_json::from_str(json).expect("decode");
+        assert_eq!(got.pod_namespace.as_deref(), Some("prod"));
+        assert_eq!(got.pod_ip.as_deref(), Some("10.42.3.5"));
+
+        let legacy: MarkDeadRequest =
+            serde_json::from_str(r#"{"pod_name":"web-1"}"#).expect("decode");
+        assert_eq!(legacy.pod_namespace, None);
+    }
+
+    #[test]
+    fn pod_detail_null_namespace_decodes_as_empty() {
+        // pod_namespace is part of pod_details' PK (NOT NULL). The
+        // controller types it Option on the wire; null and absent must
+        // map to the '' sentinel rather than failing the upsert.
+        for json in [
+            r#"{"pod_name":"web-1","pod_ip":"10.0.0.1","pod_namespace":null,"time_stamp":"2026-01-01T00:00:00","node_name":"n","is_dead":false}"#,
+            r#"{"pod_name":"web-1","pod_ip":"10.0.0.1","time_stamp":"2026-01-01T00:00:00","node_name":"n","is_dead":false}"#,
+        ] {
+            let got: PodDetail = serde_json::from_str(json).expect("decode");
+            assert_eq!(got.pod_namespace, "");
+        }
+    }
+
     #[test]
     fn mark_dead_request_pod_ip_explicit_null_treated_as_none() {
         // A JSON `null` for pod_ip should also produce None.
@@ -637,11 +830,12 @@ mod tests {
         assert!(is_routable_svc_ip("NONE"));
     }
 
-    // traffic_content_key drives the in-batch dedup in
-    // create_pod_traffic_batch. The DB path itself needs a live
-    // PostgreSQL (not available in unit tests), but the key is the part
-    // most prone to regression: include the wrong column and the dedup
-    // either over-merges distinct flows or fails to collapse repeats.
+    // traffic_content_key drives the in-batch collapse in
+    // create_pod_traffic_batch and mirrors idx_pod_traffic_flow_key.
+    // The DB path itself needs a live PostgreSQL (not available in unit
+    // tests), but the key is the part most prone to regression: include
+    // the wrong column and the dedup either over-merges distinct flows
+    // or fails to collapse repeats.
     fn sample_traffic(uuid: &str) -> PodTraffic {
         PodTraffic {
             uuid: uuid.to_string(),
@@ -658,9 +852,17 @@ mod tests {
                 .unwrap()
                 .and_hms_opt(0, 0, 0)
                 .unwrap(),
+            ..Default::default()
         }
     }
 
+    fn at_hour(h: u32) -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2026, 1, 1)
+            .unwrap()
+            .and_hms_opt(h, 0, 0)
+            .unwrap()
+    }
+
     #[test]
     fn content_key_ignores_uuid_and_timestamp() {
         // eBPF re-emits the same flow every cycle with a fresh uuid and
@@ -684,4 +886,168 @@ mod tests {
         b.traffic_in_out_port = Some("8443".to_string());
         assert_ne!(traffic_content_key(&a), traffic_content_key(&b));
     }
+
+    #[test]
+    fn collapse_folds_in_batch_repeats_into_one_row() {
+        // ON CONFLICT DO UPDATE fails if one statement hits the same row
+        // twice, so repeats must be folded before the upsert — carrying
+        // the earliest first-seen, latest last-seen and full emit count.
+        let mut a = sample_traffic("uuid-a");
+        a.time_stamp = at_hour(5);
+        let mut b = sample_traffic("uuid-b");
+        b.time_stamp = at_hour(2);
+        let mut c = sample_traffic("uuid-c");
+        c.time_stamp = at_hour(9);
+        let rows = collapse_traffic_batch(vec![a, b, c]);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].uuid, "uuid-a", "first emit's uuid is kept");
+        assert_eq!(rows[0].time_stamp, at_hour(2));
+        assert_eq!(rows[0].last_seen, at_hour(9));
+        assert_eq!(rows[0].seen_count, 3);
+    }
+
+    #[test]
+    fn collapse_stamps_singletons_and_keeps_distinct_flows() {
+        // The controller wire has no last_seen/seen_count; a lone emit
+        // must still insert as last_seen == time_stamp, seen_count == 1
+        // rather than the serde defaults (epoch, 0).
+        let a = sample_traffic("x");
+        let mut b = sample_traffic("y");
+        b.traffic_in_out_port = Some("8443".to_string());
+        let rows = collapse_traffic_batch(vec![a.clone(), b]);
+        assert_eq!(rows.len(), 2);
+        for row in &rows {
+            assert_eq!(row.last_seen, row.time_stamp);
+            assert_eq!(row.seen_count, 1);
+        }
+    }
+
+    #[test]
+    fn collapse_orders_rows_by_flow_key() {
+        // Concurrent batches must take row locks in one global order or
+        // two overlapping upserts can deadlock.
+        let mut a = sample_traffic("a");
+        a.traffic_in_out_port = Some("9000".to_string());
+        let mut b = sample_traffic("b");
+        b.traffic_in_out_port = Some("1000".to_string());
+        let rows = collapse_traffic_batch(vec![a, b]);
+        let keys: Vec<_> = rows.iter().map(traffic_content_key).collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+    }
+
+    #[test]
+    fn traffic_wire_without_seen_fields_still_decodes() {
+        // Controllers predating last_seen/seen_count post without them;
+        // the batch endpoint must keep accepting that shape.
+        let json = r#"{"uuid":"u","pod_name":"web-1","pod_namespace":"prod",
+            "pod_ip":"10.0.0.1","pod_port":"0","ip_protocol":"TCP",
+            "traffic_type":"EGRESS","traffic_in_out_ip":"10.0.0.2",
+            "traffic_in_out_port":"443","decision":"ALLOW",
+            "time_stamp":"2026-01-01T00:00:00"}"#;
+        let got: PodTraffic = serde_json::from_str(json).expect("decode");
+        assert_eq!(got.seen_count, 0);
+    }
+
+    fn syscall_report(json: &str) -> PodInputSyscalls {
+        serde_json::from_str(json).expect("decode")
+    }
+
+    #[test]
+    fn syscall_wire_without_counts_still_decodes() {
+        // Controllers predating counts send only the set, and type
+        // pod_namespace as a plain string that may be null.
+        let r = syscall_report(
+            r#"{"pod_name":"web-1","pod_namespace":null,"syscalls":["read","write"],
+                "arch":"x86_64","time_stamp":"2026-01-01T00:00:00"}"#,
+        );
+        assert!(r.counts.is_empty());
+        assert_eq!(r.pod_namespace, "");
+    }
+
+    #[test]
+    fn counted_report_records_only_counted_syscalls() {
+        // `syscalls` is the cumulative set; stamping all of it would
+        // bump last_seen on syscalls the pod didn't make this interval.
+        let r = syscall_report(
+            r#"{"pod_name":"web-1","pod_namespace":"prod","syscalls":["read","write","openat"],
+                "arch":"x86_64","time_stamp":"2026-01-01T00:00:10",
+                "counts":{"read":12,"openat":0}}"#,
+        );
+        let obs = syscall_observations(&r, "prod");
+        assert_eq!(obs.len(), 1);
+        assert_eq!(obs[0].syscall, "read");
+        assert_eq!(obs[0].count, 12);
+        assert_eq!(obs[0].pod_namespace, "prod");
+        assert_eq!(obs[0].first_seen, r.time_stamp);
+    }
+
+    #[test]
+    fn legacy_report_records_whole_set_with_zero_count() {
+        let r = syscall_report(
+            r#"{"pod_name":"web-1","pod_namespace":"","syscalls":["read","","write"],
+                "arch":"aarch64","time_stamp":"2026-01-01T00:00:00"}"#,
+        );
+        let obs = syscall_observations(&r, "");
+        let names: Vec<_> = obs.iter().map(|o| o.syscall.as_str()).collect();
+        assert_eq!(names, ["read", "write"]);
+        assert!(obs.iter().all(|o| o.count == 0 && o.arch == "aarch64"));
+    }
+
+    #[test]
+    fn collapse_observations_folds_repeats() {
+        // Two reports for the same pod in one batch (a retried post
+        // plus the next interval) must become one row per syscall, or
+        // the ON CONFLICT upsert fails the whole batch.
+        let a = syscall_report(
+            r#"{"pod_name":"web-1","pod_namespace":"prod","syscalls":["read"],
+                "arch":"x86_64","time_stamp":"2026-01-01T00:00:10","counts":{"read":3}}"#,
+        );
+        let b = syscall_report(
+            r#"{"pod_name":"web-1","pod_namespace":"prod","syscalls":["read","write"],
+                "arch":"x86_64","time_stamp":"2026-01-01T00:00:20","counts":{"read":4,"write":1}}"#,
+        );
+        let mut rows = syscall_observations(&b, "prod");
+        rows.extend(syscall_observations(&a, "prod"));
+        let rows = collapse_observations(rows);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].syscall, "read");
+        assert_eq!(rows[0].count, 7);
+        assert_eq!(rows[0].first_seen, a.time_stamp);
+        assert_eq!(rows[0].last_seen, b.time_stamp);
+        assert_eq!(rows[1].syscall, "write");
+    }
+
+    #[test]
+    #[ignore = "requires a database"]
+    fn syscall_sets_of_same_named_pods_stay_apart() {
+        use crate::schema::pod_syscalls::dsl::*;
+
+        let mut conn = crate::test_support::db();
+        conn.test_transaction::<_, DbError, _>(|conn| {
+            let report = |ns: &str, calls: &str| {
+                syscall_report(&format!(
+                    r#"{{"pod_name":"add-test-web-1","pod_namespace":"{ns}","syscalls":[{calls}],
+                        "arch":"x86_64","time_stamp":"2026-01-01T00:00:10"}}"#
+                ))
+            };
+            upsert_pod_syscalls_row(conn, &report("prod", r#""read""#), "prod")?;
+            upsert_pod_syscalls_row(conn, &report("staging", r#""write""#), "staging")?;
+            upsert_pod_syscalls_row(conn, &report("prod", r#""read","openat""#), "prod")?;
+            let rows = pod_syscalls
+                .filter(pod_name.eq("add-test-web-1"))
+                .order(pod_namespace.asc())
+                .select((pod_namespace, syscalls))
+                .load::<(String, String)>(conn)?;
+            assert_eq!(
+                rows,
+                [
+                    ("prod".to_string(), "read,openat".to_string()),
+                    ("staging".to_string(), "write".to_string()),
+                ]
+            );
+            Ok(())
+        });
+    }
 }
@@ -0,0 +1,733 @@
+//! Webhook alerts for new kernel drops and would-deny verdicts,
+//! opt-in via `ALERT_WEBHOOKS_FILE`.
+//!
+//! Ingest reports each newly inserted `decision = "DROP"` flow and the
+//! audit client each newly stored `WouldDeny` verdict. Both calls only
+//! `try_send` onto a bounded queue, like `AuditClient::try_enqueue`; a
+//! dispatcher task drops repeats seen within the dedupe window and
+//! routes the rest onto the queues of the webhooks that want them.
+//! Each webhook has its own queue and delivery task, which POSTs the
+//! JSON payload and retries with backoff, so one receiver that is down
+//! only backs up its own alerts. A full queue sheds alerts and counts
+//! them in `broker_alerts_dropped_total`.
+//!
+//! The file is YAML, read once at startup:
+//!
+//! ```yaml
+//! dedupe_window_secs: 600      # default 600; 0 sends every alert
+//! max_attempts: 5              # per delivery, default 5
+//! webhooks:
+//!   - name: payments-oncall
+//!     url: https://hooks.example.com/kguardian
+//!     secret: "..."            # optional: sign with HMAC-SHA256
+//!     namespaces: [payments]   # optional: default every namespace
+//!     events: [drop]           # optional: drop, would_deny; default both
+//! ```
+//!
+//! A signed request carries `X-Kguardian-Timestamp` (unix seconds) and
+//! `X-Kguardian-Signature: sha256=<hex>`, the HMAC of
+//! `"{timestamp}.{body}"` keyed with the webhook's secret.
+
+use crate::{AuditVerdict, PodTraffic};
+use actix_web::web::Bytes;
+use chrono::Utc;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Semaphore};
+use tracing::{debug, info, warn};
+
+const DEFAULT_DEDUPE_WINDOW_SECS: u64 = 600;
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const MAX_ATTEMPTS_CAP: u32 = 20;
+/// Alerts waiting for the dispatcher before new ones are shed.
+const QUEUE_CAPACITY: usize = 1024;
+/// Alerts waiting for one webhook before new ones for it are shed.
+const WEBHOOK_QUEUE_CAPACITY: usize = 256;
+/// Deliveries in flight per webhook. A slow receiver holds its permits
+/// through the retries, so its queue absorbs the backlog.
+const MAX_IN_FLIGHT_PER_WEBHOOK: usize = 4;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// First retry delay; doubles per attempt up to `MAX_RETRY_DELAY`.
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// Dedupe keys kept before the expired ones are swept.
+const DEDUPE_SWEEP_AT: usize = 10_000;
+
+/// What an alert reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AlertKind {
+    /// A flow the kernel dropped, first seen.
+    Drop,
+    /// An audit policy would deny a flow, first seen.
+    WouldDeny,
+}
+
+impl AlertKind {
+    const ALL: [AlertKind; 2] = [AlertKind::Drop, AlertKind::WouldDeny];
+
+    fn label(self) -> &'static str {
+        match self {
+            AlertKind::Drop => "drop",
+            AlertKind::WouldDeny => "would_deny",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AlertFile {
+    dedupe_window_secs: Option<u64>,
+    max_attempts: Option<u32>,
+    #[serde(default)]
+    webhooks: Vec<WebhookDoc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WebhookDoc {
+    name: String,
+    url: String,
+    secret: Option<String>,
+    #[serde(default)]
+    namespaces: Vec<String>,
+    #[serde(default)]
+    events: Vec<AlertKind>,
+}
+
+/// Parsed `ALERT_WEBHOOKS_FILE`.
+pub struct AlertConfig {
+    webhooks: Vec<Webhook>,
+    dedupe_window: Duration,
+    max_attempts: u32,
+}
+
+impl AlertConfig {
+    /// Read the file named by `ALERT_WEBHOOKS_FILE`. `Ok(None)` when the
+    /// variable is unset or blank; an unreadable or invalid file is an
+    /// error, so a typo can't silently turn alerting off.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let path = std::env::var("ALERT_WEBHOOKS_FILE").unwrap_or_default();
+        let path = path.trim();
+        if path.is_empty() {
+            return Ok(None);
+        }
+        let raw = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        Self::parse(&raw)
+            .map(Some)
+            .map_err(|e| format!("{path}: {e}"))
+    }
+
+    fn parse(raw: &str) -> Result<Self, String> {
+        let file: AlertFile = serde_yaml::from_str(raw).map_err(|e| e.to_string())?;
+        let mut names = HashSet::new();
+        let mut webhooks = Vec::with_capacity(file.webhooks.len());
+        for doc in file.webhooks {
+            let name = doc.name.trim().to_string();
+            if name.is_empty() {
+                return Err("webhook name must not be empty".to_string());
+            }
+            if !names.insert(name.clone()) {
+                return Err(format!("duplicate webhook name {name:?}"));
+            }
+            let url = reqwest::Url::parse(doc.url.trim())
+                .map_err(|e| format!("webhook {name:?}: invalid url: {e}"))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(format!("webhook {name:?}: url must be http or https"));
+            }
+            let key = doc
+                .secret
+                .filter(|s| !s.is_empty())
+                .map(|s| hmac::Key::new(hmac::HMAC_SHA256, s.as_bytes()));
+            let events = if doc.events.is_empty() {
+                AlertKind::ALL.to_vec()
+            } else {
+                doc.events
+            };
+            webhooks.push(Webhook {
+                name,
+                url,
+                key,
+                namespaces: doc.namespaces.into_iter().collect(),
+                events,
+                sent: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+            });
+        }
+        Ok(Self {
+            webhooks,
+            dedupe_window: Duration::from_secs(
+                file.dedupe_window_secs
+                    .unwrap_or(DEFAULT_DEDUPE_WINDOW_SECS),
+            ),
+            max_attempts: file
+                .max_attempts
+                .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+                .clamp(1, MAX_ATTEMPTS_CAP),
+        })
+    }
+}
+
+struct Webhook {
+    name: String,
+    url: reqwest::Url,
+    key: Option<hmac::Key>,
+    /// Empty: every namespace.
+    namespaces: HashSet<String>,
+    events: Vec<AlertKind>,
+    sent: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl Webhook {
+    fn wants(&self, alert: &Alert) -> bool {
+        self.events.contains(&alert.kind())
+            && (self.namespaces.is_empty() || self.namespaces.contains(&alert.namespace))
+    }
+}
+
+/// The JSON body POSTed to a webhook.
+#[derive(Debug, Serialize)]
+struct Alert {
+    /// Namespace the alert is routed by: the flow's pod, or the pod the
+    /// denying policy selects.
+    namespace: String,
+    #[serde(flatten)]
+    subject: Subject,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Subject {
+    Drop { flow: PodTraffic },
+    WouldDeny { verdict: AuditVerdict },
+}
+
+impl Alert {
+    fn kind(&self) -> AlertKind {
+        match self.subject {
+            Subject::Drop { .. } => AlertKind::Drop,
+            Subject::WouldDeny { .. } => AlertKind::WouldDeny,
+        }
+    }
+
+    /// What makes two alerts repeats of each other: the flow without its
+    /// ephemeral port, or the verdict's row key.
+    fn dedupe_key(&self) -> String {
+        let s = |v: &Option<String>| v.clone().unwrap_or_default();
+        match &self.subject {
+            Subject::Drop { flow } => {
+                let ingress = flow
+                    .traffic_type
+                    .as_deref()
+                    .is_some_and(|t| t.eq_ignore_ascii_case("INGRESS"));
+                let port = if ingress {
+                    &flow.pod_port
+                } else {
+                    &flow.traffic_in_out_port
+                };
+                format!(
+                    "drop|{}|{}|{}|{}|{}|{}",
+                    self.namespace,
+                    s(&flow.pod_name),
+                    s(&flow.traffic_type),
+                    s(&flow.traffic_in_out_ip),
+                    s(port),
+                    s(&flow.ip_protocol),
+                )
+            }
+            Subject::WouldDeny { verdict: v } => format!(
+                "would_deny|{}|{}|{}|{}|{}|{}|{}|{}",
+                v.policy_uid,
+                v.direction,
+                s(&v.src_namespace),
+                s(&v.src_pod),
+                s(&v.dst_namespace),
+                s(&v.dst_pod),
+                v.dst_port,
+                v.protocol,
+            ),
+        }
+    }
+}
+
+fn drop_alerts(rows: &[PodTraffic]) -> impl Iterator<Item = Alert> + '_ {
+    rows.iter()
+        .filter(|t| t.decision.as_deref() == Some("DROP"))
+        .map(|t| Alert {
+            namespace: t.pod_namespace.clone().unwrap_or_default(),
+            subject: Subject::Drop { flow: t.clone() },
+        })
+}
+
+/// Verdicts stored for the first time. Live evaluations stamp a new row
+/// with `first_observed_at == last_observed_at`; a repeat moves
+/// `last_observed_at` on.
+fn would_deny_alerts(rows: &[AuditVerdict]) -> impl Iterator<Item = Alert> + '_ {
+    rows.iter()
+        .filter(|v| v.verdict == "WouldDeny" && v.first_observed_at == v.last_observed_at)
+        .map(|v| {
+            let selected = if v.direction.eq_ignore_ascii_case("Ingress") {
+                &v.dst_namespace
+            } else {
+                &v.src_namespace
+            };
+            Alert {
+                namespace: selected
+                    .clone()
+                    .unwrap_or_else(|| v.policy_namespace.clone()),
+                subject: Subject::WouldDeny { verdict: v.clone() },
+            }
+        })
+}
+
+/// Remembers when each alert key was last sent.
+struct Deduper {
+    window: Duration,
+    seen: HashMap<String, Instant>,
+}
+
+impl Deduper {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Whether `key` should be sent now; records it if so.
+    fn admit(&mut self, key: String, now: Instant) -> bool {
+        if self.window.is_zero() {
+            return true;
+        }
+        if let Some(at) = self.seen.get(&key) {
+            if now.duration_since(*at) < self.window {
+                return false;
+            }
+        }
+        if self.seen.len() >= DEDUPE_SWEEP_AT {
+            let window = self.window;
+            self.seen.retain(|_, at| now.duration_since(*at) < window);
+        }
+        self.seen.insert(key, now);
+        true
+    }
+}
+
+/// `sha256=<hex>` signature of `"{timestamp}.{body}"`.
+fn signature(key: &hmac::Key, timestamp: i64, body: &[u8]) -> String {
+    let mut ctx = hmac::Context::with_key(key);
+    ctx.update(timestamp.to_string().as_bytes());
+    ctx.update(b".");
+    ctx.update(body);
+    let mut out = String::from("sha256=");
+    for b in ctx.sign().as_ref() {
+        let _ = write!(out, "{b:02x}");
+    }
+    out
+}
+
+struct Inner {
+    tx: mpsc::Sender<Alert>,
+    webhooks: Vec<Arc<Webhook>>,
+    dropped: AtomicU64,
+    suppressed: AtomicU64,
+}
+
+/// Handle ingest and the audit client report to. Cheap to clone; the
+/// default handle (no `ALERT_WEBHOOKS_FILE`) ignores every report.
+#[derive(Clone, Default)]
+pub struct Alerter {
+    inner: Option<Arc<Inner>>,
+}
+
+impl Alerter {
+    /// Spawn the dispatcher for `config`. Call once at startup, inside
+    /// the runtime.
+    pub fn start(config: Option<AlertConfig>) -> Self {
+        let Some(config) = config.filter(|c| !c.webhooks.is_empty()) else {
+            return Self::default();
+        };
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let inner = Arc::new(Inner {
+            tx,
+            webhooks: config.webhooks.into_iter().map(Arc::new).collect(),
+            dropped: AtomicU64::new(0),
+            suppressed: AtomicU64::new(0),
+        });
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        spawn_dispatcher(
+            inner.clone(),
+            http,
+            rx,
+            Deduper::new(config.dedupe_window),
+            config.max_attempts,
+        );
+        Self { inner: Some(inner) }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Webhook names, for the startup log.
+    pub fn webhook_names(&self) -> Vec<&str> {
+        self.inner
+            .iter()
+            .flat_map(|i| i.webhooks.iter().map(|w| w.name.as_str()))
+            .collect()
+    }
+
+    /// Report newly inserted flows; the `DROP`s among them alert. Call
+    /// after the insert committed. Never blocks.
+    pub fn notify_traffic(&self, rows: &[PodTraffic]) {
+        if let Some(inner) = &self.inner {
+            drop_alerts(rows).for_each(|a| inner.enqueue(a));
+        }
+    }
+
+    /// Report stored verdict rows; the new `WouldDeny`s among them
+    /// alert. Never blocks.
+    pub(crate) fn notify_verdicts(&self, rows: &[AuditVerdict]) {
+        if let Some(inner) = &self.inner {
+            would_deny_alerts(rows).for_each(|a| inner.enqueue(a));
+        }
+    }
+
+    /// The `broker_alerts_*` metrics.
+    pub fn render_metrics(&self) -> String {
+        let (dropped, suppressed) = self.inner.as_ref().map_or((0, 0), |i| {
+            (
+                i.dropped.load(Ordering::Relaxed),
+                i.suppressed.load(Ordering::Relaxed),
+            )
+        });
+        let webhooks = self.inner.as_ref().map_or(&[][..], |i| &i.webhooks[..]);
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# HELP broker_alerts_sent_total Alerts delivered, by webhook"
+        );
+        let _ = writeln!(out, "# TYPE broker_alerts_sent_total counter");
+        for w in webhooks {
+            let _ = writeln!(
+                out,
+                "broker_alerts_sent_total{{webhook=\"{}\"}} {}",
+                w.name,
+                w.sent.load(Ordering::Relaxed)
+            );
+        }
+        let _ = writeln!(
+            out,
+            "# HELP broker_alerts_failed_total Alerts given up on after their retries, by webhook"
+        );
+        let _ = writeln!(out, "# TYPE broker_alerts_failed_total counter");
+        for w in webhooks {
+            let _ = writeln!(
+                out,
+                "broker_alerts_failed_total{{webhook=\"{}\"}} {}",
+                w.name,
+                w.failed.load(Ordering::Relaxed)
+            );
+        }
+        let _ = writeln!(out, "# HELP broker_alerts_suppressed_total Alerts skipped as repeats inside the dedupe window");
+        let _ = writeln!(out, "# TYPE broker_alerts_suppressed_total counter");
+        let _ = writeln!(out, "broker_alerts_suppressed_total {suppressed}");
+        let _ = writeln!(
+            out,
+            "# HELP broker_alerts_dropped_total Alerts shed because an alert queue was full"
+        );
+        let _ = writeln!(out, "# TYPE broker_alerts_dropped_total counter");
+        let _ = writeln!(out, "broker_alerts_dropped_total {dropped}");
+        out
+    }
+}
+
+impl Inner {
+    fn enqueue(&self, alert: Alert) {
+        match self.tx.try_send(alert) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                debug!("alert queue full; shedding alert");
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+fn spawn_dispatcher(
+    inner: Arc<Inner>,
+    http: reqwest::Client,
+    mut rx: mpsc::Receiver<Alert>,
+    mut dedupe: Deduper,
+    max_attempts: u32,
+) {
+    let queues: Vec<_> = inner
+        .webhooks
+        .iter()
+        .map(|hook| spawn_deliverer(hook.clone(), http.clone(), max_attempts))
+        .collect();
+    actix_web::rt::spawn(async move {
+        while let Some(alert) = rx.recv().await {
+            let targets: Vec<_> = inner
+                .webhooks
+                .iter()
+                .zip(&queues)
+                .filter(|(w, _)| w.wants(&alert))
+                .collect();
+            if targets.is_empty() {
+                continue;
+            }
+            if !dedupe.admit(alert.dedupe_key(), Instant::now()) {
+                inner.suppressed.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            let body = match serde_json::to_vec(&alert) {
+                Ok(b) => Bytes::from(b),
+                Err(e) => {
+                    warn!("could not encode alert: {e}");
+                    continue;
+                }
+            };
+            let kind = alert.kind();
+            for (hook, queue) in targets {
+                if let Err(mpsc::error::TrySendError::Full(_)) =
+                    queue.try_send((kind, body.clone()))
+                {
+                    inner.dropped.fetch_add(1, Ordering::Relaxed);
+                    debug!(webhook = %hook.name, "webhook alert queue full; shedding alert");
+                }
+            }
+        }
+    });
+}
+
+/// Start `hook`'s delivery task and return its queue. Deliveries wait
+/// only on this webhook's own permits.
+fn spawn_deliverer(
+    hook: Arc<Webhook>,
+    http: reqwest::Client,
+    max_attempts: u32,
+) -> mpsc::Sender<(AlertKind, Bytes)> {
+    let (tx, mut rx) = mpsc::channel::<(AlertKind, Bytes)>(WEBHOOK_QUEUE_CAPACITY);
+    let permits = Arc::new(Semaphore::new(MAX_IN_FLIGHT_PER_WEBHOOK));
+    actix_web::rt::spawn(async move {
+        while let Some((kind, body)) = rx.recv().await {
+            let Ok(permit) = permits.clone().acquire_owned().await else {
+                return;
+            };
+            let http = http.clone();
+            let hook = hook.clone();
+            actix_web::rt::spawn(async move {
+                deliver(&http, &hook, kind, body, max_attempts).await;
+                drop(permit);
+            });
+        }
+    });
+    tx
+}
+
+/// POST one alert to one webhook, retrying connection errors, timeouts,
+/// 408, 429 and 5xx with exponential backoff. Other statuses are final.
+async fn deliver(
+    http: &reqwest::Client,
+    hook: &Webhook,
+    kind: AlertKind,
+    body: Bytes,
+    max_attempts: u32,
+) {
+    let mut delay = BASE_RETRY_DELAY;
+    for attempt in 1..=max_attempts {
+        let timestamp = Utc::now().timestamp();
+        let mut req = http
+            .post(hook.url.clone())
+            .header("Content-Type", "application/json")
+            .header("X-Kguardian-Event", kind.label())
+            .header("X-Kguardian-Timestamp", timestamp.to_string());
+        if let Some(key) = &hook.key {
+            req = req.header("X-Kguardian-Signature", signature(key, timestamp, &body));
+        }
+        let retryable = match req.body(body.clone()).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                hook.sent.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            Ok(resp) => {
+                let status = resp.status();
+                warn!(webhook = %hook.name, attempt, %status, "alert webhook rejected delivery");
+                status.is_server_error() || status.as_u16() == 408 || status.as_u16() == 429
+            }
+            Err(e) => {
+                warn!(webhook = %hook.name, attempt, "alert webhook unreachable: {e}");
+                true
+            }
+        };
+        if !retryable || attempt == max_attempts {
+            break;
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+    hook.failed.fetch_add(1, Ordering::Relaxed);
+    info!(webhook = %hook.name, kind = kind.label(), "gave up on alert delivery");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drop_flow(namespace: &str, port: &str) -> PodTraffic {
+        PodTraffic {
+            pod_name: Some("web-1".to_string()),
+            pod_namespace: Some(namespace.to_string()),
+            traffic_type: Some("EGRESS".to_string()),
+            traffic_in_out_ip: Some("10.9.9.9".to_string()),
+            traffic_in_out_port: Some(port.to_string()),
+            ip_protocol: Some("TCP".to_string()),
+            decision: Some("DROP".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn verdict(direction: &str, hits_again: bool) -> AuditVerdict {
+        let first = chrono::NaiveDateTime::default();
+        AuditVerdict {
+            id: 1,
+            policy_uid: "u".to_string(),
+            policy_namespace: "prod".to_string(),
+            policy_name: "deny-all".to_string(),
+            direction: direction.to_string(),
+            src_namespace: Some("web".to_string()),
+            src_pod: Some("web-1".to_string()),
+            dst_namespace: Some("data".to_string()),
+            dst_pod: Some("db-0".to_string()),
+            dst_port: 5432,
+            protocol: "TCP".to_string(),
+            reason: Some("no rule".to_string()),
+            last_observed_at: if hits_again {
+                first + chrono::Duration::seconds(5)
+            } else {
+                first
+            },
+            verdict: "WouldDeny".to_string(),
+            first_observed_at: first,
+            hits: 1,
+        }
+    }
+
+    #[test]
+    fn parses_file_with_defaults_and_rejects_bad_urls() {
+        let c = AlertConfig::parse(
+            "webhooks:\n  - name: a\n    url: https://h.example/x\n    namespaces: [prod]\n",
+        )
+        .unwrap();
+        assert_eq!(c.dedupe_window, Duration::from_secs(600));
+        assert_eq!(c.max_attempts, 5);
+        assert_eq!(c.webhooks[0].events, AlertKind::ALL.to_vec());
+        assert!(c.webhooks[0].key.is_none());
+
+        for bad in [
+            "webhooks:\n  - name: a\n    url: not a url\n",
+            "webhooks:\n  - name: a\n    url: ftp://h/x\n",
+            "webhooks:\n  - name: a\n    url: http://h/x\n  - name: a\n    url: http://h/y\n",
+            "webhooks:\n  - name: a\n    url: http://h/x\n    events: [allow]\n",
+            "webhook: []\n",
+        ] {
+            assert!(AlertConfig::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn routes_by_namespace_and_event() {
+        let c = AlertConfig::parse(
+            "webhooks:\n  - name: a\n    url: http://h/x\n    namespaces: [prod]\n    events: [drop]\n",
+        )
+        .unwrap();
+        let hook = &c.webhooks[0];
+        let flows = [drop_flow("prod", "443"), drop_flow("dev", "443")];
+        let alerts: Vec<_> = drop_alerts(&flows).collect();
+        assert!(hook.wants(&alerts[0]));
+        assert!(!hook.wants(&alerts[1]));
+        let denies: Vec<_> = would_deny_alerts(&[verdict("Egress", false)]).collect();
+        assert!(!hook.wants(&denies[0]));
+    }
+
+    #[test]
+    fn only_new_drops_and_would_denies_alert() {
+        let mut allowed = drop_flow("prod", "443");
+        allowed.decision = Some("ALLOW".to_string());
+        assert_eq!(drop_alerts(&[allowed]).count(), 0);
+
+        let mut allow = verdict("Egress", false);
+        allow.verdict = "Allow".to_string();
+        assert_eq!(
+            would_deny_alerts(&[allow, verdict("Egress", true)]).count(),
+            0
+        );
+
+        // Routed by the namespace of the pod the policy selects.
+        let ns = |d| {
+            would_deny_alerts(&[verdict(d, false)])
+                .next()
+                .unwrap()
+                .namespace
+        };
+        assert_eq!(ns("Egress"), "web");
+        assert_eq!(ns("Ingress"), "data");
+    }
+
+    #[test]
+    fn dedupe_suppresses_repeats_inside_the_window() {
+        let mut d = Deduper::new(Duration::from_secs(60));
+        let now = Instant::now();
+        let key = drop_alerts(&[drop_flow("prod", "443")])
+            .next()
+            .unwrap()
+            .dedupe_key();
+        assert!(d.admit(key.clone(), now));
+        assert!(!d.admit(key.clone(), now + Duration::from_secs(59)));
+        assert!(d.admit(key, now + Duration::from_secs(61)));
+        let other = drop_alerts(&[drop_flow("prod", "80")])
+            .next()
+            .unwrap()
+            .dedupe_key();
+        assert!(d.admit(other, now));
+
+        let mut off = Deduper::new(Duration::ZERO);
+        assert!(off.admit("k".to_string(), now));
+        assert!(off.admit("k".to_string(), now));
+    }
+
+    #[test]
+    fn signs_timestamp_and_body() {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"shh");
+        assert_eq!(
+            signature(&key, 1_760_000_000, br#"{"a":1}"#),
+            "sha256=f5f3d98fe723d34c35649871bfa9eb4530a1b2f197e96e6e8c1b95a984e3d46d"
+        );
+    }
+
+    #[test]
+    fn payload_is_tagged_by_kind() {
+        let alert = drop_alerts(&[drop_flow("prod", "443")]).next().unwrap();
+        let v = serde_json::to_value(&alert).unwrap();
+        assert_eq!(v["kind"], "drop");
+        assert_eq!(v["namespace"], "prod");
+        assert_eq!(v["flow"]["traffic_in_out_ip"], "10.9.9.9");
+        let alert = would_deny_alerts(&[verdict("Egress", false)])
+            .next()
+            .unwrap();
+        let v = serde_json::to_value(&alert).unwrap();
+        assert_eq!(v["kind"], "would_deny");
+        assert_eq!(v["verdict"]["policy_name"], "deny-all");
+    }
+}
@@ -0,0 +1,117 @@
+//! The `/v1` API and the unversioned routes it replaces.
+//!
+//! Both serve the same handlers. `/v1` answers errors with the JSON
+//! envelope from `Error`'s `ResponseError` impl and a list with no rows
+//! with `200 []`. The unversioned routes are deprecated aliases: the
+//! `legacy_alias` middleware turns the envelope back into the plain-text
+//! body they always sent, and marks every response with a `Deprecation`
+//! header pointing at the `/v1` route.
+
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, LINK};
+use actix_web::middleware::Next;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use serde::Serialize;
+
+use crate::Error;
+
+/// Path prefix of the current API version.
+pub const V1_PREFIX: &str = "/v1";
+
+/// When the unversioned routes were deprecated, as an RFC 9745
+/// `Deprecation` value (2026-10-17T00:00:00Z).
+const DEPRECATED_SINCE: &str = "@1792195200";
+
+/// Which set of routes a request came in on. The `/v1` scope stores
+/// `ApiVersion::V1` as app data; anything else is `Legacy`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    #[default]
+    Legacy,
+    V1,
+}
+
+impl ApiVersion {
+    /// Path prefix for links the response points at, e.g. `Location`.
+    pub(crate) fn prefix(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => V1_PREFIX,
+            ApiVersion::Legacy => "",
+        }
+    }
+
+    /// The response for a list read that found no rows: `200 []` on `/v1`,
+    /// the legacy routes' `404 No data found` otherwise.
+    pub(crate) fn no_rows<T: Serialize>(self) -> Result<HttpResponse, Error> {
+        match self {
+            ApiVersion::V1 => Ok(HttpResponse::Ok().json(Vec::<T>::new())),
+            ApiVersion::Legacy => Err(Error::NotFound),
+        }
+    }
+}
+
+impl FromRequest for ApiVersion {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Infallible>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .app_data::<ApiVersion>()
+            .copied()
+            .unwrap_or_default()))
+    }
+}
+
+/// Route the extractors' own rejections (a bad query string, path segment
+/// or JSON body) through `Error` too, so `/v1` has no plain-text errors.
+pub fn v1_extractor_config(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(ApiVersion::V1)
+        .app_data(
+            web::QueryConfig::default()
+                .error_handler(|e, _| Error::UserInputError(e.to_string()).into()),
+        )
+        .app_data(
+            web::PathConfig::default()
+                .error_handler(|e, _| Error::UserInputError(e.to_string()).into()),
+        )
+        .app_data(
+            web::JsonConfig::default()
+                .error_handler(|e, _| Error::UserInputError(e.to_string()).into()),
+        );
+}
+
+/// Answer for a `/v1` path no route matches.
+pub async fn v1_not_found() -> Result<HttpResponse, Error> {
+    Err(Error::NotFound)
+}
+
+/// actix `from_fn` middleware for the unversioned routes.
+pub async fn legacy_alias(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let successor = format!("<{V1_PREFIX}{}>; rel=\"successor-version\"", req.path());
+    let res = next.call(req).await?.map_into_boxed_body();
+    let legacy = res
+        .response()
+        .error()
+        .and_then(|e| e.as_error::<Error>())
+        .map(Error::legacy_response);
+    let mut res = match legacy {
+        Some(body) => res.into_response(body),
+        None => res,
+    };
+    let headers = res.headers_mut();
+    headers.insert(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static(DEPRECATED_SINCE),
+    );
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.insert(LINK, link);
+    }
+    Ok(res)
+}
@@ -1,34 +1,110 @@
 //! Audit-policy bridge: forwards observed pod_traffic events to the
-//! kguardian-evaluator and persists `WouldDeny` verdicts in
-//! `audit_verdicts` for query by the frontend / advisor.
+//! kguardian-evaluator and persists `Allow` / `WouldDeny` verdicts in
+//! `audit_verdicts` for query by the frontend / advisor. Repeats of a
+//! verdict roll up into one row (`hits`, first/last observed) rather
+//! than a row per evaluation. The peer IP of each flow is resolved to
+//! its pod via `pod_details` first, so both sides are attributed.
 //!
 //! Best-effort by design: the evaluator can be down, slow, or absent
 //! and the broker's hot-path ingest must keep working. All errors are
 //! logged at debug/warn and swallowed.
 
+use crate::alerts::Alerter;
+use crate::audit_breaker::{Breaker, BreakerConfig};
+use crate::audit_outbox::{outbox_enabled_from_env, OutboxStats};
+use crate::histogram::Histogram;
 use crate::schema;
-use crate::types::PodTraffic;
+use crate::stream::EventHub;
+use crate::types::{AuditVerdict, PodTraffic};
 use chrono::Utc;
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
 
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
+// Use a dedicated error enum so we can distinguish pool exhaustion
+// from a real diesel error in logs without abusing
+// `diesel::result::Error` variants for unrelated failure modes.
 #[derive(Debug, thiserror::Error)]
-enum AuditInsertError {
+pub(crate) enum AuditInsertError {
     #[error("connection pool: {0}")]
     Pool(#[from] diesel::r2d2::PoolError),
     #[error("insert: {0}")]
     Diesel(#[from] diesel::result::Error),
 }
 
+/// Why one audit evaluation didn't complete.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum AuditEvalError {
+    #[error("evaluator unreachable: {0}")]
+    Unreachable(reqwest::Error),
+    #[error("evaluator returned {0}")]
+    Status(reqwest::StatusCode),
+    #[error("could not decode evaluator response: {0}")]
+    Decode(reqwest::Error),
+    #[error("evaluator returned {got} result sets for {sent} flows")]
+    BatchMismatch { sent: usize, got: usize },
+    #[error("{0}")]
+    Insert(AuditInsertError),
+    #[error("audit verdict task panicked: {0}")]
+    Panicked(tokio::task::JoinError),
+    #[error("evaluator circuit open")]
+    CircuitOpen,
+}
+
+impl AuditEvalError {
+    /// Whether the same flow could succeed later. A 4xx means the
+    /// evaluator rejected the request itself — a broker bug that a
+    /// retry would only repeat.
+    pub(crate) fn retryable(&self) -> bool {
+        !matches!(self, Self::Status(s) if s.is_client_error())
+    }
+
+    fn log(&self) {
+        match self {
+            // Connection-level failures (timeout, refused, DNS):
+            // log at debug since these are commonly transient
+            // (evaluator pod restarting, brief network blip).
+            // Sustained connection failures show up as zero
+            // audit_verdicts rows AND a permit semaphore that
+            // stays near full (no in-flight calls).
+            Self::Unreachable(e) => {
+                debug!(error = %e, "evaluator unreachable; skipping audit eval")
+            }
+            // Non-2xx HTTP from a reachable evaluator means the
+            // evaluator IS responding but rejecting our requests
+            // (4xx = malformed request — a broker bug we want to
+            // know about; 5xx = evaluator-internal failure — also a
+            // signal the operator must surface). Promote to warn so
+            // operators see the problem without needing debug logs.
+            Self::Status(status) => {
+                warn!(status = %status, "evaluator returned non-2xx for audit eval")
+            }
+            Self::Decode(e) => warn!(error = %e, "could not decode evaluator response"),
+            Self::BatchMismatch { .. } => {
+                warn!(error = %self, "evaluator batch response misaligned")
+            }
+            Self::Insert(AuditInsertError::Pool(e)) => {
+                warn!(error = %e, "could not get db conn for audit verdict insert")
+            }
+            Self::Insert(AuditInsertError::Diesel(e)) => {
+                warn!(error = %e, "audit verdict insert failed")
+            }
+            Self::Panicked(e) => warn!(error = %e, "audit verdict task panicked"),
+            // The breaker logged the outage when it opened.
+            Self::CircuitOpen => debug!("evaluator circuit open; skipping audit eval"),
+        }
+    }
+}
+
 /// Wire format consumed by `POST /evaluate` — must match
 /// `evaluator/pkg/matcher.Flow` exactly.
 #[derive(Debug, Serialize)]
@@ -59,6 +135,18 @@ struct EvaluateResponse {
     results: Vec<VerdictResult>,
 }
 
+/// Body of `POST /evaluate/batch` (`evaluator/pkg/server`).
+#[derive(Debug, Serialize)]
+struct EvaluateBatchRequest<'a> {
+    flows: &'a [Flow<'a>],
+}
+
+/// `results[i]` holds the verdicts for `flows[i]`.
+#[derive(Debug, Deserialize)]
+struct EvaluateBatchResponse {
+    results: Vec<Vec<VerdictResult>>,
+}
+
 #[derive(Debug, Deserialize)]
 struct VerdictResult {
     #[serde(rename = "policyNamespace")]
@@ -90,10 +178,208 @@ struct AuditVerdictInsert {
     dst_port: i32,
     protocol: String,
     reason: Option<String>,
-    observed_at: chrono::NaiveDateTime,
+    first_observed_at: chrono::NaiveDateTime,
+    last_observed_at: chrono::NaiveDateTime,
     /// "Allow" or "WouldDeny". NotApplicable verdicts are dropped at
     /// the filter site and never reach this struct.
     verdict: String,
+    /// 0: a new row starts uncounted and `count_flows` adds its hits.
+    hits: i64,
+    /// pod_traffic uuids of the flows that produced this verdict.
+    #[diesel(skip_insertion)]
+    traffic_uuids: Vec<String>,
+}
+
+/// The rollup key: the columns of `idx_audit_verdicts_key`.
+type VerdictKey = (
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    i32,
+    String,
+    String,
+);
+
+impl AuditVerdictInsert {
+    fn key(&self) -> VerdictKey {
+        (
+            self.policy_uid.clone(),
+            self.direction.clone(),
+            self.src_namespace.clone(),
+            self.src_pod.clone(),
+            self.dst_namespace.clone(),
+            self.dst_pod.clone(),
+            self.dst_port,
+            self.protocol.clone(),
+            self.verdict.clone(),
+        )
+    }
+}
+
+fn stored_key(v: &AuditVerdict) -> VerdictKey {
+    (
+        v.policy_uid.clone(),
+        v.direction.clone(),
+        v.src_namespace.clone(),
+        v.src_pod.clone(),
+        v.dst_namespace.clone(),
+        v.dst_pod.clone(),
+        v.dst_port,
+        v.protocol.clone(),
+        v.verdict.clone(),
+    )
+}
+
+/// Fold verdicts sharing a rollup key into one row — ON CONFLICT DO
+/// UPDATE errors if one statement touches a row twice, which an
+/// evaluator listing the same policy and direction twice would cause.
+fn collapse_verdicts(rows: Vec<AuditVerdictInsert>) -> Vec<AuditVerdictInsert> {
+    let mut by_key: std::collections::BTreeMap<VerdictKey, AuditVerdictInsert> =
+        std::collections::BTreeMap::new();
+    for v in rows {
+        match by_key.entry(v.key()) {
+            std::collections::btree_map::Entry::Occupied(mut e) => {
+                let row = e.get_mut();
+                row.first_observed_at = row.first_observed_at.min(v.first_observed_at);
+                row.last_observed_at = row.last_observed_at.max(v.last_observed_at);
+                row.traffic_uuids.extend(v.traffic_uuids);
+                if v.reason.is_some() {
+                    row.reason = v.reason;
+                }
+            }
+            std::collections::btree_map::Entry::Vacant(e) => {
+                e.insert(v);
+            }
+        }
+    }
+    by_key.into_values().collect()
+}
+
+/// Insert the verdicts, or widen the observed span of the rows they
+/// repeat, then count their flows into `hits`. The reason is
+/// overwritten: it describes the policy as it was at the latest
+/// evaluation.
+fn upsert_verdicts(
+    conn: &mut PgConnection,
+    rows: &[AuditVerdictInsert],
+) -> Result<Vec<AuditVerdict>, diesel::result::Error> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::Timestamp;
+    use diesel::upsert::excluded;
+    use schema::audit_verdicts::dsl::*;
+
+    conn.transaction(|conn| {
+        let mut stored: Vec<AuditVerdict> = diesel::insert_into(audit_verdicts)
+            .values(rows)
+            .on_conflict((
+                policy_uid,
+                direction,
+                src_namespace,
+                src_pod,
+                dst_namespace,
+                dst_pod,
+                dst_port,
+                protocol,
+                verdict,
+            ))
+            .do_update()
+            .set((
+                reason.eq(excluded(reason)),
+                first_observed_at.eq(sql::<Timestamp>(
+                    "LEAST(audit_verdicts.first_observed_at, EXCLUDED.first_observed_at)",
+                )),
+                last_observed_at.eq(sql::<Timestamp>(
+                    "GREATEST(audit_verdicts.last_observed_at, EXCLUDED.last_observed_at)",
+                )),
+            ))
+            .returning(AuditVerdict::as_returning())
+            .get_results(conn)?;
+        let ids: HashMap<VerdictKey, i64> = stored.iter().map(|v| (stored_key(v), v.id)).collect();
+        let (verdict_ids, uuids): (Vec<i64>, Vec<String>) = rows
+            .iter()
+            .filter_map(|r| ids.get(&r.key()).map(|&vid| (vid, &r.traffic_uuids)))
+            .flat_map(|(vid, uuids)| uuids.iter().map(move |u| (vid, u.clone())))
+            .unzip();
+        let counted = count_flows(conn, verdict_ids, uuids)?;
+        for v in &mut stored {
+            if let Some(&h) = counted.get(&v.id) {
+                v.hits = h;
+            }
+        }
+        Ok(stored)
+    })
+}
+
+#[derive(QueryableByName)]
+struct CountedHits {
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    id: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    hits: i64,
+}
+
+/// Record each `(verdict, flow)` pair in audit_verdict_flows and add a
+/// hit per pair not recorded before, so re-evaluating a flow (a replay
+/// over it, an outbox retry) never counts it twice. Flows pruned from
+/// pod_traffic meanwhile are skipped. Returns the new `hits` of the
+/// rows that grew.
+fn count_flows(
+    conn: &mut PgConnection,
+    verdict_ids: Vec<i64>,
+    traffic_uuids: Vec<String>,
+) -> Result<HashMap<i64, i64>, diesel::result::Error> {
+    use diesel::sql_types::{Array, BigInt, Text};
+
+    if verdict_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let rows: Vec<CountedHits> = diesel::sql_query(
+        "WITH counted AS ( \
+           INSERT INTO audit_verdict_flows (verdict_id, traffic_uuid) \
+           SELECT n.verdict_id, n.traffic_uuid \
+             FROM unnest($1::bigint[], $2::varchar[]) AS n(verdict_id, traffic_uuid) \
+             JOIN pod_traffic p ON p.uuid = n.traffic_uuid \
+           ON CONFLICT DO NOTHING \
+           RETURNING verdict_id \
+         ) \
+         UPDATE audit_verdicts v SET hits = v.hits + c.n \
+           FROM (SELECT verdict_id, COUNT(*) AS n FROM counted GROUP BY verdict_id) c \
+          WHERE v.id = c.verdict_id \
+         RETURNING v.id, v.hits",
+    )
+    .bind::<Array<BigInt>, _>(verdict_ids)
+    .bind::<Array<Text>, _>(traffic_uuids)
+    .load(conn)?;
+    Ok(rows.into_iter().map(|r| (r.id, r.hits)).collect())
+}
+
+/// How one evaluation's verdicts are stored. The default is live
+/// ingest: every policy's verdict, stamped now.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct EvalScope<'a> {
+    /// Keep only the verdicts of this policy: `name`, or
+    /// `namespace/name` to tell same-named policies apart.
+    pub(crate) policy: Option<&'a str>,
+    /// Stamp verdicts with the flow's own first/last sighting instead
+    /// of now, so a replayed flow lands where it was observed.
+    pub(crate) historical: bool,
+}
+
+impl EvalScope<'_> {
+    fn observed(
+        &self,
+        traffic: &PodTraffic,
+        now: chrono::NaiveDateTime,
+    ) -> (chrono::NaiveDateTime, chrono::NaiveDateTime) {
+        if self.historical {
+            (traffic.time_stamp, traffic.last_seen)
+        } else {
+            (now, now)
+        }
+    }
 }
 
 /// Long-lived client cached by the actix application state. Holds the
@@ -118,6 +404,70 @@ pub struct AuditClient {
     /// Count of flows shed because the queue was full (evaluator backed up).
     /// Exposed as `broker_audit_dropped_total`.
     dropped: Arc<AtomicU64>,
+    /// Peer IP → pod lookups, shared by every audit task.
+    peers: Arc<PeerCache>,
+    /// How the dispatchers coalesce flows into `/evaluate/batch` calls.
+    batch: BatchConfig,
+    /// Set when the evaluator answered `/evaluate/batch` with 404; batching
+    /// is off until then.
+    batch_unsupported_until: Arc<std::sync::Mutex<Option<Instant>>>,
+    /// Flows per evaluator call, exposed as `broker_audit_batch_size`.
+    batch_sizes: Arc<Histogram>,
+    /// Set when `AUDIT_OUTBOX_ENABLED` routes new flows through the
+    /// `audit_outbox` table instead of `tx`. Holds the outbox metrics.
+    outbox: Option<Arc<OutboxStats>>,
+    /// Evaluator circuit breaker and call metrics (audit_breaker.rs).
+    breaker: Arc<Breaker>,
+    /// Where stored verdicts are published for `/stream/verdicts`.
+    events: EventHub,
+    /// Webhook alerts for new `WouldDeny` verdicts (alerts.rs).
+    alerts: Alerter,
+}
+
+/// The pod behind a flow's peer IP, as `pod_details` has it.
+#[derive(Debug, Clone, PartialEq)]
+struct PeerPod {
+    namespace: Option<String>,
+    name: String,
+}
+
+/// How long a peer lookup is reused. Pod IPs are recycled, so this
+/// bounds how long a flow can be attributed to the IP's previous pod.
+const PEER_CACHE_TTL: Duration = Duration::from_secs(30);
+/// Entries kept before expired ones are swept. Flows from one busy
+/// cluster rarely touch more distinct peers than this inside a TTL.
+const PEER_CACHE_CAPACITY: usize = 4096;
+
+/// Small TTL cache of peer IP → pod, so a steady flow doesn't cost a
+/// pod_details query per evaluation. Misses (external IPs) are cached
+/// too; a failed lookup is not.
+#[derive(Default)]
+struct PeerCache {
+    entries: std::sync::Mutex<HashMap<String, (Instant, Option<PeerPod>)>>,
+}
+
+impl PeerCache {
+    /// `Some(hit)` while fresh; `None` when the IP must be looked up.
+    fn get(&self, ip: &str, now: Instant) -> Option<Option<PeerPod>> {
+        let entries = self.entries.lock().unwrap_or_else(|p| p.into_inner());
+        entries
+            .get(ip)
+            .filter(|(at, _)| now.duration_since(*at) < PEER_CACHE_TTL)
+            .map(|(_, pod)| pod.clone())
+    }
+
+    fn insert(&self, ip: String, pod: Option<PeerPod>, now: Instant) {
+        let mut entries = self.entries.lock().unwrap_or_else(|p| p.into_inner());
+        if entries.len() >= PEER_CACHE_CAPACITY && !entries.contains_key(&ip) {
+            entries.retain(|_, (at, _)| now.duration_since(*at) < PEER_CACHE_TTL);
+            if entries.len() >= PEER_CACHE_CAPACITY {
+                // All fresh: drop the lot rather than track recency.
+                // The next TTL's worth of lookups refills it.
+                entries.clear();
+            }
+        }
+        entries.insert(ip, (now, pod));
+    }
 }
 
 /// Maximum concurrent audit /evaluate calls. Sized roughly to twice
@@ -131,6 +481,58 @@ const AUDIT_INFLIGHT_PERMITS: usize = 16;
 /// tasks. Audit is best-effort, so shedding is the correct overload response.
 const AUDIT_QUEUE_CAPACITY: usize = 2048;
 
+/// Default flows per `/evaluate/batch` call. Large enough that an ingest
+/// burst after a controller restart costs a few dozen round-trips per
+/// thousand flows; small enough that one slow batch holds little work.
+const DEFAULT_AUDIT_BATCH_MAX_FLOWS: usize = 64;
+/// Upper clamp, matching the evaluator's `MaxBatchFlows`.
+const MAX_AUDIT_BATCH_MAX_FLOWS: usize = 1000;
+/// Default time a partial batch waits for more flows. Only paid when
+/// traffic is sparse; a burst fills the batch first.
+const DEFAULT_AUDIT_BATCH_LINGER_MS: u64 = 20;
+/// How long to stay on per-flow calls after a 404 from `/evaluate/batch`
+/// before probing again.
+const BATCH_REPROBE_INTERVAL: Duration = Duration::from_secs(600);
+/// `broker_audit_batch_size` bucket bounds.
+const BATCH_SIZE_BUCKETS: &[f64] = &[1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0, 512.0];
+
+/// Batch coalescing knobs (`AUDIT_BATCH_MAX_FLOWS`, `AUDIT_BATCH_LINGER_MS`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BatchConfig {
+    pub(crate) max_flows: usize,
+    pub(crate) linger: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_flows: DEFAULT_AUDIT_BATCH_MAX_FLOWS,
+            linger: Duration::from_millis(DEFAULT_AUDIT_BATCH_LINGER_MS),
+        }
+    }
+}
+
+impl BatchConfig {
+    /// Read the env with the usual trim; `AUDIT_BATCH_MAX_FLOWS=1`
+    /// turns batching off.
+    fn from_env() -> Self {
+        let max_flows = std::env::var("AUDIT_BATCH_MAX_FLOWS")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .map(|n| n.clamp(1, MAX_AUDIT_BATCH_MAX_FLOWS))
+            .unwrap_or(DEFAULT_AUDIT_BATCH_MAX_FLOWS);
+        let linger_ms = std::env::var("AUDIT_BATCH_LINGER_MS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(|n| n.min(1000))
+            .unwrap_or(DEFAULT_AUDIT_BATCH_LINGER_MS);
+        Self {
+            max_flows,
+            linger: Duration::from_millis(linger_ms),
+        }
+    }
+}
+
 /// Default audit eval timeout in milliseconds. 500ms is plenty for
 /// an in-cluster evaluator (matcher is in-memory, sub-ms); operators
 /// running the evaluator across cells / regions / VPNs may need more.
@@ -186,17 +588,33 @@ fn is_persistable_verdict(verdict: &str) -> bool {
     verdict == "Allow" || verdict == "WouldDeny"
 }
 
-fn build_flow_for_traffic(traffic: &PodTraffic) -> Option<Flow<'_>> {
+/// Whether `r` comes from the policy `filter` names: `name` matches it
+/// in any namespace, `namespace/name` only in that one.
+fn is_policy(filter: &str, r: &VerdictResult) -> bool {
+    match filter.split_once('/') {
+        Some((namespace, name)) => r.policy_namespace == namespace && r.policy_name == name,
+        None => r.policy_name == filter,
+    }
+}
+
+/// `peer` is the pod behind `traffic_in_out_ip`, when `pod_details`
+/// knows one; without it the evaluator can only match the peer by IP,
+/// so podSelector / namespaceSelector peer rules never match it.
+fn build_flow_for_traffic<'a>(
+    traffic: &'a PodTraffic,
+    peer: Option<&'a PeerPod>,
+) -> Option<Flow<'a>> {
     let raw_traffic_type = traffic.traffic_type.as_deref().unwrap_or("");
     let normalised = raw_traffic_type.trim().to_ascii_uppercase();
+    let peer_ns = peer.and_then(|p| p.namespace.as_deref());
+    let peer_name = peer.map(|p| p.name.as_str());
     // INGRESS: pod_name/pod_namespace is the destination.
     // EGRESS: pod_name/pod_namespace is the source.
     let (src_ns, src_name, src_ip, dst_ns, dst_name, dst_ip, dst_port_str) =
         match normalised.as_str() {
             "INGRESS" => (
-                None,
-                // We don't know the source pod identity from PodTraffic alone.
-                None,
+                peer_ns,
+                peer_name,
                 traffic.traffic_in_out_ip.as_deref(),
                 traffic.pod_namespace.as_deref(),
                 traffic.pod_name.as_deref(),
@@ -207,9 +625,8 @@ fn build_flow_for_traffic(traffic: &PodTraffic) -> Option<Flow<'_>> {
                 traffic.pod_namespace.as_deref(),
                 traffic.pod_name.as_deref(),
                 traffic.pod_ip.as_deref(),
-                None,
-                // Destination pod is identified by IP only here.
-                None,
+                peer_ns,
+                peer_name,
                 traffic.traffic_in_out_ip.as_deref(),
                 traffic.traffic_in_out_port.as_deref().unwrap_or("0"),
             ),
@@ -236,6 +653,11 @@ fn build_flow_for_traffic(traffic: &PodTraffic) -> Option<Flow<'_>> {
 /// semaphore. Acquiring a permit *before* receiving keeps the channel (not an
 /// unbounded pile of spawned tasks) as the buffer: at most `permits` evals run
 /// at once, at most `capacity` flows wait, and ingest sheds beyond that.
+///
+/// Each permit carries a batch: after the first flow arrives the dispatcher
+/// keeps taking flows until the batch is full or `linger` has passed, so a
+/// burst becomes a few `/evaluate/batch` calls instead of thousands of
+/// single-flow ones.
 fn spawn_audit_dispatcher(client: AuditClient, pool: DbPool, mut rx: mpsc::Receiver<PodTraffic>) {
     actix_web::rt::spawn(async move {
         loop {
@@ -243,13 +665,24 @@ fn spawn_audit_dispatcher(client: AuditClient, pool: DbPool, mut rx: mpsc::Recei
                 Ok(p) => p,
                 Err(_) => break, // semaphore closed → shutting down
             };
-            let Some(traffic) = rx.recv().await else {
+            let Some(first) = rx.recv().await else {
                 break; // all senders dropped → shutting down
             };
+            let mut batch = vec![first];
+            if client.batching() {
+                let BatchConfig { max_flows, linger } = client.batch_config();
+                let deadline = tokio::time::Instant::now() + linger;
+                while batch.len() < max_flows {
+                    match tokio::time::timeout_at(deadline, rx.recv()).await {
+                        Ok(Some(traffic)) => batch.push(traffic),
+                        Ok(None) | Err(_) => break,
+                    }
+                }
+            }
             let worker = client.clone();
             let pool = pool.clone();
             actix_web::rt::spawn(async move {
-                worker.evaluate_and_persist(pool, traffic).await;
+                worker.evaluate_and_persist(pool, batch).await;
                 drop(permit);
             });
         }
@@ -290,17 +723,47 @@ impl AuditClient {
             in_flight: std::sync::Arc::new(tokio::sync::Semaphore::new(permits)),
             tx: None,
             dropped: Arc::new(AtomicU64::new(0)),
+            peers: Arc::new(PeerCache::default()),
+            batch: BatchConfig::from_env(),
+            batch_unsupported_until: Arc::new(std::sync::Mutex::new(None)),
+            batch_sizes: Arc::new(Histogram::new(BATCH_SIZE_BUCKETS)),
+            outbox: (enabled && outbox_enabled_from_env())
+                .then(|| Arc::new(OutboxStats::default())),
+            breaker: Arc::new(Breaker::new(BreakerConfig::from_env())),
+            events: EventHub::default(),
+            alerts: Alerter::default(),
         }
     }
 
+    /// Publish stored verdicts to `events` (stream.rs) as well. Call
+    /// before `start()`, which hands clones to the dispatchers.
+    pub fn with_events(mut self, events: EventHub) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Report stored verdicts to `alerts` as well. Call before `start()`.
+    pub fn with_alerts(mut self, alerts: Alerter) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
     /// Wire up the bounded ingest→audit queue and spawn the dispatcher that
     /// drains it. Call once at startup, after the DB pool exists. No-op (and no
     /// queue) when audit is disabled, so `try_enqueue` silently drops. Returns
     /// self so it composes: `AuditClient::from_env().start(pool)`.
+    ///
+    /// With the outbox enabled there is no queue either: ingest writes
+    /// `audit_outbox` rows and the outbox dispatcher is started instead.
     pub fn start(mut self, pool: DbPool) -> Self {
         if !self.enabled {
             return self;
         }
+        crate::audit_breaker::spawn_probe(self.clone());
+        if self.outbox.is_some() {
+            crate::audit_outbox::spawn(self.clone(), pool);
+            return self;
+        }
         let capacity = std::env::var("AUDIT_QUEUE_CAPACITY")
             .ok()
             .and_then(|v| v.trim().parse::<usize>().ok())
@@ -339,6 +802,77 @@ impl AuditClient {
     pub fn enabled(&self) -> bool {
         self.enabled
     }
+
+    /// Whether new flows go through the `audit_outbox` table (and
+    /// ingest should write it) rather than the in-memory queue.
+    pub fn outbox_enabled(&self) -> bool {
+        self.outbox.is_some()
+    }
+
+    pub(crate) fn outbox_stats(&self) -> Option<Arc<OutboxStats>> {
+        self.outbox.clone()
+    }
+
+    /// Rows waiting in `audit_outbox`, as of the last refresh. Exposed
+    /// as `broker_audit_outbox_depth`; 0 when the outbox is off.
+    pub fn outbox_depth(&self) -> u64 {
+        self.outbox.as_ref().map_or(0, |s| s.depth())
+    }
+
+    /// Age of the oldest `audit_outbox` row in seconds. Exposed as
+    /// `broker_audit_outbox_oldest_age_seconds`.
+    pub fn outbox_oldest_age_secs(&self) -> u64 {
+        self.outbox.as_ref().map_or(0, |s| s.oldest_age_secs())
+    }
+
+    /// Outbox rows given up on (evaluator 4xx or attempts exhausted).
+    /// Exposed as `broker_audit_outbox_abandoned_total`.
+    pub fn outbox_abandoned_count(&self) -> u64 {
+        self.outbox.as_ref().map_or(0, |s| s.abandoned())
+    }
+
+    /// Flows per evaluator call. Exposed as `broker_audit_batch_size`.
+    pub fn batch_size_histogram(&self) -> &Histogram {
+        &self.batch_sizes
+    }
+
+    pub(crate) fn breaker(&self) -> &Breaker {
+        &self.breaker
+    }
+
+    /// Evaluator health, breaker state, error counters and call latency
+    /// in Prometheus text, for `/metrics`.
+    pub fn render_evaluator_metrics(&self) -> String {
+        self.breaker.render(self.enabled)
+    }
+
+    /// `GET /healthz` on the evaluator, for the breaker's probe.
+    pub(crate) async fn probe(&self) -> bool {
+        let url = format!("{}/healthz", self.base_url.trim_end_matches('/'));
+        match self.http.get(&url).send().await {
+            Ok(resp) => resp.status().is_success(),
+            Err(_) => false,
+        }
+    }
+
+    /// Run one evaluator call through the breaker: refused outright
+    /// while it is open, otherwise timed and recorded.
+    async fn guarded<T>(
+        &self,
+        call: impl std::future::Future<Output = Result<T, AuditEvalError>>,
+    ) -> Result<T, AuditEvalError> {
+        if !self.breaker.admit() {
+            return Err(AuditEvalError::CircuitOpen);
+        }
+        let started = Instant::now();
+        let result = call.await;
+        self.breaker.record(&result, started.elapsed());
+        result
+    }
+
+    pub(crate) fn in_flight(&self) -> Arc<tokio::sync::Semaphore> {
+        self.in_flight.clone()
+    }
     pub fn base_url(&self) -> &str {
         &self.base_url
     }
@@ -350,129 +884,414 @@ impl AuditClient {
         self.in_flight.available_permits()
     }
 
-    /// Evaluate one flow against the evaluator and persist any verdicts.
+    /// The pod behind `ip`, from the cache or `pod_details` (live pod
+    /// first, then the newest row, as enrich.rs resolves peers). A
+    /// failed lookup degrades to an IP-only flow rather than skipping
+    /// the evaluation, and isn't cached so the next flow retries.
+    async fn resolve_peer(&self, pool: &DbPool, ip: &str) -> Option<PeerPod> {
+        self.resolve_peers(pool, &[ip]).await.remove(ip).flatten()
+    }
+
+    /// `resolve_peer` for a batch: cache hits first, then one
+    /// `enrich::resolve_peers` query for the distinct misses. IPs whose
+    /// lookup failed are missing from the map.
+    async fn resolve_peers(&self, pool: &DbPool, ips: &[&str]) -> HashMap<String, Option<PeerPod>> {
+        let now = Instant::now();
+        let mut resolved = HashMap::new();
+        let mut misses = Vec::new();
+        for &ip in ips {
+            if resolved.contains_key(ip) || misses.contains(&ip) {
+                continue;
+            }
+            match self.peers.get(ip, now) {
+                Some(hit) => {
+                    resolved.insert(ip.to_string(), hit);
+                }
+                None => misses.push(ip),
+            }
+        }
+        if misses.is_empty() {
+            return resolved;
+        }
+
+        let pool = pool.clone();
+        let misses: Vec<String> = misses.into_iter().map(str::to_string).collect();
+        let result = tokio::task::spawn_blocking(move || -> Result<Vec<_>, String> {
+            // Same short acquire timeout as the verdict insert below.
+            let mut conn = pool
+                .get_timeout(Duration::from_secs(1))
+                .map_err(|e| e.to_string())?;
+            let refs: Vec<&str> = misses.iter().map(String::as_str).collect();
+            let mut peers =
+                crate::enrich::resolve_peers(&mut conn, &refs).map_err(|e| e.to_string())?;
+            Ok(misses
+                .into_iter()
+                .map(|ip| {
+                    let pod = peers
+                        .remove(&ip)
+                        .filter(|p| p.peer_kind == "pod")
+                        .and_then(|p| {
+                            p.peer_name.map(|name| PeerPod {
+                                namespace: p.peer_namespace,
+                                name,
+                            })
+                        });
+                    (ip, pod)
+                })
+                .collect())
+        })
+        .await;
+        match result {
+            Ok(Ok(found)) => {
+                let now = Instant::now();
+                for (ip, pod) in found {
+                    self.peers.insert(ip.clone(), pod.clone(), now);
+                    resolved.insert(ip, pod);
+                }
+            }
+            Ok(Err(e)) => debug!(error = %e, "peer lookup failed; evaluating by IP only"),
+            Err(e) => warn!(error = %e, "peer lookup task panicked"),
+        }
+        resolved
+    }
+
+    /// Evaluate a batch of flows and persist any verdicts.
     /// Best-effort: errors are logged but never propagated — the ingest path
     /// must not stall on evaluator hiccups. Concurrency is bounded by the
     /// caller: the audit dispatcher holds an `in_flight` permit for the duration
     /// of this call, so a large ingest burst can't create unbounded concurrent
     /// /evaluate round-trips. Called only by the in-crate dispatcher.
-    pub(crate) async fn evaluate_and_persist(&self, pool: DbPool, traffic: PodTraffic) {
+    pub(crate) async fn evaluate_and_persist(&self, pool: DbPool, batch: Vec<PodTraffic>) {
+        let results = self.evaluate_batch(pool, &batch).await;
+        // A failed batch call hands every flow the same error; log it once.
+        let mut last: Option<&Arc<AuditEvalError>> = None;
+        for e in results.iter().filter_map(|r| r.as_ref().err()) {
+            if !last.is_some_and(|l| Arc::ptr_eq(l, e)) {
+                e.log();
+            }
+            last = Some(e);
+        }
+    }
+
+    /// Whether the dispatchers should coalesce flows. Off when
+    /// `AUDIT_BATCH_MAX_FLOWS` is 1, and for a while after the evaluator
+    /// answered `/evaluate/batch` with 404 (an evaluator older than the
+    /// broker).
+    pub(crate) fn batching(&self) -> bool {
+        if self.batch.max_flows <= 1 {
+            return false;
+        }
+        let mut until = self
+            .batch_unsupported_until
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        match *until {
+            Some(t) if Instant::now() < t => false,
+            Some(_) => {
+                // Re-probe: the evaluator may have been upgraded since.
+                *until = None;
+                true
+            }
+            None => true,
+        }
+    }
+
+    pub(crate) fn batch_config(&self) -> BatchConfig {
+        self.batch
+    }
+
+    /// Evaluate `batch` with one `POST /evaluate/batch` and persist the
+    /// verdicts with one upsert. Returns one result per input flow: a
+    /// failed batch call fails every flow with the same shared error.
+    /// Falls back to per-flow `/evaluate` calls when the evaluator has
+    /// no batch endpoint, and when it rejects the batch with a 4xx, so
+    /// one bad flow can't fail the rest.
+    pub(crate) async fn evaluate_batch(
+        &self,
+        pool: DbPool,
+        batch: &[PodTraffic],
+    ) -> Vec<Result<(), Arc<AuditEvalError>>> {
+        self.evaluate_batch_scoped(pool, batch, EvalScope::default())
+            .await
+    }
+
+    /// `evaluate_batch` with the verdicts stored per `scope` (replay).
+    pub(crate) async fn evaluate_batch_scoped(
+        &self,
+        pool: DbPool,
+        batch: &[PodTraffic],
+        scope: EvalScope<'_>,
+    ) -> Vec<Result<(), Arc<AuditEvalError>>> {
+        if batch.len() <= 1 || !self.batching() {
+            return self.evaluate_each(pool, batch, scope).await;
+        }
         if !self.enabled {
-            return;
+            return batch.iter().map(|_| Ok(())).collect();
+        }
+
+        let ips: Vec<&str> = batch
+            .iter()
+            .filter_map(|t| t.traffic_in_out_ip.as_deref())
+            .collect();
+        let peers = self.resolve_peers(&pool, &ips).await;
+        let (sources, flows): (Vec<&PodTraffic>, Vec<Flow>) = batch
+            .iter()
+            .filter_map(|traffic| {
+                let peer = traffic
+                    .traffic_in_out_ip
+                    .as_deref()
+                    .and_then(|ip| peers.get(ip))
+                    .and_then(Option::as_ref);
+                build_flow_for_traffic(traffic, peer).map(|f| (traffic, f))
+            })
+            .unzip();
+        if flows.is_empty() {
+            return batch.iter().map(|_| Ok(())).collect();
+        }
+
+        match self.post_batch(&flows).await {
+            Ok(sets) => {
+                let now = Utc::now().naive_utc();
+                let rows = sources
+                    .iter()
+                    .zip(&flows)
+                    .zip(sets)
+                    .flat_map(|((traffic, flow), results)| {
+                        verdict_rows(
+                            traffic,
+                            flow,
+                            results,
+                            scope.observed(traffic, now),
+                            scope.policy,
+                        )
+                    })
+                    .collect();
+                let res = self.persist(pool, rows, scope).await.map_err(Arc::new);
+                batch.iter().map(|_| res.clone()).collect()
+            }
+            Err(AuditEvalError::Status(reqwest::StatusCode::NOT_FOUND)) => {
+                info!(
+                    retry_in_secs = BATCH_REPROBE_INTERVAL.as_secs(),
+                    "evaluator has no /evaluate/batch; falling back to per-flow calls"
+                );
+                *self
+                    .batch_unsupported_until
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner()) =
+                    Some(Instant::now() + BATCH_REPROBE_INTERVAL);
+                self.evaluate_each(pool, batch, scope).await
+            }
+            Err(e) if !e.retryable() => {
+                // The evaluator rejected the call, most likely over one
+                // flow it can't parse. Ask about each flow on its own so
+                // only the flows it rejects are failed (and abandoned by
+                // the outbox), not every flow that shared the batch.
+                debug!(
+                    error = %e,
+                    flows = batch.len(),
+                    "evaluator rejected batch; retrying flows one by one"
+                );
+                self.evaluate_each(pool, batch, scope).await
+            }
+            Err(e) => {
+                let e = Arc::new(e);
+                batch.iter().map(|_| Err(e.clone())).collect()
+            }
+        }
+    }
+
+    /// One `/evaluate` call per flow, in order.
+    async fn evaluate_each(
+        &self,
+        pool: DbPool,
+        batch: &[PodTraffic],
+        scope: EvalScope<'_>,
+    ) -> Vec<Result<(), Arc<AuditEvalError>>> {
+        let mut results = Vec::with_capacity(batch.len());
+        for traffic in batch {
+            results.push(
+                self.evaluate(pool.clone(), traffic, scope)
+                    .await
+                    .map_err(Arc::new),
+            );
+        }
+        results
+    }
+
+    /// One `POST /evaluate/batch`; returns a result set per flow.
+    async fn post_batch(
+        &self,
+        flows: &[Flow<'_>],
+    ) -> Result<Vec<Vec<VerdictResult>>, AuditEvalError> {
+        let url = format!("{}/evaluate/batch", self.base_url.trim_end_matches('/'));
+        self.guarded(async {
+            self.batch_sizes.observe(flows.len() as f64);
+            let resp = self
+                .http
+                .post(&url)
+                .json(&EvaluateBatchRequest { flows })
+                .send()
+                .await
+                .map_err(AuditEvalError::Unreachable)?;
+            if !resp.status().is_success() {
+                return Err(AuditEvalError::Status(resp.status()));
+            }
+            let body: EvaluateBatchResponse = resp.json().await.map_err(AuditEvalError::Decode)?;
+            if body.results.len() != flows.len() {
+                return Err(AuditEvalError::BatchMismatch {
+                    sent: flows.len(),
+                    got: body.results.len(),
+                });
+            }
+            Ok(body.results)
+        })
+        .await
+    }
+
+    /// The single-flow form of `evaluate_batch_scoped`, used when
+    /// batching is off or unsupported. A flow the evaluator can't be
+    /// asked about (unknown traffic_type) is `Ok`: there is nothing to
+    /// retry.
+    async fn evaluate(
+        &self,
+        pool: DbPool,
+        traffic: &PodTraffic,
+        scope: EvalScope<'_>,
+    ) -> Result<(), AuditEvalError> {
+        if !self.enabled {
+            return Ok(());
         }
         let url = format!("{}/evaluate", self.base_url.trim_end_matches('/'));
 
-        let flow = match build_flow_for_traffic(&traffic) {
+        let peer = match traffic.traffic_in_out_ip.as_deref() {
+            Some(ip) => self.resolve_peer(&pool, ip).await,
+            None => None,
+        };
+        let flow = match build_flow_for_traffic(traffic, peer.as_ref()) {
             Some(f) => f,
             None => {
                 debug!(
                     raw = traffic.traffic_type.as_deref().unwrap_or(""),
                     "skipping audit eval for unknown traffic_type"
                 );
-                return;
+                return Ok(());
             }
         };
 
-        let resp = match self.http.post(&url).json(&flow).send().await {
-            Ok(r) => r,
-            Err(e) => {
-                // Connection-level failures (timeout, refused, DNS):
-                // log at debug since these are commonly transient
-                // (evaluator pod restarting, brief network blip).
-                // Sustained connection failures show up as zero
-                // audit_verdicts rows AND a permit semaphore that
-                // stays near full (no in-flight calls).
-                debug!(error = %e, "evaluator unreachable; skipping audit eval");
-                return;
-            }
-        };
-        if !resp.status().is_success() {
-            // Non-2xx HTTP from a reachable evaluator means the
-            // evaluator IS responding but rejecting our requests
-            // (4xx = malformed request — a broker bug we want to
-            // know about; 5xx = evaluator-internal failure — also a
-            // signal the operator must surface). Promote to warn so
-            // operators see the problem without needing debug logs.
-            tracing::warn!(status = %resp.status(), url = %url, "evaluator returned non-2xx for audit eval");
-            return;
-        }
-        let body: EvaluateResponse = match resp.json().await {
-            Ok(b) => b,
-            Err(e) => {
-                warn!(error = %e, "could not decode evaluator response");
-                return;
-            }
-        };
-
-        // Persist Allow + WouldDeny verdicts so operators can preview
-        // both sides of policy impact (what's permitted, what would be
-        // blocked). NotApplicable is dropped — every flow checks
-        // against every cluster-scoped policy plus all namespaced ones
-        // in scope, and most produce NotApplicable; storing them all
-        // would inflate audit_verdicts by 1-2 orders of magnitude with
-        // no analytical value.
-        let now = Utc::now().naive_utc();
-        let to_insert: Vec<AuditVerdictInsert> = body
-            .results
-            .into_iter()
-            .filter(|r| is_persistable_verdict(&r.verdict))
-            .map(|r| AuditVerdictInsert {
-                policy_uid: r.policy_uid,
-                policy_namespace: r.policy_namespace,
-                policy_name: r.policy_name,
-                direction: r.direction,
-                src_namespace: flow.src_pod_namespace.map(str::to_owned),
-                src_pod: flow.src_pod_name.map(str::to_owned),
-                dst_namespace: flow.dst_pod_namespace.map(str::to_owned),
-                dst_pod: flow.dst_pod_name.map(str::to_owned),
-                dst_port: flow.dst_port,
-                protocol: flow.protocol.to_owned(),
-                reason: if r.reason.is_empty() {
-                    None
-                } else {
-                    Some(r.reason)
-                },
-                observed_at: now,
-                verdict: r.verdict,
+        let body: EvaluateResponse = self
+            .guarded(async {
+                self.batch_sizes.observe(1.0);
+                let resp = self
+                    .http
+                    .post(&url)
+                    .json(&flow)
+                    .send()
+                    .await
+                    .map_err(AuditEvalError::Unreachable)?;
+                if !resp.status().is_success() {
+                    return Err(AuditEvalError::Status(resp.status()));
+                }
+                resp.json().await.map_err(AuditEvalError::Decode)
             })
-            .collect();
+            .await?;
 
-        if to_insert.is_empty() {
-            return;
+        let observed = scope.observed(traffic, Utc::now().naive_utc());
+        let rows = verdict_rows(traffic, &flow, body.results, observed, scope.policy);
+        self.persist(pool, rows, scope).await
+    }
+
+    /// Store verdict rows, then publish them to the verdict stream and
+    /// the alert webhooks. Replays (`scope.historical`) are stored only:
+    /// their rows are not new.
+    async fn persist(
+        &self,
+        pool: DbPool,
+        rows: Vec<AuditVerdictInsert>,
+        scope: EvalScope<'_>,
+    ) -> Result<(), AuditEvalError> {
+        let stored = persist_verdicts(pool, rows).await?;
+        if !scope.historical {
+            self.alerts.notify_verdicts(&stored);
+            self.events.publish_verdicts(stored);
         }
+        Ok(())
+    }
+}
 
-        // Use a dedicated error enum so we can distinguish pool
-        // exhaustion from a real diesel error in logs without abusing
-        // `diesel::result::Error` variants for unrelated failure modes.
-        //
-        // Short pool-acquire timeout (1s): this audit task is holding
-        // a semaphore permit, and r2d2's 30s default would let one
-        // pool-starved insert pin a permit for 30s, cascading into
-        // audit-semaphore saturation under sustained DB pool pressure.
-        // 1s loses some inserts when the pool genuinely backs up but
-        // restores audit throughput much faster.
-        let result = tokio::task::spawn_blocking(move || -> Result<usize, AuditInsertError> {
-            let mut conn = pool
-                .get_timeout(std::time::Duration::from_secs(1))
-                .map_err(AuditInsertError::Pool)?;
-            diesel::insert_into(schema::audit_verdicts::table)
-                .values(&to_insert)
-                .execute(&mut conn)
-                .map_err(AuditInsertError::Diesel)
+/// The verdicts worth storing for one flow. Allow + WouldDeny are
+/// persisted so operators can preview both sides of policy impact
+/// (what's permitted, what would be blocked). NotApplicable is dropped
+/// — every flow checks against every cluster-scoped policy plus all
+/// namespaced ones in scope, and most produce NotApplicable; storing
+/// them all would inflate audit_verdicts by 1-2 orders of magnitude
+/// with no analytical value.
+///
+/// `traffic` is the stored flow behind `flow`; `observed` is the
+/// verdict's first and last sighting; `policy`, when set, keeps only
+/// that policy's verdicts.
+fn verdict_rows(
+    traffic: &PodTraffic,
+    flow: &Flow<'_>,
+    results: Vec<VerdictResult>,
+    observed: (chrono::NaiveDateTime, chrono::NaiveDateTime),
+    policy: Option<&str>,
+) -> Vec<AuditVerdictInsert> {
+    results
+        .into_iter()
+        .filter(|r| is_persistable_verdict(&r.verdict))
+        .filter(|r| policy.is_none_or(|p| is_policy(p, r)))
+        .map(|r| AuditVerdictInsert {
+            policy_uid: r.policy_uid,
+            policy_namespace: r.policy_namespace,
+            policy_name: r.policy_name,
+            direction: r.direction,
+            src_namespace: flow.src_pod_namespace.map(str::to_owned),
+            src_pod: flow.src_pod_name.map(str::to_owned),
+            dst_namespace: flow.dst_pod_namespace.map(str::to_owned),
+            dst_pod: flow.dst_pod_name.map(str::to_owned),
+            dst_port: flow.dst_port,
+            protocol: flow.protocol.to_owned(),
+            reason: if r.reason.is_empty() {
+                None
+            } else {
+                Some(r.reason)
+            },
+            first_observed_at: observed.0,
+            last_observed_at: observed.1,
+            verdict: r.verdict,
+            hits: 0,
+            traffic_uuids: vec![traffic.uuid.clone()],
         })
-        .await;
+        .collect()
+}
 
-        match result {
-            Ok(Ok(n)) => debug!(rows = n, "persisted audit verdicts"),
-            Ok(Err(AuditInsertError::Pool(e))) => {
-                warn!(error = %e, "could not get db conn for audit verdict insert")
-            }
-            Ok(Err(AuditInsertError::Diesel(e))) => {
-                warn!(error = %e, "audit verdict insert failed")
-            }
-            Err(e) => warn!(error = %e, "audit verdict task panicked"),
-        }
+/// Collapse and upsert verdict rows.
+async fn persist_verdicts(
+    pool: DbPool,
+    rows: Vec<AuditVerdictInsert>,
+) -> Result<Vec<AuditVerdict>, AuditEvalError> {
+    let to_insert = collapse_verdicts(rows);
+    if to_insert.is_empty() {
+        return Ok(Vec::new());
     }
+
+    // Short pool-acquire timeout (1s): this audit task is holding
+    // a semaphore permit, and r2d2's 30s default would let one
+    // pool-starved insert pin a permit for 30s, cascading into
+    // audit-semaphore saturation under sustained DB pool pressure.
+    // 1s loses some inserts when the pool genuinely backs up but
+    // restores audit throughput much faster.
+    let stored = tokio::task::spawn_blocking(move || -> Result<_, AuditInsertError> {
+        let mut conn = pool
+            .get_timeout(std::time::Duration::from_secs(1))
+            .map_err(AuditInsertError::Pool)?;
+        upsert_verdicts(&mut conn, &to_insert).map_err(AuditInsertError::Diesel)
+    })
+    .await
+    .map_err(AuditEvalError::Panicked)?
+    .map_err(AuditEvalError::Insert)?;
+    debug!(rows = stored.len(), "persisted audit verdicts");
+    Ok(stored)
 }
 
 #[cfg(test)]
@@ -759,6 +1578,52 @@ mod tests {
         });
     }
 
+    #[test]
+    fn batch_config_clamps_and_trims() {
+        with_env("AUDIT_BATCH_MAX_FLOWS", Some(" 200\n"), || {
+            assert_eq!(BatchConfig::from_env().max_flows, 200);
+        });
+        // 0 would mean "never send"; the evaluator rejects > 1000.
+        with_env("AUDIT_BATCH_MAX_FLOWS", Some("0"), || {
+            assert_eq!(BatchConfig::from_env().max_flows, 1);
+        });
+        with_env("AUDIT_BATCH_MAX_FLOWS", Some("50000"), || {
+            assert_eq!(BatchConfig::from_env().max_flows, MAX_AUDIT_BATCH_MAX_FLOWS);
+        });
+        with_env("AUDIT_BATCH_LINGER_MS", Some("junk"), || {
+            assert_eq!(BatchConfig::from_env(), BatchConfig::default());
+        });
+    }
+
+    #[test]
+    fn batching_pauses_after_404_then_reprobes() {
+        let (client, _rx) = client_with_queue(1);
+        assert!(client.batching());
+        *client.batch_unsupported_until.lock().unwrap() =
+            Some(Instant::now() + Duration::from_secs(60));
+        assert!(!client.batching(), "per-flow calls while the marker holds");
+        *client.batch_unsupported_until.lock().unwrap() =
+            Some(Instant::now() - Duration::from_secs(1));
+        assert!(client.batching(), "expired marker re-enables batching");
+        assert!(client.batch_unsupported_until.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn batch_request_wire_shape() {
+        let t = sample_traffic(Some("EGRESS"));
+        let flows = [build_flow_for_traffic(&t, None).unwrap()];
+        let v = serde_json::to_value(EvaluateBatchRequest { flows: &flows }).unwrap();
+        assert_eq!(v["flows"].as_array().map(Vec::len), Some(1));
+        assert_eq!(v["flows"][0]["dstPort"], 443);
+
+        let resp: EvaluateBatchResponse = serde_json::from_str(
+            r#"{"results":[[],[{"policyNamespace":"p","policyName":"n","direction":"Ingress","verdict":"Allow"}]]}"#,
+        )
+        .unwrap();
+        assert_eq!(resp.results.len(), 2);
+        assert!(resp.results[0].is_empty());
+    }
+
     /// Build a minimal PodTraffic for the `build_flow_for_traffic` tests.
     /// Direction-specific fields use values that let the assertion identify
     /// which branch (INGRESS vs EGRESS) the helper took.
@@ -777,6 +1642,7 @@ mod tests {
             time_stamp: chrono::DateTime::from_timestamp(0, 0)
                 .expect("epoch is a valid timestamp")
                 .naive_utc(),
+            ..Default::default()
         }
     }
 
@@ -793,6 +1659,14 @@ mod tests {
             in_flight: std::sync::Arc::new(tokio::sync::Semaphore::new(1)),
             tx: Some(tx),
             dropped: Arc::new(AtomicU64::new(0)),
+            peers: Arc::new(PeerCache::default()),
+            batch: BatchConfig::default(),
+            batch_unsupported_until: Arc::new(std::sync::Mutex::new(None)),
+            batch_sizes: Arc::new(Histogram::new(BATCH_SIZE_BUCKETS)),
+            outbox: None,
+            breaker: Arc::new(Breaker::new(BreakerConfig::default())),
+            events: EventHub::default(),
+            alerts: Alerter::default(),
         };
         (client, rx)
     }
@@ -809,6 +1683,21 @@ mod tests {
         assert_eq!(client.dropped_count(), 2, "two shed once the queue is full");
     }
 
+    #[test]
+    fn only_evaluator_4xx_is_not_retryable() {
+        // The outbox retries everything except a request the evaluator
+        // rejected outright — resending it would be rejected again.
+        use reqwest::StatusCode;
+        assert!(!AuditEvalError::Status(StatusCode::BAD_REQUEST).retryable());
+        assert!(!AuditEvalError::Status(StatusCode::UNPROCESSABLE_ENTITY).retryable());
+        assert!(AuditEvalError::Status(StatusCode::SERVICE_UNAVAILABLE).retryable());
+        assert!(AuditEvalError::Status(StatusCode::INTERNAL_SERVER_ERROR).retryable());
+        assert!(AuditEvalError::Insert(AuditInsertError::Diesel(
+            diesel::result::Error::RollbackTransaction
+        ))
+        .retryable());
+    }
+
     #[test]
     fn try_enqueue_is_noop_without_a_queue() {
         // No dispatcher wired (start() not called): drop silently, never panic,
@@ -820,11 +1709,76 @@ mod tests {
             in_flight: std::sync::Arc::new(tokio::sync::Semaphore::new(1)),
             tx: None,
             dropped: Arc::new(AtomicU64::new(0)),
+            peers: Arc::new(PeerCache::default()),
+            batch: BatchConfig::default(),
+            batch_unsupported_until: Arc::new(std::sync::Mutex::new(None)),
+            batch_sizes: Arc::new(Histogram::new(BATCH_SIZE_BUCKETS)),
+            outbox: None,
+            breaker: Arc::new(Breaker::new(BreakerConfig::default())),
+            events: EventHub::default(),
+            alerts: Alerter::default(),
         };
         client.try_enqueue(sample_traffic(Some("INGRESS")));
         assert_eq!(client.dropped_count(), 0);
     }
 
+    fn verdict_insert(policy: &str, at: i64, reason: Option<&str>) -> AuditVerdictInsert {
+        AuditVerdictInsert {
+            policy_uid: format!("uid-{policy}"),
+            policy_namespace: "prod".to_string(),
+            policy_name: policy.to_string(),
+            direction: "Egress".to_string(),
+            src_namespace: Some("prod".to_string()),
+            src_pod: Some("web-1".to_string()),
+            dst_namespace: None,
+            dst_pod: None,
+            dst_port: 443,
+            protocol: "TCP".to_string(),
+            reason: reason.map(str::to_string),
+            first_observed_at: chrono::DateTime::from_timestamp(at, 0).unwrap().naive_utc(),
+            last_observed_at: chrono::DateTime::from_timestamp(at, 0).unwrap().naive_utc(),
+            verdict: "WouldDeny".to_string(),
+            hits: 0,
+            traffic_uuids: vec![format!("flow-{at}")],
+        }
+    }
+
+    #[test]
+    fn collapse_verdicts_folds_repeats_into_one_row() {
+        // Two rows for one key in a single upsert would make ON
+        // CONFLICT DO UPDATE fail the whole statement; they must fold
+        // into one row carrying both flows and the widest time span.
+        // NULL pods (external peer) are part of the key like any value.
+        let rows = collapse_verdicts(vec![
+            verdict_insert("a", 10, Some("old")),
+            verdict_insert("b", 10, None),
+            verdict_insert("a", 20, Some("new")),
+        ]);
+        assert_eq!(rows.len(), 2);
+        let a = rows.iter().find(|r| r.policy_name == "a").unwrap();
+        assert_eq!(a.traffic_uuids, ["flow-10", "flow-20"]);
+        assert_eq!(a.first_observed_at.and_utc().timestamp(), 10);
+        assert_eq!(a.last_observed_at.and_utc().timestamp(), 20);
+        assert_eq!(a.reason.as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn policy_filter_takes_name_or_namespace_and_name() {
+        let result = |namespace: &str| VerdictResult {
+            policy_namespace: namespace.to_string(),
+            policy_name: "deny-web".to_string(),
+            policy_uid: String::new(),
+            direction: "Egress".to_string(),
+            verdict: "WouldDeny".to_string(),
+            reason: String::new(),
+        };
+        assert!(is_policy("deny-web", &result("prod")));
+        assert!(is_policy("deny-web", &result("staging")));
+        assert!(is_policy("prod/deny-web", &result("prod")));
+        assert!(!is_policy("prod/deny-web", &result("staging")));
+        assert!(!is_policy("prod/deny", &result("prod")));
+    }
+
     #[test]
     fn is_persistable_verdict_accepts_allow_and_woulddeny() {
         assert!(is_persistable_verdict("Allow"));
@@ -884,7 +1838,7 @@ mod tests {
         // Defensive: `traffic_type` is Option<String> in the schema —
         // a NULL row must not panic, must not produce a half-built Flow.
         let traffic = sample_traffic(None);
-        assert!(build_flow_for_traffic(&traffic).is_none());
+        assert!(build_flow_for_traffic(&traffic, None).is_none());
     }
 
     #[test]
@@ -893,7 +1847,7 @@ mod tests {
         // skip — better to drop the audit eval than to send a malformed
         // Flow that the evaluator might mis-classify.
         let traffic = sample_traffic(Some("UNKNOWN"));
-        assert!(build_flow_for_traffic(&traffic).is_none());
+        assert!(build_flow_for_traffic(&traffic, None).is_none());
     }
 
     #[test]
@@ -907,7 +1861,7 @@ mod tests {
         for tt in ["", " ", "\t", "\n", "   \n  "] {
             let traffic = sample_traffic(Some(tt));
             assert!(
-                build_flow_for_traffic(&traffic).is_none(),
+                build_flow_for_traffic(&traffic, None).is_none(),
                 "traffic_type={tt:?} must produce None",
             );
         }
@@ -916,7 +1870,7 @@ mod tests {
     #[test]
     fn build_flow_ingress_puts_pod_as_destination() {
         let traffic = sample_traffic(Some("INGRESS"));
-        let flow = build_flow_for_traffic(&traffic).expect("INGRESS must produce a Flow");
+        let flow = build_flow_for_traffic(&traffic, None).expect("INGRESS must produce a Flow");
         // INGRESS: source is the external peer (traffic_in_out_ip), the
         // pod itself is the destination. The evaluator's matcher relies
         // on this orientation to apply ingress rules against dst pod.
@@ -933,7 +1887,7 @@ mod tests {
     #[test]
     fn build_flow_egress_puts_pod_as_source() {
         let traffic = sample_traffic(Some("EGRESS"));
-        let flow = build_flow_for_traffic(&traffic).expect("EGRESS must produce a Flow");
+        let flow = build_flow_for_traffic(&traffic, None).expect("EGRESS must produce a Flow");
         assert_eq!(flow.src_pod_namespace, Some("prod"));
         assert_eq!(flow.src_pod_name, Some("web"));
         assert_eq!(flow.src_ip, Some("10.0.0.1"));
@@ -944,6 +1898,66 @@ mod tests {
         assert_eq!(flow.protocol, "TCP");
     }
 
+    fn peer_pod() -> PeerPod {
+        PeerPod {
+            namespace: Some("data".to_string()),
+            name: "db-0".to_string(),
+        }
+    }
+
+    #[test]
+    fn build_flow_fills_resolved_peer_on_the_far_side() {
+        // The resolved peer goes opposite the recording pod: the source
+        // for INGRESS, the destination for EGRESS. That is what lets the
+        // evaluator's podSelector peer rules match, and what attributes
+        // both sides of the persisted verdict.
+        let peer = peer_pod();
+        let traffic = sample_traffic(Some("INGRESS"));
+        let flow = build_flow_for_traffic(&traffic, Some(&peer)).expect("Flow");
+        assert_eq!(flow.src_pod_namespace, Some("data"));
+        assert_eq!(flow.src_pod_name, Some("db-0"));
+        assert_eq!(flow.src_ip, Some("10.0.0.2"));
+        assert_eq!(flow.dst_pod_name, Some("web"));
+
+        let traffic = sample_traffic(Some("EGRESS"));
+        let flow = build_flow_for_traffic(&traffic, Some(&peer)).expect("Flow");
+        assert_eq!(flow.src_pod_name, Some("web"));
+        assert_eq!(flow.dst_pod_namespace, Some("data"));
+        assert_eq!(flow.dst_pod_name, Some("db-0"));
+        assert_eq!(flow.dst_ip, Some("10.0.0.2"));
+    }
+
+    #[test]
+    fn peer_cache_expires_after_ttl() {
+        let cache = PeerCache::default();
+        let t0 = Instant::now();
+        assert_eq!(cache.get("10.0.0.2", t0), None, "cold cache must miss");
+        cache.insert("10.0.0.2".to_string(), Some(peer_pod()), t0);
+        cache.insert("1.1.1.1".to_string(), None, t0);
+        assert_eq!(cache.get("10.0.0.2", t0), Some(Some(peer_pod())));
+        // A cached "not a pod" is a hit too, so external peers don't
+        // query pod_details on every flow.
+        assert_eq!(cache.get("1.1.1.1", t0), Some(None));
+        // Past the TTL the IP may belong to another pod: look it up again.
+        assert_eq!(cache.get("10.0.0.2", t0 + PEER_CACHE_TTL), None);
+    }
+
+    #[test]
+    fn peer_cache_stays_bounded() {
+        let cache = PeerCache::default();
+        let t0 = Instant::now();
+        for i in 0..PEER_CACHE_CAPACITY + 10 {
+            cache.insert(format!("ip-{i}"), None, t0);
+        }
+        let len = cache.entries.lock().unwrap().len();
+        assert!(len <= PEER_CACHE_CAPACITY, "cache grew to {len}");
+        assert_eq!(
+            cache.get(&format!("ip-{}", PEER_CACHE_CAPACITY + 9), t0),
+            Some(None),
+            "the newest entry survives the sweep"
+        );
+    }
+
     #[test]
     fn build_flow_is_case_insensitive_for_direction() {
         // The controller emits "INGRESS"/"EGRESS"; the evaluator's
@@ -954,7 +1968,7 @@ mod tests {
         // bug fix.
         for variant in ["ingress", "Ingress", "iNgReSs", "INGRESS "] {
             let traffic = sample_traffic(Some(variant));
-            let flow = build_flow_for_traffic(&traffic)
+            let flow = build_flow_for_traffic(&traffic, None)
                 .unwrap_or_else(|| panic!("variant {variant:?} must yield Flow"));
             assert_eq!(
                 flow.dst_pod_name,
@@ -964,7 +1978,7 @@ mod tests {
         }
         for variant in ["egress", "Egress", "eGrEsS", " egress\n"] {
             let traffic = sample_traffic(Some(variant));
-            let flow = build_flow_for_traffic(&traffic)
+            let flow = build_flow_for_traffic(&traffic, None)
                 .unwrap_or_else(|| panic!("variant {variant:?} must yield Flow"));
             assert_eq!(
                 flow.src_pod_name,
@@ -981,12 +1995,12 @@ mod tests {
         // dst_port=0 as a wildcard match.
         let mut traffic = sample_traffic(Some("INGRESS"));
         traffic.pod_port = None;
-        let flow = build_flow_for_traffic(&traffic).expect("Flow");
+        let flow = build_flow_for_traffic(&traffic, None).expect("Flow");
         assert_eq!(flow.dst_port, 0);
 
         let mut traffic = sample_traffic(Some("EGRESS"));
         traffic.traffic_in_out_port = None;
-        let flow = build_flow_for_traffic(&traffic).expect("Flow");
+        let flow = build_flow_for_traffic(&traffic, None).expect("Flow");
         assert_eq!(flow.dst_port, 0);
     }
 
@@ -998,7 +2012,7 @@ mod tests {
         // empty (evaluator would error on the unknown protocol value).
         let mut traffic = sample_traffic(Some("INGRESS"));
         traffic.ip_protocol = None;
-        let flow = build_flow_for_traffic(&traffic).expect("Flow");
+        let flow = build_flow_for_traffic(&traffic, None).expect("Flow");
         assert_eq!(flow.protocol, "TCP");
     }
 
@@ -1014,7 +2028,7 @@ mod tests {
         for bad in ["abc", "8080-rest", "8080.5", "two thousand"] {
             let mut traffic = sample_traffic(Some("INGRESS"));
             traffic.pod_port = Some(bad.to_string());
-            let flow = build_flow_for_traffic(&traffic)
+            let flow = build_flow_for_traffic(&traffic, None)
                 .unwrap_or_else(|| panic!("non-numeric port {bad:?} should still yield a Flow"));
             assert_eq!(
                 flow.dst_port, 0,
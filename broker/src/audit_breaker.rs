@@ -0,0 +1,380 @@
+//! Circuit breaker and health tracking for the evaluator client.
+//!
+//! Without it, an evaluator outage costs the full eval timeout on every
+//! queued flow and shows up only in debug logs. The breaker counts
+//! consecutive connection failures and 5xx answers; past
+//! `AUDIT_BREAKER_FAILURE_THRESHOLD` it opens and calls fail at once
+//! with `AuditEvalError::CircuitOpen`. A probe task hits the
+//! evaluator's `/healthz` every `AUDIT_BREAKER_PROBE_INTERVAL_SECS`:
+//! while open, a healthy answer moves the breaker to half-open, where a
+//! single real call decides between closed and open again. The probe
+//! also runs while closed, so an idle broker notices an outage too.
+//!
+//! 4xx and undecodable answers are counted but don't trip the breaker:
+//! the evaluator is up, and the request or the versions are wrong.
+
+use crate::audit::{AuditClient, AuditEvalError};
+use crate::histogram::Histogram;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+const DEFAULT_PROBE_INTERVAL_SECS: u64 = 5;
+const MAX_PROBE_INTERVAL_SECS: u64 = 300;
+/// `broker_audit_eval_latency_seconds` bucket bounds, around the 500ms
+/// default eval timeout.
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// `AUDIT_BREAKER_FAILURE_THRESHOLD` / `AUDIT_BREAKER_PROBE_INTERVAL_SECS`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BreakerConfig {
+    pub(crate) failure_threshold: u32,
+    pub(crate) probe_interval: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            probe_interval: Duration::from_secs(DEFAULT_PROBE_INTERVAL_SECS),
+        }
+    }
+}
+
+impl BreakerConfig {
+    pub(crate) fn from_env() -> Self {
+        let failure_threshold = std::env::var("AUDIT_BREAKER_FAILURE_THRESHOLD")
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .map(|n| n.max(1))
+            .unwrap_or(DEFAULT_FAILURE_THRESHOLD);
+        let probe_secs = std::env::var("AUDIT_BREAKER_PROBE_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(|n| n.clamp(1, MAX_PROBE_INTERVAL_SECS))
+            .unwrap_or(DEFAULT_PROBE_INTERVAL_SECS);
+        Self {
+            failure_threshold,
+            probe_interval: Duration::from_secs(probe_secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl BreakerState {
+    /// Value of `broker_audit_breaker_state`.
+    fn gauge(self) -> u8 {
+        match self {
+            Self::Closed => 0,
+            Self::Open => 1,
+            Self::HalfOpen => 2,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Inner {
+    Closed {
+        failures: u32,
+    },
+    Open,
+    /// `trial` is set once the one admitted call is under way.
+    HalfOpen {
+        trial: bool,
+    },
+}
+
+/// Failure classes of `broker_audit_eval_errors_total`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ErrorClass {
+    Connect,
+    Client,
+    Server,
+    Decode,
+}
+
+impl ErrorClass {
+    const ALL: [Self; 4] = [Self::Connect, Self::Client, Self::Server, Self::Decode];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Connect => "connect",
+            Self::Client => "4xx",
+            Self::Server => "5xx",
+            Self::Decode => "decode",
+        }
+    }
+
+    /// Whether the evaluator itself looks unhealthy.
+    fn trips(self) -> bool {
+        matches!(self, Self::Connect | Self::Server)
+    }
+
+    /// `None` for errors that aren't the evaluator's (the verdict
+    /// insert, a panicked task, the breaker itself).
+    pub(crate) fn of(e: &AuditEvalError) -> Option<Self> {
+        match e {
+            AuditEvalError::Unreachable(_) => Some(Self::Connect),
+            AuditEvalError::Status(s) if s.is_client_error() => Some(Self::Client),
+            AuditEvalError::Status(_) => Some(Self::Server),
+            AuditEvalError::Decode(_) | AuditEvalError::BatchMismatch { .. } => Some(Self::Decode),
+            _ => None,
+        }
+    }
+}
+
+pub struct Breaker {
+    config: BreakerConfig,
+    state: Mutex<Inner>,
+    latency: Histogram,
+    errors: [AtomicU64; 4],
+    rejected: AtomicU64,
+}
+
+impl Breaker {
+    pub(crate) fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(Inner::Closed { failures: 0 }),
+            latency: Histogram::new(LATENCY_BUCKETS),
+            errors: Default::default(),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn state(&self) -> BreakerState {
+        match *self.lock() {
+            Inner::Closed { .. } => BreakerState::Closed,
+            Inner::Open => BreakerState::Open,
+            Inner::HalfOpen { .. } => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Whether a call may go out now. Half-open admits one at a time.
+    pub(crate) fn admit(&self) -> bool {
+        let admitted = match &mut *self.lock() {
+            Inner::Closed { .. } => true,
+            Inner::Open | Inner::HalfOpen { trial: true } => false,
+            Inner::HalfOpen { trial } => {
+                *trial = true;
+                true
+            }
+        };
+        if !admitted {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        admitted
+    }
+
+    /// Record the outcome of an admitted call that took `elapsed`.
+    pub(crate) fn record<T>(&self, result: &Result<T, AuditEvalError>, elapsed: Duration) {
+        self.latency.observe(elapsed.as_secs_f64());
+        let class = result.as_ref().err().and_then(ErrorClass::of);
+        if let Some(class) = class {
+            self.errors[class as usize].fetch_add(1, Ordering::Relaxed);
+        }
+        if class.is_some_and(ErrorClass::trips) {
+            self.on_failure();
+        } else {
+            self.on_success();
+        }
+    }
+
+    fn on_success(&self) {
+        let mut state = self.lock();
+        match *state {
+            Inner::HalfOpen { .. } => {
+                info!("evaluator answered; audit circuit closed");
+                *state = Inner::Closed { failures: 0 };
+            }
+            Inner::Closed { ref mut failures } => *failures = 0,
+            // A straggler from before the breaker opened; the probe
+            // decides when to try again.
+            Inner::Open => {}
+        }
+    }
+
+    fn on_failure(&self) {
+        let mut state = self.lock();
+        match *state {
+            Inner::Closed { ref mut failures } => {
+                *failures += 1;
+                if *failures >= self.config.failure_threshold {
+                    warn!(
+                        failures = *failures,
+                        "evaluator failing; audit circuit open, flows are skipped until it recovers"
+                    );
+                    *state = Inner::Open;
+                }
+            }
+            Inner::HalfOpen { .. } => {
+                warn!("evaluator still failing; audit circuit re-opened");
+                *state = Inner::Open;
+            }
+            Inner::Open => {}
+        }
+    }
+
+    /// Apply a `/healthz` probe result. A healthy probe only half-opens
+    /// the breaker; a real call has to succeed to close it.
+    pub(crate) fn probed(&self, healthy: bool) {
+        if healthy {
+            let mut state = self.lock();
+            if matches!(*state, Inner::Open) {
+                info!("evaluator healthy again; audit circuit half-open");
+                *state = Inner::HalfOpen { trial: false };
+            }
+        } else {
+            self.on_failure();
+        }
+    }
+
+    /// `broker_audit_evaluator_up`, `broker_audit_breaker_state`, the
+    /// error and rejection counters and the latency histogram.
+    pub(crate) fn render(&self, enabled: bool) -> String {
+        let state = self.state();
+        let up = u8::from(enabled && state != BreakerState::Open);
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP broker_audit_evaluator_up 1 if audit is enabled and the evaluator circuit is not open");
+        let _ = writeln!(out, "# TYPE broker_audit_evaluator_up gauge");
+        let _ = writeln!(out, "broker_audit_evaluator_up {up}");
+        let _ = writeln!(out, "# HELP broker_audit_breaker_state Evaluator circuit breaker state (0 closed, 1 open, 2 half-open)");
+        let _ = writeln!(out, "# TYPE broker_audit_breaker_state gauge");
+        let _ = writeln!(out, "broker_audit_breaker_state {}", state.gauge());
+        let _ = writeln!(out, "# HELP broker_audit_eval_errors_total Failed evaluator calls by class (connect, 4xx, 5xx, decode)");
+        let _ = writeln!(out, "# TYPE broker_audit_eval_errors_total counter");
+        for class in ErrorClass::ALL {
+            let n = self.errors[class as usize].load(Ordering::Relaxed);
+            let _ = writeln!(
+                out,
+                "broker_audit_eval_errors_total{{class=\"{}\"}} {n}",
+                class.label()
+            );
+        }
+        let _ = writeln!(out, "# HELP broker_audit_breaker_rejected_total Evaluator calls skipped because the circuit was open");
+        let _ = writeln!(out, "# TYPE broker_audit_breaker_rejected_total counter");
+        let _ = writeln!(
+            out,
+            "broker_audit_breaker_rejected_total {}",
+            self.rejected.load(Ordering::Relaxed)
+        );
+        out.push_str(&self.latency.render(
+            "broker_audit_eval_latency_seconds",
+            "Evaluator call latency, successful or not (/evaluate and /evaluate/batch)",
+        ));
+        out
+    }
+}
+
+/// Start the probe loop. Called from `AuditClient::start`.
+pub(crate) fn spawn_probe(client: AuditClient) {
+    let interval = client.breaker().config.probe_interval;
+    actix_web::rt::spawn(async move {
+        let mut tick = tokio::time::interval(interval);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tick.tick().await;
+            let healthy = client.probe().await;
+            client.breaker().probed(healthy);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(threshold: u32) -> Breaker {
+        Breaker::new(BreakerConfig {
+            failure_threshold: threshold,
+            probe_interval: Duration::from_secs(1),
+        })
+    }
+
+    fn status(code: u16) -> Result<(), AuditEvalError> {
+        Err(AuditEvalError::Status(
+            reqwest::StatusCode::from_u16(code).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn opens_after_threshold_and_rejects() {
+        let b = breaker(3);
+        for _ in 0..2 {
+            assert!(b.admit());
+            b.record(&status(503), Duration::ZERO);
+        }
+        // A success resets the streak.
+        b.record(&Ok(()), Duration::ZERO);
+        for _ in 0..3 {
+            b.record(&status(502), Duration::ZERO);
+        }
+        assert_eq!(b.state(), BreakerState::Open);
+        assert!(!b.admit());
+        assert_eq!(b.rejected.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn client_and_decode_errors_do_not_trip() {
+        let b = breaker(1);
+        b.record(&status(400), Duration::ZERO);
+        b.record(
+            &Err::<(), _>(AuditEvalError::BatchMismatch { sent: 2, got: 1 }),
+            Duration::ZERO,
+        );
+        assert_eq!(b.state(), BreakerState::Closed);
+        let body = b.render(true);
+        assert!(body.contains("broker_audit_eval_errors_total{class=\"4xx\"} 1\n"));
+        assert!(body.contains("broker_audit_eval_errors_total{class=\"decode\"} 1\n"));
+        assert!(body.contains("broker_audit_eval_errors_total{class=\"5xx\"} 0\n"));
+        assert!(body.contains("broker_audit_eval_latency_seconds_count 2\n"));
+    }
+
+    #[test]
+    fn probe_half_opens_and_one_trial_decides() {
+        let b = breaker(1);
+        b.record(&status(500), Duration::ZERO);
+        assert_eq!(b.state(), BreakerState::Open);
+        b.probed(true);
+        assert_eq!(b.state(), BreakerState::HalfOpen);
+        assert!(b.admit());
+        assert!(!b.admit(), "only one trial call while half-open");
+        b.record(&status(500), Duration::ZERO);
+        assert_eq!(b.state(), BreakerState::Open);
+
+        b.probed(true);
+        assert!(b.admit());
+        b.record(&Ok(()), Duration::ZERO);
+        assert_eq!(b.state(), BreakerState::Closed);
+        assert!(b.admit());
+    }
+
+    #[test]
+    fn failed_probes_open_an_idle_breaker() {
+        let b = breaker(2);
+        b.probed(false);
+        assert!(b.render(true).contains("broker_audit_evaluator_up 1\n"));
+        b.probed(false);
+        assert_eq!(b.state(), BreakerState::Open);
+        let body = b.render(true);
+        assert!(body.contains("broker_audit_evaluator_up 0\n"));
+        assert!(body.contains("broker_audit_breaker_state 1\n"));
+        // Disabled audit is never "up".
+        assert!(breaker(1)
+            .render(false)
+            .contains("broker_audit_evaluator_up 0\n"));
+    }
+}
@@ -0,0 +1,455 @@
+//! Durable ingest→audit hand-off, opt-in via `AUDIT_OUTBOX_ENABLED`.
+//!
+//! The default audit path is a bounded in-memory queue: when the
+//! evaluator is down or slow the queue fills and new flows are shed
+//! (`broker_audit_dropped_total`), and a broker restart loses whatever
+//! was queued. With the outbox on, `create_pod_traffic_batch` writes one
+//! `audit_outbox` row per new flow in the same transaction as the flow,
+//! and the dispatcher here drains the table:
+//!
+//! - rows are claimed with `FOR UPDATE SKIP LOCKED` and leased by pushing
+//!   `next_attempt_at` forward, so several broker replicas can drain one
+//!   table without evaluating a flow twice in the common case;
+//! - a successful evaluation deletes the row;
+//! - a failure reschedules it with exponential backoff (1s doubling to
+//!   5 min) and records the error; a 4xx from the evaluator, or running
+//!   out of `AUDIT_OUTBOX_MAX_ATTEMPTS`, abandons it instead. A batch
+//!   the evaluator rejects is re-sent one flow per call first (see
+//!   `AuditClient::evaluate_batch`), so only the flows it rejects on
+//!   their own are abandoned.
+//!
+//! Delivery is at-least-once: a broker that dies between the evaluator
+//! answering and the row being deleted re-evaluates the flow once its
+//! lease expires, which adds one to the verdict's hit count.
+//!
+//! Depth and age of the oldest row are refreshed every
+//! `STATS_REFRESH_INTERVAL` by a separate task rather than at scrape
+//! time, so `/metrics` never waits on a count over a backed-up table.
+
+use crate::audit::{audit_eval_timeout_ms, AuditEvalError};
+use crate::audit_breaker::BreakerState;
+use crate::{schema, AuditClient, PodTraffic};
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+/// Rows claimed per round. Each still waits for an `in_flight` permit,
+/// so this only bounds how far ahead of the evaluator one replica leases.
+const CLAIM_BATCH: i64 = 64;
+/// Sleep between claims when nothing is due (or the claim failed).
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// First retry delay; doubles per attempt up to `MAX_RETRY_DELAY`.
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+/// Floor on how long a claimed row stays hidden from other dispatchers.
+/// Raised to four eval timeouts when `AUDIT_EVAL_TIMEOUT_MS` is large.
+const MIN_LEASE: Duration = Duration::from_secs(60);
+const STATS_REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+/// Bound on the stored `last_error`, which can carry a reqwest chain.
+const MAX_ERROR_LEN: usize = 512;
+
+/// Gauges and counters behind the `broker_audit_outbox_*` metrics.
+#[derive(Default)]
+pub(crate) struct OutboxStats {
+    depth: AtomicU64,
+    oldest_age_secs: AtomicU64,
+    abandoned: AtomicU64,
+}
+
+impl OutboxStats {
+    pub(crate) fn depth(&self) -> u64 {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn oldest_age_secs(&self) -> u64 {
+        self.oldest_age_secs.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn abandoned(&self) -> u64 {
+        self.abandoned.load(Ordering::Relaxed)
+    }
+}
+
+/// `AUDIT_OUTBOX_ENABLED`: same truthy spellings as the other opt-in
+/// flags. Anything else, including a typo, leaves the in-memory queue.
+pub(crate) fn outbox_enabled_from_env() -> bool {
+    std::env::var("AUDIT_OUTBOX_ENABLED").is_ok_and(|v| {
+        matches!(
+            v.trim().to_ascii_lowercase().as_str(),
+            "true" | "1" | "yes" | "on"
+        )
+    })
+}
+
+/// `AUDIT_OUTBOX_MAX_ATTEMPTS`: give up on a flow after this many failed
+/// evaluations. Unset or 0 retries until it succeeds or pod_traffic
+/// retention prunes the flow.
+fn max_attempts() -> Option<u32> {
+    std::env::var("AUDIT_OUTBOX_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u32>().ok())
+        .filter(|n| *n > 0)
+}
+
+/// Delay before retry number `attempts` (1-based): 1s, 2s, 4s, ...
+/// capped at 5 min.
+pub(crate) fn retry_delay(attempts: u32) -> Duration {
+    let exp = attempts.saturating_sub(1).min(16);
+    BASE_RETRY_DELAY
+        .saturating_mul(1 << exp)
+        .min(MAX_RETRY_DELAY)
+}
+
+fn lease() -> Duration {
+    MIN_LEASE.max(Duration::from_millis(audit_eval_timeout_ms()).saturating_mul(4))
+}
+
+fn to_chrono(d: Duration) -> chrono::Duration {
+    chrono::Duration::from_std(d).unwrap_or(chrono::Duration::MAX)
+}
+
+/// Queue the given pod_traffic rows for evaluation. Called inside the
+/// ingest transaction, so a flow and its outbox row commit together.
+pub(crate) fn enqueue(conn: &mut PgConnection, rows: &[PodTraffic]) -> QueryResult<usize> {
+    use schema::audit_outbox::dsl::*;
+
+    let values: Vec<_> = rows.iter().map(|r| traffic_uuid.eq(&r.uuid)).collect();
+    diesel::insert_into(audit_outbox)
+        .values(&values)
+        .on_conflict_do_nothing()
+        .execute(conn)
+}
+
+/// One leased outbox row and the flow it refers to.
+struct Claimed {
+    id: i64,
+    attempts: i32,
+    traffic: PodTraffic,
+}
+
+/// Lease up to `limit` due rows. The lease is the only state change:
+/// if this broker dies mid-evaluation the rows come due again once it
+/// lapses.
+fn claim(
+    conn: &mut PgConnection,
+    limit: i64,
+    now: NaiveDateTime,
+    lease: Duration,
+) -> QueryResult<Vec<Claimed>> {
+    use schema::audit_outbox::dsl as o;
+    use schema::pod_traffic::dsl as t;
+
+    conn.transaction(|conn| {
+        let due: Vec<(i64, i32, String)> = o::audit_outbox
+            .select((o::id, o::attempts, o::traffic_uuid))
+            .filter(o::next_attempt_at.le(now))
+            .order((o::next_attempt_at.asc(), o::id.asc()))
+            .limit(limit)
+            .for_update()
+            .skip_locked()
+            .load(conn)?;
+        if due.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<i64> = due.iter().map(|(id, _, _)| *id).collect();
+        diesel::update(o::audit_outbox.filter(o::id.eq_any(&ids)))
+            .set(o::next_attempt_at.eq(now + to_chrono(lease)))
+            .execute(conn)?;
+
+        let uuids: Vec<&str> = due.iter().map(|(_, _, u)| u.as_str()).collect();
+        let mut flows: HashMap<String, PodTraffic> = t::pod_traffic
+            .filter(t::uuid.eq_any(&uuids))
+            .select(PodTraffic::as_select())
+            .load(conn)?
+            .into_iter()
+            .map(|row| (row.uuid.clone(), row))
+            .collect();
+
+        // The foreign key cascades, so every claimed row has its flow.
+        Ok(due
+            .into_iter()
+            .filter_map(|(id, attempts, uuid)| {
+                flows.remove(&uuid).map(|traffic| Claimed {
+                    id,
+                    attempts,
+                    traffic,
+                })
+            })
+            .collect())
+    })
+}
+
+fn delete(conn: &mut PgConnection, ids: &[i64]) -> QueryResult<usize> {
+    use schema::audit_outbox::dsl::*;
+    diesel::delete(audit_outbox.filter(id.eq_any(ids))).execute(conn)
+}
+
+fn reschedule(
+    conn: &mut PgConnection,
+    row_id: i64,
+    new_attempts: i32,
+    at: NaiveDateTime,
+    error: &str,
+) -> QueryResult<usize> {
+    use schema::audit_outbox::dsl::*;
+    let mut error = error.to_owned();
+    if error.len() > MAX_ERROR_LEN {
+        let mut end = MAX_ERROR_LEN;
+        while !error.is_char_boundary(end) {
+            end -= 1;
+        }
+        error.truncate(end);
+    }
+    diesel::update(audit_outbox.filter(id.eq(row_id)))
+        .set((
+            attempts.eq(new_attempts),
+            next_attempt_at.eq(at),
+            last_error.eq(error),
+        ))
+        .execute(conn)
+}
+
+/// Row count and the age of the oldest row, in seconds.
+fn depth_and_age(conn: &mut PgConnection, now: NaiveDateTime) -> QueryResult<(u64, u64)> {
+    use diesel::dsl::{count_star, min};
+    use schema::audit_outbox::dsl::*;
+
+    let (n, oldest): (i64, Option<NaiveDateTime>) = audit_outbox
+        .select((count_star(), min(created_at)))
+        .first(conn)?;
+    let age = oldest.map_or(0, |t| (now - t).num_seconds().max(0) as u64);
+    Ok((n.max(0) as u64, age))
+}
+
+/// What to do with a row after a failed evaluation attempt.
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Retry { attempts: i32, delay: Duration },
+    Abandon,
+}
+
+fn outcome(attempts: i32, retryable: bool, max_attempts: Option<u32>) -> Outcome {
+    let attempts = attempts.saturating_add(1);
+    let exhausted = max_attempts.is_some_and(|max| attempts as u32 >= max);
+    if !retryable || exhausted {
+        Outcome::Abandon
+    } else {
+        Outcome::Retry {
+            attempts,
+            delay: retry_delay(attempts as u32),
+        }
+    }
+}
+
+/// Start the outbox dispatcher and the stats refresher. Called from
+/// `AuditClient::start` in place of the in-memory queue.
+pub(crate) fn spawn(client: AuditClient, pool: DbPool) {
+    let Some(stats) = client.outbox_stats() else {
+        return;
+    };
+    let max_attempts = max_attempts();
+    info!(
+        max_attempts = max_attempts.unwrap_or(0),
+        "audit outbox enabled; flows are evaluated from the audit_outbox table"
+    );
+
+    let stats_pool = pool.clone();
+    actix_web::rt::spawn(async move {
+        let mut tick = tokio::time::interval(STATS_REFRESH_INTERVAL);
+        loop {
+            tick.tick().await;
+            let pool = stats_pool.clone();
+            let res = tokio::task::spawn_blocking(move || {
+                let mut conn = pool.get_timeout(Duration::from_secs(1))?;
+                depth_and_age(&mut conn, Utc::now().naive_utc())
+                    .map_err(Box::<dyn std::error::Error + Send + Sync>::from)
+            })
+            .await;
+            match res {
+                Ok(Ok((depth, age))) => {
+                    stats.depth.store(depth, Ordering::Relaxed);
+                    stats.oldest_age_secs.store(age, Ordering::Relaxed);
+                }
+                Ok(Err(e)) => debug!(error = %e, "could not refresh audit outbox stats"),
+                Err(e) => warn!(error = %e, "audit outbox stats task panicked"),
+            }
+        }
+    });
+
+    actix_web::rt::spawn(async move {
+        let lease = lease();
+        loop {
+            // Leave the rows due while the evaluator is known to be
+            // down; the breaker's probe reopens the way.
+            if client.breaker().state() == BreakerState::Open {
+                tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+                continue;
+            }
+            let claim_pool = pool.clone();
+            let claimed = tokio::task::spawn_blocking(move || {
+                let mut conn = claim_pool.get_timeout(Duration::from_secs(1))?;
+                claim(&mut conn, CLAIM_BATCH, Utc::now().naive_utc(), lease)
+                    .map_err(Box::<dyn std::error::Error + Send + Sync>::from)
+            })
+            .await;
+            let claimed = match claimed {
+                Ok(Ok(rows)) => rows,
+                Ok(Err(e)) => {
+                    debug!(error = %e, "could not claim audit outbox rows");
+                    Vec::new()
+                }
+                Err(e) => {
+                    warn!(error = %e, "audit outbox claim task panicked");
+                    Vec::new()
+                }
+            };
+            if claimed.is_empty() {
+                tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+                continue;
+            }
+
+            // Claimed rows go out in evaluator batches, one permit each.
+            let chunk = if client.batching() {
+                client.batch_config().max_flows
+            } else {
+                1
+            };
+            let mut claimed = claimed.into_iter().peekable();
+            while claimed.peek().is_some() {
+                let rows: Vec<Claimed> = claimed.by_ref().take(chunk).collect();
+                let permit = match client.in_flight().acquire_owned().await {
+                    Ok(p) => p,
+                    Err(_) => return, // semaphore closed → shutting down
+                };
+                let worker = client.clone();
+                let pool = pool.clone();
+                actix_web::rt::spawn(async move {
+                    let flows: Vec<PodTraffic> = rows.iter().map(|r| r.traffic.clone()).collect();
+                    let results = worker.evaluate_batch(pool.clone(), &flows).await;
+                    drop(permit);
+                    settle(&worker, pool, rows, results, max_attempts).await;
+                });
+            }
+        }
+    });
+}
+
+/// Record the results of one batch: finished and abandoned rows are
+/// deleted together, failed ones rescheduled. A failure here leaves
+/// the rows leased, so they are retried when the lease lapses.
+async fn settle(
+    client: &AuditClient,
+    pool: DbPool,
+    rows: Vec<Claimed>,
+    results: Vec<Result<(), Arc<AuditEvalError>>>,
+    max_attempts: Option<u32>,
+) {
+    let mut finished = Vec::new();
+    let mut retries = Vec::new();
+    for (row, result) in rows.into_iter().zip(results) {
+        let Err(e) = result else {
+            finished.push(row.id);
+            continue;
+        };
+        if matches!(*e, AuditEvalError::CircuitOpen) {
+            // Never sent, so it doesn't use up an attempt.
+            retries.push((row.id, row.attempts, IDLE_POLL_INTERVAL, e.to_string()));
+            continue;
+        }
+        match outcome(row.attempts, e.retryable(), max_attempts) {
+            Outcome::Retry { attempts, delay } => {
+                debug!(
+                    traffic_uuid = %row.traffic.uuid,
+                    attempts,
+                    retry_in_secs = delay.as_secs(),
+                    error = %e,
+                    "audit eval failed; will retry"
+                );
+                retries.push((row.id, attempts, delay, e.to_string()));
+            }
+            Outcome::Abandon => {
+                if let Some(stats) = client.outbox_stats() {
+                    stats.abandoned.fetch_add(1, Ordering::Relaxed);
+                }
+                warn!(
+                    traffic_uuid = %row.traffic.uuid,
+                    attempts = row.attempts.saturating_add(1),
+                    error = %e,
+                    "giving up on audit eval for flow"
+                );
+                finished.push(row.id);
+            }
+        }
+    }
+
+    let res = tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get_timeout(Duration::from_secs(1))?;
+        if !finished.is_empty() {
+            delete(&mut conn, &finished)?;
+        }
+        let now = Utc::now().naive_utc();
+        for (row_id, attempts, delay, error) in retries {
+            reschedule(&mut conn, row_id, attempts, now + to_chrono(delay), &error)?;
+        }
+        Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+    })
+    .await;
+    match res {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => debug!(error = %e, "could not settle audit outbox rows"),
+        Err(e) => warn!(error = %e, "audit outbox settle task panicked"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_then_caps() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(2), Duration::from_secs(2));
+        assert_eq!(retry_delay(5), Duration::from_secs(16));
+        assert_eq!(retry_delay(9), Duration::from_secs(256));
+        assert_eq!(retry_delay(10), MAX_RETRY_DELAY);
+        // No overflow however long the evaluator has been down.
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn outcome_retries_until_non_retryable_or_exhausted() {
+        assert_eq!(
+            outcome(0, true, None),
+            Outcome::Retry {
+                attempts: 1,
+                delay: Duration::from_secs(1)
+            }
+        );
+        assert_eq!(
+            outcome(1000, true, None),
+            Outcome::Retry {
+                attempts: 1001,
+                delay: MAX_RETRY_DELAY
+            }
+        );
+        // A request the evaluator rejects would be rejected again.
+        assert_eq!(outcome(0, false, None), Outcome::Abandon);
+        assert_eq!(
+            outcome(3, true, Some(5)),
+            Outcome::Retry {
+                attempts: 4,
+                delay: Duration::from_secs(8)
+            }
+        );
+        assert_eq!(outcome(4, true, Some(5)), Outcome::Abandon);
+    }
+}
@@ -0,0 +1,573 @@
+//! `POST /audit/replay` and `GET /audit/replay/{job}`: re-evaluate stored
+//! traffic against the current audit policies.
+//!
+//! Verdicts are computed once, when a flow is first ingested, so a new
+//! `AuditNetworkPolicy` only has an opinion on flows that arrive after
+//! it. A replay walks one namespace's `pod_traffic` rows seen in
+//! `[since, until)` and sends them through the same `AuditClient` path
+//! as live ingest, in evaluator batches, one `in_flight` permit at a
+//! time so live audit keeps most of the evaluator. Verdicts are stamped
+//! with the flow's own first and last sighting and, with `policy`, kept
+//! only for that policy.
+//!
+//! Each replay is a row in `audit_replay_jobs`, updated after every
+//! batch. The broker that accepted the job runs it; any replica can
+//! report on it. A job whose row stops moving (its broker restarted) is
+//! reported as `interrupted`.
+//!
+//! A flow counts once towards a verdict's `hits` however often it is
+//! evaluated (audit_verdict_flows), so replaying a window again, or
+//! over flows live audit already evaluated, adds no hits; it only
+//! stores verdicts the flows did not have yet.
+
+use crate::api_version::ApiVersion;
+use crate::audit::EvalScope;
+use crate::audit_outbox::retry_delay;
+use crate::get::{normalise_empty_to_none, TimeRange};
+use crate::{schema, AuditClient, Error, ErrorBody, PodTraffic};
+use actix_web::{get, http::header, post, web, HttpResponse};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+type DbError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Window when `since` is absent; matches the default verdict retention.
+const DEFAULT_WINDOW_DAYS: i64 = 30;
+/// Flows read from pod_traffic per query.
+const PAGE_SIZE: i64 = 500;
+/// Replays allowed to run at once across the cluster. Each holds one
+/// evaluator permit; the cap keeps replays from crowding out live audit.
+const MAX_RUNNING_JOBS: i64 = 4;
+/// A running job whose row hasn't moved for this long is reported as
+/// `interrupted`: the broker running it is gone.
+const STALE_AFTER_MINUTES: i64 = 5;
+/// Tries per batch while the evaluator is unreachable or failing before
+/// the job gives up.
+const MAX_BATCH_ATTEMPTS: u32 = 5;
+
+const REPLAY_RUNNING: &str = "running";
+const REPLAY_COMPLETED: &str = "completed";
+const REPLAY_FAILED: &str = "failed";
+const REPLAY_INTERRUPTED: &str = "interrupted";
+
+#[derive(Deserialize, ToSchema)]
+pub struct AuditReplayRequest {
+    /// Namespace whose pods' flows are replayed. Required.
+    pub namespace: String,
+    /// RFC3339; defaults to 30 days before `until`.
+    pub since: Option<String>,
+    /// RFC3339; defaults to now.
+    pub until: Option<String>,
+    /// Keep only this policy's verdicts: `name`, or `namespace/name`
+    /// when several namespaces have a policy of that name. Absent or
+    /// empty keeps all.
+    pub policy: Option<String>,
+}
+
+/// A replay and its progress, as stored in `audit_replay_jobs`.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = schema::audit_replay_jobs)]
+pub struct AuditReplayJob {
+    pub id: String,
+    pub namespace: String,
+    pub policy: Option<String>,
+    pub since: NaiveDateTime,
+    pub until: NaiveDateTime,
+    /// `running`, `completed`, `failed` or `interrupted`.
+    pub state: String,
+    /// Flows matched when the job started.
+    pub total: i64,
+    /// Flows sent to the evaluator so far, including failed ones.
+    pub processed: i64,
+    /// Flows the evaluator rejected.
+    pub failed: i64,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+impl AuditReplayJob {
+    /// Report a running job nobody is advancing as `interrupted`.
+    fn with_effective_state(mut self, now: NaiveDateTime) -> Self {
+        if self.state == REPLAY_RUNNING
+            && now - self.updated_at > Duration::minutes(STALE_AFTER_MINUTES)
+        {
+            self.state = REPLAY_INTERRUPTED.to_string();
+        }
+        self
+    }
+}
+
+/// A validated replay request.
+#[derive(Debug, Clone, PartialEq)]
+struct ReplaySpec {
+    namespace: String,
+    policy: Option<String>,
+    since: NaiveDateTime,
+    until: NaiveDateTime,
+}
+
+impl ReplaySpec {
+    fn parse(req: AuditReplayRequest, now: NaiveDateTime) -> Result<Self, String> {
+        let namespace = req.namespace.trim().to_string();
+        if namespace.is_empty() {
+            return Err("namespace is required".to_string());
+        }
+        let range = TimeRange::parse(req.since.as_deref(), req.until.as_deref())?;
+        let until = range.until.unwrap_or(now);
+        let since = range
+            .since
+            .unwrap_or(until - Duration::days(DEFAULT_WINDOW_DAYS));
+        if since >= until {
+            return Err(format!(
+                "invalid time range: since ({since}) must be before until ({until})"
+            ));
+        }
+        let policy = normalise_empty_to_none(req.policy.map(|p| p.trim().to_string()));
+        if let Some(p) = &policy {
+            let malformed = p
+                .split_once('/')
+                .is_some_and(|(ns, name)| ns.is_empty() || name.is_empty() || name.contains('/'));
+            if malformed {
+                return Err(format!(
+                    "invalid policy {p:?}: expected name or namespace/name"
+                ));
+            }
+        }
+        Ok(Self {
+            namespace,
+            policy,
+            since,
+            until,
+        })
+    }
+}
+
+/// Start re-evaluating stored traffic against the current policies.
+#[utoipa::path(
+    tag = "audit",
+    responses(
+        (status = 202, description = "Job started; `Location` points at it", body = AuditReplayJob),
+        (status = 400, description = "Invalid request", body = ErrorBody),
+        (status = 409, description = "Audit is disabled", body = ErrorBody),
+        (status = 429, description = "Too many replays are running", body = ErrorBody),
+    )
+)]
+#[post("/audit/replay")]
+pub async fn start_audit_replay(
+    pool: web::Data<DbPool>,
+    audit: web::Data<AuditClient>,
+    version: ApiVersion,
+    body: web::Json<AuditReplayRequest>,
+) -> Result<HttpResponse, Error> {
+    if !audit.enabled() {
+        return Err(Error::Conflict(
+            "audit is disabled: EVALUATOR_URL is not set".to_string(),
+        ));
+    }
+    let spec = ReplaySpec::parse(body.into_inner(), Utc::now().naive_utc())?;
+
+    let p = pool.clone();
+    let job_spec = spec.clone();
+    let job = web::block(move || {
+        let mut conn = p.get()?;
+        create_job(&mut conn, &job_spec, Utc::now().naive_utc())
+    })
+    .await??;
+    let Some(job) = job else {
+        return Err(Error::TooManyRequests(format!(
+            "{MAX_RUNNING_JOBS} audit replays are already running; wait for one to finish"
+        )));
+    };
+
+    info!(
+        job = %job.id,
+        namespace = %job.namespace,
+        policy = job.policy.as_deref().unwrap_or(""),
+        total = job.total,
+        "starting audit replay"
+    );
+    actix_web::rt::spawn(run_job(
+        audit.get_ref().clone(),
+        pool.get_ref().clone(),
+        job.id.clone(),
+        spec,
+    ));
+
+    Ok(HttpResponse::Accepted()
+        .insert_header((
+            header::LOCATION,
+            format!("{}/audit/replay/{}", version.prefix(), job.id),
+        ))
+        .json(job))
+}
+
+/// Progress of an audit replay job.
+#[utoipa::path(
+    tag = "audit",
+    responses(
+        (status = 200, body = AuditReplayJob),
+        (status = 404, description = "No data found", body = ErrorBody),
+    )
+)]
+#[get("/audit/replay/{job}")]
+pub async fn get_audit_replay(
+    pool: web::Data<DbPool>,
+    job_id: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let job_id = job_id.into_inner();
+    let job = web::block(move || {
+        use schema::audit_replay_jobs::dsl::*;
+        let mut conn = pool.get()?;
+        audit_replay_jobs
+            .find(&job_id)
+            .select(AuditReplayJob::as_select())
+            .first(&mut conn)
+            .optional()
+            .map_err(DbError::from)
+    })
+    .await??;
+
+    let job = job.ok_or(Error::NotFound)?;
+    Ok(HttpResponse::Ok().json(job.with_effective_state(Utc::now().naive_utc())))
+}
+
+/// Insert the job row with its flow count, or `None` when
+/// `MAX_RUNNING_JOBS` replays are already running. The check and the
+/// insert hold a lock on the table that concurrent requests (on any
+/// replica) also take, so two of them can't both see a free slot.
+fn create_job(
+    conn: &mut PgConnection,
+    spec: &ReplaySpec,
+    now: NaiveDateTime,
+) -> Result<Option<AuditReplayJob>, DbError> {
+    use schema::audit_replay_jobs::dsl as j;
+
+    // Counted before taking the lock: on a large pod_traffic this is
+    // the slow part.
+    let total = matching_traffic(spec).count().get_result::<i64>(conn)?;
+    let job = conn.transaction(|conn| {
+        // SHARE ROW EXCLUSIVE conflicts with itself, so concurrent
+        // create_job calls serialize here, while readers of the job
+        // rows are not blocked.
+        diesel::sql_query("LOCK TABLE audit_replay_jobs IN SHARE ROW EXCLUSIVE MODE")
+            .execute(conn)?;
+        let running: i64 = j::audit_replay_jobs
+            .filter(j::state.eq(REPLAY_RUNNING))
+            .filter(j::updated_at.gt(now - Duration::minutes(STALE_AFTER_MINUTES)))
+            .count()
+            .get_result(conn)?;
+        if running >= MAX_RUNNING_JOBS {
+            return Ok(None);
+        }
+        diesel::insert_into(j::audit_replay_jobs)
+            .values((
+                j::id.eq(uuid::Uuid::new_v4().to_string()),
+                j::namespace.eq(&spec.namespace),
+                j::policy.eq(&spec.policy),
+                j::since.eq(spec.since),
+                j::until.eq(spec.until),
+                j::state.eq(REPLAY_RUNNING),
+                j::total.eq(total),
+                j::created_at.eq(now),
+                j::updated_at.eq(now),
+            ))
+            .returning(AuditReplayJob::as_returning())
+            .get_result(conn)
+            .map(Some)
+    })?;
+    Ok(job)
+}
+
+/// The namespace's flows seen in `[since, until)`: first seen before
+/// `until` and last seen at or after `since`.
+fn matching_traffic(spec: &ReplaySpec) -> schema::pod_traffic::BoxedQuery<'_, diesel::pg::Pg> {
+    use schema::pod_traffic::dsl::*;
+    pod_traffic
+        .filter(pod_namespace.eq(&spec.namespace))
+        .filter(time_stamp.lt(spec.until))
+        .filter(last_seen.ge(spec.since))
+        .into_boxed()
+}
+
+/// The next page after `after`, in `(time_stamp, uuid)` order.
+fn load_page(
+    conn: &mut PgConnection,
+    spec: &ReplaySpec,
+    after: Option<&(NaiveDateTime, String)>,
+) -> QueryResult<Vec<PodTraffic>> {
+    use schema::pod_traffic::dsl::*;
+    let mut q = matching_traffic(spec);
+    if let Some((t, u)) = after {
+        q = q.filter(
+            time_stamp
+                .gt(*t)
+                .or(time_stamp.eq(*t).and(uuid.gt(u.clone()))),
+        );
+    }
+    q.order((time_stamp.asc(), uuid.asc()))
+        .limit(PAGE_SIZE)
+        .select(PodTraffic::as_select())
+        .load(conn)
+}
+
+fn record_progress(
+    conn: &mut PgConnection,
+    job_id: &str,
+    done: i64,
+    rejected: i64,
+) -> QueryResult<usize> {
+    use schema::audit_replay_jobs::dsl::*;
+    diesel::update(audit_replay_jobs.find(job_id))
+        .set((
+            processed.eq(done),
+            failed.eq(rejected),
+            updated_at.eq(Utc::now().naive_utc()),
+        ))
+        .execute(conn)
+}
+
+fn finish(conn: &mut PgConnection, job_id: &str, err: Option<String>) -> QueryResult<usize> {
+    use schema::audit_replay_jobs::dsl::*;
+    let now = Utc::now().naive_utc();
+    let new_state = if err.is_some() {
+        REPLAY_FAILED
+    } else {
+        REPLAY_COMPLETED
+    };
+    diesel::update(audit_replay_jobs.find(job_id))
+        .set((
+            state.eq(new_state),
+            error.eq(err),
+            updated_at.eq(now),
+            finished_at.eq(now),
+        ))
+        .execute(conn)
+}
+
+/// Run `f` against a pooled connection off the async runtime.
+async fn with_conn<T, F>(pool: &DbPool, f: F) -> Result<T, DbError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> QueryResult<T> + Send + 'static,
+{
+    let pool = pool.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get()?;
+        f(&mut conn).map_err(DbError::from)
+    })
+    .await?
+}
+
+async fn run_job(audit: AuditClient, pool: DbPool, job_id: String, spec: ReplaySpec) {
+    let outcome = replay(&audit, &pool, &job_id, &spec).await;
+    if let Err(e) = &outcome {
+        warn!(job = %job_id, error = %e, "audit replay failed");
+    } else {
+        info!(job = %job_id, "audit replay completed");
+    }
+    let id = job_id.clone();
+    let err = outcome.err();
+    if let Err(e) = with_conn(&pool, move |conn| finish(conn, &id, err)).await {
+        warn!(job = %job_id, error = %e, "could not record audit replay result");
+    }
+}
+
+/// Page through the matching flows and evaluate them batch by batch.
+/// Flows the evaluator rejects (4xx) are counted and skipped; a batch
+/// that keeps failing for any other reason ends the job.
+async fn replay(
+    audit: &AuditClient,
+    pool: &DbPool,
+    job_id: &str,
+    spec: &ReplaySpec,
+) -> Result<(), String> {
+    let scope = EvalScope {
+        policy: spec.policy.as_deref(),
+        historical: true,
+    };
+    let chunk = if audit.batching() {
+        audit.batch_config().max_flows
+    } else {
+        1
+    };
+    let (mut done, mut rejected) = (0i64, 0i64);
+    let mut after: Option<(NaiveDateTime, String)> = None;
+
+    loop {
+        let page_spec = spec.clone();
+        let cursor = after.clone();
+        let page = with_conn(pool, move |conn| {
+            load_page(conn, &page_spec, cursor.as_ref())
+        })
+        .await
+        .map_err(|e| format!("reading pod_traffic: {e}"))?;
+        let Some(last) = page.last() else {
+            return Ok(());
+        };
+        after = Some((last.time_stamp, last.uuid.clone()));
+
+        for batch in page.chunks(chunk) {
+            let mut pending = batch.to_vec();
+            let mut attempt = 0;
+            while !pending.is_empty() {
+                attempt += 1;
+                let permit = audit
+                    .in_flight()
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| "broker is shutting down".to_string())?;
+                let results = audit
+                    .evaluate_batch_scoped(pool.clone(), &pending, scope)
+                    .await;
+                drop(permit);
+
+                let mut retry = Vec::new();
+                let mut last_err = None;
+                for (traffic, result) in pending.into_iter().zip(results) {
+                    match result {
+                        Ok(()) => done += 1,
+                        Err(e) if e.retryable() => {
+                            last_err = Some(e.to_string());
+                            retry.push(traffic);
+                        }
+                        Err(e) => {
+                            debug!(job = %job_id, error = %e, "evaluator rejected replayed flow");
+                            done += 1;
+                            rejected += 1;
+                        }
+                    }
+                }
+                if let Some(err) = last_err {
+                    if attempt >= MAX_BATCH_ATTEMPTS {
+                        return Err(err);
+                    }
+                    tokio::time::sleep(retry_delay(attempt)).await;
+                }
+                pending = retry;
+            }
+
+            let id = job_id.to_string();
+            if let Err(e) =
+                with_conn(pool, move |conn| record_progress(conn, &id, done, rejected)).await
+            {
+                debug!(job = %job_id, error = %e, "could not record audit replay progress");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        chrono::DateTime::parse_from_rfc3339(s).unwrap().naive_utc()
+    }
+
+    fn request(namespace: &str, since: Option<&str>, until: Option<&str>) -> AuditReplayRequest {
+        AuditReplayRequest {
+            namespace: namespace.to_string(),
+            since: since.map(str::to_string),
+            until: until.map(str::to_string),
+            policy: None,
+        }
+    }
+
+    #[test]
+    fn spec_defaults_to_thirty_days_before_now() {
+        let now = at("2026-10-17T12:00:00Z");
+        let spec = ReplaySpec::parse(request(" prod ", None, None), now).unwrap();
+        assert_eq!(spec.namespace, "prod");
+        assert_eq!(spec.until, now);
+        assert_eq!(spec.since, at("2026-09-17T12:00:00Z"));
+        assert_eq!(spec.policy, None);
+    }
+
+    #[test]
+    fn spec_rejects_missing_namespace_and_bad_ranges() {
+        let now = at("2026-10-17T12:00:00Z");
+        assert!(ReplaySpec::parse(request("  ", None, None), now)
+            .unwrap_err()
+            .contains("namespace"));
+        assert!(
+            ReplaySpec::parse(request("prod", Some("yesterday"), None), now)
+                .unwrap_err()
+                .contains("since")
+        );
+        // A since after the default until (now) is an empty window.
+        assert!(
+            ReplaySpec::parse(request("prod", Some("2026-10-18T00:00:00Z"), None), now)
+                .unwrap_err()
+                .contains("must be before")
+        );
+    }
+
+    #[test]
+    fn empty_policy_means_every_policy() {
+        let now = at("2026-10-17T12:00:00Z");
+        let mut req = request("prod", None, None);
+        req.policy = Some("  ".to_string());
+        assert_eq!(ReplaySpec::parse(req, now).unwrap().policy, None);
+        let mut req = request("prod", None, None);
+        req.policy = Some("deny-web".to_string());
+        assert_eq!(
+            ReplaySpec::parse(req, now).unwrap().policy.as_deref(),
+            Some("deny-web")
+        );
+        let mut req = request("prod", None, None);
+        req.policy = Some("prod/deny-web".to_string());
+        assert_eq!(
+            ReplaySpec::parse(req, now).unwrap().policy.as_deref(),
+            Some("prod/deny-web")
+        );
+        for bad in ["/deny-web", "prod/", "a/b/c"] {
+            let mut req = request("prod", None, None);
+            req.policy = Some(bad.to_string());
+            assert!(ReplaySpec::parse(req, now)
+                .unwrap_err()
+                .contains("namespace/name"));
+        }
+    }
+
+    #[test]
+    fn stalled_running_job_reads_as_interrupted() {
+        let now = at("2026-10-17T12:00:00Z");
+        let job = AuditReplayJob {
+            id: "j".to_string(),
+            namespace: "prod".to_string(),
+            policy: None,
+            since: at("2026-10-01T00:00:00Z"),
+            until: now,
+            state: REPLAY_RUNNING.to_string(),
+            total: 10,
+            processed: 4,
+            failed: 0,
+            error: None,
+            created_at: at("2026-10-17T11:00:00Z"),
+            updated_at: at("2026-10-17T11:58:00Z"),
+            finished_at: None,
+        };
+        assert_eq!(job.clone().with_effective_state(now).state, REPLAY_RUNNING);
+
+        let stalled = AuditReplayJob {
+            updated_at: at("2026-10-17T11:50:00Z"),
+            ..job.clone()
+        };
+        assert_eq!(stalled.with_effective_state(now).state, REPLAY_INTERRUPTED);
+
+        // Finished jobs keep their state however old they are.
+        let done = AuditReplayJob {
+            state: REPLAY_COMPLETED.to_string(),
+            updated_at: at("2026-10-01T00:00:00Z"),
+            ..job
+        };
+        assert_eq!(done.with_effective_state(now).state, REPLAY_COMPLETED);
+    }
+}
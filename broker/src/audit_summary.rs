@@ -0,0 +1,540 @@
+//! `GET /audit/summary`: per-policy rollup of `audit_verdicts`.
+//!
+//! `/audit/verdicts` lists rows, at most 500 a page, so it can't answer
+//! "policy X would deny 4 distinct flows, 3 of them in the last hour".
+//! This folds the rows in Postgres instead: per `(policy_namespace,
+//! policy_name)`, the Allow and WouldDeny totals, the distinct peers
+//! per direction, and hourly and daily histograms over the window.
+//!
+//! Each row is one distinct verdict with its first and last sighting.
+//! The totals count the verdicts whose `[first, last]` span overlaps
+//! the window; the histograms put each of them in one bucket, the one
+//! holding its last sighting in the window, so a histogram's buckets
+//! add up to the totals it covers.
+//!
+//! Both queries lead with `verdict IN (...)` and a lower bound on
+//! `last_observed_at`, which is the `(verdict, last_observed_at DESC)`
+//! index.
+
+use crate::get::{normalise_empty_to_none, TimeRange};
+use crate::{Error, ErrorBody};
+use actix_web::{get, web, HttpResponse};
+use chrono::{Duration, DurationRound, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::sql_query;
+use diesel::sql_types::{Array, BigInt, Nullable, Text, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+type DbError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Window when `since` is absent; matches the default retention.
+const DEFAULT_WINDOW_DAYS: i64 = 30;
+/// Longest window, and so the most daily buckets, one request can ask for.
+const MAX_WINDOW_DAYS: i64 = 90;
+/// The hourly histogram covers the last day of the window.
+const HOURLY_BUCKETS: i64 = 24;
+
+#[derive(Deserialize, IntoParams)]
+pub struct AuditSummaryQuery {
+    /// Policy namespace. `namespace=` (empty) selects cluster-scoped
+    /// policies, as on `/audit/verdicts`; absent spans all.
+    pub namespace: Option<String>,
+    /// Policy name.
+    pub policy: Option<String>,
+    /// RFC3339; defaults to 30 days before `until`.
+    pub since: Option<String>,
+    /// RFC3339; defaults to now.
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AuditPolicySummary {
+    pub policy_namespace: String,
+    pub policy_name: String,
+    pub allow: VerdictTotals,
+    pub would_deny: VerdictTotals,
+    /// One bucket per hour over the last 24 hours of the window.
+    pub hourly: Vec<VerdictBucket>,
+    /// One bucket per UTC day of the window.
+    pub daily: Vec<VerdictBucket>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VerdictTotals {
+    /// Distinct verdicts (rows).
+    pub verdicts: i64,
+    /// Flows counted across those verdicts.
+    pub hits: i64,
+    /// Distinct source pods of Ingress verdicts. Unresolved peers
+    /// (external addresses) count as one.
+    pub ingress_peers: i64,
+    /// Distinct destination pods of Egress verdicts, likewise.
+    pub egress_peers: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VerdictBucket {
+    pub start: NaiveDateTime,
+    /// Allow verdicts last seen in the bucket.
+    pub allow: i64,
+    /// WouldDeny verdicts last seen in the bucket.
+    pub would_deny: i64,
+}
+
+#[derive(QueryableByName)]
+struct TotalsRow {
+    #[diesel(sql_type = Text)]
+    policy_namespace: String,
+    #[diesel(sql_type = Text)]
+    policy_name: String,
+    #[diesel(sql_type = Text)]
+    verdict: String,
+    #[diesel(sql_type = BigInt)]
+    verdicts: i64,
+    #[diesel(sql_type = BigInt)]
+    hits: i64,
+    #[diesel(sql_type = BigInt)]
+    ingress_peers: i64,
+    #[diesel(sql_type = BigInt)]
+    egress_peers: i64,
+}
+
+#[derive(QueryableByName)]
+struct BucketRow {
+    #[diesel(sql_type = Text)]
+    policy_namespace: String,
+    #[diesel(sql_type = Text)]
+    policy_name: String,
+    #[diesel(sql_type = Timestamp)]
+    start: NaiveDateTime,
+    #[diesel(sql_type = BigInt)]
+    allow: i64,
+    #[diesel(sql_type = BigInt)]
+    would_deny: i64,
+}
+
+/// The resolved window: both ends set, at most `MAX_WINDOW_DAYS` long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SummaryWindow {
+    pub since: NaiveDateTime,
+    pub until: NaiveDateTime,
+}
+
+impl SummaryWindow {
+    fn resolve(range: TimeRange, now: NaiveDateTime) -> Result<Self, String> {
+        let until = range.until.unwrap_or(now);
+        let since = range
+            .since
+            .unwrap_or(until - Duration::days(DEFAULT_WINDOW_DAYS));
+        if since >= until {
+            return Err(format!(
+                "invalid time range: since ({since}) must be before until ({until})"
+            ));
+        }
+        if until - since > Duration::days(MAX_WINDOW_DAYS) {
+            return Err(format!(
+                "invalid time range: at most {MAX_WINDOW_DAYS} days per summary"
+            ));
+        }
+        Ok(Self { since, until })
+    }
+
+    fn hourly(&self) -> Vec<NaiveDateTime> {
+        let from = self.since.max(self.until - Duration::hours(HOURLY_BUCKETS));
+        bucket_starts(from, self.until, Duration::hours(1))
+    }
+
+    fn daily(&self) -> Vec<NaiveDateTime> {
+        bucket_starts(self.since, self.until, Duration::days(1))
+    }
+}
+
+/// Starts of the `width`-aligned buckets covering `[from, until)`. The
+/// first bucket starts at or before `from` so its counts line up with
+/// the calendar hour or UTC day.
+pub fn bucket_starts(
+    from: NaiveDateTime,
+    until: NaiveDateTime,
+    width: Duration,
+) -> Vec<NaiveDateTime> {
+    let mut start = from.duration_trunc(width).unwrap_or(from);
+    let mut starts = Vec::new();
+    while start < until {
+        starts.push(start);
+        start += width;
+    }
+    starts
+}
+
+/// Verdict totals and a histogram per policy.
+#[utoipa::path(
+    tag = "audit",
+    params(AuditSummaryQuery),
+    responses(
+        (status = 200, body = Vec<AuditPolicySummary>),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+    )
+)]
+#[get("/audit/summary")]
+pub async fn get_audit_summary(
+    pool: web::Data<DbPool>,
+    query: web::Query<AuditSummaryQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("select audit verdict summary");
+    let q = query.into_inner();
+    let window = TimeRange::parse(q.since.as_deref(), q.until.as_deref())
+        .and_then(|r| SummaryWindow::resolve(r, Utc::now().naive_utc()))?;
+    // Same filter semantics as /audit/verdicts: empty policy means no
+    // filter, empty namespace means cluster-scoped.
+    let policy = normalise_empty_to_none(q.policy);
+    let namespace = q.namespace;
+    let summary = web::block(move || {
+        let mut conn = pool.get()?;
+        audit_summary(&mut conn, namespace.as_deref(), policy.as_deref(), window)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(summary))
+}
+
+/// Totals, then one histogram query per bucket width.
+pub fn audit_summary(
+    conn: &mut PgConnection,
+    namespace: Option<&str>,
+    policy: Option<&str>,
+    window: SummaryWindow,
+) -> Result<Vec<AuditPolicySummary>, DbError> {
+    let totals = sql_query(
+        "SELECT policy_namespace, policy_name, verdict, \
+                COUNT(*) AS verdicts, \
+                COALESCE(SUM(hits), 0)::BIGINT AS hits, \
+                COUNT(DISTINCT COALESCE(src_namespace, '') || '/' || COALESCE(src_pod, '')) \
+                    FILTER (WHERE direction = 'Ingress') AS ingress_peers, \
+                COUNT(DISTINCT COALESCE(dst_namespace, '') || '/' || COALESCE(dst_pod, '')) \
+                    FILTER (WHERE direction = 'Egress') AS egress_peers \
+         FROM audit_verdicts \
+         WHERE verdict IN ('Allow', 'WouldDeny') \
+           AND last_observed_at >= $1 AND first_observed_at < $2 \
+           AND ($3::text IS NULL OR policy_namespace = $3) \
+           AND ($4::text IS NULL OR policy_name = $4) \
+         GROUP BY 1, 2, 3",
+    )
+    .bind::<Timestamp, _>(window.since)
+    .bind::<Timestamp, _>(window.until)
+    .bind::<Nullable<Text>, _>(namespace)
+    .bind::<Nullable<Text>, _>(policy)
+    .load::<TotalsRow>(conn)?;
+
+    let hourly_starts = window.hourly();
+    let daily_starts = window.daily();
+    let hourly = histogram(
+        conn,
+        namespace,
+        policy,
+        window,
+        &hourly_starts,
+        Duration::hours(1),
+    )?;
+    let daily = histogram(
+        conn,
+        namespace,
+        policy,
+        window,
+        &daily_starts,
+        Duration::days(1),
+    )?;
+    Ok(build_summary(
+        totals,
+        hourly,
+        daily,
+        &hourly_starts,
+        &daily_starts,
+    ))
+}
+
+/// Verdicts per bucket, each counted once: in the bucket of its last
+/// sighting, or of the window's end when it was still seen after it.
+/// Verdicts last seen before `window.since` count nowhere, even when
+/// the first bucket starts earlier.
+fn histogram(
+    conn: &mut PgConnection,
+    namespace: Option<&str>,
+    policy: Option<&str>,
+    window: SummaryWindow,
+    starts: &[NaiveDateTime],
+    width: Duration,
+) -> Result<Vec<BucketRow>, DbError> {
+    let Some(first) = starts.first() else {
+        return Ok(Vec::new());
+    };
+    let rows = sql_query(
+        "SELECT v.policy_namespace, v.policy_name, b.start, \
+                COUNT(*) FILTER (WHERE v.verdict = 'Allow') AS allow, \
+                COUNT(*) FILTER (WHERE v.verdict = 'WouldDeny') AS would_deny \
+         FROM ( \
+           SELECT policy_namespace, policy_name, verdict, \
+                  LEAST(last_observed_at, $4 - interval '1 microsecond') AS seen \
+           FROM audit_verdicts \
+           WHERE verdict IN ('Allow', 'WouldDeny') \
+             AND last_observed_at >= $3 AND first_observed_at < $4 \
+             AND ($5::text IS NULL OR policy_namespace = $5) \
+             AND ($6::text IS NULL OR policy_name = $6) \
+         ) v \
+         JOIN unnest($1::timestamp[]) AS b(start) \
+           ON v.seen >= b.start AND v.seen < b.start + make_interval(secs => $2) \
+         GROUP BY 1, 2, 3",
+    )
+    .bind::<Array<Timestamp>, _>(starts)
+    .bind::<BigInt, _>(width.num_seconds())
+    .bind::<Timestamp, _>((*first).max(window.since))
+    .bind::<Timestamp, _>(window.until)
+    .bind::<Nullable<Text>, _>(namespace)
+    .bind::<Nullable<Text>, _>(policy)
+    .load::<BucketRow>(conn)?;
+    Ok(rows)
+}
+
+/// One summary per policy seen in the totals, sorted by namespace then
+/// name, with every bucket present (zero-filled) so the histograms of
+/// different policies line up.
+fn build_summary(
+    totals: Vec<TotalsRow>,
+    hourly: Vec<BucketRow>,
+    daily: Vec<BucketRow>,
+    hourly_starts: &[NaiveDateTime],
+    daily_starts: &[NaiveDateTime],
+) -> Vec<AuditPolicySummary> {
+    let empty = |starts: &[NaiveDateTime]| -> Vec<VerdictBucket> {
+        starts
+            .iter()
+            .map(|&start| VerdictBucket {
+                start,
+                allow: 0,
+                would_deny: 0,
+            })
+            .collect()
+    };
+    let mut by_policy: BTreeMap<(String, String), AuditPolicySummary> = BTreeMap::new();
+    for row in totals {
+        let entry = by_policy
+            .entry((row.policy_namespace.clone(), row.policy_name.clone()))
+            .or_insert_with(|| AuditPolicySummary {
+                policy_namespace: row.policy_namespace,
+                policy_name: row.policy_name,
+                allow: VerdictTotals::default(),
+                would_deny: VerdictTotals::default(),
+                hourly: empty(hourly_starts),
+                daily: empty(daily_starts),
+            });
+        let t = VerdictTotals {
+            verdicts: row.verdicts,
+            hits: row.hits,
+            ingress_peers: row.ingress_peers,
+            egress_peers: row.egress_peers,
+        };
+        if row.verdict == "Allow" {
+            entry.allow = t;
+        } else {
+            entry.would_deny = t;
+        }
+    }
+    for (rows, hourly) in [(hourly, true), (daily, false)] {
+        for row in rows {
+            let Some(entry) = by_policy.get_mut(&(row.policy_namespace, row.policy_name)) else {
+                continue;
+            };
+            let buckets = if hourly {
+                &mut entry.hourly
+            } else {
+                &mut entry.daily
+            };
+            if let Some(b) = buckets.iter_mut().find(|b| b.start == row.start) {
+                b.allow = row.allow;
+                b.would_deny = row.would_deny;
+            }
+        }
+    }
+    by_policy.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> NaiveDateTime {
+        chrono::DateTime::parse_from_rfc3339(s).unwrap().naive_utc()
+    }
+
+    #[test]
+    fn buckets_align_to_the_hour_and_day() {
+        // Buckets start on the calendar boundary at or before `from`, so
+        // "the last hour" means the same thing across policies and calls.
+        let hours = bucket_starts(
+            ts("2026-10-17T10:20:00Z"),
+            ts("2026-10-17T12:05:00Z"),
+            Duration::hours(1),
+        );
+        assert_eq!(
+            hours,
+            [
+                ts("2026-10-17T10:00:00Z"),
+                ts("2026-10-17T11:00:00Z"),
+                ts("2026-10-17T12:00:00Z")
+            ]
+        );
+        let days = bucket_starts(
+            ts("2026-10-15T23:00:00Z"),
+            ts("2026-10-17T00:00:00Z"),
+            Duration::days(1),
+        );
+        assert_eq!(
+            days,
+            [ts("2026-10-15T00:00:00Z"), ts("2026-10-16T00:00:00Z")]
+        );
+    }
+
+    #[test]
+    fn window_defaults_and_bounds() {
+        let now = ts("2026-10-17T12:00:00Z");
+        let w = SummaryWindow::resolve(TimeRange::default(), now).unwrap();
+        assert_eq!(w.until, now);
+        assert_eq!(w.since, now - Duration::days(DEFAULT_WINDOW_DAYS));
+        assert_eq!(w.hourly().len(), HOURLY_BUCKETS as usize);
+        assert_eq!(w.daily().len(), DEFAULT_WINDOW_DAYS as usize + 1);
+
+        // A since later than the defaulted until is the inverted-window
+        // bug TimeRange::parse rejects when both are given.
+        let late = TimeRange {
+            since: Some(now + Duration::hours(1)),
+            until: None,
+        };
+        assert!(SummaryWindow::resolve(late, now).is_err());
+
+        let long = TimeRange {
+            since: Some(now - Duration::days(MAX_WINDOW_DAYS + 1)),
+            until: None,
+        };
+        assert!(SummaryWindow::resolve(long, now).is_err());
+    }
+
+    #[test]
+    fn short_window_has_short_hourly_histogram() {
+        let now = ts("2026-10-17T12:00:00Z");
+        let range = TimeRange {
+            since: Some(now - Duration::hours(3)),
+            until: Some(now),
+        };
+        let w = SummaryWindow::resolve(range, now).unwrap();
+        assert_eq!(w.hourly().len(), 3);
+        assert_eq!(w.daily(), [ts("2026-10-17T00:00:00Z")]);
+    }
+
+    fn totals(ns: &str, name: &str, verdict: &str, verdicts: i64) -> TotalsRow {
+        TotalsRow {
+            policy_namespace: ns.to_string(),
+            policy_name: name.to_string(),
+            verdict: verdict.to_string(),
+            verdicts,
+            hits: verdicts * 10,
+            ingress_peers: 1,
+            egress_peers: 2,
+        }
+    }
+
+    #[test]
+    fn summary_merges_verdicts_and_zero_fills_buckets() {
+        let starts = [ts("2026-10-17T10:00:00Z"), ts("2026-10-17T11:00:00Z")];
+        let s = build_summary(
+            vec![
+                totals("prod", "web-deny", "WouldDeny", 4),
+                totals("", "baseline", "Allow", 1),
+                totals("prod", "web-deny", "Allow", 2),
+            ],
+            vec![BucketRow {
+                policy_namespace: "prod".to_string(),
+                policy_name: "web-deny".to_string(),
+                start: starts[1],
+                allow: 0,
+                would_deny: 3,
+            }],
+            Vec::new(),
+            &starts,
+            &starts[..1],
+        );
+        // Cluster-scoped ("" namespace) sorts first.
+        assert_eq!(s.len(), 2);
+        assert_eq!(s[0].policy_name, "baseline");
+        assert_eq!(s[0].would_deny, VerdictTotals::default());
+        let web = &s[1];
+        assert_eq!(web.would_deny.verdicts, 4);
+        assert_eq!(web.allow.verdicts, 2);
+        assert_eq!(web.hourly.len(), 2);
+        assert_eq!(web.hourly[0].would_deny, 0);
+        assert_eq!(web.hourly[1].would_deny, 3);
+        assert_eq!(web.daily.len(), 1);
+    }
+
+    #[test]
+    #[ignore = "requires a database"]
+    fn histogram_counts_each_verdict_once_inside_the_window() {
+        use crate::schema::audit_verdicts::dsl as v;
+
+        let mut conn = crate::test_support::db();
+        conn.test_transaction::<_, DbError, _>(|conn| {
+            let rows = [
+                // Seen across all three days: one bucket, its last day.
+                (
+                    "WouldDeny",
+                    1,
+                    "2026-10-10T01:00:00Z",
+                    "2026-10-12T03:00:00Z",
+                ),
+                // First seen before the window.
+                ("Allow", 2, "2026-10-09T00:00:00Z", "2026-10-10T13:00:00Z"),
+                // Still seen after the window: its final bucket.
+                ("Allow", 3, "2026-10-12T10:00:00Z", "2026-10-14T00:00:00Z"),
+                // Last seen on the first day but before `since`.
+                ("Allow", 4, "2026-10-10T02:00:00Z", "2026-10-10T06:00:00Z"),
+            ];
+            for (verdict, port, first, last) in rows {
+                diesel::insert_into(v::audit_verdicts)
+                    .values((
+                        v::policy_uid.eq("summary-test"),
+                        v::policy_namespace.eq("summary-test"),
+                        v::policy_name.eq("histogram"),
+                        v::direction.eq("Egress"),
+                        v::dst_port.eq(port),
+                        v::protocol.eq("TCP"),
+                        v::verdict.eq(verdict),
+                        v::first_observed_at.eq(ts(first)),
+                        v::last_observed_at.eq(ts(last)),
+                    ))
+                    .execute(conn)?;
+            }
+            let window = SummaryWindow {
+                since: ts("2026-10-10T12:00:00Z"),
+                until: ts("2026-10-13T00:00:00Z"),
+            };
+            let s = audit_summary(conn, Some("summary-test"), Some("histogram"), window)?;
+            assert_eq!(s.len(), 1);
+            let s = &s[0];
+            assert_eq!((s.allow.verdicts, s.would_deny.verdicts), (2, 1));
+            let daily: Vec<_> = s.daily.iter().map(|b| (b.allow, b.would_deny)).collect();
+            assert_eq!(daily, [(1, 0), (0, 0), (1, 1)]);
+            let hourly = |at: &str| {
+                let b = s.hourly.iter().find(|b| b.start == ts(at)).unwrap();
+                (b.allow, b.would_deny)
+            };
+            assert_eq!(hourly("2026-10-12T03:00:00Z"), (0, 1));
+            assert_eq!(hourly("2026-10-12T23:00:00Z"), (1, 0));
+            let in_hourly: i64 = s.hourly.iter().map(|b| b.allow + b.would_deny).sum();
+            assert_eq!(in_hourly, 2);
+            Ok(())
+        });
+    }
+}
@@ -4,12 +4,39 @@
 //! relied on being an in-cluster-only service. That leaves its write
 //! endpoints (pod traffic / syscalls / specs) open to any workload that
 //! can route to the Service, which can forge rows and poison audit
-//! verdicts. When `BROKER_AUTH_TOKEN` is set, every endpoint except
-//! `/health` and `/metrics` requires `Authorization: Bearer <token>`.
+//! verdicts. Auth is on when `BROKER_AUTH_TOKEN` or
+//! `BROKER_AUTH_TOKENS_FILE` is set; every endpoint except `/health`,
+//! `/metrics` and the API docs then requires
+//! `Authorization: Bearer <token>`.
 //!
 //! Unset (the default) preserves the original no-auth behaviour so
 //! existing deployments are unaffected — this is strictly opt-in.
 //!
+//! Each token carries scopes, and each route needs one of them:
+//!
+//! - `read` — every GET, plus `POST /simulate/networkpolicy`, which only
+//!   reads recorded flows
+//! - `ingest` — the other POSTs (traffic, specs, syscalls, mark_dead)
+//! - `admin` — `ADMIN_ROUTES` (audit replay); it also grants the other two
+//!
+//! `BROKER_AUTH_TOKEN` is a single shared token named `shared` with every
+//! scope, as before. `BROKER_AUTH_TOKENS_FILE` names a YAML file of named,
+//! scoped tokens, re-read every `RELOAD_INTERVAL` so a rotated Secret
+//! takes effect without a restart:
+//!
+//! ```yaml
+//! tokens:
+//!   - name: controller
+//!     secret: "..."
+//!     scopes: [ingest, read]
+//!   - name: grafana
+//!     secret: "..."
+//!     scopes: [read]
+//! ```
+//!
+//! Each authorized request is logged with the token's name; secrets are
+//! never logged.
+//!
 //! `/health` is exempt because kubelet probes can't carry the header;
 //! `/metrics` is exempt because Prometheus scrapes it and the gauges are
 //! low-sensitivity. Browser clients (the frontend talks to the broker
@@ -18,31 +45,250 @@
 
 use actix_web::body::MessageBody;
 use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
 use actix_web::middleware::Next;
 use actix_web::{web, Error};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often the tokens file is checked for changes. Kubelet takes up to
+/// a minute to project an updated Secret, so this adds little on top.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Routes that need the `admin` scope, relative to `/v1`; subpaths
+/// included.
+const ADMIN_ROUTES: &[&str] = &["/audit/replay"];
+
+/// What a token may do. `Admin` implies the other two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Read,
+    Ingest,
+    Admin,
+}
+
+impl Scope {
+    fn label(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Ingest => "ingest",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokensFile {
+    #[serde(default)]
+    tokens: Vec<TokenDoc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokenDoc {
+    name: String,
+    secret: String,
+    scopes: Vec<Scope>,
+}
 
-/// Holds the optional shared secret the broker checks incoming requests
-/// against. Cloned into each worker via `app_data`.
+/// A configured token. No `Debug`, so the secret can't end up in a log.
+#[derive(Clone)]
+struct Token {
+    name: String,
+    secret: String,
+    scopes: Vec<Scope>,
+}
+
+impl Token {
+    fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&Scope::Admin) || self.scopes.contains(&scope)
+    }
+}
+
+/// Outcome of checking a presented token against a route's scope.
+#[derive(Debug, PartialEq)]
+enum Check {
+    /// No configured token matches.
+    Unknown,
+    /// The token is valid but lacks the scope; carries its name.
+    Denied(String),
+    /// Carries the token's name.
+    Allowed(String),
+}
+
+/// The tokens the broker checks incoming requests against. Cloned into
+/// each worker via `app_data`; clones share the reloadable token list.
 #[derive(Clone)]
 pub struct AuthConfig {
-    token: Option<String>,
+    shared: Option<Token>,
+    file: Option<PathBuf>,
+    tokens: Arc<RwLock<Vec<Token>>>,
 }
 
 impl AuthConfig {
-    /// Read the token from `BROKER_AUTH_TOKEN`. Empty / whitespace-only
-    /// is treated as unset (auth disabled), matching how the provider
-    /// keys are handled elsewhere.
-    pub fn from_env() -> Self {
-        let token = std::env::var("BROKER_AUTH_TOKEN")
+    /// Read `BROKER_AUTH_TOKEN` and the file named by
+    /// `BROKER_AUTH_TOKENS_FILE`. Empty / whitespace-only values are
+    /// treated as unset, matching how the provider keys are handled
+    /// elsewhere. An unreadable or invalid file is an error, so a typo
+    /// can't silently turn auth off.
+    pub fn from_env() -> Result<Self, String> {
+        let shared = std::env::var("BROKER_AUTH_TOKEN")
             .ok()
             .map(|t| t.trim().to_string())
-            .filter(|t| !t.is_empty());
-        AuthConfig { token }
+            .filter(|t| !t.is_empty())
+            .map(|secret| Token {
+                name: "shared".to_string(),
+                secret,
+                scopes: vec![Scope::Admin],
+            });
+        let file = std::env::var("BROKER_AUTH_TOKENS_FILE")
+            .ok()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from);
+        let config = AuthConfig {
+            shared,
+            file,
+            tokens: Arc::default(),
+        };
+        let file_tokens = match &config.file {
+            Some(path) => Self::read(path)?,
+            None => Vec::new(),
+        };
+        config.install(file_tokens)?;
+        Ok(config)
+    }
+
+    fn read(path: &PathBuf) -> Result<Vec<Token>, String> {
+        let raw = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        parse(&raw).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Replace the token list with `file_tokens` plus the shared token.
+    fn install(&self, file_tokens: Vec<Token>) -> Result<(), String> {
+        let mut tokens = file_tokens;
+        if let Some(shared) = &self.shared {
+            if tokens.iter().any(|t| t.name == shared.name) {
+                return Err(format!(
+                    "token name {:?} is reserved for BROKER_AUTH_TOKEN",
+                    shared.name
+                ));
+            }
+            if tokens.iter().any(|t| t.secret == shared.secret) {
+                return Err("a token reuses the BROKER_AUTH_TOKEN secret".to_string());
+            }
+            tokens.push(shared.clone());
+        }
+        *self.tokens.write().unwrap_or_else(|e| e.into_inner()) = tokens;
+        Ok(())
     }
 
     pub fn enabled(&self) -> bool {
-        self.token.is_some()
+        self.shared.is_some() || self.file.is_some()
+    }
+
+    /// Names of the configured tokens, for the startup log.
+    pub fn token_names(&self) -> Vec<String> {
+        self.lock_read().iter().map(|t| t.name.clone()).collect()
+    }
+
+    fn lock_read(&self) -> std::sync::RwLockReadGuard<'_, Vec<Token>> {
+        self.tokens.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Match `presented` against every token (in constant time per token,
+    /// without stopping at the first match) and check it grants `scope`.
+    fn check(&self, presented: &str, scope: Scope) -> Check {
+        let tokens = self.lock_read();
+        let mut found = None;
+        for token in tokens.iter() {
+            if ct_eq(presented, &token.secret) {
+                found = Some(token);
+            }
+        }
+        match found {
+            None => Check::Unknown,
+            Some(t) if t.allows(scope) => Check::Allowed(t.name.clone()),
+            Some(t) => Check::Denied(t.name.clone()),
+        }
+    }
+
+    /// Re-read the tokens file every `RELOAD_INTERVAL` and swap in the new
+    /// tokens when its contents change. A file that fails to read or
+    /// parse is logged and the current tokens stay in force. No-op
+    /// without `BROKER_AUTH_TOKENS_FILE`.
+    pub fn spawn_reload(&self) {
+        let Some(path) = self.file.clone() else {
+            return;
+        };
+        let config = self.clone();
+        actix_web::rt::spawn(async move {
+            let mut last = std::fs::read_to_string(&path).ok();
+            loop {
+                tokio::time::sleep(RELOAD_INTERVAL).await;
+                let raw = match std::fs::read_to_string(&path) {
+                    Ok(raw) => raw,
+                    Err(e) => {
+                        warn!(path = %path.display(), error = %e, "cannot read broker API tokens file; keeping current tokens");
+                        continue;
+                    }
+                };
+                if last.as_deref() == Some(raw.as_str()) {
+                    continue;
+                }
+                match parse(&raw).and_then(|tokens| config.install(tokens)) {
+                    Ok(()) => {
+                        info!(tokens = ?config.token_names(), "reloaded broker API tokens");
+                    }
+                    Err(e) => {
+                        warn!(path = %path.display(), error = %e, "invalid broker API tokens file; keeping current tokens");
+                    }
+                }
+                // Remember the contents either way, so a bad file is
+                // reported once rather than every interval.
+                last = Some(raw);
+            }
+        });
+    }
+}
+
+fn parse(raw: &str) -> Result<Vec<Token>, String> {
+    let file: TokensFile = serde_yaml::from_str(raw).map_err(|e| e.to_string())?;
+    let mut names = HashSet::new();
+    let mut secrets = HashSet::new();
+    let mut tokens = Vec::with_capacity(file.tokens.len());
+    for doc in file.tokens {
+        let name = doc.name.trim().to_string();
+        if name.is_empty() {
+            return Err("token name must not be empty".to_string());
+        }
+        if !names.insert(name.clone()) {
+            return Err(format!("duplicate token name {name:?}"));
+        }
+        let secret = doc.secret.trim().to_string();
+        if secret.is_empty() {
+            return Err(format!("token {name:?}: secret must not be empty"));
+        }
+        // Two names for one secret would make the logged name a guess.
+        if !secrets.insert(secret.clone()) {
+            return Err(format!("token {name:?}: secret is used by another token"));
+        }
+        if doc.scopes.is_empty() {
+            return Err(format!("token {name:?}: scopes must not be empty"));
+        }
+        tokens.push(Token {
+            name,
+            secret,
+            scopes: doc.scopes,
+        });
     }
+    Ok(tokens)
 }
 
 /// Constant-time comparison so a wrong token can't be recovered byte by
@@ -60,21 +306,55 @@ fn ct_eq(a: &str, b: &str) -> bool {
 }
 
 /// True if the request is allowed to skip the bearer check: unset token
-/// (auth disabled), a CORS preflight, or the probe/metrics endpoints.
+/// (auth disabled), a CORS preflight, the probe/metrics endpoints, or the
+/// API docs (they describe the API, they don't expose its data).
 fn is_exempt(req: &ServiceRequest) -> bool {
-    req.method() == actix_web::http::Method::OPTIONS
-        || req.path() == "/health"
-        || req.path() == "/metrics"
+    let path = routing_path(req);
+    req.method() == Method::OPTIONS
+        || path == "/health"
+        || path == "/metrics"
+        || path == "/openapi.json"
+        || path.starts_with("/swagger-ui/")
+}
+
+/// The path actix routes on: percent-encoded characters decoded, except
+/// the ones that would change its segments (`%2F`). Scopes must be
+/// decided on this, not `req.path()`, or `/audit/%72eplay` reaches the
+/// replay handler with the ingest scope.
+fn routing_path(req: &ServiceRequest) -> &str {
+    req.match_info().as_str()
+}
+
+/// The path without its `/v1` prefix, if it has one.
+fn strip_v1(path: &str) -> Option<&str> {
+    path.strip_prefix(api::V1_PREFIX)
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// The scope a route needs. `/v1` and the unversioned aliases match alike.
+fn required_scope(method: &Method, path: &str) -> Scope {
+    let path = strip_v1(path).unwrap_or(path);
+    let admin = ADMIN_ROUTES.iter().any(|route| {
+        path.strip_prefix(route)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    });
+    if admin {
+        Scope::Admin
+    } else if *method == Method::GET || *method == Method::HEAD || path == "/simulate/networkpolicy"
+    {
+        Scope::Read
+    } else {
+        Scope::Ingest
+    }
 }
 
-/// True if the `Authorization: Bearer <token>` header matches `expected`.
-fn bearer_ok(req: &ServiceRequest, expected: &str) -> bool {
+/// The token from the `Authorization: Bearer <token>` header.
+fn bearer(req: &ServiceRequest) -> Option<&str> {
     req.headers()
         .get("Authorization")
         .and_then(|h| h.to_str().ok())
         .and_then(|h| h.strip_prefix("Bearer "))
-        .map(|t| ct_eq(t.trim(), expected))
-        .unwrap_or(false)
+        .map(str::trim)
 }
 
 /// actix `from_fn` middleware enforcing the optional bearer token.
@@ -82,25 +362,91 @@ pub async fn require_bearer(
     req: ServiceRequest,
     next: Next<impl MessageBody + 'static>,
 ) -> Result<ServiceResponse<impl MessageBody>, Error> {
-    let expected = req
+    let config = req
         .app_data::<web::Data<AuthConfig>>()
-        .and_then(|c| c.token.clone());
+        .filter(|c| c.enabled())
+        .cloned();
+    let Some(config) = config else {
+        return next.call(req).await;
+    };
+    if is_exempt(&req) {
+        return next.call(req).await;
+    }
 
-    if let Some(expected) = expected {
-        if !is_exempt(&req) && !bearer_ok(&req, &expected) {
-            return Err(actix_web::error::ErrorUnauthorized(
+    // /v1 answers with the JSON error envelope, the deprecated
+    // unversioned routes with the plain text they always sent.
+    let path = routing_path(&req);
+    let v1 = strip_v1(path).is_some();
+    let scope = required_scope(req.method(), path);
+    match bearer(&req).map_or(Check::Unknown, |t| config.check(t, scope)) {
+        Check::Unknown => {
+            if v1 {
+                return Err(api::Error::Unauthorized.into());
+            }
+            Err(actix_web::error::ErrorUnauthorized(
                 "missing or invalid bearer token",
-            ));
+            ))
+        }
+        Check::Denied(token) => {
+            warn!(
+                token = %token,
+                scope = scope.label(),
+                method = %req.method(),
+                path = req.path(),
+                "broker API request denied: token lacks scope"
+            );
+            let e = api::Error::Forbidden {
+                token,
+                scope: scope.label(),
+            };
+            if v1 {
+                return Err(e.into());
+            }
+            Err(actix_web::error::ErrorForbidden(e.to_string()))
+        }
+        Check::Allowed(token) => {
+            info!(
+                token = %token,
+                method = %req.method(),
+                path = req.path(),
+                "broker API request"
+            );
+            next.call(req).await
         }
     }
-
-    next.call(req).await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn config(shared: Option<&str>, file: &str) -> AuthConfig {
+        let config = AuthConfig {
+            shared: shared.map(|s| Token {
+                name: "shared".to_string(),
+                secret: s.to_string(),
+                scopes: vec![Scope::Admin],
+            }),
+            file: Some(PathBuf::from("tokens.yaml")),
+            tokens: Arc::default(),
+        };
+        config.install(parse(file).unwrap()).unwrap();
+        config
+    }
+
+    const TOKENS: &str = r#"
+tokens:
+  - name: controller
+    secret: ctl-secret
+    scopes: [ingest, read]
+  - name: grafana
+    secret: ro-secret
+    scopes: [read]
+  - name: ops
+    secret: ops-secret
+    scopes: [admin]
+"#;
+
     #[test]
     fn ct_eq_matches_only_identical_strings() {
         assert!(ct_eq("s3cret-token", "s3cret-token"));
@@ -113,15 +459,146 @@ mod tests {
     #[test]
     fn from_env_treats_blank_as_disabled() {
         // Saved/restored around the test to avoid cross-test bleed; the
-        // broker reads this once at startup in production.
+        // broker reads this once at startup in production. A tokens file
+        // would enable auth on its own, so it is cleared for the test.
+        let _guard = crate::test_support::env_lock();
         let prev = std::env::var("BROKER_AUTH_TOKEN").ok();
+        let prev_file = std::env::var("BROKER_AUTH_TOKENS_FILE").ok();
+        std::env::remove_var("BROKER_AUTH_TOKENS_FILE");
         std::env::set_var("BROKER_AUTH_TOKEN", "   ");
-        assert!(!AuthConfig::from_env().enabled());
+        assert!(!AuthConfig::from_env().unwrap().enabled());
         std::env::set_var("BROKER_AUTH_TOKEN", "real-token");
-        assert!(AuthConfig::from_env().enabled());
+        assert!(AuthConfig::from_env().unwrap().enabled());
         match prev {
             Some(v) => std::env::set_var("BROKER_AUTH_TOKEN", v),
             None => std::env::remove_var("BROKER_AUTH_TOKEN"),
         }
+        if let Some(v) = prev_file {
+            std::env::set_var("BROKER_AUTH_TOKENS_FILE", v);
+        }
+    }
+
+    #[test]
+    fn routes_map_to_scopes() {
+        let cases = [
+            (Method::GET, "/v1/pod/traffic", Scope::Read),
+            (Method::GET, "/pod/info", Scope::Read),
+            (Method::POST, "/v1/simulate/networkpolicy", Scope::Read),
+            (Method::POST, "/v1/pod/traffic/batch", Scope::Ingest),
+            (Method::POST, "/pod/mark_dead", Scope::Ingest),
+            (Method::POST, "/v1/audit/replay", Scope::Admin),
+            (Method::GET, "/audit/replay/42", Scope::Admin),
+            (Method::GET, "/v1/audit/replayed", Scope::Read),
+        ];
+        for (method, path, scope) in cases {
+            assert_eq!(required_scope(&method, path), scope, "{method} {path}");
+        }
+    }
+
+    // actix routes on the decoded path, so `/audit/%72eplay` reaches the
+    // replay handler; the scope check must see it as `/audit/replay` too.
+    #[actix_web::test]
+    async fn encoded_paths_need_the_scope_of_the_route_they_reach() {
+        use actix_web::{middleware::from_fn, test, App, HttpResponse};
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config(None, TOKENS)))
+                .wrap(from_fn(require_bearer))
+                .route("/audit/replay", web::post().to(HttpResponse::Ok))
+                .route("/audit/replay/{id}", web::get().to(HttpResponse::Ok))
+                .route("/v1/audit/replay", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+        let cases = [
+            (Method::POST, "/audit/%72eplay", "ctl-secret", 403),
+            (Method::POST, "/v1/audit/%72eplay", "ctl-secret", 403),
+            (Method::POST, "/%61udit/replay", "ctl-secret", 403),
+            (Method::GET, "/audit/%72eplay/1", "ro-secret", 403),
+            (Method::POST, "/audit/%72eplay", "ops-secret", 200),
+            (Method::GET, "/%61udit/replay/1", "ops-secret", 200),
+        ];
+        for (method, path, token, status) in cases {
+            let req = test::TestRequest::default()
+                .method(method.clone())
+                .uri(path)
+                .insert_header(("Authorization", format!("Bearer {token}")))
+                .to_request();
+            let got = match test::try_call_service(&app, req).await {
+                Ok(res) => res.status().as_u16(),
+                Err(e) => e.as_response_error().status_code().as_u16(),
+            };
+            assert_eq!(got, status, "{method} {path} as {token}");
+        }
+    }
+
+    #[test]
+    fn check_enforces_scopes_and_admin_grants_all() {
+        let c = config(None, TOKENS);
+        assert_eq!(c.check("nope", Scope::Read), Check::Unknown);
+        assert_eq!(
+            c.check("ro-secret", Scope::Read),
+            Check::Allowed("grafana".to_string())
+        );
+        assert_eq!(
+            c.check("ro-secret", Scope::Ingest),
+            Check::Denied("grafana".to_string())
+        );
+        assert_eq!(
+            c.check("ctl-secret", Scope::Admin),
+            Check::Denied("controller".to_string())
+        );
+        for scope in [Scope::Read, Scope::Ingest, Scope::Admin] {
+            assert_eq!(
+                c.check("ops-secret", scope),
+                Check::Allowed("ops".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn shared_token_keeps_every_scope_alongside_the_file() {
+        let c = config(Some("legacy"), TOKENS);
+        assert_eq!(
+            c.check("legacy", Scope::Admin),
+            Check::Allowed("shared".to_string())
+        );
+        assert_eq!(c.token_names(), ["controller", "grafana", "ops", "shared"]);
+        let clash = "tokens:\n  - {name: shared, secret: x, scopes: [read]}\n";
+        assert!(c.install(parse(clash).unwrap()).is_err());
+    }
+
+    #[test]
+    fn install_swaps_tokens_for_every_clone() {
+        let c = config(None, TOKENS);
+        let worker = c.clone();
+        let rotated = "tokens:\n  - {name: grafana, secret: ro-rotated, scopes: [read]}\n";
+        c.install(parse(rotated).unwrap()).unwrap();
+        assert_eq!(worker.check("ro-secret", Scope::Read), Check::Unknown);
+        assert_eq!(
+            worker.check("ro-rotated", Scope::Read),
+            Check::Allowed("grafana".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_rejects_bad_files() {
+        for (raw, want) in [
+            ("tokens:\n  - {name: '', secret: x, scopes: [read]}\n", "name must not be empty"),
+            (
+                "tokens:\n  - {name: a, secret: x, scopes: [read]}\n  - {name: a, secret: y, scopes: [read]}\n",
+                "duplicate token name",
+            ),
+            (
+                "tokens:\n  - {name: a, secret: x, scopes: [read]}\n  - {name: b, secret: x, scopes: [read]}\n",
+                "used by another token",
+            ),
+            ("tokens:\n  - {name: a, secret: ' ', scopes: [read]}\n", "secret must not be empty"),
+            ("tokens:\n  - {name: a, secret: x, scopes: []}\n", "scopes must not be empty"),
+            ("tokens:\n  - {name: a, secret: x, scopes: [write]}\n", "unknown variant"),
+        ] {
+            let err = parse(raw).err().unwrap_or_default();
+            assert!(err.contains(want), "{raw:?}: {err}");
+        }
     }
 }
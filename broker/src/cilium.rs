@@ -0,0 +1,623 @@
+//! Cilium policy generation:
+//! `GET /policy/ciliumnetworkpolicy/{namespace}/{workload}` and
+//! `GET /policy/ciliumclusterwidenetworkpolicy/{workload}`.
+//!
+//! The flows and peers are the NetworkPolicy generator's (see
+//! policy.rs); what changes is how a peer is written:
+//!
+//! - A selector peer becomes a `toEndpoints`/`fromEndpoints` selector
+//!   with Cilium's `k8s:` label prefix and the peer's namespace, which
+//!   Cilium otherwise assumes is the policy's own.
+//! - A host-networked pod becomes the `host` and `remote-node` entities,
+//!   and the `default/kubernetes` Service the `kube-apiserver` entity.
+//! - Anything else becomes `toCIDR`/`fromCIDR`, or the `world` entity
+//!   with `?external=world`.
+//!
+//! The clusterwide variant covers one workload in every namespace it
+//! runs in: namespaces whose pods share a selector share one rule, with
+//! the union of their flows.
+
+use crate::get::{validate_enum_filter, TimeRange};
+use crate::networkpolicy::{group_flows, no_selector, object_meta, GroupedFlows, ObjectMeta, Port};
+use crate::policy::{
+    load_policy_input, workload_namespaces, OutputFormat, PolicyInput, PolicyPeer,
+};
+use crate::{Error, ErrorBody};
+use actix_web::{get, web, HttpResponse};
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+/// Label Cilium puts on every endpoint with its pod's namespace.
+const CILIUM_NAMESPACE_LABEL: &str = "k8s:io.kubernetes.pod.namespace";
+
+/// Query params for the Cilium endpoints.
+#[derive(Deserialize, IntoParams)]
+pub struct CiliumQuery {
+    /// `yaml` (default) or `json`.
+    pub format: Option<String>,
+    /// `cidr` (default): one `toCIDR` per external address. `world`:
+    /// every external address folds into the `world` entity.
+    pub external: Option<String>,
+    /// RFC3339; only flows seen at or after it.
+    pub since: Option<String>,
+    /// RFC3339; only flows first recorded before it.
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalPeers {
+    Cidr,
+    World,
+}
+
+impl ExternalPeers {
+    fn parse(raw: Option<&str>) -> Result<Self, String> {
+        match raw.filter(|s| !s.is_empty()) {
+            None => Ok(Self::Cidr),
+            Some(v) => {
+                validate_enum_filter("external", v, &["cidr", "world"])?;
+                Ok(if v == "world" {
+                    Self::World
+                } else {
+                    Self::Cidr
+                })
+            }
+        }
+    }
+}
+
+/// `cilium.io/v2` CiliumNetworkPolicy or CiliumClusterwideNetworkPolicy.
+/// One rule goes in `spec`; several (clusterwide, when namespaces
+/// select the workload differently) in `specs`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CiliumPolicy {
+    pub api_version: &'static str,
+    pub kind: &'static str,
+    pub metadata: ObjectMeta,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spec: Option<CiliumRule>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub specs: Vec<CiliumRule>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CiliumRule {
+    pub endpoint_selector: EndpointSelector,
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingress: Option<Vec<CiliumIngressRule>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub egress: Option<Vec<CiliumEgressRule>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointSelector {
+    pub match_labels: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub match_expressions: Vec<MatchExpression>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+pub struct MatchExpression {
+    pub key: String,
+    pub operator: &'static str,
+    pub values: Vec<String>,
+}
+
+/// How a peer is written in a Cilium rule.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CiliumPeer {
+    Endpoints(EndpointSelector),
+    Entities(&'static [&'static str]),
+    Cidr(String),
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CiliumIngressRule {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub from_endpoints: Vec<EndpointSelector>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub from_entities: Vec<&'static str>,
+    #[serde(rename = "fromCIDR", skip_serializing_if = "Vec::is_empty")]
+    pub from_cidr: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub to_ports: Vec<CiliumPortRule>,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CiliumEgressRule {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub to_endpoints: Vec<EndpointSelector>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub to_entities: Vec<&'static str>,
+    #[serde(rename = "toCIDR", skip_serializing_if = "Vec::is_empty")]
+    pub to_cidr: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub to_ports: Vec<CiliumPortRule>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CiliumPortRule {
+    pub ports: Vec<CiliumPortProtocol>,
+}
+
+/// Cilium takes the port as a string (it also accepts named ports).
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CiliumPortProtocol {
+    pub port: String,
+    pub protocol: &'static str,
+}
+
+/// A CiliumNetworkPolicy for a workload, built from its observed traffic.
+#[utoipa::path(
+    tag = "policies",
+    params(CiliumQuery),
+    responses(
+        (status = 200, description = "Generated policy, as JSON or YAML per `format`", content((CiliumPolicy = "application/json"), (CiliumPolicy = "application/yaml"))),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+        (status = 404, description = "No data found", body = ErrorBody),
+        (status = 422, description = "The workload has no labels to select it by", body = ErrorBody),
+    )
+)]
+#[get("/policy/ciliumnetworkpolicy/{namespace}/{workload}")]
+pub async fn get_cilium_network_policy(
+    pool: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    query: web::Query<CiliumQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("generate ciliumnetworkpolicy for workload");
+    let (namespace, workload) = path.into_inner();
+    let (format, external, range) = parse_query(&query)?;
+    let input = web::block(move || {
+        let mut conn = pool.get()?;
+        load_policy_input(&mut conn, &namespace, &workload, range)
+    })
+    .await??;
+
+    let input = input.ok_or(Error::NotFound)?;
+    check_target(&input).map_err(Error::Unprocessable)?;
+    format.respond(&build_cilium_policy(&input, external))
+}
+
+/// A CiliumClusterwideNetworkPolicy for a workload across every namespace.
+#[utoipa::path(
+    tag = "policies",
+    params(CiliumQuery),
+    responses(
+        (status = 200, description = "Generated policy, as JSON or YAML per `format`", content((CiliumPolicy = "application/json"), (CiliumPolicy = "application/yaml"))),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+        (status = 404, description = "No data found", body = ErrorBody),
+        (status = 422, description = "The workload has no labels to select it by", body = ErrorBody),
+    )
+)]
+#[get("/policy/ciliumclusterwidenetworkpolicy/{workload}")]
+pub async fn get_cilium_clusterwide_policy(
+    pool: web::Data<DbPool>,
+    path: web::Path<String>,
+    query: web::Query<CiliumQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("generate ciliumclusterwidenetworkpolicy for workload");
+    let workload = path.into_inner();
+    let (format, external, range) = parse_query(&query)?;
+    let inputs = web::block(move || {
+        let mut conn = pool.get()?;
+        let mut inputs = Vec::new();
+        for ns in workload_namespaces(&mut conn, &workload)? {
+            inputs.extend(load_policy_input(&mut conn, &ns, &workload, range)?);
+        }
+        Ok::<_, crate::policy::DbError>(inputs)
+    })
+    .await??;
+
+    if inputs.is_empty() {
+        return Err(Error::NotFound);
+    }
+    inputs
+        .iter()
+        .try_for_each(check_target)
+        .map_err(Error::Unprocessable)?;
+    format.respond(&build_cilium_clusterwide_policy(&inputs, external))
+}
+
+fn parse_query(q: &CiliumQuery) -> Result<(OutputFormat, ExternalPeers, TimeRange), String> {
+    Ok((
+        OutputFormat::parse(q.format.as_deref())?,
+        ExternalPeers::parse(q.external.as_deref())?,
+        TimeRange::parse(q.since.as_deref(), q.until.as_deref())?,
+    ))
+}
+
+/// Reasons a workload can't get a Cilium endpoint policy.
+fn check_target(input: &PolicyInput) -> Result<(), String> {
+    let t = &input.target;
+    if t.host_network {
+        return Err(format!(
+            "workload {}/{} runs with hostNetwork; Cilium endpoint policies don't apply to host-networked pods",
+            t.namespace, t.workload
+        ));
+    }
+    if t.selector.is_empty() {
+        return Err(no_selector(input));
+    }
+    Ok(())
+}
+
+pub fn build_cilium_policy(input: &PolicyInput, external: ExternalPeers) -> CiliumPolicy {
+    let t = &input.target;
+    let selector = endpoint_selector(&t.selector, None);
+    let grouped = group_flows(input, |p| cilium_peer(p, external));
+    let deny_all = grouped.ingress.is_empty() && grouped.egress.is_empty();
+    let kind = if deny_all {
+        "cilium-policy-deny-all"
+    } else {
+        "cilium-policy"
+    };
+    let description = if deny_all {
+        format!(
+            "Default-deny Cilium network policy for workload {}",
+            t.workload
+        )
+    } else {
+        format!(
+            "Cilium network policy for workload {} generated by kguardian",
+            t.workload
+        )
+    };
+    CiliumPolicy {
+        api_version: "cilium.io/v2",
+        kind: "CiliumNetworkPolicy",
+        metadata: object_meta(Some(t.namespace.clone()), &t.workload, kind),
+        spec: Some(cilium_rule(selector, description, grouped)),
+        specs: Vec::new(),
+    }
+}
+
+/// Namespaces sharing a selector, and their merged flows.
+type SelectorGroup<'a> = (Vec<&'a str>, GroupedFlows<CiliumPeer>);
+
+/// One rule per distinct selector across the inputs (one per namespace
+/// the workload runs in), restricted to those namespaces.
+pub fn build_cilium_clusterwide_policy(
+    inputs: &[PolicyInput],
+    external: ExternalPeers,
+) -> CiliumPolicy {
+    let workload = inputs
+        .first()
+        .map(|i| i.target.workload.clone())
+        .unwrap_or_default();
+    let mut groups: BTreeMap<&BTreeMap<String, String>, SelectorGroup> = BTreeMap::new();
+    for input in inputs {
+        let (namespaces, merged) = groups.entry(&input.target.selector).or_insert_with(|| {
+            (
+                Vec::new(),
+                GroupedFlows {
+                    ingress: BTreeMap::new(),
+                    egress: BTreeMap::new(),
+                },
+            )
+        });
+        namespaces.push(&input.target.namespace);
+        let grouped = group_flows(input, |p| cilium_peer(p, external));
+        for (peer, ports) in grouped.ingress {
+            merged.ingress.entry(peer).or_default().extend(ports);
+        }
+        for (peer, ports) in grouped.egress {
+            merged.egress.entry(peer).or_default().extend(ports);
+        }
+    }
+    let mut rules: Vec<CiliumRule> = groups
+        .into_iter()
+        .map(|(labels, (namespaces, grouped))| {
+            let description = format!(
+                "Cilium clusterwide network policy for workload {workload} in {} generated by kguardian",
+                namespaces.join(", ")
+            );
+            cilium_rule(endpoint_selector(labels, Some(&namespaces)), description, grouped)
+        })
+        .collect();
+    let (spec, specs) = if rules.len() == 1 {
+        (rules.pop(), Vec::new())
+    } else {
+        (None, rules)
+    };
+    CiliumPolicy {
+        api_version: "cilium.io/v2",
+        kind: "CiliumClusterwideNetworkPolicy",
+        metadata: object_meta(None, &workload, "cilium-clusterwide-policy"),
+        spec,
+        specs,
+    }
+}
+
+/// A rule from grouped flows. With no flows at all, one empty rule per
+/// direction: that puts the endpoints in default deny while allowing
+/// nothing, where omitting both sections would leave them unenforced.
+fn cilium_rule(
+    endpoint_selector: EndpointSelector,
+    description: String,
+    grouped: GroupedFlows<CiliumPeer>,
+) -> CiliumRule {
+    let GroupedFlows { ingress, egress } = grouped;
+    if ingress.is_empty() && egress.is_empty() {
+        return CiliumRule {
+            endpoint_selector,
+            description,
+            ingress: Some(vec![CiliumIngressRule::default()]),
+            egress: Some(vec![CiliumEgressRule::default()]),
+        };
+    }
+    CiliumRule {
+        endpoint_selector,
+        description,
+        ingress: (!ingress.is_empty()).then(|| {
+            ingress
+                .into_iter()
+                .map(|(peer, ports)| {
+                    let mut rule = CiliumIngressRule {
+                        to_ports: port_rules(ports),
+                        ..Default::default()
+                    };
+                    match peer {
+                        CiliumPeer::Endpoints(s) => rule.from_endpoints.push(s),
+                        CiliumPeer::Entities(e) => rule.from_entities.extend(e),
+                        CiliumPeer::Cidr(c) => rule.from_cidr.push(c),
+                    }
+                    rule
+                })
+                .collect()
+        }),
+        egress: (!egress.is_empty()).then(|| {
+            egress
+                .into_iter()
+                .map(|(peer, ports)| {
+                    let mut rule = CiliumEgressRule {
+                        to_ports: port_rules(ports),
+                        ..Default::default()
+                    };
+                    match peer {
+                        CiliumPeer::Endpoints(s) => rule.to_endpoints.push(s),
+                        CiliumPeer::Entities(e) => rule.to_entities.extend(e),
+                        CiliumPeer::Cidr(c) => rule.to_cidr.push(c),
+                    }
+                    rule
+                })
+                .collect()
+        }),
+    }
+}
+
+fn cilium_peer(peer: &PolicyPeer, external: ExternalPeers) -> CiliumPeer {
+    match peer {
+        PolicyPeer::Selector { namespace, labels } => {
+            let mut selector = endpoint_selector(labels, None);
+            selector
+                .match_labels
+                .insert(CILIUM_NAMESPACE_LABEL.to_string(), namespace.clone());
+            CiliumPeer::Endpoints(selector)
+        }
+        // `host` is the local node only; the peer may be on any node.
+        PolicyPeer::Host(_) => CiliumPeer::Entities(&["host", "remote-node"]),
+        PolicyPeer::ApiServer(_) => CiliumPeer::Entities(&["kube-apiserver"]),
+        PolicyPeer::Cidr(cidr) => match external {
+            ExternalPeers::Cidr => CiliumPeer::Cidr(cidr.clone()),
+            ExternalPeers::World => CiliumPeer::Entities(&["world"]),
+        },
+    }
+}
+
+/// `labels` with the `k8s:` source prefix, optionally limited to
+/// `namespaces`.
+fn endpoint_selector(
+    labels: &BTreeMap<String, String>,
+    namespaces: Option<&[&str]>,
+) -> EndpointSelector {
+    EndpointSelector {
+        match_labels: labels
+            .iter()
+            .map(|(k, v)| (format!("k8s:{k}"), v.clone()))
+            .collect(),
+        match_expressions: namespaces
+            .map(|ns| {
+                vec![MatchExpression {
+                    key: CILIUM_NAMESPACE_LABEL.to_string(),
+                    operator: "In",
+                    values: ns.iter().map(|n| n.to_string()).collect(),
+                }]
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// One port rule per port, as the advisor writes them.
+fn port_rules(ports: BTreeSet<Port>) -> Vec<CiliumPortRule> {
+    ports
+        .into_iter()
+        .map(|p| CiliumPortRule {
+            ports: vec![CiliumPortProtocol {
+                port: p.port.to_string(),
+                protocol: p.protocol,
+            }],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::PolicyTarget;
+    use crate::WorkloadTraffic;
+    use chrono::NaiveDateTime;
+
+    fn egress(peer: &str, port: &str) -> WorkloadTraffic {
+        WorkloadTraffic {
+            traffic_type: Some("EGRESS".to_string()),
+            ip_protocol: Some("TCP".to_string()),
+            pod_port: Some("0".to_string()),
+            traffic_in_out_ip: Some(peer.to_string()),
+            traffic_in_out_port: Some(port.to_string()),
+            decision: Some("ALLOW".to_string()),
+            first_seen: NaiveDateTime::default(),
+            last_seen: NaiveDateTime::default(),
+            seen_count: 1,
+            pod_count: 1,
+        }
+    }
+
+    fn input(ns: &str, flows: Vec<WorkloadTraffic>) -> PolicyInput {
+        PolicyInput {
+            target: PolicyTarget {
+                namespace: ns.to_string(),
+                workload: "web".to_string(),
+                selector: BTreeMap::from([("app".to_string(), "web".to_string())]),
+                host_network: false,
+            },
+            flows,
+            peers: [
+                (
+                    "10.0.0.1",
+                    PolicyPeer::Selector {
+                        namespace: "data".to_string(),
+                        labels: BTreeMap::from([("app".to_string(), "postgres".to_string())]),
+                    },
+                ),
+                (
+                    "10.96.0.1",
+                    PolicyPeer::ApiServer("10.96.0.1/32".to_string()),
+                ),
+                (
+                    "192.168.1.10",
+                    PolicyPeer::Host("192.168.1.10/32".to_string()),
+                ),
+                ("8.8.8.8", PolicyPeer::Cidr("8.8.8.8/32".to_string())),
+            ]
+            .into_iter()
+            .map(|(ip, p)| (ip.to_string(), p))
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn peers_map_to_endpoints_entities_and_cidrs() {
+        let p = build_cilium_policy(
+            &input(
+                "prod",
+                vec![
+                    egress("10.0.0.1", "5432"),
+                    egress("10.96.0.1", "443"),
+                    egress("192.168.1.10", "9100"),
+                    egress("8.8.8.8", "443"),
+                ],
+            ),
+            ExternalPeers::Cidr,
+        );
+        let v = serde_json::to_value(&p).unwrap();
+        let spec = &v["spec"];
+        assert_eq!(spec["endpointSelector"]["matchLabels"]["k8s:app"], "web");
+        let egress = spec["egress"].as_array().unwrap();
+        // Cross-namespace endpoints must carry their namespace, or
+        // Cilium reads them as the policy's own.
+        let db = egress
+            .iter()
+            .find(|r| r.get("toEndpoints").is_some())
+            .unwrap();
+        assert_eq!(
+            db["toEndpoints"][0]["matchLabels"][CILIUM_NAMESPACE_LABEL],
+            "data"
+        );
+        assert_eq!(db["toPorts"][0]["ports"][0]["port"], "5432");
+        let entities: Vec<_> = egress
+            .iter()
+            .filter_map(|r| r.get("toEntities"))
+            .cloned()
+            .collect();
+        assert!(entities.contains(&serde_json::json!(["kube-apiserver"])));
+        assert!(entities.contains(&serde_json::json!(["host", "remote-node"])));
+        assert!(egress
+            .iter()
+            .any(|r| r["toCIDR"] == serde_json::json!(["8.8.8.8/32"])));
+    }
+
+    #[test]
+    fn external_world_folds_cidrs_into_one_rule() {
+        let mut i = input(
+            "prod",
+            vec![egress("8.8.8.8", "443"), egress("1.1.1.1", "53")],
+        );
+        i.peers.insert(
+            "1.1.1.1".to_string(),
+            PolicyPeer::Cidr("1.1.1.1/32".to_string()),
+        );
+        let p = build_cilium_policy(&i, ExternalPeers::World);
+        let egress = p.spec.unwrap().egress.unwrap();
+        assert_eq!(egress.len(), 1);
+        assert_eq!(egress[0].to_entities, ["world"]);
+        assert_eq!(egress[0].to_ports.len(), 2);
+    }
+
+    #[test]
+    fn no_flows_is_default_deny() {
+        // Omitting ingress/egress entirely would leave the pods
+        // unenforced; an empty rule per direction denies everything.
+        let p = build_cilium_policy(&input("prod", vec![]), ExternalPeers::Cidr);
+        assert_eq!(p.metadata.name, "web-cilium-policy-deny-all");
+        let yaml = serde_yaml::to_string(&p).unwrap();
+        assert!(
+            yaml.contains("ingress:\n  - {}") && yaml.contains("egress:\n  - {}"),
+            "{yaml}"
+        );
+    }
+
+    #[test]
+    fn clusterwide_merges_namespaces_sharing_a_selector() {
+        let p = build_cilium_clusterwide_policy(
+            &[
+                input("prod", vec![egress("10.0.0.1", "5432")]),
+                input(
+                    "staging",
+                    vec![egress("10.0.0.1", "5432"), egress("8.8.8.8", "443")],
+                ),
+            ],
+            ExternalPeers::Cidr,
+        );
+        assert_eq!(p.kind, "CiliumClusterwideNetworkPolicy");
+        assert!(p.metadata.namespace.is_none());
+        assert!(p.specs.is_empty());
+        let spec = p.spec.unwrap();
+        assert_eq!(
+            spec.endpoint_selector.match_expressions[0].values,
+            ["prod", "staging"]
+        );
+        assert_eq!(spec.egress.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn clusterwide_splits_differing_selectors_into_specs() {
+        let mut other = input("legacy", vec![egress("8.8.8.8", "443")]);
+        other.target.selector = BTreeMap::from([("name".to_string(), "web".to_string())]);
+        let p = build_cilium_clusterwide_policy(
+            &[input("prod", vec![egress("10.0.0.1", "5432")]), other],
+            ExternalPeers::Cidr,
+        );
+        assert!(p.spec.is_none());
+        assert_eq!(p.specs.len(), 2);
+    }
+
+    #[test]
+    fn host_network_target_is_rejected() {
+        let mut i = input("prod", vec![]);
+        i.target.host_network = true;
+        let err = check_target(&i).unwrap_err();
+        assert!(err.contains("hostNetwork"), "{err}");
+    }
+}
@@ -0,0 +1,270 @@
+//! Opaque keyset-pagination cursors for the traffic and verdict reads.
+//!
+//! `/pod/traffic`, `/pod/traffic/{name}` and `/audit/verdicts` all read
+//! most-recent-first with a primary-key tiebreak — `(time_stamp DESC,
+//! uuid DESC)` and `(last_observed_at DESC, id DESC)` respectively;
+//! `/audit/verdicts?sort=hits` reads `(hits DESC, id DESC)`. A cursor
+//! is the sort key of the last row on a page; the next page is
+//! everything strictly after it in that ordering. That turns "page N"
+//! into an index range scan (no OFFSET re-reading N pages of rows) and
+//! keeps pages stable while new rows are inserted at the head. The
+//! `sort=hits` order is the exception: see `VerdictHitsCursor`.
+//!
+//! The token is hex-encoded `<kind>|<unix micros or hits>|<key>` so callers
+//! treat it as opaque. `kind` stops a traffic cursor being replayed
+//! against `/audit/verdicts` (or vice versa) and silently matching
+//! nothing. Hex rather than base64 keeps the token URL-safe without a
+//! new dependency.
+
+use chrono::{DateTime, NaiveDateTime};
+
+/// Response header carrying the cursor for the next page. Absent on
+/// the last page. A header (not a body envelope) keeps the JSON array
+/// body byte-identical for existing consumers.
+pub const NEXT_CURSOR_HEADER: &str = "X-Next-Cursor";
+
+const TRAFFIC_KIND: &str = "t";
+const VERDICT_KIND: &str = "v";
+const VERDICT_HITS_KIND: &str = "h";
+
+/// Position after a `pod_traffic` row in `(time_stamp DESC, uuid DESC)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrafficCursor {
+    pub time_stamp: NaiveDateTime,
+    pub uuid: String,
+}
+
+/// Position after an `audit_verdicts` row in `(last_observed_at DESC, id DESC)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerdictCursor {
+    pub last_observed_at: NaiveDateTime,
+    pub id: i64,
+}
+
+/// Position after an `audit_verdicts` row in `(hits DESC, id DESC)`.
+/// Best-effort: hit counts keep growing while a caller pages, so a row
+/// can move across the cursor between requests and be skipped or
+/// returned twice. There is no snapshot to page instead, since hit counts
+/// aren't versioned. The keyset still never loops.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerdictHitsCursor {
+    pub hits: i64,
+    pub id: i64,
+}
+
+impl TrafficCursor {
+    pub fn encode(&self) -> String {
+        encode(TRAFFIC_KIND, self.time_stamp, &self.uuid)
+    }
+
+    pub fn decode(raw: &str) -> Result<Self, String> {
+        let (time_stamp, uuid) = decode(TRAFFIC_KIND, raw)?;
+        if uuid.is_empty() {
+            return Err(invalid_cursor(raw));
+        }
+        Ok(Self { time_stamp, uuid })
+    }
+}
+
+impl VerdictCursor {
+    pub fn encode(&self) -> String {
+        encode(VERDICT_KIND, self.last_observed_at, &self.id.to_string())
+    }
+
+    pub fn decode(raw: &str) -> Result<Self, String> {
+        let (last_observed_at, key) = decode(VERDICT_KIND, raw)?;
+        let id = key.parse::<i64>().map_err(|_| invalid_cursor(raw))?;
+        Ok(Self {
+            last_observed_at,
+            id,
+        })
+    }
+}
+
+impl VerdictHitsCursor {
+    pub fn encode(&self) -> String {
+        encode_parts(VERDICT_HITS_KIND, self.hits, &self.id.to_string())
+    }
+
+    pub fn decode(raw: &str) -> Result<Self, String> {
+        let (hits, key) = decode_parts(VERDICT_HITS_KIND, raw)?;
+        let id = key.parse::<i64>().map_err(|_| invalid_cursor(raw))?;
+        Ok(Self { hits, id })
+    }
+}
+
+fn encode(kind: &str, ts: NaiveDateTime, key: &str) -> String {
+    encode_parts(kind, ts.and_utc().timestamp_micros(), key)
+}
+
+fn encode_parts(kind: &str, n: i64, key: &str) -> String {
+    let plain = format!("{kind}|{n}|{key}");
+    plain.bytes().map(|b| format!("{b:02x}")).collect()
+}
+
+fn decode(kind: &str, raw: &str) -> Result<(NaiveDateTime, String), String> {
+    let (micros, key) = decode_parts(kind, raw)?;
+    let ts = DateTime::from_timestamp_micros(micros)
+        .ok_or_else(|| invalid_cursor(raw))?
+        .naive_utc();
+    Ok((ts, key))
+}
+
+fn decode_parts(kind: &str, raw: &str) -> Result<(i64, String), String> {
+    let bytes = hex_decode(raw.trim()).ok_or_else(|| invalid_cursor(raw))?;
+    let plain = String::from_utf8(bytes).map_err(|_| invalid_cursor(raw))?;
+    // splitn(3): the key is the remainder, so a '|' inside it survives.
+    let mut parts = plain.splitn(3, '|');
+    let (Some(got_kind), Some(n), Some(key)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid_cursor(raw));
+    };
+    if got_kind != kind {
+        return Err(invalid_cursor(raw));
+    }
+    let n = n.parse::<i64>().map_err(|_| invalid_cursor(raw))?;
+    Ok((n, key.to_string()))
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.is_empty() || !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| s.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect()
+}
+
+/// The 400 body for a malformed or foreign cursor. Names the field and
+/// value, same shape as `validate_enum_filter`'s message.
+fn invalid_cursor(raw: &str) -> String {
+    format!("invalid cursor={raw:?}; pass back the X-Next-Cursor value unchanged")
+}
+
+/// Split a `limit + 1` over-fetch into the page and the cursor for the
+/// next one. Reads fetch one extra row so the last page is detected
+/// without a COUNT: if the extra row came back there is more to read,
+/// and the cursor points after the last row actually returned.
+pub fn split_page<T, C>(
+    mut rows: Vec<T>,
+    limit: i64,
+    cursor_of: impl Fn(&T) -> C,
+) -> (Vec<T>, Option<C>) {
+    let limit = usize::try_from(limit).unwrap_or(0);
+    if rows.len() <= limit {
+        return (rows, None);
+    }
+    rows.truncate(limit);
+    let next = rows.last().map(cursor_of);
+    (rows, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(micros: i64) -> NaiveDateTime {
+        DateTime::from_timestamp_micros(micros).unwrap().naive_utc()
+    }
+
+    #[test]
+    fn traffic_cursor_round_trips() {
+        let c = TrafficCursor {
+            time_stamp: ts(1_778_582_334_123_456),
+            uuid: "550e8400-e29b-41d4-a716-446655440000".to_string(),
+        };
+        assert_eq!(TrafficCursor::decode(&c.encode()), Ok(c));
+    }
+
+    #[test]
+    fn verdict_cursor_round_trips() {
+        let c = VerdictCursor {
+            last_observed_at: ts(1_778_582_334_000_001),
+            id: 987_654_321,
+        };
+        assert_eq!(VerdictCursor::decode(&c.encode()), Ok(c));
+    }
+
+    #[test]
+    fn verdict_hits_cursor_round_trips() {
+        let c = VerdictHitsCursor {
+            hits: 1_234_567,
+            id: 987_654_321,
+        };
+        assert_eq!(VerdictHitsCursor::decode(&c.encode()), Ok(c));
+    }
+
+    #[test]
+    fn cursor_preserves_microsecond_precision() {
+        // Batch ingest stamps rows microseconds apart; truncating to
+        // millis would make the next page skip or repeat tie rows.
+        let c = TrafficCursor {
+            time_stamp: ts(1_000_000_000_000_001),
+            uuid: "u".to_string(),
+        };
+        let back = TrafficCursor::decode(&c.encode()).unwrap();
+        assert_eq!(
+            back.time_stamp.and_utc().timestamp_micros(),
+            1_000_000_000_000_001
+        );
+    }
+
+    #[test]
+    fn cursor_kinds_do_not_cross() {
+        // A traffic cursor replayed against /audit/verdicts must 400,
+        // not decode into a position that silently matches nothing.
+        let t = TrafficCursor {
+            time_stamp: ts(0),
+            uuid: "42".to_string(),
+        };
+        assert!(VerdictCursor::decode(&t.encode()).is_err());
+        let v = VerdictCursor {
+            last_observed_at: ts(0),
+            id: 42,
+        };
+        assert!(TrafficCursor::decode(&v.encode()).is_err());
+        // Nor may a recency cursor be replayed under ?sort=hits, where
+        // its micros would be read as a hit count.
+        assert!(VerdictHitsCursor::decode(&v.encode()).is_err());
+        let h = VerdictHitsCursor { hits: 42, id: 42 };
+        assert!(VerdictCursor::decode(&h.encode()).is_err());
+    }
+
+    #[test]
+    fn cursor_rejects_garbage() {
+        for bad in ["", "zz", "abc", "not-a-cursor", "7c7c", "747c787c75"] {
+            assert!(
+                TrafficCursor::decode(bad).is_err(),
+                "{bad:?} must be rejected"
+            );
+            assert!(
+                VerdictCursor::decode(bad).is_err(),
+                "{bad:?} must be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_cursor_error_names_field() {
+        let err = TrafficCursor::decode("nope").unwrap_err();
+        assert!(err.contains("cursor"), "error must name field: {err}");
+        assert!(err.contains("nope"), "error must name value: {err}");
+    }
+
+    #[test]
+    fn split_page_emits_cursor_only_when_over_fetched() {
+        // limit+1 rows back → more to read; cursor is the last KEPT row.
+        let (page, next) = split_page(vec![5, 4, 3, 2], 3, |n| *n);
+        assert_eq!(page, vec![5, 4, 3]);
+        assert_eq!(next, Some(3));
+
+        // Exactly `limit` rows → last page, no cursor.
+        let (page, next) = split_page(vec![5, 4, 3], 3, |n| *n);
+        assert_eq!(page, vec![5, 4, 3]);
+        assert_eq!(next, None);
+
+        let (page, next) = split_page(Vec::<i32>::new(), 3, |n| *n);
+        assert!(page.is_empty());
+        assert_eq!(next, None);
+    }
+}
@@ -0,0 +1,205 @@
+//! `?enrich=true` on the traffic reads: resolve each row's
+//! `traffic_in_out_ip` to the pod or Service behind it.
+//!
+//! Consumers used to do this themselves with one `/pod/ip/{ip}` and
+//! `/svc/ip/{ip}` call per peer. Here the page is read as before and
+//! its distinct peer IPs are resolved in one query that joins
+//! `pod_details` and `svc_details`, so enrichment costs one extra round
+//! trip per page, whatever the number of rows.
+
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{Array, Nullable, Text};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use utoipa::ToSchema;
+
+type DbError = Box<dyn std::error::Error + Send + Sync>;
+
+/// What a peer IP resolved to. `peer_kind` is `pod`, `service` or
+/// `external`; the other fields are null for `external`, and
+/// `peer_identity` (the pod's `pod_identity`) is null for services.
+#[derive(Debug, Clone, PartialEq, Serialize, QueryableByName, ToSchema)]
+pub struct Peer {
+    #[diesel(sql_type = Text)]
+    pub peer_kind: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub peer_namespace: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub peer_name: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub peer_identity: Option<String>,
+}
+
+impl Peer {
+    fn external() -> Self {
+        Self {
+            peer_kind: "external".to_string(),
+            peer_namespace: None,
+            peer_name: None,
+            peer_identity: None,
+        }
+    }
+}
+
+/// A read's row with its peer fields alongside. Both halves flatten,
+/// so an enriched row is the plain row plus four keys.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Enriched<T> {
+    #[serde(flatten)]
+    pub row: T,
+    #[serde(flatten)]
+    pub peer: Peer,
+}
+
+/// Rows that carry a peer IP.
+pub trait PeerIp {
+    fn peer_ip(&self) -> Option<&str>;
+}
+
+impl PeerIp for crate::PodTraffic {
+    fn peer_ip(&self) -> Option<&str> {
+        self.traffic_in_out_ip.as_deref()
+    }
+}
+
+impl PeerIp for crate::WorkloadTraffic {
+    fn peer_ip(&self) -> Option<&str> {
+        self.traffic_in_out_ip.as_deref()
+    }
+}
+
+/// Body of a traffic read: plain rows, or rows with peer fields when
+/// the caller asked for `enrich=true`.
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum TrafficRows<T> {
+    Plain(Vec<T>),
+    Enriched(Vec<Enriched<T>>),
+}
+
+#[derive(QueryableByName)]
+struct ResolvedPeer {
+    #[diesel(sql_type = Text)]
+    ip: String,
+    #[diesel(embed)]
+    peer: Peer,
+}
+
+/// Wrap `rows` as `TrafficRows`, resolving peers only when `enrich`.
+pub fn maybe_enrich<T: PeerIp>(
+    conn: &mut PgConnection,
+    rows: Vec<T>,
+    enrich: bool,
+) -> Result<TrafficRows<T>, DbError> {
+    if !enrich {
+        return Ok(TrafficRows::Plain(rows));
+    }
+    let ips: BTreeSet<&str> = rows.iter().filter_map(|r| r.peer_ip()).collect();
+    let ips: Vec<&str> = ips.into_iter().collect();
+    let peers = resolve_peers(conn, &ips)?;
+    Ok(TrafficRows::Enriched(attach_peers(rows, &peers)))
+}
+
+fn attach_peers<T: PeerIp>(rows: Vec<T>, peers: &HashMap<String, Peer>) -> Vec<Enriched<T>> {
+    rows.into_iter()
+        .map(|row| {
+            let peer = row
+                .peer_ip()
+                .and_then(|ip| peers.get(ip))
+                .cloned()
+                .unwrap_or_else(Peer::external);
+            Enriched { row, peer }
+        })
+        .collect()
+}
+
+/// Resolve peer IPs in one statement. Pod IPs are reused, so an IP can
+/// match several pod_details rows: a live pod wins over dead ones, then
+/// the most recently updated row. A pod match wins over a Service match;
+/// ClusterIPs and pod IPs come from disjoint ranges, so the two only
+/// meet on bad data. Resolution is as of now: an old flow to an IP
+/// since reused by another pod names the current holder.
+pub fn resolve_peers(
+    conn: &mut PgConnection,
+    ips: &[&str],
+) -> Result<HashMap<String, Peer>, DbError> {
+    if ips.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let rows = sql_query(
+        "SELECT ip.addr AS ip, \
+                CASE WHEN p.pod_name IS NOT NULL THEN 'pod' \
+                     WHEN s.svc_ip IS NOT NULL THEN 'service' \
+                     ELSE 'external' END AS peer_kind, \
+                CASE WHEN p.pod_name IS NOT NULL THEN NULLIF(p.pod_namespace, '') \
+                     ELSE s.svc_namespace END AS peer_namespace, \
+                COALESCE(p.pod_name, s.svc_name) AS peer_name, \
+                p.pod_identity AS peer_identity \
+         FROM unnest($1::text[]) AS ip(addr) \
+         LEFT JOIN LATERAL ( \
+             SELECT d.pod_name, d.pod_namespace, d.pod_identity FROM pod_details d \
+             WHERE d.pod_ip = ip.addr \
+             ORDER BY d.is_dead, d.time_stamp DESC LIMIT 1 \
+         ) p ON true \
+         LEFT JOIN svc_details s ON s.svc_ip = ip.addr",
+    )
+    .bind::<Array<Text>, _>(ips)
+    .load::<ResolvedPeer>(conn)?;
+    Ok(rows.into_iter().map(|r| (r.ip, r.peer)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PodTraffic;
+
+    fn flow(peer: Option<&str>) -> PodTraffic {
+        PodTraffic {
+            uuid: "u".to_string(),
+            traffic_in_out_ip: peer.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unresolved_and_missing_peers_are_external() {
+        let mut peers = HashMap::new();
+        peers.insert(
+            "10.0.0.5".to_string(),
+            Peer {
+                peer_kind: "pod".to_string(),
+                peer_namespace: Some("prod".to_string()),
+                peer_name: Some("web-1".to_string()),
+                peer_identity: Some("web".to_string()),
+            },
+        );
+        let rows = attach_peers(
+            vec![flow(Some("10.0.0.5")), flow(Some("1.1.1.1")), flow(None)],
+            &peers,
+        );
+        let kinds: Vec<_> = rows.iter().map(|r| r.peer.peer_kind.as_str()).collect();
+        assert_eq!(kinds, ["pod", "external", "external"]);
+        assert_eq!(rows[0].peer.peer_identity.as_deref(), Some("web"));
+    }
+
+    #[test]
+    fn enriched_row_is_plain_row_plus_peer_keys() {
+        // Consumers switch on ?enrich=true without changing how they
+        // read the existing fields, so the row must flatten, not nest.
+        let plain = serde_json::to_value(TrafficRows::Plain(vec![flow(Some("1.1.1.1"))])).unwrap();
+        let enriched = serde_json::to_value(TrafficRows::Enriched(attach_peers(
+            vec![flow(Some("1.1.1.1"))],
+            &HashMap::new(),
+        )))
+        .unwrap();
+        let (plain, enriched) = (&plain[0], &enriched[0]);
+        for (k, v) in plain.as_object().unwrap() {
+            assert_eq!(&enriched[k], v, "field {k} changed");
+        }
+        for k in ["peer_kind", "peer_namespace", "peer_name", "peer_identity"] {
+            assert!(enriched.get(k).is_some(), "missing {k}");
+            assert!(plain.get(k).is_none(), "{k} leaked into the plain row");
+        }
+    }
+}
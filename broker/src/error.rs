@@ -1,5 +1,9 @@
 use actix_web::error::BlockingError;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
 use diesel::r2d2;
+use serde::Serialize;
+use utoipa::ToSchema;
 
 /// All errors possible to occur during reconciliation
 #[derive(Debug, thiserror::Error)]
@@ -33,6 +37,46 @@ pub enum Error {
         #[from]
         source: r2d2::Error,
     },
+
+    /// Any other failure behind a handler, e.g. its `DbError`.
+    #[error("Internal Error: {source}")]
+    InternalError {
+        #[from]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// The requested pod, service, workload or job has no rows.
+    #[error("No data found")]
+    NotFound,
+
+    /// A legacy name-only pod route was given a name that exists in
+    /// several namespaces.
+    #[error("pod name {name:?} is ambiguous; it exists in namespaces {namespaces:?}; use {route}")]
+    AmbiguousPodName {
+        name: String,
+        namespaces: Vec<String>,
+        route: &'static str,
+    },
+
+    /// The request is valid but the broker's state doesn't allow it.
+    #[error("{0}")]
+    Conflict(String),
+
+    /// The rows exist but can't be turned into what was asked for.
+    #[error("{0}")]
+    Unprocessable(String),
+
+    /// A concurrency cap is reached; retry later.
+    #[error("{0}")]
+    TooManyRequests(String),
+
+    /// Missing or invalid bearer token.
+    #[error("missing or invalid bearer token")]
+    Unauthorized,
+
+    /// A valid bearer token without the scope the route needs.
+    #[error("token {token:?} lacks the {scope:?} scope for this route")]
+    Forbidden { token: String, scope: &'static str },
 }
 
 impl From<String> for Error {
@@ -40,3 +84,152 @@ impl From<String> for Error {
         Error::UserInputError(s)
     }
 }
+
+/// Body of every error response under `/v1`.
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// Stable, machine-readable error class, e.g. `invalid_argument`.
+    pub code: &'static str,
+    /// Human-readable description. Not stable; don't match on it.
+    pub message: String,
+    /// Structured context for some codes, otherwise null.
+    #[schema(required = true, value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
+}
+
+impl Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Error::UserInputError(_) => "invalid_argument",
+            Error::NotFound => "not_found",
+            Error::AmbiguousPodName { .. } => "ambiguous_pod_name",
+            Error::Conflict(_) => "conflict",
+            Error::Unprocessable(_) => "unprocessable",
+            Error::TooManyRequests(_) => "too_many_requests",
+            Error::Unauthorized => "unauthorized",
+            Error::Forbidden { .. } => "forbidden",
+            _ => "internal",
+        }
+    }
+
+    /// The `message` of the envelope, which is also the whole body on the
+    /// legacy routes. Internal errors keep their cause out of the envelope;
+    /// `error_response` logs it instead.
+    fn message(&self) -> String {
+        match self {
+            Error::UserInputError(msg) => msg.clone(),
+            _ if self.status_code().is_server_error() => "internal server error".to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        let details = match self {
+            Error::AmbiguousPodName {
+                namespaces, route, ..
+            } => Some(serde_json::json!({ "namespaces": namespaces, "route": route })),
+            Error::Forbidden { scope, .. } => Some(serde_json::json!({ "scope": scope })),
+            _ => None,
+        };
+        ErrorBody {
+            code: self.code(),
+            message: self.message(),
+            details,
+        }
+    }
+
+    /// The plain-text response the unversioned routes sent before `/v1`:
+    /// the bare message for client errors, the cause for server errors.
+    pub fn legacy_response(&self) -> HttpResponse {
+        let body = match self {
+            Error::UserInputError(msg) => msg.clone(),
+            Error::ActixWebError { source } => source.to_string(),
+            Error::InternalError { source } => source.to_string(),
+            Error::SQLError { source } => source.to_string(),
+            Error::R2D2Error { source } => source.to_string(),
+            Error::BlockingError { source } => source.to_string(),
+            _ => self.to_string(),
+        };
+        HttpResponse::build(self.status_code()).body(body)
+    }
+}
+
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::UserInputError(_) => StatusCode::BAD_REQUEST,
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::AmbiguousPodName { .. } | Error::Conflict(_) => StatusCode::CONFLICT,
+            Error::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::Forbidden { .. } => StatusCode::FORBIDDEN,
+            Error::ActixWebError { source } => source.as_response_error().status_code(),
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if self.status_code().is_server_error() {
+            tracing::error!(error = %self, "request failed");
+        }
+        HttpResponse::build(self.status_code()).json(self.body())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_input_keeps_its_message() {
+        let e = Error::from("invalid limit".to_string());
+        assert_eq!(e.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            e.body(),
+            ErrorBody {
+                code: "invalid_argument",
+                message: "invalid limit".to_string(),
+                details: None,
+            }
+        );
+    }
+
+    #[test]
+    fn ambiguous_name_lists_namespaces_in_details() {
+        let e = Error::AmbiguousPodName {
+            name: "web-0".to_string(),
+            namespaces: vec!["a".to_string(), "b".to_string()],
+            route: "/pod/traffic/{namespace}/{name}",
+        };
+        let body = e.body();
+        assert_eq!(e.status_code(), StatusCode::CONFLICT);
+        assert_eq!(body.code, "ambiguous_pod_name");
+        assert_eq!(
+            body.details,
+            Some(serde_json::json!({
+                "namespaces": ["a", "b"],
+                "route": "/pod/traffic/{namespace}/{name}",
+            }))
+        );
+    }
+
+    #[test]
+    fn internal_errors_hide_the_cause_in_the_envelope_only() {
+        let e = Error::from(diesel::result::Error::NotFound);
+        assert_eq!(e.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(e.body().code, "internal");
+        assert_eq!(e.body().message, "internal server error");
+        let legacy = e.legacy_response();
+        assert_eq!(legacy.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn legacy_not_found_body_is_unchanged() {
+        assert_eq!(Error::NotFound.to_string(), "No data found");
+        assert_eq!(
+            Error::NotFound.legacy_response().status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+}
@@ -1,46 +1,127 @@
-use crate::{schema, PodDetail, PodSyscalls, PodTraffic, SvcDetail};
-use actix_web::{get, web, HttpResponse, Responder};
+use crate::api_version::ApiVersion;
+use crate::cursor::{
+    split_page, TrafficCursor, VerdictCursor, VerdictHitsCursor, NEXT_CURSOR_HEADER,
+};
+use crate::enrich::maybe_enrich;
+use crate::{
+    schema, Error, ErrorBody, PodDetail, PodSyscallObservation, PodSyscalls, PodTraffic, SvcDetail,
+};
+use actix_web::{get, web, HttpResponse};
+use chrono::{DateTime, NaiveDateTime};
+use diesel::dsl::sql;
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
+use diesel::sql_types::{BigInt, Bool, Text, Timestamp};
 use tracing::{debug, info};
+use utoipa::IntoParams;
 
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 type DbError = Box<dyn std::error::Error + Send + Sync>;
 
+/// Recent traffic across the cluster, newest first.
+#[utoipa::path(
+    tag = "traffic",
+    params(PodTrafficQuery),
+    responses(
+        (status = 200, description = "One page of flows", body = crate::TrafficRows<PodTraffic>, headers(("X-Next-Cursor" = String, description = "Cursor for the next page; absent on the last page"))),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+    )
+)]
 #[get("/pod/traffic")]
 pub async fn get_pod_traffic(
     pool: web::Data<DbPool>,
     query: web::Query<PodTrafficQuery>,
-) -> actix_web::Result<impl Responder> {
+) -> Result<HttpResponse, Error> {
     debug!("select pod traffic table");
-    let row_limit = clamp_pod_traffic_limit(query.limit);
-    let pod_traffic = web::block(move || {
+    let q = query.into_inner();
+    let row_limit = clamp_pod_traffic_limit(q.limit);
+    let after = parse_traffic_cursor(q.cursor.as_deref())?;
+    let range = TimeRange::parse(q.since.as_deref(), q.until.as_deref())?;
+    let enrich = q.enrich;
+    let (rows, next) = web::block(move || {
         let mut conn = pool.get()?;
-        pod_traffic(&mut conn, row_limit)
+        let (rows, next) = pod_traffic(&mut conn, row_limit, after.as_ref(), range)?;
+        Ok::<_, DbError>((maybe_enrich(&mut conn, rows, enrich)?, next))
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
 
-    Ok(match pod_traffic {
-        Some(p) => HttpResponse::Ok().json(p),
-        None => HttpResponse::NotFound().body("No data found"),
-    })
+    Ok(paged_response(rows, next.map(|c| c.encode())))
 }
 
 /// Query params for the cluster-wide `GET /pod/traffic` endpoint.
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
 pub struct PodTrafficQuery {
     /// Cap rows returned (most-recent-first). Defaults to 5000, hard cap 20000.
     pub limit: Option<i64>,
+    /// Opaque `X-Next-Cursor` value from the previous page. Absent (or
+    /// empty) starts from the newest row.
+    pub cursor: Option<String>,
+    /// RFC3339; only flows still seen at or after it (`last_seen`).
+    pub since: Option<String>,
+    /// RFC3339; only flows first seen before it (`time_stamp`).
+    pub until: Option<String>,
+    /// Add peer_kind / peer_namespace / peer_name / peer_identity to
+    /// each row (see enrich.rs).
+    #[serde(default)]
+    pub enrich: bool,
+}
+
+/// Optional `[since, until)` window on a read's timestamp column.
+///
+/// Both bounds arrive as RFC3339 strings and are normalised to the
+/// UTC-naive form every timestamp column stores (the broker stamps rows
+/// with `Utc::now().naive_utc()`), so `2026-05-12T12:00:00+02:00` and
+/// `2026-05-12T10:00:00Z` select the same rows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRange {
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+}
+
+impl TimeRange {
+    /// Validate `?since=` / `?until=`. Empty values mean "no bound"
+    /// (same form-field rationale as `normalise_empty_to_none`); anything
+    /// else must be RFC3339 or the caller gets a 400 naming the field and
+    /// value, like `validate_enum_filter`. An empty or inverted window is
+    /// also a 400 — it can only ever return zero rows, which is almost
+    /// always a swapped-arguments bug rather than a real question.
+    pub(crate) fn parse(since: Option<&str>, until: Option<&str>) -> Result<Self, String> {
+        let since = since
+            .filter(|s| !s.is_empty())
+            .map(|v| validate_time_filter("since", v))
+            .transpose()?;
+        let until = until
+            .filter(|s| !s.is_empty())
+            .map(|v| validate_time_filter("until", v))
+            .transpose()?;
+        if let (Some(s), Some(u)) = (since, until) {
+            if s >= u {
+                return Err(format!(
+                    "invalid time range: since ({s}) must be before until ({u})"
+                ));
+            }
+        }
+        Ok(Self { since, until })
+    }
+}
+
+pub(crate) fn validate_time_filter(field: &str, value: &str) -> Result<NaiveDateTime, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.naive_utc())
+        .map_err(|_| {
+            format!("invalid {field}={value:?}; must be an RFC3339 timestamp (e.g. 2026-05-12T10:00:00Z)")
+        })
 }
 
 /// Clamp the caller-supplied row limit into [1, 20000] with a default of
 /// 5000 when unset. Extracted so the policy can be unit-tested without a
 /// live DB, mirroring `clamp_audit_limit`.
 ///
-/// The bound is not cosmetic: `pod_traffic` is a high-insert, never-pruned
-/// table that grows into the millions of rows (observed at 6.7M rows / 2 GB
-/// in production). The pre-bound query was an unbounded
+/// The bound is not cosmetic: `pod_traffic` is a high-insert table (pruned
+/// only when `POD_TRAFFIC_RETENTION_DAYS` is set) that grows into the
+/// millions of rows (observed at 6.7M rows / 2 GB in production). The
+/// pre-bound query was an unbounded
 /// `SELECT * ... ORDER BY time_stamp DESC` — a parallel seq-scan + sort of
 /// the whole table that took tens of seconds and serialised a multi-hundred-MB
 /// body. That stalled the broker, spiked its memory, and overran the
@@ -50,50 +131,145 @@ pub struct PodTrafficQuery {
 /// and the default 5000 is ~1.7 MB. The sole cluster-wide consumer
 /// (mcp-server's get_cluster_traffic) only aggregates the rows into per-pod
 /// counts, so a most-recent-first window is the right shape; its counts now
-/// describe the recent window rather than all history.
+/// describe the recent window rather than all history. Callers that need
+/// all history walk it page by page with `cursor`.
 pub(crate) fn clamp_pod_traffic_limit(raw: Option<i64>) -> i64 {
     raw.unwrap_or(5_000).clamp(1, 20_000)
 }
 
+/// Decode an optional `?cursor=`. Empty is treated as absent (first
+/// page) for the same form-field reason as `normalise_empty_to_none`.
+pub(crate) fn parse_traffic_cursor(raw: Option<&str>) -> Result<Option<TrafficCursor>, String> {
+    raw.filter(|s| !s.is_empty())
+        .map(TrafficCursor::decode)
+        .transpose()
+}
+
+/// Ordering of an /audit/verdicts page and where it resumes.
+#[derive(Debug, PartialEq)]
+pub enum VerdictPage {
+    /// `?sort=recent` (default): `(last_observed_at DESC, id DESC)`.
+    Recent(Option<VerdictCursor>),
+    /// `?sort=hits`: `(hits DESC, id DESC)`. Best-effort across pages,
+    /// see `VerdictHitsCursor`.
+    Hits(Option<VerdictHitsCursor>),
+}
+
+impl VerdictPage {
+    /// Validate `?sort=` and decode `?cursor=` against it. Empty values
+    /// are treated as absent, as in `parse_traffic_cursor`.
+    pub(crate) fn parse(sort: Option<&str>, cursor: Option<&str>) -> Result<Self, String> {
+        let sort = sort.filter(|s| !s.is_empty()).unwrap_or("recent");
+        validate_enum_filter("sort", sort, VALID_VERDICT_SORTS)?;
+        let cursor = cursor.filter(|s| !s.is_empty());
+        Ok(if sort == "hits" {
+            Self::Hits(cursor.map(VerdictHitsCursor::decode).transpose()?)
+        } else {
+            Self::Recent(cursor.map(VerdictCursor::decode).transpose()?)
+        })
+    }
+}
+
+/// 200 with the JSON array body, plus `X-Next-Cursor` when there is a
+/// further page. The body shape is the same as before pagination
+/// existed, so consumers that ignore the header keep working.
+fn paged_response<B: serde::Serialize>(body: B, next: Option<String>) -> HttpResponse {
+    let mut resp = HttpResponse::Ok();
+    if let Some(next) = next {
+        resp.insert_header((NEXT_CURSOR_HEADER, next));
+    }
+    resp.json(body)
+}
+
+/// Keyset predicate: rows strictly after `c` in `(time_stamp DESC, uuid
+/// DESC)` order. Written as a row-value comparison rather than the
+/// expanded `ts < x OR (ts = x AND uuid < y)` so Postgres turns it into
+/// a single range bound on idx_pod_traffic_time_stamp.
+fn traffic_after(
+    c: &TrafficCursor,
+) -> Box<dyn BoxableExpression<schema::pod_traffic::table, Pg, SqlType = Bool>> {
+    Box::new(
+        sql::<Bool>("(pod_traffic.time_stamp, pod_traffic.uuid) < (")
+            .bind::<Timestamp, _>(c.time_stamp)
+            .sql(", ")
+            .bind::<Text, _>(c.uuid.clone())
+            .sql(")"),
+    )
+}
+
+fn traffic_cursor_of(row: &PodTraffic) -> TrafficCursor {
+    TrafficCursor {
+        time_stamp: row.time_stamp,
+        uuid: row.uuid.clone(),
+    }
+}
+
 pub fn pod_traffic(
     conn: &mut PgConnection,
     row_limit: i64,
-) -> Result<Option<Vec<PodTraffic>>, DbError> {
+    after: Option<&TrafficCursor>,
+    range: TimeRange,
+) -> Result<(Vec<PodTraffic>, Option<TrafficCursor>), DbError> {
     use schema::pod_traffic::dsl::*;
 
     // Stable display order — most recent first with uuid (the PK) as
     // the tiebreak. Same UX-stability class as the audit_verdicts
-    // ORDER BY (observed_at DESC, id DESC) — without this, the
+    // ORDER BY (last_observed_at DESC, id DESC) — without this, the
     // frontend's "all pod traffic" panel reshuffled between reads as
     // Postgres heap state changed (any insert/delete shifts row
     // positions). uuid DESC is deterministic for ties in time_stamp
     // (which the broker stamps from chrono::Utc::now().naive_utc(),
     // and microsecond-level ties are common inside a batch ingest).
+    // The total order is also what makes the keyset cursor exact.
     //
     // The .limit() bounds the whole-table read (see clamp_pod_traffic_limit);
     // the idx_pod_traffic_time_stamp index (time_stamp DESC, uuid DESC) lets
     // this ORDER BY ... LIMIT run as an index scan of `row_limit` rows instead
     // of a full seq-scan + sort of the millions-of-rows table.
-    let pod = pod_traffic
+    //
+    // since/until keep the flows active in the window (`last_seen >=
+    // since`, `time_stamp < until`), as /graph does: a flow first seen
+    // before `since` that is still live belongs in it. `until` narrows
+    // the same index scan; `since` is checked on the rows it visits.
+    let mut q = pod_traffic.into_boxed();
+    if let Some(t) = range.since {
+        q = q.filter(last_seen.ge(t));
+    }
+    if let Some(t) = range.until {
+        q = q.filter(time_stamp.lt(t));
+    }
+    if let Some(c) = after {
+        q = q.filter(traffic_after(c));
+    }
+    let rows = q
         .order((time_stamp.desc(), uuid.desc()))
-        .limit(row_limit)
-        .load::<PodTraffic>(conn)
-        .optional()?;
+        .limit(row_limit + 1)
+        .select(PodTraffic::as_select())
+        .load(conn)?;
 
-    Ok(pod)
+    Ok(split_page(rows, row_limit, traffic_cursor_of))
 }
 
+/// All known pods, with their manifests reduced to labels and hostNetwork.
+#[utoipa::path(
+    tag = "pods",
+    responses(
+        (status = 200, body = Vec<PodDetail>),
+    )
+)]
 #[get("/pod/info")]
-pub async fn get_pod_details(pool: web::Data<DbPool>) -> actix_web::Result<impl Responder> {
+pub async fn get_pod_details(
+    pool: web::Data<DbPool>,
+    version: ApiVersion,
+) -> Result<HttpResponse, Error> {
     debug!("select pod details table");
     let pod_detail = web::block(move || {
         let mut conn = pool.get()?;
         pod_details(&mut conn)
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
 
-    Ok(match pod_detail {
+    match pod_detail {
         Some(mut p) => {
             // Strip the bulky parts of each stored Pod manifest before
             // returning the whole table. /pod/info is polled by the
@@ -109,10 +285,10 @@ pub async fn get_pod_details(pool: web::Data<DbPool>) -> actix_web::Result<impl
                     compact_pod_obj(obj);
                 }
             }
-            HttpResponse::Ok().json(p)
+            Ok(HttpResponse::Ok().json(p))
         }
-        None => HttpResponse::NotFound().body("No data found"),
-    })
+        None => version.no_rows::<PodDetail>(),
+    }
 }
 
 /// Reduce a stored Pod manifest to just the fields consumers need: labels
@@ -166,10 +342,8 @@ pub(crate) fn compact_svc_spec(v: &mut serde_json::Value) {
 pub fn pod_details(conn: &mut PgConnection) -> Result<Option<Vec<PodDetail>>, DbError> {
     use schema::pod_details::dsl::*;
     // Stable display order so the frontend's pod-info table doesn't
-    // reshuffle between reads. pod_namespace is Nullable — Postgres
-    // sorts NULLs LAST for ASC by default, which lands cluster-wide
-    // (namespaceless) entries at the bottom. pod_name is the PK so
-    // ties are impossible within a namespace.
+    // reshuffle between reads. (pod_namespace, pod_name) is the PK so
+    // ties are impossible; namespace-unknown rows ('') sort first.
     let pod = pod_details
         .order((pod_namespace.asc(), pod_name.asc()))
         .load::<PodDetail>(conn)
@@ -177,20 +351,25 @@ pub fn pod_details(conn: &mut PgConnection) -> Result<Option<Vec<PodDetail>>, Db
     Ok(pod)
 }
 
-// New API: Get all pods for a specific node
+/// Live pods scheduled on a node.
+#[utoipa::path(
+    tag = "pods",
+    responses(
+        (status = 200, body = Vec<PodDetail>),
+    )
+)]
 #[get("/pod/list/{node}")]
 pub async fn get_pods_by_node(
     pool: web::Data<DbPool>,
     node: web::Path<String>,
-) -> actix_web::Result<impl Responder> {
+) -> Result<HttpResponse, Error> {
     debug!("Getting pods for node: {}", node);
     let node_name = node.into_inner();
     let pods = web::block(move || {
         let mut conn = pool.get()?;
         pods_by_node(&mut conn, &node_name)
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
 
     Ok(HttpResponse::Ok().json(pods))
 }
@@ -209,20 +388,29 @@ pub fn pods_by_node(conn: &mut PgConnection, node: &str) -> Result<Vec<PodDetail
     Ok(pods)
 }
 
+/// All known services.
+#[utoipa::path(
+    tag = "services",
+    responses(
+        (status = 200, body = Vec<SvcDetail>),
+    )
+)]
 #[get("/svc/info")]
-pub async fn get_svc_details(pool: web::Data<DbPool>) -> actix_web::Result<impl Responder> {
+pub async fn get_svc_details(
+    pool: web::Data<DbPool>,
+    version: ApiVersion,
+) -> Result<HttpResponse, Error> {
     debug!("select svc details table");
     let svc_detail = web::block(move || {
         let mut conn = pool.get()?;
         svc_details_all(&mut conn)
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
 
-    Ok(match svc_detail {
-        Some(s) => HttpResponse::Ok().json(s),
-        None => HttpResponse::NotFound().body("No data found"),
-    })
+    match svc_detail {
+        Some(s) => Ok(HttpResponse::Ok().json(s)),
+        None => version.no_rows::<SvcDetail>(),
+    }
 }
 
 pub fn svc_details_all(conn: &mut PgConnection) -> Result<Option<Vec<SvcDetail>>, DbError> {
@@ -240,24 +428,29 @@ pub fn svc_details_all(conn: &mut PgConnection) -> Result<Option<Vec<SvcDetail>>
     Ok(svcs)
 }
 
+/// The service that owns a cluster IP.
+#[utoipa::path(
+    tag = "services",
+    responses(
+        (status = 200, body = SvcDetail),
+        (status = 404, description = "No data found", body = ErrorBody),
+    )
+)]
 #[get("/svc/ip/{ip}")]
 pub async fn get_svc_by_ip(
     pool: web::Data<DbPool>,
     ip: web::Path<String>,
-) -> actix_web::Result<impl Responder> {
+) -> Result<HttpResponse, Error> {
     info!("select svc details by ip");
     let ip = ip.into_inner();
     let svc_detail = web::block(move || {
         let mut conn = pool.get()?;
         svc_ip(&mut conn, &ip)
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
 
-    Ok(match svc_detail {
-        Some(p) => HttpResponse::Ok().json(p),
-        None => HttpResponse::NotFound().body("No data found"),
-    })
+    let p = svc_detail.ok_or(Error::NotFound)?;
+    Ok(HttpResponse::Ok().json(p))
 }
 
 pub fn svc_ip(conn: &mut PgConnection, ip: &str) -> Result<Option<SvcDetail>, DbError> {
@@ -269,43 +462,54 @@ pub fn svc_ip(conn: &mut PgConnection, ip: &str) -> Result<Option<SvcDetail>, Db
     Ok(svc)
 }
 
-// POD BY NAME
+/// A pod by name alone. Prefer `/pod/{namespace}/{name}`.
+#[utoipa::path(
+    tag = "pods",
+    responses(
+        (status = 200, body = PodDetail),
+        (status = 404, description = "No data found", body = ErrorBody),
+        (status = 409, description = "The pod name exists in several namespaces", body = ErrorBody),
+    )
+)]
 #[get("/pod/name/{name}")]
 pub async fn get_pod_by_name(
     pool: web::Data<DbPool>,
     name: web::Path<String>,
-) -> actix_web::Result<impl Responder> {
+) -> Result<HttpResponse, Error> {
     info!("select pod details by name");
     let name = name.into_inner();
+    let lookup_name = name.clone();
     let pod_detail = web::block(move || {
         let mut conn = pool.get()?;
-        pod_name(&mut conn, &name)
-    })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
-
-    Ok(match pod_detail {
-        Some(p) => HttpResponse::Ok().json(p),
-        None => HttpResponse::NotFound().body("No data found"),
+        by_unambiguous_name(&mut conn, &lookup_name, |conn| pod_name(conn, &lookup_name))
     })
+    .await??;
+
+    match pod_detail {
+        ByName::Found(Some(p)) => Ok(HttpResponse::Ok().json(p)),
+        ByName::Found(None) => Err(Error::NotFound),
+        ByName::Ambiguous(namespaces) => Err(Error::AmbiguousPodName {
+            name,
+            namespaces,
+            route: "/pod/{namespace}/{name}",
+        }),
+    }
 }
 
 pub fn pod_name(conn: &mut PgConnection, name: &str) -> Result<Option<PodDetail>, DbError> {
     use schema::pod_details::dsl::*;
-    // pod_details PK is pod_name (per the iteration-86 schema fix and
-    // confirmed in schema.rs), so this filter matches AT MOST one row.
-    // The upsert on /pod/spec uses on_conflict(pod_name).do_update(),
-    // so a StatefulSet pod restarting reusing the same name replaces
-    // the old row in place — the dead entry never survives alongside
-    // the new live entry.
+    // pod_details is keyed on (pod_namespace, pod_name). Callers go
+    // through by_unambiguous_name first, so the name lives in at most
+    // one namespace and this filter matches AT MOST one row. The
+    // upsert on /pod/spec conflicts on that key, so a StatefulSet pod
+    // restarting under the same name replaces the old row in place —
+    // the dead entry never survives alongside the new live entry.
     //
-    // The (is_dead ASC, time_stamp DESC) ordering is now defense-in-
-    // depth: it's a no-op for a single-row result, but if a future
-    // schema migration ever permits multiple rows per pod_name (e.g.,
-    // a join with a workload_revisions side-table), this query would
-    // still surface the alive-and-current row first — falling back to
-    // the most-recent dead entry only when nothing is alive. Cheap
-    // insurance against a schema regression.
+    // The (is_dead ASC, time_stamp DESC) ordering is defense-in-depth:
+    // it's a no-op for a single-row result, but should several rows
+    // ever match it still surfaces the alive-and-current row first —
+    // falling back to the most-recent dead entry only when nothing is
+    // alive.
     let pod = pod_details
         .filter(pod_name.eq(name.to_string()))
         .order((is_dead.asc(), time_stamp.desc()))
@@ -314,25 +518,103 @@ pub fn pod_name(conn: &mut PgConnection, name: &str) -> Result<Option<PodDetail>
     Ok(pod)
 }
 
-// POD BY IP
+// POD BY NAMESPACE + NAME
+//
+// Registered after every other two-segment /pod/... GET in main.rs:
+// actix matches in registration order, and /pod/{namespace}/{name}
+// would otherwise swallow /pod/name/x, /pod/ip/x, /pod/list/x,
+// /pod/traffic/x and /pod/syscalls/x.
+
+/// A pod by namespace and name.
+#[utoipa::path(
+    tag = "pods",
+    responses(
+        (status = 200, body = PodDetail),
+        (status = 404, description = "No data found", body = ErrorBody),
+    )
+)]
+#[get("/pod/{namespace}/{name}")]
+pub async fn get_pod_by_namespaced_name(
+    pool: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, Error> {
+    info!("select pod details by namespace and name");
+    let (namespace, name) = path.into_inner();
+    let pod_detail = web::block(move || {
+        let mut conn = pool.get()?;
+        pod_by_namespaced_name(&mut conn, &namespace, &name)
+    })
+    .await??;
+
+    let p = pod_detail.ok_or(Error::NotFound)?;
+    Ok(HttpResponse::Ok().json(p))
+}
+
+pub fn pod_by_namespaced_name(
+    conn: &mut PgConnection,
+    namespace: &str,
+    name: &str,
+) -> Result<Option<PodDetail>, DbError> {
+    use schema::pod_details::dsl::*;
+    let pod = pod_details
+        .find((namespace, name))
+        .first::<PodDetail>(conn)
+        .optional()?;
+    Ok(pod)
+}
+
+/// Result of a legacy name-only read. Pod names are only unique within
+/// a namespace, so a name that pod_details knows in several namespaces
+/// can't be resolved and the read is not attempted.
+pub(crate) enum ByName<T> {
+    Found(T),
+    Ambiguous(Vec<String>),
+}
+
+/// Run `read` only if `name` exists in at most one namespace in
+/// pod_details (served by idx_pod_details_pod_name). A name pod_details
+/// doesn't know at all — e.g. a dead pod already pruned — is not
+/// ambiguous, so the legacy routes keep returning its traffic/syscalls.
+pub(crate) fn by_unambiguous_name<T>(
+    conn: &mut PgConnection,
+    name: &str,
+    read: impl FnOnce(&mut PgConnection) -> Result<T, DbError>,
+) -> Result<ByName<T>, DbError> {
+    use schema::pod_details::dsl::*;
+    let namespaces: Vec<String> = pod_details
+        .filter(pod_name.eq(name))
+        .select(pod_namespace)
+        .order(pod_namespace.asc())
+        .load(conn)?;
+    if namespaces.len() > 1 {
+        return Ok(ByName::Ambiguous(namespaces));
+    }
+    Ok(ByName::Found(read(conn)?))
+}
+
+/// The pod that owns an IP.
+#[utoipa::path(
+    tag = "pods",
+    responses(
+        (status = 200, body = PodDetail),
+        (status = 404, description = "No data found", body = ErrorBody),
+    )
+)]
 #[get("/pod/ip/{ip}")]
 pub async fn get_pod_by_ip(
     pool: web::Data<DbPool>,
     ip: web::Path<String>,
-) -> actix_web::Result<impl Responder> {
+) -> Result<HttpResponse, Error> {
     info!("select pod details by ip");
     let ip = ip.into_inner();
     let pod_detail = web::block(move || {
         let mut conn = pool.get()?;
         pod_ip(&mut conn, &ip)
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
 
-    Ok(match pod_detail {
-        Some(p) => HttpResponse::Ok().json(p),
-        None => HttpResponse::NotFound().body("No data found"),
-    })
+    let p = pod_detail.ok_or(Error::NotFound)?;
+    Ok(HttpResponse::Ok().json(p))
 }
 
 pub fn pod_ip(conn: &mut PgConnection, ip: &str) -> Result<Option<PodDetail>, DbError> {
@@ -344,31 +626,133 @@ pub fn pod_ip(conn: &mut PgConnection, ip: &str) -> Result<Option<PodDetail>, Db
     Ok(pod)
 }
 
-// POD TRAFFIC BY PODNAME
+/// Traffic of a pod by name alone. Prefer `/pod/traffic/{namespace}/{name}`.
+#[utoipa::path(
+    tag = "traffic",
+    params(PodTrafficNameQuery),
+    responses(
+        (status = 200, description = "The pod's flows, newest first", body = crate::TrafficRows<PodTraffic>, headers(("X-Next-Cursor" = String, description = "Cursor for the next page; absent on the last page"))),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+        (status = 409, description = "The pod name exists in several namespaces", body = ErrorBody),
+    )
+)]
 #[get("/pod/traffic/{name}")]
 pub async fn get_pod_traffic_name(
     pool: web::Data<DbPool>,
     name: web::Path<String>,
-) -> actix_web::Result<impl Responder> {
+    query: web::Query<PodTrafficNameQuery>,
+) -> Result<HttpResponse, Error> {
     info!("select pod traffic for the pod name");
     let pod_name = name.into_inner();
-    let pod_detail = web::block(move || {
+    let q = query.into_inner();
+    let after = parse_traffic_cursor(q.cursor.as_deref())?;
+    let range = TimeRange::parse(q.since.as_deref(), q.until.as_deref())?;
+    let page = pod_traffic_page_limit(q.limit, after.is_some());
+    let lookup_name = pod_name.clone();
+    let enrich = q.enrich;
+    let result = web::block(move || {
         let mut conn = pool.get()?;
-        pod_traffic_by_name(&mut conn, &pod_name)
+        by_unambiguous_name(&mut conn, &lookup_name, |conn| {
+            let (rows, next) =
+                pod_traffic_by_name(conn, None, &lookup_name, page, after.as_ref(), range)?;
+            Ok((maybe_enrich(conn, rows, enrich)?, next))
+        })
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
+
+    match result {
+        ByName::Found((rows, next)) => Ok(paged_response(rows, next.map(|c| c.encode()))),
+        ByName::Ambiguous(namespaces) => Err(Error::AmbiguousPodName {
+            name: pod_name,
+            namespaces,
+            route: "/pod/traffic/{namespace}/{name}",
+        }),
+    }
+}
 
-    Ok(match pod_detail {
-        Some(p) => HttpResponse::Ok().json(p),
-        None => HttpResponse::NotFound().body("No data found"),
+/// Traffic of a pod by namespace and name.
+#[utoipa::path(
+    tag = "traffic",
+    params(PodTrafficNameQuery),
+    responses(
+        (status = 200, description = "The pod's flows, newest first", body = crate::TrafficRows<PodTraffic>, headers(("X-Next-Cursor" = String, description = "Cursor for the next page; absent on the last page"))),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+    )
+)]
+#[get("/pod/traffic/{namespace}/{name}")]
+pub async fn get_pod_traffic_namespaced_name(
+    pool: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    query: web::Query<PodTrafficNameQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("select pod traffic for the pod namespace and name");
+    let (namespace, pod_name) = path.into_inner();
+    let q = query.into_inner();
+    let after = parse_traffic_cursor(q.cursor.as_deref())?;
+    let range = TimeRange::parse(q.since.as_deref(), q.until.as_deref())?;
+    let page = pod_traffic_page_limit(q.limit, after.is_some());
+    let enrich = q.enrich;
+    let (rows, next) = web::block(move || {
+        let mut conn = pool.get()?;
+        let (rows, next) = pod_traffic_by_name(
+            &mut conn,
+            Some(&namespace),
+            &pod_name,
+            page,
+            after.as_ref(),
+            range,
+        )?;
+        Ok::<_, DbError>((maybe_enrich(&mut conn, rows, enrich)?, next))
     })
+    .await??;
+
+    Ok(paged_response(rows, next.map(|c| c.encode())))
+}
+
+/// Query params for `GET /pod/traffic/{name}` and
+/// `GET /pod/traffic/{namespace}/{name}`.
+#[derive(serde::Deserialize, IntoParams)]
+pub struct PodTrafficNameQuery {
+    /// Page size, clamped like `/pod/traffic`. Only applied when set (or
+    /// when `cursor` is set) — see `pod_traffic_page_limit`.
+    pub limit: Option<i64>,
+    /// Opaque `X-Next-Cursor` value from the previous page.
+    pub cursor: Option<String>,
+    /// RFC3339; only flows still seen at or after it (`last_seen`).
+    pub since: Option<String>,
+    /// RFC3339; only flows first seen before it (`time_stamp`).
+    pub until: Option<String>,
+    /// Add peer_kind / peer_namespace / peer_name / peer_identity to
+    /// each row (see enrich.rs).
+    #[serde(default)]
+    pub enrich: bool,
 }
 
+/// Page size for the per-pod read, or `None` for the legacy full read.
+///
+/// The advisor's policy generator reads `/pod/traffic/{name}` without
+/// parameters and expects the pod's whole (deduped) history, so that
+/// shape stays unbounded. Any paging parameter opts in to the same
+/// clamp as the cluster-wide endpoint.
+pub(crate) fn pod_traffic_page_limit(limit: Option<i64>, has_cursor: bool) -> Option<i64> {
+    if limit.is_some() || has_cursor {
+        Some(clamp_pod_traffic_limit(limit))
+    } else {
+        None
+    }
+}
+
+/// Traffic of one pod, optionally pinned to a namespace. `None` is the
+/// legacy name-only read. A pinned read also returns the pod's rows with
+/// no namespace (NULL or '').
 pub fn pod_traffic_by_name(
     conn: &mut PgConnection,
+    namespace: Option<&str>,
     name: &str,
-) -> Result<Option<Vec<PodTraffic>>, DbError> {
+    page: Option<i64>,
+    after: Option<&TrafficCursor>,
+    range: TimeRange,
+) -> Result<(Vec<PodTraffic>, Option<TrafficCursor>), DbError> {
     use schema::pod_traffic::dsl::*;
     // See pod_traffic() for the (time_stamp DESC, uuid DESC) rationale.
     // This is also what the advisor's policy generator reads via
@@ -376,48 +760,202 @@ pub fn pod_traffic_by_name(
     // (deduplicatePorts) already produces deterministic YAML, but
     // stable input here means simpler reasoning + fewer surprises if
     // a future generator change becomes input-order sensitive.
-    let pod_tr = pod_traffic
+    let mut q = pod_traffic
         .filter(pod_name.eq(name.to_string()))
+        .into_boxed();
+    if let Some(ns) = namespace {
+        // Rows from before namespaces were recorded carry NULL, and
+        // controllers that can't resolve one send ''. Neither can be
+        // attributed, so they are kept, as on the syscall reads.
+        q = q.filter(
+            pod_namespace
+                .eq_any([ns.to_string(), String::new()])
+                .or(pod_namespace.is_null()),
+        );
+    }
+    // Same activity-overlap window as pod_traffic().
+    if let Some(t) = range.since {
+        q = q.filter(last_seen.ge(t));
+    }
+    if let Some(t) = range.until {
+        q = q.filter(time_stamp.lt(t));
+    }
+    if let Some(c) = after {
+        q = q.filter(traffic_after(c));
+    }
+    let q = q
         .order((time_stamp.desc(), uuid.desc()))
-        .load::<PodTraffic>(conn)
-        .optional()?;
-    Ok(pod_tr)
+        .select(PodTraffic::as_select());
+    match page {
+        Some(row_limit) => {
+            let rows = q.limit(row_limit + 1).load(conn)?;
+            Ok(split_page(rows, row_limit, traffic_cursor_of))
+        }
+        None => Ok((q.load(conn)?, None)),
+    }
 }
 
-// POD SYS CALLS BY PODNAME
+/// Syscalls of a pod by name alone. Prefer `/pod/syscalls/{namespace}/{name}`.
+#[utoipa::path(
+    tag = "syscalls",
+    params(TimeRangeQuery),
+    responses(
+        (status = 200, body = PodSyscalls),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+        (status = 404, description = "No data found", body = ErrorBody),
+        (status = 409, description = "The pod name exists in several namespaces", body = ErrorBody),
+    )
+)]
 #[get("/pod/syscalls/{name}")]
 pub async fn get_pod_syscall_name(
     pool: web::Data<DbPool>,
     name: web::Path<String>,
-) -> actix_web::Result<impl Responder> {
+    query: web::Query<TimeRangeQuery>,
+) -> Result<HttpResponse, Error> {
     info!("select pod syscall for the pod name");
     let pod_name = name.into_inner();
+    let range = TimeRange::parse(query.since.as_deref(), query.until.as_deref())?;
+    let lookup_name = pod_name.clone();
     let pod_syscalls = web::block(move || {
         let mut conn = pool.get()?;
-        pod_syscalls_by_name(&mut conn, &pod_name)
+        by_unambiguous_name(&mut conn, &lookup_name, |conn| {
+            pod_syscalls_by_name(conn, None, &lookup_name, range)
+        })
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    .await??;
+
+    match pod_syscalls {
+        ByName::Found(Some(p)) => Ok(HttpResponse::Ok().json(p)),
+        ByName::Found(None) => Err(Error::NotFound),
+        ByName::Ambiguous(namespaces) => Err(Error::AmbiguousPodName {
+            name: pod_name,
+            namespaces,
+            route: "/pod/syscalls/{namespace}/{name}",
+        }),
+    }
+}
 
-    Ok(match pod_syscalls {
-        Some(p) => HttpResponse::Ok().json(p),
-        None => HttpResponse::NotFound().body("No data found"),
+/// Syscalls of a pod by namespace and name.
+#[utoipa::path(
+    tag = "syscalls",
+    params(TimeRangeQuery),
+    responses(
+        (status = 200, body = PodSyscalls),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+        (status = 404, description = "No data found", body = ErrorBody),
+    )
+)]
+#[get("/pod/syscalls/{namespace}/{name}")]
+pub async fn get_pod_syscall_namespaced_name(
+    pool: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    query: web::Query<TimeRangeQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("select pod syscall for the pod namespace and name");
+    let (namespace, pod_name) = path.into_inner();
+    let range = TimeRange::parse(query.since.as_deref(), query.until.as_deref())?;
+    let pod_syscalls = web::block(move || {
+        let mut conn = pool.get()?;
+        pod_syscalls_by_name(&mut conn, Some(&namespace), &pod_name, range)
     })
+    .await??;
+
+    let p = pod_syscalls.ok_or(Error::NotFound)?;
+    Ok(HttpResponse::Ok().json(p))
 }
 
+/// Each syscall a pod made, with when it was first and last seen.
+#[utoipa::path(
+    tag = "syscalls",
+    params(TimeRangeQuery),
+    responses(
+        (status = 200, body = Vec<PodSyscallObservation>),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+    )
+)]
+#[get("/pod/syscalls/{namespace}/{name}/observations")]
+pub async fn get_pod_syscall_observations(
+    pool: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    query: web::Query<TimeRangeQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("select pod syscall observations for the pod namespace and name");
+    let (namespace, pod_name) = path.into_inner();
+    let range = TimeRange::parse(query.since.as_deref(), query.until.as_deref())?;
+    let observations = web::block(move || {
+        let mut conn = pool.get()?;
+        pod_syscall_observations_by_name(&mut conn, &namespace, &pod_name, range)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(observations))
+}
+
+/// Per-syscall observations of one pod, newest first appearance first.
+/// `since`/`until` bound `first_seen`, so `since=<deploy time>` lists
+/// the syscalls the pod started making after that deploy.
+pub fn pod_syscall_observations_by_name(
+    conn: &mut PgConnection,
+    namespace: &str,
+    name: &str,
+    range: TimeRange,
+) -> Result<Vec<PodSyscallObservation>, DbError> {
+    use schema::pod_syscall_observations::dsl::*;
+    // '' rows come from controllers that didn't send a namespace and
+    // whose pod name the broker couldn't resolve; kept for the same
+    // reason as in pod_syscalls_by_name.
+    let mut q = pod_syscall_observations
+        .filter(pod_name.eq(name.to_string()))
+        .filter(pod_namespace.eq_any([namespace.to_string(), String::new()]))
+        .into_boxed();
+    if let Some(t) = range.since {
+        q = q.filter(first_seen.ge(t));
+    }
+    if let Some(t) = range.until {
+        q = q.filter(first_seen.lt(t));
+    }
+    Ok(q.order((first_seen.desc(), syscall.asc(), arch.asc()))
+        .load::<PodSyscallObservation>(conn)?)
+}
+
+/// Query params for reads that take only a time window.
+#[derive(serde::Deserialize, IntoParams)]
+pub struct TimeRangeQuery {
+    /// RFC3339 lower bound (inclusive).
+    pub since: Option<String>,
+    /// RFC3339 upper bound (exclusive).
+    pub until: Option<String>,
+}
+
+/// Syscall rows of one pod, optionally pinned to a namespace.
 pub fn pod_syscalls_by_name(
     conn: &mut PgConnection,
+    namespace: Option<&str>,
     name: &str,
+    range: TimeRange,
 ) -> Result<Option<Vec<PodSyscalls>>, DbError> {
     use schema::pod_syscalls::dsl::*;
-    let pod_tr = pod_syscalls
+    let mut q = pod_syscalls
         .filter(pod_name.eq(name.to_string()))
-        .load::<PodSyscalls>(conn)
-        .optional()?;
+        .into_boxed();
+    if let Some(ns) = namespace {
+        // Controllers that don't resolve the namespace for syscall
+        // events send '' — such a row can't be attributed, so it is
+        // kept rather than hiding the pod's syscalls entirely.
+        q = q.filter(pod_namespace.eq_any([ns.to_string(), String::new()]));
+    }
+    // time_stamp is when the pod's syscall row was first recorded.
+    if let Some(t) = range.since {
+        q = q.filter(time_stamp.ge(t));
+    }
+    if let Some(t) = range.until {
+        q = q.filter(time_stamp.lt(t));
+    }
+    let pod_tr = q.load::<PodSyscalls>(conn).optional()?;
     Ok(pod_tr)
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
 pub struct AuditVerdictsQuery {
     /// Filter to a single policy by name. Combine with a concrete `namespace`
     /// for an AuditNetworkPolicy; for cluster-scoped verdicts send `namespace=`
@@ -426,7 +964,7 @@ pub struct AuditVerdictsQuery {
     pub policy: Option<String>,
     pub namespace: Option<String>,
     /// Filter rows by verdict — "Allow" or "WouldDeny". The DB has the
-    /// (verdict, observed_at) composite index from the audit_verdict_column
+    /// (verdict, last_observed_at) composite index from the audit_verdict_column
     /// migration, so server-side filtering is index-backed; without this
     /// filter the frontends Would-Deny view has to pull both verdicts
     /// then drop Allow client-side, burning the row limit.
@@ -434,8 +972,20 @@ pub struct AuditVerdictsQuery {
     /// Filter rows by direction — "Ingress" or "Egress". Pairs with the
     /// frontend tabs that split each direction.
     pub direction: Option<String>,
-    /// Cap rows returned. Defaults to 100, hard cap 500.
+    /// `recent` (default) orders by last sighting, `hits` by how many
+    /// evaluations produced the verdict. Paging `hits` is best-effort:
+    /// counts rise between pages, so rows can be skipped or repeated.
+    pub sort: Option<String>,
+    /// Cap rows returned. Defaults to 100, hard cap 500. Walk further
+    /// back with `cursor`.
     pub limit: Option<i64>,
+    /// Opaque `X-Next-Cursor` value from the previous page, issued
+    /// under the same `sort`.
+    pub cursor: Option<String>,
+    /// RFC3339; only verdicts last observed at or after it.
+    pub since: Option<String>,
+    /// RFC3339; only verdicts first observed before it.
+    pub until: Option<String>,
 }
 
 /// Clamp the caller-supplied row limit into the [1, 500] window with a
@@ -475,6 +1025,8 @@ pub(crate) fn normalise_empty_to_none(raw: Option<String>) -> Option<String> {
 const VALID_VERDICTS: &[&str] = &["Allow", "WouldDeny"];
 /// Whitelist of valid direction values. See VALID_VERDICTS above.
 const VALID_DIRECTIONS: &[&str] = &["Ingress", "Egress"];
+/// `?sort=` values for /audit/verdicts.
+const VALID_VERDICT_SORTS: &[&str] = &["recent", "hits"];
 
 pub(crate) fn validate_enum_filter(
     field: &str,
@@ -490,11 +1042,40 @@ pub(crate) fn validate_enum_filter(
     }
 }
 
+/// A verdict as the unversioned `/audit/verdicts` serves it. Adds the
+/// deprecated `observed_at`, the name `last_observed_at` had before
+/// verdicts were rolled up, for consumers that still read it.
+#[derive(serde::Serialize)]
+struct LegacyAuditVerdict {
+    #[serde(flatten)]
+    verdict: crate::AuditVerdict,
+    observed_at: NaiveDateTime,
+}
+
+impl From<crate::AuditVerdict> for LegacyAuditVerdict {
+    fn from(verdict: crate::AuditVerdict) -> Self {
+        Self {
+            observed_at: verdict.last_observed_at,
+            verdict,
+        }
+    }
+}
+
+/// Stored audit verdicts.
+#[utoipa::path(
+    tag = "audit",
+    params(AuditVerdictsQuery),
+    responses(
+        (status = 200, description = "One page of verdicts", body = Vec<crate::AuditVerdict>, headers(("X-Next-Cursor" = String, description = "Cursor for the next page; absent on the last page. Best-effort under sort=hits: rows whose hit count changes between pages can be skipped or repeated"))),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+    )
+)]
 #[get("/audit/verdicts")]
 pub async fn get_audit_verdicts(
     pool: web::Data<DbPool>,
+    version: ApiVersion,
     query: web::Query<AuditVerdictsQuery>,
-) -> actix_web::Result<impl Responder> {
+) -> Result<HttpResponse, Error> {
     let q = query.into_inner();
     let limit = clamp_audit_limit(q.limit);
     let policy_name = normalise_empty_to_none(q.policy.clone());
@@ -516,16 +1097,14 @@ pub async fn get_audit_verdicts(
     let verdict_filter = normalise_empty_to_none(q.verdict.clone());
     let direction_filter = normalise_empty_to_none(q.direction.clone());
     if let Some(v) = verdict_filter.as_deref() {
-        if let Err(msg) = validate_enum_filter("verdict", v, VALID_VERDICTS) {
-            return Ok(HttpResponse::BadRequest().body(msg));
-        }
+        validate_enum_filter("verdict", v, VALID_VERDICTS)?;
     }
     if let Some(d) = direction_filter.as_deref() {
-        if let Err(msg) = validate_enum_filter("direction", d, VALID_DIRECTIONS) {
-            return Ok(HttpResponse::BadRequest().body(msg));
-        }
+        validate_enum_filter("direction", d, VALID_DIRECTIONS)?;
     }
-    let rows = web::block(move || {
+    let page = VerdictPage::parse(q.sort.as_deref(), q.cursor.as_deref())?;
+    let range = TimeRange::parse(q.since.as_deref(), q.until.as_deref())?;
+    let (rows, next) = web::block(move || {
         let mut conn = pool.get()?;
         audit_verdicts_query(
             &mut conn,
@@ -534,14 +1113,27 @@ pub async fn get_audit_verdicts(
             verdict_filter,
             direction_filter,
             limit,
+            page,
+            range,
         )
     })
-    .await?
-    .map_err(actix_web::error::ErrorInternalServerError)?;
-
-    Ok(HttpResponse::Ok().json(rows))
+    .await??;
+
+    Ok(match version {
+        ApiVersion::V1 => paged_response(rows, next),
+        ApiVersion::Legacy => paged_response(
+            rows.into_iter()
+                .map(LegacyAuditVerdict::from)
+                .collect::<Vec<_>>(),
+            next,
+        ),
+    })
 }
 
+/// One page of /audit/verdicts and the encoded cursor for the next.
+/// Each row is one distinct verdict, so `since`/`until` select the rows
+/// whose [first, last] sighting overlaps the window.
+#[allow(clippy::too_many_arguments)]
 pub fn audit_verdicts_query(
     conn: &mut PgConnection,
     by_policy: Option<String>,
@@ -549,7 +1141,9 @@ pub fn audit_verdicts_query(
     by_verdict: Option<String>,
     by_direction: Option<String>,
     row_limit: i64,
-) -> Result<Vec<crate::AuditVerdict>, DbError> {
+    page: VerdictPage,
+    range: TimeRange,
+) -> Result<(Vec<crate::AuditVerdict>, Option<String>), DbError> {
     use schema::audit_verdicts::dsl::*;
     let mut q = audit_verdicts.into_boxed();
     if let Some(name) = by_policy {
@@ -564,25 +1158,96 @@ pub fn audit_verdicts_query(
     if let Some(d) = by_direction {
         q = q.filter(direction.eq(d));
     }
-    // Tie-break by id DESC. Without it, multiple rows that share the
-    // same observed_at (the broker stamps with Utc::now().naive_utc()
-    // and microsecond-level ties are common when a single ingest
-    // batch produces N verdicts) come back in arbitrary order from
-    // postgres — every repeat of the same request reshuffles the
-    // top-N visible to the frontend's Would-Deny view. id is the
-    // BIGSERIAL PK (monotonic), so id DESC is a deterministic stand-
-    // in for "most recently inserted" within the same observed_at.
-    let rows = q
-        .order((observed_at.desc(), id.desc()))
-        .limit(row_limit)
-        .load::<crate::AuditVerdict>(conn)?;
-    Ok(rows)
+    if let Some(t) = range.since {
+        q = q.filter(last_observed_at.ge(t));
+    }
+    if let Some(t) = range.until {
+        q = q.filter(first_observed_at.lt(t));
+    }
+    // Tie-break by id DESC. Without it, rows that share the sort key
+    // (microsecond ties are common when a single ingest batch produces
+    // N verdicts; hit counts tie all the time) come back in arbitrary
+    // order from postgres — every repeat of the same request
+    // reshuffles the top-N visible to the frontend's Would-Deny view.
+    // id is the BIGSERIAL PK, so the order is total and the keyset
+    // predicates below (row-value comparisons, same shape as
+    // traffic_after) never skip or repeat a tie.
+    match page {
+        VerdictPage::Recent(after) => {
+            if let Some(c) = after {
+                q = q.filter(
+                    sql::<Bool>("(audit_verdicts.last_observed_at, audit_verdicts.id) < (")
+                        .bind::<Timestamp, _>(c.last_observed_at)
+                        .sql(", ")
+                        .bind::<BigInt, _>(c.id)
+                        .sql(")"),
+                );
+            }
+            let rows = q
+                .order((last_observed_at.desc(), id.desc()))
+                .limit(row_limit + 1)
+                .load::<crate::AuditVerdict>(conn)?;
+            let (rows, next) = split_page(rows, row_limit, |v| VerdictCursor {
+                last_observed_at: v.last_observed_at,
+                id: v.id,
+            });
+            Ok((rows, next.map(|c| c.encode())))
+        }
+        VerdictPage::Hits(after) => {
+            if let Some(c) = after {
+                q = q.filter(
+                    sql::<Bool>("(audit_verdicts.hits, audit_verdicts.id) < (")
+                        .bind::<BigInt, _>(c.hits)
+                        .sql(", ")
+                        .bind::<BigInt, _>(c.id)
+                        .sql(")"),
+                );
+            }
+            let rows = q
+                .order((hits.desc(), id.desc()))
+                .limit(row_limit + 1)
+                .load::<crate::AuditVerdict>(conn)?;
+            let (rows, next) = split_page(rows, row_limit, |v| VerdictHitsCursor {
+                hits: v.hits,
+                id: v.id,
+            });
+            Ok((rows, next.map(|c| c.encode())))
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn legacy_verdict_keeps_observed_at() {
+        let last = chrono::DateTime::from_timestamp(1_800_000_000, 0)
+            .unwrap()
+            .naive_utc();
+        let v = crate::AuditVerdict {
+            id: 1,
+            policy_uid: "u".to_string(),
+            policy_namespace: "prod".to_string(),
+            policy_name: "p".to_string(),
+            direction: "Egress".to_string(),
+            src_namespace: None,
+            src_pod: None,
+            dst_namespace: None,
+            dst_pod: None,
+            dst_port: 443,
+            protocol: "TCP".to_string(),
+            reason: None,
+            last_observed_at: last,
+            verdict: "Allow".to_string(),
+            first_observed_at: last - chrono::Duration::hours(1),
+            hits: 2,
+        };
+        let json = serde_json::to_value(LegacyAuditVerdict::from(v)).unwrap();
+        assert_eq!(json["observed_at"], json["last_observed_at"]);
+        assert_eq!(json["hits"], 2);
+    }
+
     #[test]
     fn compact_pod_obj_drops_bulk_keeps_labels() {
         // Guards the /pod/info weight fix: the response must drop the
@@ -825,6 +1490,142 @@ mod tests {
         assert!(r.is_err(), "non-numeric limit must fail to parse");
     }
 
+    #[test]
+    fn pod_traffic_query_parses_cursor() {
+        let q: PodTrafficQuery =
+            serde_urlencoded::from_str("limit=10&cursor=abcd").expect("must parse");
+        assert_eq!(q.cursor.as_deref(), Some("abcd"));
+    }
+
+    #[test]
+    fn empty_cursor_means_first_page() {
+        // `?cursor=` from a form/tool with the field blank starts at the
+        // head rather than 400ing.
+        assert_eq!(parse_traffic_cursor(Some("")), Ok(None));
+        assert_eq!(parse_traffic_cursor(None), Ok(None));
+        assert_eq!(
+            VerdictPage::parse(Some(""), Some("")),
+            Ok(VerdictPage::Recent(None))
+        );
+    }
+
+    #[test]
+    fn malformed_cursor_is_rejected() {
+        assert!(parse_traffic_cursor(Some("garbage")).is_err());
+        assert!(VerdictPage::parse(None, Some("garbage")).is_err());
+    }
+
+    #[test]
+    fn verdict_sort_selects_cursor_kind() {
+        // A cursor only resumes the ordering that issued it; one from
+        // ?sort=recent replayed with ?sort=hits must 400, not be read
+        // as a hit count.
+        let recent = VerdictCursor {
+            last_observed_at: NaiveDateTime::default(),
+            id: 7,
+        };
+        assert_eq!(
+            VerdictPage::parse(None, Some(&recent.encode())),
+            Ok(VerdictPage::Recent(Some(recent.clone())))
+        );
+        assert!(VerdictPage::parse(Some("hits"), Some(&recent.encode())).is_err());
+        let hits = VerdictHitsCursor { hits: 12, id: 7 };
+        assert_eq!(
+            VerdictPage::parse(Some("hits"), Some(&hits.encode())),
+            Ok(VerdictPage::Hits(Some(hits.clone())))
+        );
+        assert!(VerdictPage::parse(Some("recent"), Some(&hits.encode())).is_err());
+    }
+
+    #[test]
+    fn unknown_verdict_sort_is_rejected() {
+        let err = VerdictPage::parse(Some("oldest"), None).unwrap_err();
+        assert!(err.contains("sort") && err.contains("oldest"), "{err}");
+    }
+
+    #[test]
+    fn per_pod_read_stays_unbounded_without_paging_params() {
+        // The advisor reads /pod/traffic/{name} bare and needs the whole
+        // history; only an explicit limit or cursor opts in to paging.
+        assert_eq!(pod_traffic_page_limit(None, false), None);
+        assert_eq!(pod_traffic_page_limit(Some(50), false), Some(50));
+        assert_eq!(pod_traffic_page_limit(None, true), Some(5_000));
+        assert_eq!(pod_traffic_page_limit(Some(1_000_000), true), Some(20_000));
+    }
+
+    #[test]
+    fn time_range_parses_rfc3339_and_normalises_to_utc() {
+        let r = TimeRange::parse(
+            Some("2026-05-12T12:00:00+02:00"),
+            Some("2026-05-13T00:00:00Z"),
+        )
+        .expect("valid window");
+        assert_eq!(
+            r.since,
+            Some(
+                chrono::NaiveDate::from_ymd_opt(2026, 5, 12)
+                    .unwrap()
+                    .and_hms_opt(10, 0, 0)
+                    .unwrap()
+            ),
+            "offset must be folded into the UTC-naive stored form"
+        );
+        assert!(r.until.is_some());
+    }
+
+    #[test]
+    fn time_range_bounds_are_optional() {
+        assert_eq!(TimeRange::parse(None, None), Ok(TimeRange::default()));
+        // Empty form fields mean "no bound", not a 400.
+        assert_eq!(
+            TimeRange::parse(Some(""), Some("")),
+            Ok(TimeRange::default())
+        );
+        let only_since = TimeRange::parse(Some("2026-05-12T00:00:00Z"), None).unwrap();
+        assert!(only_since.since.is_some() && only_since.until.is_none());
+    }
+
+    #[test]
+    fn time_range_rejects_non_rfc3339() {
+        // Date-only, epoch seconds, and naive (zone-less) timestamps are
+        // all ambiguous about the zone; reject instead of guessing.
+        for bad in [
+            "2026-05-12",
+            "1778582334",
+            "2026-05-12T10:00:00",
+            "yesterday",
+        ] {
+            let err = TimeRange::parse(Some(bad), None).unwrap_err();
+            assert!(err.contains("since"), "error must name field: {err}");
+            assert!(err.contains(bad), "error must name value: {err}");
+        }
+        assert!(TimeRange::parse(None, Some("nope"))
+            .unwrap_err()
+            .contains("until"));
+    }
+
+    #[test]
+    fn time_range_rejects_empty_or_inverted_window() {
+        assert!(
+            TimeRange::parse(Some("2026-05-13T00:00:00Z"), Some("2026-05-12T00:00:00Z")).is_err()
+        );
+        assert!(
+            TimeRange::parse(Some("2026-05-12T00:00:00Z"), Some("2026-05-12T00:00:00Z")).is_err()
+        );
+    }
+
+    #[test]
+    fn queries_parse_since_and_until() {
+        let q: PodTrafficQuery =
+            serde_urlencoded::from_str("since=2026-05-12T00:00:00Z&until=2026-05-13T00:00:00Z")
+                .expect("must parse");
+        assert_eq!(q.since.as_deref(), Some("2026-05-12T00:00:00Z"));
+        assert_eq!(q.until.as_deref(), Some("2026-05-13T00:00:00Z"));
+        let q = parse_query("since=2026-05-12T00:00:00Z");
+        assert_eq!(q.since.as_deref(), Some("2026-05-12T00:00:00Z"));
+        assert!(q.until.is_none());
+    }
+
     #[test]
     fn normalise_empty_to_none_empty_string_becomes_none() {
         // `?policy=` on the wire serdes to Some("") via web::Query
@@ -943,4 +1744,100 @@ mod tests {
         assert!(err.contains("verdict"), "error must name field: {err}");
         assert!(err.contains("Maybe"), "error must name value: {err}");
     }
+
+    #[test]
+    fn ambiguous_pod_name_names_namespaces_and_route() {
+        // The 409 must tell the caller both why (which namespaces share
+        // the name) and what to call instead.
+        let msg = Error::AmbiguousPodName {
+            name: "web-1".to_string(),
+            namespaces: vec!["prod".to_string(), "staging".to_string()],
+            route: "/pod/traffic/{namespace}/{name}",
+        }
+        .to_string();
+        assert!(msg.contains("\"web-1\""), "must name the pod: {msg}");
+        assert!(msg.contains("prod") && msg.contains("staging"), "{msg}");
+        assert!(msg.contains("/pod/traffic/{namespace}/{name}"), "{msg}");
+    }
+
+    #[test]
+    #[ignore = "requires a database"]
+    fn namespaced_traffic_keeps_namespace_unknown_rows() {
+        use schema::pod_traffic::dsl as t;
+
+        let mut conn = crate::test_support::db();
+        conn.test_transaction::<_, DbError, _>(|conn| {
+            let name = "get-test-web-1";
+            let rows = [
+                ("a", Some("get-test")),
+                ("b", None),
+                ("c", Some("")),
+                ("d", Some("get-test-other")),
+            ];
+            for (id, ns) in rows {
+                diesel::insert_into(t::pod_traffic)
+                    .values((
+                        t::uuid.eq(format!("get-test-{id}")),
+                        t::pod_name.eq(name),
+                        t::pod_namespace.eq(ns),
+                        t::pod_ip.eq(format!("get-test-{id}")),
+                        t::time_stamp.eq(NaiveDateTime::default()),
+                        t::last_seen.eq(NaiveDateTime::default()),
+                    ))
+                    .execute(conn)?;
+            }
+            let (got, _) = pod_traffic_by_name(
+                conn,
+                Some("get-test"),
+                name,
+                None,
+                None,
+                TimeRange::default(),
+            )?;
+            let mut ids: Vec<_> = got.into_iter().map(|r| r.uuid).collect();
+            ids.sort();
+            assert_eq!(ids, ["get-test-a", "get-test-b", "get-test-c"]);
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[ignore = "requires a database"]
+    fn traffic_window_keeps_flows_active_in_it() {
+        use schema::pod_traffic::dsl as t;
+
+        let ts = |s: &str| chrono::DateTime::parse_from_rfc3339(s).unwrap().naive_utc();
+        let mut conn = crate::test_support::db();
+        conn.test_transaction::<_, DbError, _>(|conn| {
+            let name = "get-test-window";
+            let rows = [
+                // First seen before `since`, still live in the window.
+                ("a", "2026-05-01T00:00:00Z", "2026-05-06T00:00:00Z"),
+                // Last seen before `since`.
+                ("b", "2026-05-01T00:00:00Z", "2026-05-04T00:00:00Z"),
+                ("c", "2026-05-05T10:00:00Z", "2026-05-05T11:00:00Z"),
+                // First seen at `until`.
+                ("d", "2026-05-07T00:00:00Z", "2026-05-07T01:00:00Z"),
+            ];
+            for (id, first, last) in rows {
+                diesel::insert_into(t::pod_traffic)
+                    .values((
+                        t::uuid.eq(format!("get-test-window-{id}")),
+                        t::pod_name.eq(name),
+                        t::pod_ip.eq(format!("get-test-window-{id}")),
+                        t::time_stamp.eq(ts(first)),
+                        t::last_seen.eq(ts(last)),
+                    ))
+                    .execute(conn)?;
+            }
+            let range = TimeRange {
+                since: Some(ts("2026-05-05T09:00:00Z")),
+                until: Some(ts("2026-05-07T00:00:00Z")),
+            };
+            let (got, _) = pod_traffic_by_name(conn, None, name, None, None, range)?;
+            let ids: Vec<_> = got.into_iter().map(|r| r.uuid).collect();
+            assert_eq!(ids, ["get-test-window-c", "get-test-window-a"]);
+            Ok(())
+        });
+    }
 }
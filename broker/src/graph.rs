@@ -0,0 +1,327 @@
+//! `GET /graph`: the service-dependency graph, aggregated in Postgres.
+//!
+//! The frontend used to download `/pod/traffic` (capped at 20000 rows),
+//! `/pod/info` and `/svc/info` and stitch the graph client-side, so on
+//! a large cluster it drew whatever fit under the cap. Here pod_traffic
+//! is folded down to one row per edge before anything leaves the
+//! database, so the response grows with the number of distinct edges
+//! rather than the number of flows.
+//!
+//! Nodes are workloads (`namespace` + `pod_identity`, as in
+//! workload.rs, falling back to the pod name for pods without one),
+//! Services, and external peers as host CIDRs. Edges point from the
+//! side that opened the connection to the side that accepted it, and
+//! keep the direction the flow was observed in: a flow recorded by
+//! both ends shows up once as EGRESS and once as INGRESS.
+
+use crate::get::{normalise_empty_to_none, TimeRange};
+use crate::{Error, ErrorBody};
+use actix_web::{get, web, HttpResponse};
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::sql_query;
+use diesel::sql_types::{Array, BigInt, Nullable, Text, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+type DbError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Deserialize, IntoParams)]
+pub struct GraphQuery {
+    /// Only flows recorded by pods in this namespace. Absent or empty
+    /// means the whole cluster.
+    pub namespace: Option<String>,
+    /// RFC3339; only flows still seen at or after it (`last_seen`).
+    pub since: Option<String>,
+    /// RFC3339; only flows first seen before it (`time_stamp`).
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GraphNode {
+    /// `<kind>:<namespace>/<name>`, or `external:<cidr>`.
+    pub id: String,
+    /// `workload`, `service` or `external`.
+    pub kind: String,
+    /// Null for external nodes.
+    pub namespace: Option<String>,
+    /// Workload identity, Service name, or CIDR.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    /// `EGRESS` or `INGRESS`: which end recorded the flow.
+    pub direction: Option<String>,
+    pub protocol: Option<String>,
+    /// Destination ports, numerically sorted.
+    pub ports: Vec<String>,
+    /// Sum of `seen_count` over ALLOW flows. Rows without a decision
+    /// predate drop tracking and count here.
+    pub allow_count: i64,
+    /// Sum of `seen_count` over DROP flows.
+    pub drop_count: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// One aggregated edge as Postgres returns it, endpoints unresolved
+/// into node ids yet.
+#[derive(QueryableByName)]
+struct EdgeRow {
+    #[diesel(sql_type = Text)]
+    src_kind: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    src_namespace: Option<String>,
+    #[diesel(sql_type = Text)]
+    src_name: String,
+    #[diesel(sql_type = Text)]
+    dst_kind: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    dst_namespace: Option<String>,
+    #[diesel(sql_type = Text)]
+    dst_name: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    direction: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    protocol: Option<String>,
+    #[diesel(sql_type = Array<Nullable<Text>>)]
+    ports: Vec<Option<String>>,
+    #[diesel(sql_type = BigInt)]
+    allow_count: i64,
+    #[diesel(sql_type = BigInt)]
+    drop_count: i64,
+}
+
+/// Pod-to-pod traffic as nodes and edges.
+#[utoipa::path(
+    tag = "graph",
+    params(GraphQuery),
+    responses(
+        (status = 200, body = Graph),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+    )
+)]
+#[get("/graph")]
+pub async fn get_graph(
+    pool: web::Data<DbPool>,
+    query: web::Query<GraphQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("select service dependency graph");
+    let q = query.into_inner();
+    let range = TimeRange::parse(q.since.as_deref(), q.until.as_deref())?;
+    let namespace = normalise_empty_to_none(q.namespace);
+    let graph = web::block(move || {
+        let mut conn = pool.get()?;
+        dependency_graph(&mut conn, namespace.as_deref(), range)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(graph))
+}
+
+/// Fold pod_traffic into edges, then resolve each side to a node.
+///
+/// `flows` groups first, so the peer lookups below run once per
+/// distinct peer IP rather than once per row. A peer IP resolves to a
+/// pod (live first, then newest; same rule as enrich.rs), then to a
+/// Service, else to an external host CIDR built as text — a cast to
+/// `inet` would fail the whole read on one malformed address.
+pub fn dependency_graph(
+    conn: &mut PgConnection,
+    namespace: Option<&str>,
+    range: TimeRange,
+) -> Result<Graph, DbError> {
+    let rows = sql_query(
+        "WITH flows AS ( \
+             SELECT COALESCE(pod_namespace, '') AS ns, pod_name, traffic_type, ip_protocol, \
+                    traffic_in_out_ip AS peer_ip, \
+                    CASE WHEN traffic_type = 'INGRESS' THEN pod_port \
+                         ELSE traffic_in_out_port END AS port, \
+                    SUM(seen_count) FILTER (WHERE decision IS DISTINCT FROM 'DROP') AS allow_count, \
+                    SUM(seen_count) FILTER (WHERE decision = 'DROP') AS drop_count \
+             FROM pod_traffic \
+             WHERE ($1::text IS NULL OR pod_namespace = $1) \
+               AND ($2::timestamp IS NULL OR last_seen >= $2) \
+               AND ($3::timestamp IS NULL OR time_stamp < $3) \
+             GROUP BY 1, 2, 3, 4, 5, 6 \
+         ), \
+         peer_pod AS ( \
+             SELECT DISTINCT ON (pod_ip) pod_ip, pod_namespace, \
+                    COALESCE(pod_identity, pod_name) AS workload \
+             FROM pod_details \
+             WHERE pod_ip IN (SELECT peer_ip FROM flows) \
+             ORDER BY pod_ip, is_dead, time_stamp DESC \
+         ), \
+         sides AS ( \
+             SELECT 'workload' AS local_kind, f.ns AS local_namespace, \
+                    COALESCE(ld.pod_identity, f.pod_name, '') AS local_name, \
+                    CASE WHEN pp.pod_ip IS NOT NULL THEN 'workload' \
+                         WHEN s.svc_ip IS NOT NULL THEN 'service' \
+                         ELSE 'external' END AS peer_kind, \
+                    CASE WHEN pp.pod_ip IS NOT NULL THEN pp.pod_namespace \
+                         WHEN s.svc_ip IS NOT NULL THEN COALESCE(s.svc_namespace, '') \
+                    END AS peer_namespace, \
+                    CASE WHEN pp.pod_ip IS NOT NULL THEN pp.workload \
+                         WHEN s.svc_ip IS NOT NULL THEN COALESCE(s.svc_name, s.svc_ip) \
+                         ELSE COALESCE(f.peer_ip, '') || \
+                              CASE WHEN strpos(f.peer_ip, ':') > 0 THEN '/128' ELSE '/32' END \
+                    END AS peer_name, \
+                    f.traffic_type, f.ip_protocol, f.port, f.allow_count, f.drop_count \
+             FROM flows f \
+             LEFT JOIN pod_details ld ON ld.pod_namespace = f.ns AND ld.pod_name = f.pod_name \
+             LEFT JOIN peer_pod pp ON pp.pod_ip = f.peer_ip \
+             LEFT JOIN svc_details s ON s.svc_ip = f.peer_ip \
+         ) \
+         SELECT CASE WHEN traffic_type = 'INGRESS' THEN peer_kind ELSE local_kind END AS src_kind, \
+                CASE WHEN traffic_type = 'INGRESS' THEN peer_namespace ELSE local_namespace END AS src_namespace, \
+                CASE WHEN traffic_type = 'INGRESS' THEN peer_name ELSE local_name END AS src_name, \
+                CASE WHEN traffic_type = 'INGRESS' THEN local_kind ELSE peer_kind END AS dst_kind, \
+                CASE WHEN traffic_type = 'INGRESS' THEN local_namespace ELSE peer_namespace END AS dst_namespace, \
+                CASE WHEN traffic_type = 'INGRESS' THEN local_name ELSE peer_name END AS dst_name, \
+                traffic_type AS direction, ip_protocol AS protocol, \
+                array_agg(DISTINCT port) AS ports, \
+                COALESCE(SUM(allow_count), 0)::BIGINT AS allow_count, \
+                COALESCE(SUM(drop_count), 0)::BIGINT AS drop_count \
+         FROM sides \
+         GROUP BY 1, 2, 3, 4, 5, 6, 7, 8",
+    )
+    .bind::<Nullable<Text>, _>(namespace)
+    .bind::<Nullable<Timestamp>, _>(range.since)
+    .bind::<Nullable<Timestamp>, _>(range.until)
+    .load::<EdgeRow>(conn)?;
+    Ok(build_graph(rows))
+}
+
+fn node(kind: String, namespace: Option<String>, name: String) -> GraphNode {
+    let id = match &namespace {
+        Some(ns) => format!("{kind}:{ns}/{name}"),
+        None => format!("{kind}:{name}"),
+    };
+    GraphNode {
+        id,
+        kind,
+        namespace,
+        name,
+    }
+}
+
+/// Turn edge rows into the response: nodes deduped by id, ports sorted
+/// numerically, and both lists in a stable order so the frontend's
+/// layout doesn't reshuffle between polls.
+fn build_graph(rows: Vec<EdgeRow>) -> Graph {
+    let mut nodes: BTreeMap<String, GraphNode> = BTreeMap::new();
+    let mut edges = Vec::with_capacity(rows.len());
+    for r in rows {
+        let src = node(r.src_kind, r.src_namespace, r.src_name);
+        let dst = node(r.dst_kind, r.dst_namespace, r.dst_name);
+        let mut ports: Vec<String> = r.ports.into_iter().flatten().collect();
+        ports.sort_by_key(|p| (p.parse::<u32>().unwrap_or(u32::MAX), p.clone()));
+        edges.push(GraphEdge {
+            source: src.id.clone(),
+            target: dst.id.clone(),
+            direction: r.direction,
+            protocol: r.protocol,
+            ports,
+            allow_count: r.allow_count,
+            drop_count: r.drop_count,
+        });
+        nodes.entry(src.id.clone()).or_insert(src);
+        nodes.entry(dst.id.clone()).or_insert(dst);
+    }
+    edges.sort_by(|a, b| {
+        (&a.source, &a.target, &a.direction, &a.protocol).cmp(&(
+            &b.source,
+            &b.target,
+            &b.direction,
+            &b.protocol,
+        ))
+    });
+    Graph {
+        nodes: nodes.into_values().collect(),
+        edges,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(
+        src: (&str, Option<&str>, &str),
+        dst: (&str, Option<&str>, &str),
+        ports: &[&str],
+    ) -> EdgeRow {
+        EdgeRow {
+            src_kind: src.0.to_string(),
+            src_namespace: src.1.map(str::to_string),
+            src_name: src.2.to_string(),
+            dst_kind: dst.0.to_string(),
+            dst_namespace: dst.1.map(str::to_string),
+            dst_name: dst.2.to_string(),
+            direction: Some("EGRESS".to_string()),
+            protocol: Some("TCP".to_string()),
+            ports: ports.iter().map(|p| Some(p.to_string())).collect(),
+            allow_count: 1,
+            drop_count: 0,
+        }
+    }
+
+    #[test]
+    fn nodes_are_deduped_across_edges() {
+        let g = build_graph(vec![
+            row(
+                ("workload", Some("prod"), "web"),
+                ("workload", Some("data"), "db"),
+                &["5432"],
+            ),
+            row(
+                ("workload", Some("prod"), "web"),
+                ("external", None, "1.1.1.1/32"),
+                &["443"],
+            ),
+        ]);
+        let ids: Vec<_> = g.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "external:1.1.1.1/32",
+                "workload:data/db",
+                "workload:prod/web"
+            ]
+        );
+        assert_eq!(g.edges.len(), 2);
+    }
+
+    #[test]
+    fn ports_sort_numerically() {
+        // "443" < "80" as strings; the UI lists ports in numeric order.
+        let g = build_graph(vec![row(
+            ("workload", Some("prod"), "web"),
+            ("service", Some("prod"), "api"),
+            &["8080", "443", "80"],
+        )]);
+        assert_eq!(g.edges[0].ports, ["80", "443", "8080"]);
+    }
+
+    #[test]
+    fn null_ports_are_dropped() {
+        let mut r = row(
+            ("workload", Some("a"), "x"),
+            ("workload", Some("b"), "y"),
+            &["53"],
+        );
+        r.ports.push(None);
+        let g = build_graph(vec![r]);
+        assert_eq!(g.edges[0].ports, ["53"]);
+    }
+}
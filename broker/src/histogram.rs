@@ -0,0 +1,92 @@
+//! Minimal lock-free Prometheus histogram for the broker's hand-rolled
+//! `/metrics` text. Fixed bucket bounds, cumulative on render.
+
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// Per-bucket (non-cumulative) counts; one extra slot for `+Inf`.
+    buckets: Vec<AtomicU64>,
+    /// Running sum, stored as `f64` bits.
+    sum: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    /// `bounds` must be sorted ascending; `+Inf` is implied.
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0f64.to_bits()),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, value: f64) {
+        let slot = self
+            .bounds
+            .iter()
+            .position(|le| value <= *le)
+            .unwrap_or(self.bounds.len());
+        self.buckets[slot].fetch_add(1, Ordering::Relaxed);
+        let _ = self
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + value).to_bits())
+            });
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render `# HELP`, `# TYPE` and the `_bucket` / `_sum` / `_count`
+    /// series in Prometheus text format.
+    pub fn render(&self, name: &str, help: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        let mut cumulative = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let le = match self.bounds.get(i) {
+                Some(b) => b.to_string(),
+                None => "+Inf".to_string(),
+            };
+            let _ = writeln!(out, "{name}_bucket{{le=\"{le}\"}} {cumulative}");
+        }
+        let sum = f64::from_bits(self.sum.load(Ordering::Relaxed));
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {}", self.count.load(Ordering::Relaxed));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_cumulative_buckets_sum_and_count() {
+        let h = Histogram::new(&[1.0, 4.0, 16.0]);
+        for v in [1.0, 3.0, 4.0, 10.0, 100.0] {
+            h.observe(v);
+        }
+        let body = h.render("x_size", "Sizes");
+        assert!(body.starts_with("# HELP x_size Sizes\n# TYPE x_size histogram\n"));
+        assert!(body.contains("\nx_size_bucket{le=\"1\"} 1\n"));
+        assert!(body.contains("\nx_size_bucket{le=\"4\"} 3\n"));
+        assert!(body.contains("\nx_size_bucket{le=\"16\"} 4\n"));
+        assert!(body.contains("\nx_size_bucket{le=\"+Inf\"} 5\n"));
+        assert!(body.contains("\nx_size_sum 118\n"));
+        assert!(body.ends_with("\nx_size_count 5\n"));
+    }
+
+    #[test]
+    fn empty_histogram_renders_zeroes() {
+        let body = Histogram::new(&[0.5]).render("y", "h");
+        assert!(body.contains("y_bucket{le=\"0.5\"} 0\n"));
+        assert!(body.contains("y_bucket{le=\"+Inf\"} 0\n"));
+        assert!(body.contains("y_sum 0\n"));
+        assert!(body.contains("y_count 0\n"));
+    }
+}
@@ -1,24 +1,59 @@
 mod add;
+mod alerts;
+mod api_version;
 mod audit;
+mod audit_breaker;
+mod audit_outbox;
+mod audit_replay;
+mod audit_summary;
+mod cilium;
+mod cursor;
+mod enrich;
 mod error;
 mod get;
+mod graph;
+mod histogram;
+mod networkpolicy;
+mod openapi;
+mod policy;
 mod retention;
+mod seccomp;
+mod simulate;
+mod stream;
 mod telemetry;
 mod types;
 mod version_check;
+mod workload;
 pub use add::{add_pod_details, add_pods_batch, add_pods_syscalls, add_svc_details, mark_pod_dead};
+pub use alerts::{AlertConfig, Alerter};
+pub use api_version::{legacy_alias, v1_extractor_config, v1_not_found, ApiVersion, V1_PREFIX};
 pub use audit::AuditClient;
+pub use audit_replay::{get_audit_replay, start_audit_replay, AuditReplayJob};
+pub use audit_summary::{get_audit_summary, AuditPolicySummary, VerdictBucket, VerdictTotals};
+pub use cilium::{get_cilium_clusterwide_policy, get_cilium_network_policy};
+pub use cursor::NEXT_CURSOR_HEADER;
+pub use enrich::{Enriched, Peer, TrafficRows};
 pub use error::*;
+pub use graph::{get_graph, Graph, GraphEdge, GraphNode};
+pub use histogram::Histogram;
+pub use networkpolicy::get_network_policy;
+pub use openapi::{openapi_json, ApiDoc};
 pub use retention::spawn as spawn_retention;
+pub use seccomp::{get_seccomp_profile, SeccompProfile, SeccompRule};
+pub use simulate::{simulate_network_policy, SimulatedFlow, SimulationResult};
+pub use stream::{stream_traffic, stream_verdicts, EventHub};
 pub use telemetry::*;
 pub use types::*;
 pub use version_check::{get_version, spawn as spawn_version_check, VersionCheckState};
+pub use workload::{get_workload_syscalls, get_workload_traffic, WorkloadSyscall, WorkloadTraffic};
 mod conn;
 pub use conn::*;
 mod schema;
 pub use get::{
-    get_audit_verdicts, get_pod_by_ip, get_pod_by_name, get_pod_details, get_pod_syscall_name,
-    get_pod_traffic, get_pod_traffic_name, get_pods_by_node, get_svc_by_ip, get_svc_details,
+    get_audit_verdicts, get_pod_by_ip, get_pod_by_name, get_pod_by_namespaced_name,
+    get_pod_details, get_pod_syscall_name, get_pod_syscall_namespaced_name,
+    get_pod_syscall_observations, get_pod_traffic, get_pod_traffic_name,
+    get_pod_traffic_namespaced_name, get_pods_by_node, get_svc_by_ip, get_svc_details,
 };
 pub use schema::{pod_details, pod_traffic};
 
@@ -41,4 +76,25 @@ pub(crate) mod test_support {
     pub fn env_lock() -> MutexGuard<'static, ()> {
         ENV_LOCK.lock().unwrap_or_else(|p| p.into_inner())
     }
+
+    /// Connection to the scratch database in `TEST_DATABASE_URL`, with
+    /// the broker's migrations applied, for the `#[ignore = "requires a
+    /// database"]` tests (`cargo test -- --ignored`). Tests work inside
+    /// `test_transaction` and filter on names of their own, so rows
+    /// already in the database neither leak in nor get changed.
+    pub fn db() -> diesel::PgConnection {
+        use diesel::Connection;
+        use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+        const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./db/migrations");
+        static MIGRATED: std::sync::Once = std::sync::Once::new();
+
+        let url = std::env::var("TEST_DATABASE_URL")
+            .expect("TEST_DATABASE_URL must name a scratch Postgres database");
+        let mut conn = diesel::PgConnection::establish(&url).expect("connect to TEST_DATABASE_URL");
+        MIGRATED.call_once(|| {
+            conn.run_pending_migrations(MIGRATIONS)
+                .expect("migrate TEST_DATABASE_URL");
+        });
+        conn
+    }
 }
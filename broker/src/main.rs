@@ -5,10 +5,16 @@ use actix_web::middleware::from_fn;
 use actix_web::{get, web, App, HttpResponse, HttpServer};
 use api::{
     add_pod_details, add_pods_batch, add_pods_syscalls, add_svc_details, establish_connection,
-    get_audit_verdicts, get_pod_by_ip, get_pod_by_name, get_pod_details, get_pod_syscall_name,
-    get_pod_traffic, get_pod_traffic_name, get_pods_by_node, get_svc_by_ip, get_svc_details,
-    get_version, mark_pod_dead, set_statement_timeout, spawn_retention, spawn_version_check,
-    AuditClient, StatementTimeoutCustomizer, VersionCheckState,
+    get_audit_replay, get_audit_summary, get_audit_verdicts, get_cilium_clusterwide_policy,
+    get_cilium_network_policy, get_graph, get_network_policy, get_pod_by_ip, get_pod_by_name,
+    get_pod_by_namespaced_name, get_pod_details, get_pod_syscall_name,
+    get_pod_syscall_namespaced_name, get_pod_syscall_observations, get_pod_traffic,
+    get_pod_traffic_name, get_pod_traffic_namespaced_name, get_pods_by_node, get_seccomp_profile,
+    get_svc_by_ip, get_svc_details, get_version, get_workload_syscalls, get_workload_traffic,
+    legacy_alias, mark_pod_dead, openapi_json, set_statement_timeout, simulate_network_policy,
+    spawn_retention, spawn_version_check, start_audit_replay, stream_traffic, stream_verdicts,
+    v1_extractor_config, v1_not_found, AlertConfig, Alerter, ApiDoc, AuditClient, EventHub,
+    StatementTimeoutCustomizer, VersionCheckState, NEXT_CURSOR_HEADER, V1_PREFIX,
 };
 
 use diesel::r2d2;
@@ -19,6 +25,7 @@ mod telemetry;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use std::time::Instant;
 use tracing::{info, warn};
+use utoipa::OpenApi;
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./db/migrations");
 
 /// Process-start instant used for the broker_uptime_seconds metric.
@@ -238,21 +245,43 @@ async fn main() -> Result<(), std::io::Error> {
     // audit is disabled). Ingest enqueues onto it instead of spawning a task
     // per flow, so a slow evaluator sheds load rather than back-pressuring
     // capture or growing memory unbounded.
-    let audit_client = AuditClient::from_env().start(pool.clone());
+    // The event hub feeds /stream/traffic and /stream/verdicts: ingest
+    // publishes committed flows to it, the audit client stored verdicts.
+    let events = EventHub::default();
+    // Webhook alerts for new DROP flows and WouldDeny verdicts. A file
+    // that is set but unreadable fails startup rather than silently
+    // turning alerting off.
+    let alerts = Alerter::start(
+        AlertConfig::from_env().unwrap_or_else(|e| panic!("invalid ALERT_WEBHOOKS_FILE: {e}")),
+    );
+    let audit_client = AuditClient::from_env()
+        .with_events(events.clone())
+        .with_alerts(alerts.clone())
+        .start(pool.clone());
 
     // Optional bearer-token auth on the broker API. Off unless
-    // BROKER_AUTH_TOKEN is set, so existing deployments are unaffected.
-    let auth_config = auth::AuthConfig::from_env();
+    // BROKER_AUTH_TOKEN or BROKER_AUTH_TOKENS_FILE is set, so existing
+    // deployments are unaffected. Like the alerts file, a tokens file
+    // that is set but unreadable fails startup.
+    let auth_config = auth::AuthConfig::from_env()
+        .unwrap_or_else(|e| panic!("invalid BROKER_AUTH_TOKENS_FILE: {e}"));
     if auth_config.enabled() {
-        info!("broker API auth ENABLED — requiring bearer token (except /health, /metrics)");
+        info!(
+            tokens = ?auth_config.token_names(),
+            "broker API auth ENABLED — requiring bearer token (except /health, /metrics, API docs)"
+        );
+        auth_config.spawn_reload();
     } else {
-        info!("broker API auth disabled (set BROKER_AUTH_TOKEN to require a bearer token)");
+        info!("broker API auth disabled (set BROKER_AUTH_TOKEN or BROKER_AUTH_TOKENS_FILE to require a bearer token)");
     }
     if audit_client.enabled() {
         info!(url = %audit_client.base_url(), "audit evaluator integration enabled");
     } else {
         info!("audit evaluator integration disabled (set EVALUATOR_URL to enable)");
     }
+    if alerts.enabled() {
+        info!(webhooks = ?alerts.webhook_names(), "webhook alerts enabled");
+    }
 
     // Background pruner for audit_verdicts. Runs in-process so the
     // broker is self-contained — no separate CronJob needed in the
@@ -264,6 +293,9 @@ async fn main() -> Result<(), std::io::Error> {
     let version_state = web::Data::new(VersionCheckState::default());
     spawn_version_check(pool.clone(), version_state.clone());
 
+    // The OpenAPI document is built once; /openapi.json serves it as is.
+    let openapi = web::Data::new(api_doc());
+
     let listen_addr = listen_addr();
     info!(addr = %listen_addr, "broker HTTP server starting");
     HttpServer::new(move || {
@@ -271,6 +303,9 @@ async fn main() -> Result<(), std::io::Error> {
             .allow_any_origin()
             .allow_any_method()
             .allow_any_header()
+            // Browsers hide non-safelisted response headers from JS
+            // unless exposed; the UI reads the pagination cursor.
+            .expose_headers([NEXT_CURSOR_HEADER])
             .max_age(3600);
 
         App::new()
@@ -280,38 +315,111 @@ async fn main() -> Result<(), std::io::Error> {
             .wrap(cors)
             .app_data(web::Data::new(pool.clone()))
             .app_data(web::Data::new(audit_client.clone()))
+            .app_data(web::Data::new(events.clone()))
+            .app_data(web::Data::new(alerts.clone()))
             .app_data(web::Data::new(auth_config.clone()))
             .app_data(version_state.clone())
-            .service(add_pods_batch)
-            .service(add_pod_details)
-            .service(add_pods_syscalls)
-            .service(get_pod_traffic)
-            .service(get_pod_details)
-            .service(add_svc_details)
-            .service(get_pod_by_ip)
-            .service(get_pod_by_name)
-            .service(get_svc_details)
-            .service(get_svc_by_ip)
-            .service(get_pod_traffic_name)
-            .service(get_pod_syscall_name)
-            .service(get_pods_by_node)
-            .service(get_audit_verdicts)
-            .service(mark_pod_dead)
-            .service(get_version)
+            .app_data(openapi.clone())
             .service(health_check)
             .service(metrics)
+            .service(openapi_json)
+            .configure(swagger_ui)
+            .service(
+                web::scope(V1_PREFIX)
+                    .configure(v1_extractor_config)
+                    .configure(api_routes)
+                    .default_service(web::to(v1_not_found)),
+            )
+            // The unversioned routes, kept as deprecated aliases of /v1.
+            // Last: an empty-prefix scope claims every remaining path.
+            .service(
+                web::scope("")
+                    .wrap(from_fn(legacy_alias))
+                    .configure(api_routes),
+            )
     })
     .bind(listen_addr)?
     .run()
     .await
 }
 
+/// Every API route. Registered twice: under /v1 and, deprecated, at the
+/// root. /health, /metrics and the docs stay unversioned.
+fn api_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(add_pods_batch)
+        .service(add_pod_details)
+        .service(add_pods_syscalls)
+        .service(get_pod_traffic)
+        .service(get_pod_details)
+        .service(add_svc_details)
+        .service(get_pod_by_ip)
+        .service(get_pod_by_name)
+        .service(get_svc_details)
+        .service(get_svc_by_ip)
+        .service(get_pod_traffic_name)
+        .service(get_pod_traffic_namespaced_name)
+        .service(get_pod_syscall_name)
+        .service(get_pod_syscall_namespaced_name)
+        .service(get_pod_syscall_observations)
+        .service(get_pods_by_node)
+        .service(get_workload_traffic)
+        .service(get_workload_syscalls)
+        .service(get_graph)
+        .service(get_network_policy)
+        .service(get_cilium_network_policy)
+        .service(get_cilium_clusterwide_policy)
+        .service(get_seccomp_profile)
+        .service(simulate_network_policy)
+        .service(get_audit_verdicts)
+        .service(get_audit_summary)
+        .service(start_audit_replay)
+        .service(get_audit_replay)
+        .service(stream_traffic)
+        .service(stream_verdicts)
+        .service(mark_pod_dead)
+        .service(get_version)
+        // Last: /pod/{namespace}/{name} matches every two-segment
+        // /pod/... path, so the fixed-prefix routes above must win.
+        .service(get_pod_by_namespaced_name);
+}
+
+/// The probe and metrics endpoints live here rather than in the library,
+/// so their paths are merged into `ApiDoc` at startup.
+#[derive(OpenApi)]
+#[openapi(paths(health_check, metrics))]
+struct OpsDoc;
+
+/// The full OpenAPI document served at /openapi.json and committed as
+/// docs/api-reference/openapi.json.
+fn api_doc() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi().merge_from(OpsDoc::openapi())
+}
+
+/// Swagger UI at /swagger-ui/, reading /openapi.json. Only built with
+/// `--features swagger-ui`, which vendors the UI's assets into the binary.
+#[cfg(feature = "swagger-ui")]
+fn swagger_ui(cfg: &mut web::ServiceConfig) {
+    use utoipa_swagger_ui::{Config, SwaggerUi};
+    cfg.service(SwaggerUi::new("/swagger-ui/{_:.*}").config(Config::from("/openapi.json")));
+}
+
+#[cfg(not(feature = "swagger-ui"))]
+fn swagger_ui(_: &mut web::ServiceConfig) {}
+
 // Verifying schema state on /health (rather than just connectivity) is
 // what makes the broker self-heal when the database is replaced or
 // wiped beneath us. Without this, /health passes on pool.get() while
 // every real query 500s with "relation does not exist" — silent for
 // hours. With it, the kubelet sees a failing liveness probe, restarts
 // the pod, and the startup-migration retry repopulates the schema.
+#[utoipa::path(
+    tag = "ops",
+    security(()),
+    responses(
+        (status = 200, description = "Database reachable and schema current", body = String),
+        (status = 503, description = "Database unavailable or schema not up to date", body = String),
+    )
+)]
 #[get("/health")]
 pub async fn health_check(
     pool: web::Data<r2d2::Pool<r2d2::ConnectionManager<diesel::PgConnection>>>,
@@ -334,15 +442,17 @@ pub async fn health_check(
         })
         .await;
 
+    // Plain text: probes only look at the status, and `/health` sits
+    // outside `/v1` and its JSON envelope.
     match result {
         Ok(Ok(true)) => HttpResponse::Ok()
-            .content_type("application/json")
+            .content_type("text/plain; charset=utf-8")
             .body("Healthy!"),
         Ok(Ok(false)) => HttpResponse::ServiceUnavailable()
-            .content_type("application/json")
+            .content_type("text/plain; charset=utf-8")
             .body("Database schema not up to date"),
         Ok(Err(_)) | Err(_) => HttpResponse::ServiceUnavailable()
-            .content_type("application/json")
+            .content_type("text/plain; charset=utf-8")
             .body("Database unavailable"),
     }
 }
@@ -357,6 +467,9 @@ pub(crate) fn render_metrics_text(
     audit_enabled: u8,
     audit_inflight_available: usize,
     audit_dropped_total: u64,
+    audit_outbox_depth: u64,
+    audit_outbox_oldest_age_secs: u64,
+    audit_outbox_abandoned_total: u64,
     db_pool_idle: u32,
     db_pool_max: u32,
     uptime_secs: u64,
@@ -378,6 +491,15 @@ pub(crate) fn render_metrics_text(
             "# HELP broker_audit_dropped_total Flows shed because the bounded audit queue was full (evaluator backed up)\n",
             "# TYPE broker_audit_dropped_total counter\n",
             "broker_audit_dropped_total {audit_dropped_total}\n",
+            "# HELP broker_audit_outbox_depth Flows waiting in the audit_outbox table (0 unless AUDIT_OUTBOX_ENABLED)\n",
+            "# TYPE broker_audit_outbox_depth gauge\n",
+            "broker_audit_outbox_depth {audit_outbox_depth}\n",
+            "# HELP broker_audit_outbox_oldest_age_seconds Age of the oldest audit_outbox row (how far audit lags ingest)\n",
+            "# TYPE broker_audit_outbox_oldest_age_seconds gauge\n",
+            "broker_audit_outbox_oldest_age_seconds {audit_outbox_oldest_age_secs}\n",
+            "# HELP broker_audit_outbox_abandoned_total Outbox flows given up on (evaluator 4xx or AUDIT_OUTBOX_MAX_ATTEMPTS reached)\n",
+            "# TYPE broker_audit_outbox_abandoned_total counter\n",
+            "broker_audit_outbox_abandoned_total {audit_outbox_abandoned_total}\n",
             "# HELP broker_db_pool_idle Idle connections in the r2d2 pool (saturation = broker_db_pool_max - this)\n",
             "# TYPE broker_db_pool_idle gauge\n",
             "broker_db_pool_idle {db_pool_idle}\n",
@@ -393,6 +515,9 @@ pub(crate) fn render_metrics_text(
         audit_enabled = audit_enabled,
         audit_inflight_available = audit_inflight_available,
         audit_dropped_total = audit_dropped_total,
+        audit_outbox_depth = audit_outbox_depth,
+        audit_outbox_oldest_age_secs = audit_outbox_oldest_age_secs,
+        audit_outbox_abandoned_total = audit_outbox_abandoned_total,
         db_pool_idle = db_pool_idle,
         db_pool_max = db_pool_max,
         uptime_secs = uptime_secs,
@@ -409,10 +534,18 @@ pub(crate) fn render_metrics_text(
 ///   - audit semaphore saturation (the cap from #c05b7835 — operators
 ///     need to see when it's pegged to know they should bump
 ///     AUDIT_INFLIGHT_PERMITS)
+#[utoipa::path(
+    tag = "ops",
+    security(()),
+    responses(
+        (status = 200, description = "Prometheus text format", content_type = "text/plain", body = String),
+    )
+)]
 #[get("/metrics")]
 pub async fn metrics(
     pool: web::Data<r2d2::Pool<r2d2::ConnectionManager<diesel::PgConnection>>>,
     audit: web::Data<api::AuditClient>,
+    alerts: web::Data<Alerter>,
 ) -> HttpResponse {
     let pool_inner = pool.get_ref().clone();
     let schema_state = tokio::task::spawn_blocking(
@@ -444,6 +577,11 @@ pub async fn metrics(
 
     let audit_inflight = audit.get_ref().available_permits();
     let audit_dropped = audit.get_ref().dropped_count();
+    // Outbox gauges are refreshed by the dispatcher's stats task, not
+    // counted here, so a backed-up table can't slow the scrape.
+    let outbox_depth = audit.get_ref().outbox_depth();
+    let outbox_age = audit.get_ref().outbox_oldest_age_secs();
+    let outbox_abandoned = audit.get_ref().outbox_abandoned_count();
     // r2d2 pool state — paired metrics let operators compute
     // saturation = max - idle. broker_db_pool_idle pegged at 0 for
     // sustained time means the pool is fully utilised; bump
@@ -453,22 +591,46 @@ pub async fn metrics(
     let db_pool_max = pool.get_ref().max_size();
     let uptime_secs = UPTIME_ANCHOR.get_or_init(Instant::now).elapsed().as_secs();
 
-    let body = render_metrics_text(
+    let mut body = render_metrics_text(
         u8::from(schema_ready),
         u8::from(db_reachable),
         u8::from(audit.get_ref().enabled()),
         audit_inflight,
         audit_dropped,
+        outbox_depth,
+        outbox_age,
+        outbox_abandoned,
         db_pool_idle,
         db_pool_max,
         uptime_secs,
     );
+    body.push_str(&audit.get_ref().batch_size_histogram().render(
+        "broker_audit_batch_size",
+        "Flows per evaluator call (1 for /evaluate, more for coalesced /evaluate/batch)",
+    ));
+    body.push_str(&audit.get_ref().render_evaluator_metrics());
+    body.push_str(&alerts.get_ref().render_metrics());
 
     HttpResponse::Ok()
         .content_type("text/plain; version=0.0.4; charset=utf-8")
         .body(body)
 }
 
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::sync::{Mutex, MutexGuard};
+
+    /// The binary's own env lock: it links the library built without
+    /// `cfg(test)`, so the library's `test_support` isn't reachable here.
+    /// Same rule — every env-mutating test in this crate holds it.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Acquire the env lock, tolerating poison (see the library's).
+    pub fn env_lock() -> MutexGuard<'static, ()> {
+        ENV_LOCK.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -481,13 +643,16 @@ mod tests {
 
     #[test]
     fn renders_all_metric_names() {
-        let body = render_metrics_text(1, 1, 1, 16, 0, 16, 16, 0);
+        let body = render_metrics_text(1, 1, 1, 16, 0, 0, 0, 0, 16, 16, 0);
         for name in [
             "broker_db_schema_ready",
             "broker_db_reachable",
             "broker_audit_enabled",
             "broker_audit_inflight_available",
             "broker_audit_dropped_total",
+            "broker_audit_outbox_depth",
+            "broker_audit_outbox_oldest_age_seconds",
+            "broker_audit_outbox_abandoned_total",
             "broker_db_pool_idle",
             "broker_db_pool_max",
             "broker_uptime_seconds",
@@ -498,7 +663,7 @@ mod tests {
 
     #[test]
     fn each_metric_has_help_and_type() {
-        let body = render_metrics_text(1, 1, 1, 16, 0, 16, 16, 0);
+        let body = render_metrics_text(1, 1, 1, 16, 0, 0, 0, 0, 16, 16, 0);
         // Each metric must have a # HELP and a # TYPE line.
         for name in [
             "broker_db_schema_ready",
@@ -506,6 +671,9 @@ mod tests {
             "broker_audit_enabled",
             "broker_audit_inflight_available",
             "broker_audit_dropped_total",
+            "broker_audit_outbox_depth",
+            "broker_audit_outbox_oldest_age_seconds",
+            "broker_audit_outbox_abandoned_total",
             "broker_db_pool_idle",
             "broker_db_pool_max",
             "broker_uptime_seconds",
@@ -521,12 +689,15 @@ mod tests {
     fn renders_zero_state() {
         // All-zero state: DB unreachable, audit disabled, no permits available,
         // pool saturated (0 idle).
-        let body = render_metrics_text(0, 0, 0, 0, 0, 0, 0, 0);
+        let body = render_metrics_text(0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0);
         assert!(body.contains("\nbroker_db_schema_ready 0\n"));
         assert!(body.contains("\nbroker_db_reachable 0\n"));
         assert!(body.contains("\nbroker_audit_enabled 0\n"));
         assert!(body.contains("\nbroker_audit_inflight_available 0\n"));
         assert!(body.contains("\nbroker_audit_dropped_total 0\n"));
+        assert!(body.contains("\nbroker_audit_outbox_depth 0\n"));
+        assert!(body.contains("\nbroker_audit_outbox_oldest_age_seconds 0\n"));
+        assert!(body.contains("\nbroker_audit_outbox_abandoned_total 0\n"));
         assert!(body.contains("\nbroker_db_pool_idle 0\n"));
         assert!(body.contains("\nbroker_db_pool_max 0\n"));
         assert!(body.contains("\nbroker_uptime_seconds 0\n"));
@@ -534,10 +705,13 @@ mod tests {
 
     #[test]
     fn renders_populated_state() {
-        let body = render_metrics_text(1, 1, 1, 16, 7, 12, 16, 12345);
+        let body = render_metrics_text(1, 1, 1, 16, 7, 40, 95, 3, 12, 16, 12345);
         assert!(body.contains("\nbroker_db_schema_ready 1\n"));
         assert!(body.contains("\nbroker_audit_inflight_available 16\n"));
         assert!(body.contains("\nbroker_audit_dropped_total 7\n"));
+        assert!(body.contains("\nbroker_audit_outbox_depth 40\n"));
+        assert!(body.contains("\nbroker_audit_outbox_oldest_age_seconds 95\n"));
+        assert!(body.contains("\nbroker_audit_outbox_abandoned_total 3\n"));
         // 12 idle out of 16 max = 4 in use; pin both so saturation is computable
         assert!(body.contains("\nbroker_db_pool_idle 12\n"));
         assert!(body.contains("\nbroker_db_pool_max 16\n"));
@@ -547,7 +721,7 @@ mod tests {
     #[test]
     fn wire_shape_is_prometheus_compatible() {
         // Each non-comment line must look like `<name> <value>\n`.
-        let body = render_metrics_text(1, 1, 0, 8, 0, 4, 16, 60);
+        let body = render_metrics_text(1, 1, 0, 8, 0, 0, 0, 0, 4, 16, 60);
         for line in body.lines() {
             if line.is_empty() || line.starts_with('#') {
                 continue;
@@ -581,6 +755,7 @@ mod tests {
     /// but possible) might be affected. The same pattern is reused
     /// in retention.rs (with_env, scoped to retention env vars).
     fn with_env<F: FnOnce()>(key: &str, value: Option<&str>, f: F) {
+        let _guard = crate::test_support::env_lock();
         let prev = std::env::var(key).ok();
         match value {
             Some(v) => std::env::set_var(key, v),
@@ -805,4 +980,33 @@ mod tests {
         // Larger permits still get headroom.
         assert_eq!(pool_size_with_headroom(10, 32), 32 + h);
     }
+
+    /// Committed copy of the generated document. Regenerate with
+    /// `UPDATE_OPENAPI=1 cargo test committed_openapi_is_current`.
+    const OPENAPI_DOC: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../docs/api-reference/openapi.json"
+    );
+
+    #[test]
+    fn committed_openapi_is_current() {
+        let generated = api_doc().to_pretty_json().unwrap() + "\n";
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(OPENAPI_DOC, &generated).unwrap();
+            return;
+        }
+        // info.version follows Cargo.toml, which release-please bumps
+        // without regenerating the docs; everything else must match.
+        let without_version = |doc: &str| {
+            let mut v: serde_json::Value = serde_json::from_str(doc).unwrap();
+            v["info"]["version"].take();
+            v
+        };
+        let committed = std::fs::read_to_string(OPENAPI_DOC).unwrap();
+        assert!(
+            without_version(&committed) == without_version(&generated),
+            "docs/api-reference/openapi.json is out of date with the handlers; \
+             regenerate it with `UPDATE_OPENAPI=1 cargo test committed_openapi_is_current`"
+        );
+    }
 }
@@ -0,0 +1,418 @@
+//! `GET /policy/networkpolicy/{namespace}/{workload}`: a
+//! `networking.k8s.io/v1` NetworkPolicy for a workload, built in the
+//! broker so any client (curl in CI included) can fetch one without
+//! the advisor binary.
+//!
+//! The output follows the advisor's StandardPolicyGenerator: one rule
+//! per distinct peer with its ports sorted by number then protocol,
+//! rules sorted by peer, the `<name>-standard-policy` naming and
+//! labels, and a deny-all policy when no flow qualifies.
+
+use crate::get::TimeRange;
+use crate::policy::{
+    is_policy_peer, load_policy_input, parse_port, policy_protocol, OutputFormat, PolicyInput,
+    PolicyPeer, PolicyQuery,
+};
+use crate::{Error, ErrorBody};
+use actix_web::{get, web, HttpResponse};
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::info;
+use utoipa::ToSchema;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+/// Namespace label every namespace carries (Kubernetes 1.21+); what the
+/// advisor's generators put in `namespaceSelector`.
+pub(crate) const NAMESPACE_NAME_LABEL: &str = "kubernetes.io/metadata.name";
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicy {
+    pub api_version: &'static str,
+    pub kind: &'static str,
+    pub metadata: ObjectMeta,
+    pub spec: NetworkPolicySpec,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ObjectMeta {
+    pub name: String,
+    /// None for cluster-scoped kinds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicySpec {
+    pub pod_selector: LabelSelector,
+    pub policy_types: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingress: Option<Vec<IngressRule>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub egress: Option<Vec<EgressRule>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelSelector {
+    pub match_labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[schema(as = NetworkPolicyPeer)]
+#[serde(rename_all = "camelCase")]
+pub struct Peer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pod_selector: Option<LabelSelector>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace_selector: Option<LabelSelector>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_block: Option<IpBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+pub struct IpBlock {
+    pub cidr: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[schema(as = NetworkPolicyPort)]
+pub struct Port {
+    pub port: u16,
+    pub protocol: &'static str,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IngressRule {
+    pub from: Vec<Peer>,
+    pub ports: Vec<Port>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EgressRule {
+    pub to: Vec<Peer>,
+    pub ports: Vec<Port>,
+}
+
+/// A Kubernetes NetworkPolicy for a workload, built from its observed traffic.
+#[utoipa::path(
+    tag = "policies",
+    params(PolicyQuery),
+    responses(
+        (status = 200, description = "Generated policy, as JSON or YAML per `format`", content((NetworkPolicy = "application/json"), (NetworkPolicy = "application/yaml"))),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+        (status = 404, description = "No data found", body = ErrorBody),
+        (status = 422, description = "The workload has no labels to select it by", body = ErrorBody),
+    )
+)]
+#[get("/policy/networkpolicy/{namespace}/{workload}")]
+pub async fn get_network_policy(
+    pool: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    query: web::Query<PolicyQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("generate networkpolicy for workload");
+    let (namespace, workload) = path.into_inner();
+    let format = OutputFormat::parse(query.format.as_deref())?;
+    let range = TimeRange::parse(query.since.as_deref(), query.until.as_deref())?;
+    let input = web::block(move || {
+        let mut conn = pool.get()?;
+        load_policy_input(&mut conn, &namespace, &workload, range)
+    })
+    .await??;
+
+    let input = input.ok_or(Error::NotFound)?;
+    if input.target.selector.is_empty() {
+        return Err(Error::Unprocessable(no_selector(&input)));
+    }
+    format.respond(&build_network_policy(&input))
+}
+
+/// 422 body when there is nothing to select the workload's pods by. An
+/// empty podSelector would select every pod in the namespace.
+pub(crate) fn no_selector(input: &PolicyInput) -> String {
+    format!(
+        "workload {}/{} has no recorded selector or pod labels; refusing to emit an empty podSelector",
+        input.target.namespace, input.target.workload
+    )
+}
+
+/// Per-direction rules of a workload: ports keyed by peer, both sorted.
+pub(crate) struct GroupedFlows<P> {
+    pub ingress: BTreeMap<P, BTreeSet<Port>>,
+    pub egress: BTreeMap<P, BTreeSet<Port>>,
+}
+
+/// Group the input's flows by peer, with `to_peer` deciding what a peer
+/// is (so replicas behind one selector share a rule). Flows without a
+/// usable peer or port are skipped.
+pub(crate) fn group_flows<P: Ord>(
+    input: &PolicyInput,
+    mut to_peer: impl FnMut(&PolicyPeer) -> P,
+) -> GroupedFlows<P> {
+    let mut grouped = GroupedFlows {
+        ingress: BTreeMap::new(),
+        egress: BTreeMap::new(),
+    };
+    for flow in &input.flows {
+        let ip = flow.traffic_in_out_ip.as_deref();
+        if !is_policy_peer(ip) {
+            continue;
+        }
+        let Some(peer) = ip.and_then(|ip| input.peers.get(ip)) else {
+            continue;
+        };
+        // INGRESS: the port is ours (pod_port). EGRESS: the peer's.
+        let (rules, port) = match flow.traffic_type.as_deref().map(str::to_ascii_uppercase) {
+            Some(t) if t == "INGRESS" => (&mut grouped.ingress, flow.pod_port.as_deref()),
+            Some(t) if t == "EGRESS" => (&mut grouped.egress, flow.traffic_in_out_port.as_deref()),
+            _ => continue,
+        };
+        let Some(port) = parse_port(port) else {
+            continue;
+        };
+        rules.entry(to_peer(peer)).or_default().insert(Port {
+            port,
+            protocol: policy_protocol(flow.ip_protocol.as_deref()),
+        });
+    }
+    grouped
+}
+
+pub fn build_network_policy(input: &PolicyInput) -> NetworkPolicy {
+    let GroupedFlows { ingress, egress } = group_flows(input, to_peer);
+
+    let target = &input.target;
+    if ingress.is_empty() && egress.is_empty() {
+        return NetworkPolicy {
+            api_version: "networking.k8s.io/v1",
+            kind: "NetworkPolicy",
+            metadata: object_meta(
+                Some(target.namespace.clone()),
+                &target.workload,
+                "standard-policy-deny-all",
+            ),
+            spec: NetworkPolicySpec {
+                pod_selector: LabelSelector {
+                    match_labels: target.selector.clone(),
+                },
+                policy_types: vec!["Ingress", "Egress"],
+                ingress: Some(Vec::new()),
+                egress: Some(Vec::new()),
+            },
+        };
+    }
+
+    let mut policy_types = Vec::new();
+    if !ingress.is_empty() {
+        policy_types.push("Ingress");
+    }
+    if !egress.is_empty() {
+        policy_types.push("Egress");
+    }
+    NetworkPolicy {
+        api_version: "networking.k8s.io/v1",
+        kind: "NetworkPolicy",
+        metadata: object_meta(
+            Some(target.namespace.clone()),
+            &target.workload,
+            "standard-policy",
+        ),
+        spec: NetworkPolicySpec {
+            pod_selector: LabelSelector {
+                match_labels: target.selector.clone(),
+            },
+            policy_types,
+            ingress: (!ingress.is_empty()).then(|| {
+                ingress
+                    .into_iter()
+                    .map(|(peer, ports)| IngressRule {
+                        from: vec![peer],
+                        ports: ports.into_iter().collect(),
+                    })
+                    .collect()
+            }),
+            egress: (!egress.is_empty()).then(|| {
+                egress
+                    .into_iter()
+                    .map(|(peer, ports)| EgressRule {
+                        to: vec![peer],
+                        ports: ports.into_iter().collect(),
+                    })
+                    .collect()
+            }),
+        },
+    }
+}
+
+fn to_peer(peer: &PolicyPeer) -> Peer {
+    match peer {
+        PolicyPeer::Selector {
+            namespace, labels, ..
+        } => Peer {
+            pod_selector: Some(LabelSelector {
+                match_labels: labels.clone(),
+            }),
+            namespace_selector: Some(LabelSelector {
+                match_labels: BTreeMap::from([(
+                    NAMESPACE_NAME_LABEL.to_string(),
+                    namespace.clone(),
+                )]),
+            }),
+            ip_block: None,
+        },
+        // Host-networked pods and the API server have no selector a
+        // NetworkPolicy can use.
+        PolicyPeer::Host(cidr) | PolicyPeer::ApiServer(cidr) | PolicyPeer::Cidr(cidr) => Peer {
+            pod_selector: None,
+            namespace_selector: None,
+            ip_block: Some(IpBlock { cidr: cidr.clone() }),
+        },
+    }
+}
+
+/// `<workload>-<kind>` with the advisor's `CreateStandardLabels` set.
+pub(crate) fn object_meta(namespace: Option<String>, workload: &str, kind: &str) -> ObjectMeta {
+    ObjectMeta {
+        name: format!("{workload}-{kind}"),
+        namespace,
+        labels: BTreeMap::from([
+            ("app.kubernetes.io/name".to_string(), workload.to_string()),
+            ("app.kubernetes.io/component".to_string(), kind.to_string()),
+            (
+                "app.kubernetes.io/part-of".to_string(),
+                "kguardian".to_string(),
+            ),
+        ]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::PolicyTarget;
+    use crate::WorkloadTraffic;
+    use chrono::NaiveDateTime;
+    use std::collections::HashMap;
+
+    fn flow(
+        dir: &str,
+        proto: &str,
+        pod_port: &str,
+        peer: &str,
+        peer_port: &str,
+    ) -> WorkloadTraffic {
+        WorkloadTraffic {
+            traffic_type: Some(dir.to_string()),
+            ip_protocol: Some(proto.to_string()),
+            pod_port: Some(pod_port.to_string()),
+            traffic_in_out_ip: Some(peer.to_string()),
+            traffic_in_out_port: Some(peer_port.to_string()),
+            decision: Some("ALLOW".to_string()),
+            first_seen: NaiveDateTime::default(),
+            last_seen: NaiveDateTime::default(),
+            seen_count: 1,
+            pod_count: 1,
+        }
+    }
+
+    fn input(flows: Vec<WorkloadTraffic>, peers: &[(&str, PolicyPeer)]) -> PolicyInput {
+        PolicyInput {
+            target: PolicyTarget {
+                namespace: "prod".to_string(),
+                workload: "web".to_string(),
+                selector: BTreeMap::from([("app".to_string(), "web".to_string())]),
+                host_network: false,
+            },
+            flows,
+            peers: peers
+                .iter()
+                .map(|(ip, p)| (ip.to_string(), p.clone()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    fn db() -> PolicyPeer {
+        PolicyPeer::Selector {
+            namespace: "data".to_string(),
+            labels: BTreeMap::from([("app".to_string(), "postgres".to_string())]),
+        }
+    }
+
+    #[test]
+    fn replicas_of_one_peer_merge_into_one_rule() {
+        // Two postgres replicas (two IPs) resolve to the same selector;
+        // the policy must say so once, with both ports merged and sorted.
+        let p = build_network_policy(&input(
+            vec![
+                flow("EGRESS", "TCP", "0", "10.0.0.1", "5432"),
+                flow("EGRESS", "TCP", "0", "10.0.0.2", "5432"),
+                flow("EGRESS", "TCP", "0", "10.0.0.2", "443"),
+            ],
+            &[("10.0.0.1", db()), ("10.0.0.2", db())],
+        ));
+        let egress = p.spec.egress.unwrap();
+        assert_eq!(egress.len(), 1);
+        let ports: Vec<_> = egress[0].ports.iter().map(|p| p.port).collect();
+        assert_eq!(ports, [443, 5432]);
+        assert_eq!(p.spec.policy_types, ["Egress"]);
+        assert!(p.spec.ingress.is_none());
+        assert_eq!(p.metadata.name, "web-standard-policy");
+    }
+
+    #[test]
+    fn ingress_uses_the_pods_own_port() {
+        let p = build_network_policy(&input(
+            vec![flow("INGRESS", "TCP", "8080", "8.8.8.8", "0")],
+            &[("8.8.8.8", PolicyPeer::Cidr("8.8.8.8/32".to_string()))],
+        ));
+        let ingress = p.spec.ingress.unwrap();
+        assert_eq!(ingress[0].ports[0].port, 8080);
+        assert_eq!(
+            ingress[0].from[0].ip_block.as_ref().unwrap().cidr,
+            "8.8.8.8/32"
+        );
+    }
+
+    #[test]
+    fn no_usable_flow_yields_deny_all() {
+        // Ephemeral (0) ports and loopback peers can't become rules.
+        let p = build_network_policy(&input(
+            vec![
+                flow("EGRESS", "TCP", "0", "10.0.0.1", "0"),
+                flow("EGRESS", "TCP", "0", "127.0.0.1", "80"),
+            ],
+            &[
+                ("10.0.0.1", db()),
+                ("127.0.0.1", PolicyPeer::Cidr("127.0.0.1/32".to_string())),
+            ],
+        ));
+        assert_eq!(p.metadata.name, "web-standard-policy-deny-all");
+        assert_eq!(p.spec.policy_types, ["Ingress", "Egress"]);
+        assert_eq!(p.spec.egress.map(|e| e.len()), Some(0));
+    }
+
+    #[test]
+    fn yaml_uses_kubernetes_field_names() {
+        let p = build_network_policy(&input(
+            vec![flow("EGRESS", "UDP", "0", "10.0.0.1", "53")],
+            &[("10.0.0.1", db())],
+        ));
+        let yaml = serde_yaml::to_string(&p).unwrap();
+        for needle in [
+            "apiVersion: networking.k8s.io/v1",
+            "kind: NetworkPolicy",
+            "podSelector:",
+            "namespaceSelector:",
+            "kubernetes.io/metadata.name: data",
+            "protocol: UDP",
+        ] {
+            assert!(yaml.contains(needle), "missing {needle:?} in\n{yaml}");
+        }
+    }
+}
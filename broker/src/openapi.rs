@@ -0,0 +1,123 @@
+//! OpenAPI description of the broker API, served at `GET /openapi.json`.
+//!
+//! Generated from the handlers' `#[utoipa::path]` attributes and the
+//! request/response types, so it cannot drift from the routes the way
+//! the hand-written docs file did. docs/api-reference/openapi.json is a
+//! committed copy of it; a test in main.rs fails when the two differ.
+//! It describes the `/v1` routes and the unversioned ops endpoints.
+
+use actix_web::{get, web, HttpResponse};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::OpenApi as OpenApiDoc;
+use utoipa::{Modify, OpenApi};
+
+use crate::ErrorBody;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "kguardian broker API",
+        description = "Stores the traffic, syscalls, pods and services the kguardian \
+            controller observes, and serves them back with the policies, profiles and \
+            audit verdicts derived from them.",
+        license(name = "BUSL-1.1")
+    ),
+    paths(openapi_json),
+    nest((path = "/v1", api = V1Api)),
+    modifiers(&BearerAuth),
+    security(("bearer" = [])),
+    tags(
+        (name = "pods", description = "Pods the controller has reported"),
+        (name = "services", description = "Services the controller has reported"),
+        (name = "traffic", description = "Observed network flows"),
+        (name = "syscalls", description = "Observed syscalls and seccomp profiles"),
+        (name = "workloads", description = "Traffic and syscalls rolled up per workload"),
+        (name = "graph", description = "The pod-to-pod traffic graph"),
+        (name = "policies", description = "Network policies generated from observed traffic"),
+        (name = "audit", description = "Verdicts of audit-mode policies against observed traffic"),
+        (name = "ingest", description = "Writes from the kguardian controller"),
+        (name = "ops", description = "Probes, metrics and this document"),
+        (name = "version", description = "Broker version"),
+    )
+)]
+pub struct ApiDoc;
+
+/// The routes under `/v1`. The unversioned aliases of the same routes
+/// are deprecated and left out of the document.
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::add::add_pods_batch,
+        crate::add::add_pod_details,
+        crate::add::add_pods_syscalls,
+        crate::add::add_svc_details,
+        crate::add::mark_pod_dead,
+        crate::get::get_pod_details,
+        crate::get::get_pods_by_node,
+        crate::get::get_pod_by_name,
+        crate::get::get_pod_by_namespaced_name,
+        crate::get::get_pod_by_ip,
+        crate::get::get_svc_details,
+        crate::get::get_svc_by_ip,
+        crate::get::get_pod_traffic,
+        crate::get::get_pod_traffic_name,
+        crate::get::get_pod_traffic_namespaced_name,
+        crate::stream::stream_traffic,
+        crate::get::get_pod_syscall_name,
+        crate::get::get_pod_syscall_namespaced_name,
+        crate::get::get_pod_syscall_observations,
+        crate::seccomp::get_seccomp_profile,
+        crate::workload::get_workload_traffic,
+        crate::workload::get_workload_syscalls,
+        crate::graph::get_graph,
+        crate::networkpolicy::get_network_policy,
+        crate::cilium::get_cilium_network_policy,
+        crate::cilium::get_cilium_clusterwide_policy,
+        crate::simulate::simulate_network_policy,
+        crate::get::get_audit_verdicts,
+        crate::audit_summary::get_audit_summary,
+        crate::audit_replay::start_audit_replay,
+        crate::audit_replay::get_audit_replay,
+        crate::stream::stream_verdicts,
+        crate::version_check::get_version,
+    ),
+    components(schemas(ErrorBody))
+)]
+struct V1Api;
+
+/// Adds the `bearer` scheme the document's top-level `security` names.
+/// The broker only enforces it when `BROKER_AUTH_TOKEN` or
+/// `BROKER_AUTH_TOKENS_FILE` is set.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut OpenApiDoc) {
+        let scheme = HttpBuilder::new()
+            .scheme(HttpAuthScheme::Bearer)
+            .description(Some(
+                "Required on every endpoint except /health, /metrics and the API docs \
+                 when the broker runs with BROKER_AUTH_TOKEN or BROKER_AUTH_TOKENS_FILE \
+                 set. GETs need a token with the read scope, other POSTs ingest, and \
+                 /audit/replay admin; a token without the scope gets 403 forbidden.",
+            ))
+            .build();
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme("bearer", SecurityScheme::Http(scheme));
+    }
+}
+
+/// This document, as JSON. The app stores the merged document (these
+/// paths plus the probe endpoints in main.rs) as app data.
+#[utoipa::path(
+    tag = "ops",
+    security(()),
+    responses(
+        (status = 200, description = "The OpenAPI 3.1 document", content_type = "application/json"),
+    )
+)]
+#[get("/openapi.json")]
+pub async fn openapi_json(doc: web::Data<OpenApiDoc>) -> HttpResponse {
+    HttpResponse::Ok().json(doc.get_ref())
+}
@@ -0,0 +1,497 @@
+//! Inputs shared by the broker-side policy generators.
+//!
+//! The Go advisor builds policies from raw rows pulled over HTTP, one
+//! `/svc/ip/{ip}` and `/pod/ip/{ip}` call per peer. The generators here
+//! read the same data straight from Postgres, for a whole workload
+//! (see workload.rs) rather than one pod, and resolve every peer IP in
+//! one query. Peer resolution follows the advisor's
+//! `createNetworkPolicyPeer`, so both produce the same selectors: a
+//! Service with a selector wins, then a pod with labels, else the IP
+//! becomes a host CIDR.
+
+use crate::get::{validate_enum_filter, TimeRange};
+use crate::workload::{workload_members, workload_traffic, WorkloadTraffic};
+use crate::{schema, Error, PodDetail};
+use actix_web::HttpResponse;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{Array, Json, Nullable, Text};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
+use utoipa::IntoParams;
+
+pub(crate) type DbError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Pod labels that differ per replica or per rollout. Selecting on them
+/// would pin a policy to one ReplicaSet (or one pod), so they are never
+/// copied into a selector.
+const UNSTABLE_POD_LABELS: &[&str] = &[
+    "pod-template-hash",
+    "controller-revision-hash",
+    "statefulset.kubernetes.io/pod-name",
+    "apps.kubernetes.io/pod-index",
+    "pod-template-generation",
+];
+
+/// Query params shared by the `/policy/...` endpoints.
+#[derive(Deserialize, IntoParams)]
+pub struct PolicyQuery {
+    /// `yaml` (default) or `json`.
+    pub format: Option<String>,
+    /// RFC3339; only flows seen at or after it.
+    pub since: Option<String>,
+    /// RFC3339; only flows first recorded before it.
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Yaml,
+    Json,
+}
+
+impl OutputFormat {
+    /// Absent or empty means YAML, the form `kubectl apply -f` takes.
+    pub(crate) fn parse(raw: Option<&str>) -> Result<Self, String> {
+        match raw.filter(|s| !s.is_empty()) {
+            None => Ok(Self::Yaml),
+            Some(v) => {
+                validate_enum_filter("format", v, &["yaml", "json"])?;
+                Ok(if v == "json" { Self::Json } else { Self::Yaml })
+            }
+        }
+    }
+
+    pub(crate) fn respond<T: Serialize>(self, doc: &T) -> Result<HttpResponse, Error> {
+        Ok(match self {
+            Self::Json => HttpResponse::Ok().json(doc),
+            Self::Yaml => HttpResponse::Ok().content_type("application/yaml").body(
+                serde_yaml::to_string(doc)
+                    .map_err(|e| Error::InternalError { source: e.into() })?,
+            ),
+        })
+    }
+}
+
+/// The workload a policy is generated for.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyTarget {
+    pub namespace: String,
+    pub workload: String,
+    /// Labels for the policy's own pod selector. Empty when neither the
+    /// workload's selector nor any member's labels were recorded.
+    pub selector: BTreeMap<String, String>,
+    /// `spec.hostNetwork` of the newest member. Such pods share the
+    /// node's network namespace, so per-pod policies don't apply to them.
+    pub host_network: bool,
+}
+
+/// What a peer IP resolved to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PolicyPeer {
+    /// Pods matched by `labels` in `namespace`, reached directly or
+    /// through a Service that selects them.
+    Selector {
+        namespace: String,
+        labels: BTreeMap<String, String>,
+    },
+    /// A host-networked pod, as a host CIDR: the IP is its node's, and
+    /// label selectors don't match host traffic.
+    Host(String),
+    /// The `default/kubernetes` Service, as a host CIDR. It has no
+    /// selector, but Cilium can name it as an entity.
+    ApiServer(String),
+    /// Anything else, as a host CIDR.
+    Cidr(String),
+}
+
+/// Everything a generator needs for one workload.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyInput {
+    pub target: PolicyTarget,
+    /// Distinct allowed flows of the workload. DROP flows were refused
+    /// by a policy already in force; allowing them is a call for a
+    /// person, not a generator.
+    pub flows: Vec<WorkloadTraffic>,
+    /// Resolution of every peer IP in `flows`.
+    pub peers: HashMap<String, PolicyPeer>,
+}
+
+/// Load the workload, its allowed flows and their peers. `None` when no
+/// pod of that workload is known.
+pub fn load_policy_input(
+    conn: &mut PgConnection,
+    namespace: &str,
+    workload: &str,
+    range: TimeRange,
+) -> Result<Option<PolicyInput>, DbError> {
+    let members = workload_members(conn, namespace, workload)?;
+    if members.is_empty() {
+        return Ok(None);
+    }
+    let target = load_target(conn, namespace, workload, &members)?;
+    let flows: Vec<WorkloadTraffic> = workload_traffic(conn, namespace, &members, range)?
+        .into_iter()
+        .filter(|f| f.decision.as_deref() != Some("DROP"))
+        .collect();
+    let ips: BTreeSet<&str> = flows
+        .iter()
+        .filter_map(|f| f.traffic_in_out_ip.as_deref())
+        .collect();
+    let ips: Vec<&str> = ips.into_iter().collect();
+    let peers = resolve_policy_peers(conn, &ips)?;
+    Ok(Some(PolicyInput {
+        target,
+        flows,
+        peers,
+    }))
+}
+
+/// The selector comes from the workload's recorded
+/// `workload_selector_labels` (its controller's `spec.selector`); pods
+/// recorded before the controller sent those fall back to their own
+/// labels minus the per-rollout ones. Live, newest members first.
+fn load_target(
+    conn: &mut PgConnection,
+    namespace: &str,
+    workload: &str,
+    members: &[String],
+) -> Result<PolicyTarget, DbError> {
+    use schema::pod_details::dsl::*;
+    let rows = pod_details
+        .filter(pod_namespace.eq(namespace))
+        .filter(pod_name.eq_any(members))
+        .order((is_dead.asc(), time_stamp.desc()))
+        .select(PodDetail::as_select())
+        .load::<PodDetail>(conn)?;
+    let selector = rows
+        .iter()
+        .find_map(|r| non_empty(string_map(r.workload_selector_labels.as_ref())))
+        .or_else(|| {
+            rows.iter()
+                .find_map(|r| non_empty(pod_labels(r.pod_obj.as_ref())))
+        })
+        .unwrap_or_default();
+    Ok(PolicyTarget {
+        namespace: namespace.to_string(),
+        workload: workload.to_string(),
+        selector,
+        host_network: rows
+            .first()
+            .map(|r| host_network_of(r.pod_obj.as_ref()))
+            .unwrap_or(false),
+    })
+}
+
+/// Namespaces that have a pod with this `pod_identity`, sorted.
+pub fn workload_namespaces(
+    conn: &mut PgConnection,
+    workload: &str,
+) -> Result<Vec<String>, DbError> {
+    use schema::pod_details::dsl::*;
+    Ok(pod_details
+        .filter(pod_identity.eq(workload))
+        .select(pod_namespace)
+        .distinct()
+        .order(pod_namespace.asc())
+        .load::<String>(conn)?)
+}
+
+#[derive(QueryableByName)]
+struct PeerRow {
+    #[diesel(sql_type = Text)]
+    ip: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    svc_ip: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    svc_namespace: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    svc_name: Option<String>,
+    #[diesel(sql_type = Nullable<Json>)]
+    service_spec: Option<serde_json::Value>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pod_namespace: Option<String>,
+    #[diesel(sql_type = Nullable<Json>)]
+    workload_selector_labels: Option<serde_json::Value>,
+    #[diesel(sql_type = Nullable<Json>)]
+    pod_obj: Option<serde_json::Value>,
+}
+
+/// Resolve peer IPs in one statement. Pod rows are picked as in
+/// enrich.rs (live first, then newest).
+pub fn resolve_policy_peers(
+    conn: &mut PgConnection,
+    ips: &[&str],
+) -> Result<HashMap<String, PolicyPeer>, DbError> {
+    if ips.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let rows = sql_query(
+        "SELECT ip.addr AS ip, s.svc_ip, s.svc_namespace, s.svc_name, s.service_spec, \
+                p.pod_namespace, p.workload_selector_labels, p.pod_obj \
+         FROM unnest($1::text[]) AS ip(addr) \
+         LEFT JOIN LATERAL ( \
+             SELECT d.pod_namespace, d.workload_selector_labels, d.pod_obj FROM pod_details d \
+             WHERE d.pod_ip = ip.addr \
+             ORDER BY d.is_dead, d.time_stamp DESC LIMIT 1 \
+         ) p ON true \
+         LEFT JOIN svc_details s ON s.svc_ip = ip.addr",
+    )
+    .bind::<Array<Text>, _>(ips)
+    .load::<PeerRow>(conn)?;
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let peer = peer_from_row(&r);
+            (r.ip, peer)
+        })
+        .collect())
+}
+
+fn peer_from_row(r: &PeerRow) -> PolicyPeer {
+    if r.svc_namespace.as_deref() == Some("default") && r.svc_name.as_deref() == Some("kubernetes")
+    {
+        return PolicyPeer::ApiServer(host_cidr(&r.ip));
+    }
+    if r.svc_ip.is_some() {
+        let selector = string_map(
+            r.service_spec
+                .as_ref()
+                .and_then(|s| s.get("spec"))
+                .and_then(|s| s.get("selector")),
+        );
+        if !selector.is_empty() {
+            return PolicyPeer::Selector {
+                namespace: r.svc_namespace.clone().unwrap_or_default(),
+                labels: selector,
+            };
+        }
+    }
+    if let Some(ns) = &r.pod_namespace {
+        if host_network_of(r.pod_obj.as_ref()) {
+            return PolicyPeer::Host(host_cidr(&r.ip));
+        }
+        let labels = non_empty(string_map(r.workload_selector_labels.as_ref()))
+            .or_else(|| non_empty(pod_labels(r.pod_obj.as_ref())));
+        if let Some(labels) = labels {
+            return PolicyPeer::Selector {
+                namespace: ns.clone(),
+                labels,
+            };
+        }
+    }
+    PolicyPeer::Cidr(host_cidr(&r.ip))
+}
+
+/// `/32` for IPv4, `/128` for IPv6.
+pub(crate) fn host_cidr(ip: &str) -> String {
+    if ip.contains(':') {
+        format!("{ip}/128")
+    } else {
+        format!("{ip}/32")
+    }
+}
+
+/// A port as recorded, if it is a real one. Rejects 0 (the controller
+/// zeroes ephemeral ports) and anything that isn't a plain number, the
+/// same rule as the advisor's `parsePort`.
+pub(crate) fn parse_port(raw: Option<&str>) -> Option<u16> {
+    raw?.parse::<u16>().ok().filter(|p| *p > 0)
+}
+
+/// Kubernetes protocol name. Unknown values become TCP, as in the
+/// advisor's `protocolPtr`.
+pub(crate) fn policy_protocol(raw: Option<&str>) -> &'static str {
+    match raw.map(str::to_ascii_uppercase).as_deref() {
+        Some("UDP") => "UDP",
+        Some("SCTP") => "SCTP",
+        _ => "TCP",
+    }
+}
+
+/// Peers that can go in a policy: a parseable, non-loopback address.
+/// Anything else (a missing peer, a corrupt row) would make the whole
+/// manifest fail validation at apply time.
+pub(crate) fn is_policy_peer(ip: Option<&str>) -> bool {
+    ip.and_then(|ip| ip.parse::<IpAddr>().ok())
+        .is_some_and(|ip| !ip.is_loopback() && !ip.is_unspecified())
+}
+
+pub(crate) fn string_map(v: Option<&serde_json::Value>) -> BTreeMap<String, String> {
+    v.and_then(|v| v.as_object())
+        .map(|o| {
+            o.iter()
+                .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn pod_labels(pod_obj: Option<&serde_json::Value>) -> BTreeMap<String, String> {
+    let mut labels = string_map(pod_obj.and_then(|o| o.get("metadata")?.get("labels")));
+    labels.retain(|k, _| !UNSTABLE_POD_LABELS.contains(&k.as_str()));
+    labels
+}
+
+fn host_network_of(pod_obj: Option<&serde_json::Value>) -> bool {
+    pod_obj
+        .and_then(|o| o.get("spec")?.get("hostNetwork")?.as_bool())
+        .unwrap_or(false)
+}
+
+fn non_empty(m: BTreeMap<String, String>) -> Option<BTreeMap<String, String>> {
+    (!m.is_empty()).then_some(m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn row(ip: &str) -> PeerRow {
+        PeerRow {
+            ip: ip.to_string(),
+            svc_ip: None,
+            svc_namespace: None,
+            svc_name: None,
+            service_spec: None,
+            pod_namespace: None,
+            workload_selector_labels: None,
+            pod_obj: None,
+        }
+    }
+
+    #[test]
+    fn service_selector_wins_over_pod() {
+        let mut r = row("10.96.0.10");
+        r.svc_ip = Some("10.96.0.10".to_string());
+        r.svc_namespace = Some("kube-system".to_string());
+        r.service_spec = Some(json!({"spec": {"selector": {"k8s-app": "kube-dns"}}}));
+        r.pod_namespace = Some("other".to_string());
+        r.workload_selector_labels = Some(json!({"app": "x"}));
+        let PolicyPeer::Selector {
+            namespace, labels, ..
+        } = peer_from_row(&r)
+        else {
+            panic!("expected selector");
+        };
+        assert_eq!(namespace, "kube-system");
+        assert_eq!(labels.get("k8s-app").map(String::as_str), Some("kube-dns"));
+    }
+
+    #[test]
+    fn selectorless_service_falls_through_to_cidr() {
+        // A Service without a selector (manual Endpoints) can't be
+        // expressed as a podSelector; same fallback as the advisor.
+        let mut r = row("10.96.0.20");
+        r.svc_ip = Some("10.96.0.20".to_string());
+        r.service_spec = Some(json!({"spec": {}}));
+        assert_eq!(
+            peer_from_row(&r),
+            PolicyPeer::Cidr("10.96.0.20/32".to_string())
+        );
+    }
+
+    #[test]
+    fn pod_peer_prefers_workload_selector_over_pod_labels() {
+        let mut r = row("10.1.0.1");
+        r.pod_namespace = Some("prod".to_string());
+        r.workload_selector_labels = Some(json!({"app": "web"}));
+        r.pod_obj =
+            Some(json!({"metadata": {"labels": {"app": "web", "pod-template-hash": "abc"}}}));
+        let PolicyPeer::Selector { labels, .. } = peer_from_row(&r) else {
+            panic!("expected selector");
+        };
+        assert_eq!(labels.len(), 1);
+    }
+
+    #[test]
+    fn pod_label_fallback_drops_per_rollout_labels() {
+        // A selector carrying pod-template-hash would stop matching at
+        // the next rollout.
+        let mut r = row("10.1.0.1");
+        r.pod_namespace = Some("prod".to_string());
+        r.pod_obj =
+            Some(json!({"metadata": {"labels": {"app": "web", "pod-template-hash": "abc"}}}));
+        let PolicyPeer::Selector { labels, .. } = peer_from_row(&r) else {
+            panic!("expected selector");
+        };
+        assert_eq!(labels.keys().collect::<Vec<_>>(), ["app"]);
+    }
+
+    #[test]
+    fn host_network_pod_and_apiserver_are_not_selectors() {
+        // A hostNetwork pod's IP is its node's; a selector on its labels
+        // would never match the traffic.
+        let mut r = row("192.168.1.10");
+        r.pod_namespace = Some("kube-system".to_string());
+        r.pod_obj = Some(json!({"metadata": {"labels": {"k8s-app": "node-exporter"}},
+                                "spec": {"hostNetwork": true}}));
+        assert_eq!(
+            peer_from_row(&r),
+            PolicyPeer::Host("192.168.1.10/32".to_string())
+        );
+
+        let mut r = row("10.96.0.1");
+        r.svc_ip = Some("10.96.0.1".to_string());
+        r.svc_namespace = Some("default".to_string());
+        r.svc_name = Some("kubernetes".to_string());
+        assert_eq!(
+            peer_from_row(&r),
+            PolicyPeer::ApiServer("10.96.0.1/32".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_ip_is_host_cidr() {
+        assert_eq!(
+            peer_from_row(&row("8.8.8.8")),
+            PolicyPeer::Cidr("8.8.8.8/32".to_string())
+        );
+        assert_eq!(
+            peer_from_row(&row("2001:db8::1")),
+            PolicyPeer::Cidr("2001:db8::1/128".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_port_rejects_zero_and_junk() {
+        assert_eq!(parse_port(Some("5432")), Some(5432));
+        for bad in [
+            None,
+            Some("0"),
+            Some(""),
+            Some("8.5"),
+            Some("80a"),
+            Some("70000"),
+        ] {
+            assert_eq!(parse_port(bad), None, "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn only_routable_addresses_are_peers() {
+        assert!(is_policy_peer(Some("10.0.0.1")));
+        assert!(is_policy_peer(Some("2001:db8::1")));
+        for bad in [
+            None,
+            Some(""),
+            Some("bogus"),
+            Some("127.0.0.1"),
+            Some("::1"),
+            Some("0.0.0.0"),
+        ] {
+            assert!(!is_policy_peer(bad), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn output_format_defaults_to_yaml() {
+        assert_eq!(OutputFormat::parse(None), Ok(OutputFormat::Yaml));
+        assert_eq!(OutputFormat::parse(Some("")), Ok(OutputFormat::Yaml));
+        assert_eq!(OutputFormat::parse(Some("json")), Ok(OutputFormat::Json));
+        let err = OutputFormat::parse(Some("toml")).unwrap_err();
+        assert!(err.contains("format") && err.contains("toml"), "{err}");
+    }
+}
@@ -1,7 +1,9 @@
-//! Periodic cleanup of old audit_verdicts rows.
+//! Periodic cleanup of old audit_verdicts, pod_traffic and dead
+//! pod_details rows.
 //!
 //! The audit_verdicts table grows monotonically with the volume of
-//! "would deny" flow events. Without a retention policy, indexes
+//! "would deny" flow events, and pod_traffic with every distinct flow
+//! the controllers report. Without a retention policy, indexes
 //! degrade and disk usage climbs indefinitely on busy clusters.
 //!
 //! This module spawns a tokio task on broker startup that wakes every
@@ -10,7 +12,7 @@
 //! ```text
 //! WITH expired AS (
 //!     SELECT id FROM audit_verdicts
-//!     WHERE observed_at < timezone('UTC', NOW()) - INTERVAL '<N> days'
+//!     WHERE last_observed_at < timezone('UTC', NOW()) - INTERVAL '<N> days'
 //!     ORDER BY id LIMIT <batch_size>
 //! )
 //! DELETE FROM audit_verdicts WHERE id IN (SELECT id FROM expired);
@@ -25,12 +27,22 @@
 //!
 //! Configuration:
 //!
-//! - `AUDIT_VERDICTS_RETENTION_DAYS` (default 30) — anything older than
-//!   N days is eligible for deletion. Setting to 0 disables retention.
+//! - `AUDIT_VERDICTS_RETENTION_DAYS` (default 30) — verdicts not
+//!   observed for N days are eligible for deletion; one that keeps
+//!   firing keeps its row and hit count. Setting to 0 disables retention.
 //! - `AUDIT_VERDICTS_RETENTION_INTERVAL_SECS` (default 3600 = 1h) — how
 //!   often the cleanup task runs.
 //! - `AUDIT_VERDICTS_RETENTION_BATCH_SIZE` (default 5_000, clamped to
-//!   [100, 100_000]) — rows deleted per batch.
+//!   [100, 100_000]) — rows deleted per batch. Shared by every pass.
+//! - `POD_TRAFFIC_RETENTION_DAYS` (default 0 = off) — pod_traffic rows
+//!   not seen for N days (by `last_seen`) are pruned. Off by default:
+//!   policy generation reads the full flow history, so dropping it must
+//!   be an explicit operator choice rather than something an upgrade
+//!   does silently.
+//! - `POD_TRAFFIC_PRUNE_DEAD_PODS` (default false) — also delete the
+//!   traffic of dead pods as they age out of pod_details, so pruned
+//!   pods don't leave orphaned flows behind. Flows still seen within
+//!   the window (a new pod reusing the IP) are kept.
 //!
 //! Errors are logged and the task continues; a transient DB outage
 //! never crashes the broker.
@@ -76,6 +88,11 @@ const MAX_BATCHES_PER_PASS: u32 = 200;
 /// pruning must NOT be coupled to AUDIT_VERDICTS_RETENTION_DAYS=0.
 const DEFAULT_DEAD_POD_RETENTION_DAYS: u32 = 7;
 
+/// pod_traffic retention is opt-in. The advisor and `/pod/traffic/{name}`
+/// build policies from the full observed history; a default window would
+/// quietly narrow generated policies on upgrade.
+const DEFAULT_POD_TRAFFIC_RETENTION_DAYS: u32 = 0;
+
 /// Resolve the dead-pod pruning window from the audit retention setting.
 /// Pure + testable so the decoupling can't silently regress: audit_days==0
 /// (audit pruning disabled) must still yield a non-zero dead-pod window,
@@ -88,22 +105,28 @@ fn dead_pod_retention_window(audit_days: u32) -> u32 {
     }
 }
 
-/// Spawn a background task that periodically prunes audit_verdicts and
-/// dead pods. Returns immediately; the task lives for the broker's lifetime.
+/// Spawn a background task that periodically prunes audit_verdicts,
+/// pod_traffic and dead pods. Returns immediately; the task lives for the
+/// broker's lifetime.
 ///
 /// `AUDIT_VERDICTS_RETENTION_DAYS=0` disables ONLY audit_verdicts pruning.
 /// Dead-pod pruning of pod_details always runs (with its own default
-/// window) — it must not be coupled to the audit setting.
+/// window) — it must not be coupled to the audit setting. The pod_traffic
+/// passes are each opt-in via their own env vars.
 pub fn spawn(pool: DbPool) {
     let audit_days = retention_days();
     // Dead-pod pruning runs independently: use the audit window when set,
     // otherwise a standalone default. Setting audit retention to 0 only
     // disables audit_verdicts pruning, not pod_details cleanup.
     let dead_pod_days = dead_pod_retention_window(audit_days);
+    let traffic_days = pod_traffic_retention_days();
+    let prune_dead_pod_traffic = pod_traffic_prune_dead_pods();
     let interval = retention_interval();
     info!(
         audit_days,
         dead_pod_days,
+        traffic_days,
+        prune_dead_pod_traffic,
         interval_secs = interval.as_secs(),
         "retention loop scheduled (audit_days=0 means audit pruning off; dead-pod pruning still runs)"
     );
@@ -115,109 +138,66 @@ pub fn spawn(pool: DbPool) {
         loop {
             // Audit pruning only when enabled; dead-pod pruning always.
             if audit_days > 0 {
-                run_pass(&pool, audit_days).await;
+                run_batched_pass(&pool, "audit_verdicts", move |conn, batch_size| {
+                    run_batch(conn, audit_days, batch_size)
+                })
+                .await;
             }
-            run_dead_pod_pass(&pool, dead_pod_days).await;
-            tokio::time::sleep(interval).await;
-        }
-    });
-}
-
-/// One pass pruning pods that have been dead longer than the retention
-/// window. `pod_details` keeps a row per pod ever seen and dead pods are
-/// otherwise never removed, so it grows unbounded with pod churn — and
-/// `/pod/info` returns the whole table including each pod's full manifest
-/// JSON, so the bloat directly degrades both the broker (large serialise +
-/// memory spike) and the frontend. Reuses the same window, batch size and
-/// batched-DELETE discipline as the verdict prune.
-async fn run_dead_pod_pass(pool: &DbPool, days: u32) {
-    let batch_size = retention_batch_size();
-    let mut total_deleted: usize = 0;
-    for batch_idx in 0..MAX_BATCHES_PER_PASS {
-        let pool = pool.clone();
-        let result = tokio::task::spawn_blocking(move || -> Result<usize, RetentionError> {
-            run_dead_pod_batch(&pool, days, batch_size)
-        })
-        .await;
-        match result {
-            Ok(Ok(0)) => {
-                if total_deleted == 0 {
-                    debug!("pod_details retention: 0 dead pods pruned");
-                } else {
-                    info!(
-                        rows = total_deleted,
-                        batches = batch_idx,
-                        "pod_details retention pruned dead pods",
-                    );
-                }
-                return;
+            if traffic_days > 0 {
+                run_batched_pass(&pool, "pod_traffic", move |conn, batch_size| {
+                    run_pod_traffic_batch(conn, traffic_days, batch_size)
+                })
+                .await;
             }
-            Ok(Ok(n)) => total_deleted += n,
-            Ok(Err(RetentionError::Pool(e))) => {
-                warn!(error = %e, pruned_before_failure = total_deleted, "pod_details retention: could not get db conn");
-                return;
-            }
-            Ok(Err(RetentionError::Diesel(e))) => {
-                warn!(error = %e, pruned_before_failure = total_deleted, "pod_details retention: DELETE failed");
-                return;
-            }
-            Err(e) => {
-                warn!(error = %e, pruned_before_failure = total_deleted, "pod_details retention task panicked");
-                return;
+            // Cascade BEFORE the dead-pod pass: it finds the traffic by
+            // joining the pod_details rows that pass is about to delete.
+            // A pass cut short by the batch cap leaves those pods in
+            // place, so the next interval still finds their traffic.
+            if prune_dead_pod_traffic {
+                run_batched_pass(&pool, "dead-pod pod_traffic", move |conn, batch_size| {
+                    run_dead_pod_traffic_batch(conn, dead_pod_days, batch_size)
+                })
+                .await;
             }
+            run_batched_pass(&pool, "pod_details", move |conn, batch_size| {
+                run_dead_pod_batch(conn, dead_pod_days, batch_size)
+            })
+            .await;
+            tokio::time::sleep(interval).await;
         }
-    }
-    info!(
-        rows = total_deleted,
-        cap = MAX_BATCHES_PER_PASS,
-        "pod_details retention hit per-pass batch cap; remaining dead pods will be pruned on next interval",
-    );
-}
-
-/// Batched DELETE of dead pods older than the window. pod_details' PK is
-/// pod_name, so the CTE selects and deletes by pod_name.
-fn run_dead_pod_batch(pool: &DbPool, days: u32, batch_size: i64) -> Result<usize, RetentionError> {
-    let mut conn = pool.get().map_err(RetentionError::Pool)?;
-    let interval = format!("{} days", days);
-    let deleted = sql_query(
-        "WITH expired AS (\
-             SELECT pod_name FROM pod_details \
-             WHERE is_dead = true AND time_stamp < timezone('UTC', NOW()) - $1::interval \
-             ORDER BY pod_name \
-             LIMIT $2 \
-         ) \
-         DELETE FROM pod_details WHERE pod_name IN (SELECT pod_name FROM expired)",
-    )
-    .bind::<diesel::sql_types::Text, _>(interval)
-    .bind::<diesel::sql_types::BigInt, _>(batch_size)
-    .execute(&mut conn)
-    .map_err(RetentionError::Diesel)?;
-    Ok(deleted)
+    });
 }
 
-/// One cleanup pass — issues batched DELETEs in a loop until the
-/// window is empty, the per-pass cap is hit, or an error occurs.
-/// Each batch runs in its own `spawn_blocking` task so the broker's
+/// One cleanup pass: calls `batch` in a loop until the window is empty,
+/// the per-pass cap is hit, or an error occurs. `batch` issues a single
+/// batched DELETE on a pooled connection and returns the rows it removed
+/// (0 means the window is empty). Each batch runs in its own `spawn_blocking` task so the broker's
 /// blocking pool stays responsive to other work between iterations.
-/// Logs the cumulative result and never propagates errors.
-async fn run_pass(pool: &DbPool, days: u32) {
+/// `label` names the pass in logs. Logs the cumulative result and never
+/// propagates errors.
+async fn run_batched_pass<F>(pool: &DbPool, label: &'static str, batch: F)
+where
+    F: Fn(&mut PgConnection, i64) -> QueryResult<usize> + Copy + Send + 'static,
+{
     let batch_size = retention_batch_size();
     let mut total_deleted: usize = 0;
     for batch_idx in 0..MAX_BATCHES_PER_PASS {
         let pool = pool.clone();
         let result = tokio::task::spawn_blocking(move || -> Result<usize, RetentionError> {
-            run_batch(&pool, days, batch_size)
+            let mut conn = pool.get()?;
+            Ok(batch(&mut conn, batch_size)?)
         })
         .await;
         match result {
             Ok(Ok(0)) => {
                 if total_deleted == 0 {
-                    debug!("audit_verdicts retention: 0 rows pruned");
+                    debug!(pass = label, "retention: 0 rows pruned");
                 } else {
                     info!(
+                        pass = label,
                         rows = total_deleted,
                         batches = batch_idx,
-                        "audit_verdicts retention pruned old rows",
+                        "retention pruned old rows",
                     );
                 }
                 return;
@@ -225,25 +205,28 @@ async fn run_pass(pool: &DbPool, days: u32) {
             Ok(Ok(n)) => total_deleted += n,
             Ok(Err(RetentionError::Pool(e))) => {
                 warn!(
+                    pass = label,
                     error = %e,
                     pruned_before_failure = total_deleted,
-                    "audit_verdicts retention: could not get db conn",
+                    "retention: could not get db conn",
                 );
                 return;
             }
             Ok(Err(RetentionError::Diesel(e))) => {
                 warn!(
+                    pass = label,
                     error = %e,
                     pruned_before_failure = total_deleted,
-                    "audit_verdicts retention: DELETE failed",
+                    "retention: DELETE failed",
                 );
                 return;
             }
             Err(e) => {
                 warn!(
+                    pass = label,
                     error = %e,
                     pruned_before_failure = total_deleted,
-                    "audit_verdicts retention task panicked",
+                    "retention task panicked",
                 );
                 return;
             }
@@ -256,17 +239,17 @@ async fn run_pass(pool: &DbPool, days: u32) {
     // can't keep up with; bump retention.intervalSeconds DOWN or
     // batch size up).
     info!(
+        pass = label,
         rows = total_deleted,
         cap = MAX_BATCHES_PER_PASS,
-        "audit_verdicts retention hit per-pass batch cap; remaining rows will be pruned on next interval",
+        "retention hit per-pass batch cap; remaining rows will be pruned on next interval",
     );
 }
 
-/// Execute a single batched DELETE. Returns the number of rows
-/// actually removed (0 means the window is empty). Kept synchronous
-/// so the caller can run it inside `spawn_blocking`.
-fn run_batch(pool: &DbPool, days: u32, batch_size: i64) -> Result<usize, RetentionError> {
-    let mut conn = pool.get().map_err(RetentionError::Pool)?;
+/// Batched DELETE of audit_verdicts rows not observed within the window.
+/// Kept synchronous, like every batch function, so `run_batched_pass` can
+/// run it inside `spawn_blocking`.
+fn run_batch(conn: &mut PgConnection, days: u32, batch_size: i64) -> QueryResult<usize> {
     let interval = format!("{} days", days);
     // Postgres doesn't allow LIMIT directly on DELETE. The CTE
     // pattern selects up to N expired rows by primary key, then
@@ -278,7 +261,7 @@ fn run_batch(pool: &DbPool, days: u32, batch_size: i64) -> Result<usize, Retenti
     // as text — the server casts to interval. That avoids any
     // SQL-injection surface even if `days` were ever sourced from
     // user input (it isn't, but defensible).
-    // observed_at is stored as TIMESTAMP (no timezone) carrying UTC
+    // last_observed_at is stored as TIMESTAMP (no timezone) carrying UTC
     // values (audit.rs sets it via Utc::now().naive_utc()). Use
     // `timezone('UTC', NOW())` so the right-hand side is a UTC-naive
     // timestamp regardless of the postgres session timezone. The
@@ -287,10 +270,10 @@ fn run_batch(pool: &DbPool, days: u32, batch_size: i64) -> Result<usize, Retenti
     // default would compute the wrong retention window (typically off
     // by single-digit hours on a multi-day boundary — small but real
     // correctness drift).
-    let deleted = sql_query(
+    sql_query(
         "WITH expired AS (\
              SELECT id FROM audit_verdicts \
-             WHERE observed_at < timezone('UTC', NOW()) - $1::interval \
+             WHERE last_observed_at < timezone('UTC', NOW()) - $1::interval \
              ORDER BY id \
              LIMIT $2 \
          ) \
@@ -298,9 +281,90 @@ fn run_batch(pool: &DbPool, days: u32, batch_size: i64) -> Result<usize, Retenti
     )
     .bind::<diesel::sql_types::Text, _>(interval)
     .bind::<diesel::sql_types::BigInt, _>(batch_size)
-    .execute(&mut conn)
-    .map_err(RetentionError::Diesel)?;
-    Ok(deleted)
+    .execute(conn)
+}
+
+/// Batched DELETE of pod_traffic rows not seen within the window.
+/// Windows on `last_seen`, not `time_stamp` (first seen): a long-lived
+/// flow that is still active must survive, or its next event re-inserts
+/// it as new. Stalest first via `idx_pod_traffic_last_seen`, so a pass
+/// cut short by the batch cap has still removed the stalest flows. Same
+/// UTC-naive comparison as `run_batch` — last_seen carries UTC values.
+fn run_pod_traffic_batch(
+    conn: &mut PgConnection,
+    days: u32,
+    batch_size: i64,
+) -> QueryResult<usize> {
+    let interval = format!("{} days", days);
+    sql_query(
+        "WITH expired AS (\
+             SELECT uuid FROM pod_traffic \
+             WHERE last_seen < timezone('UTC', NOW()) - $1::interval \
+             ORDER BY last_seen, uuid \
+             LIMIT $2 \
+         ) \
+         DELETE FROM pod_traffic WHERE uuid IN (SELECT uuid FROM expired)",
+    )
+    .bind::<diesel::sql_types::Text, _>(interval)
+    .bind::<diesel::sql_types::BigInt, _>(batch_size)
+    .execute(conn)
+}
+
+/// Batched DELETE of the traffic of dead pods that `run_dead_pod_batch`
+/// will prune — same `is_dead` + window predicate, joined on pod_details'
+/// (pod_namespace, pod_name) key. A traffic row with no namespace matches
+/// the '' that a namespace-unknown pod_details row carries. Deleting by the
+/// traffic PK keeps each batch bounded even for a pod with a huge flow
+/// history.
+///
+/// Flows are keyed on the pod IP, not the name, so a live pod that got
+/// the dead pod's IP updates the dead pod's rows. Only rows also not seen
+/// within the window go: deleting a live flow would make its next event
+/// a new flow, evaluated and alerted on again.
+fn run_dead_pod_traffic_batch(
+    conn: &mut PgConnection,
+    days: u32,
+    batch_size: i64,
+) -> QueryResult<usize> {
+    let interval = format!("{} days", days);
+    sql_query(
+        "WITH expired AS (\
+             SELECT t.uuid FROM pod_traffic t \
+             JOIN pod_details d ON d.pod_name = t.pod_name \
+                               AND d.pod_namespace = COALESCE(t.pod_namespace, '') \
+             WHERE d.is_dead = true AND d.time_stamp < timezone('UTC', NOW()) - $1::interval \
+               AND t.last_seen < timezone('UTC', NOW()) - $1::interval \
+             LIMIT $2 \
+         ) \
+         DELETE FROM pod_traffic WHERE uuid IN (SELECT uuid FROM expired)",
+    )
+    .bind::<diesel::sql_types::Text, _>(interval)
+    .bind::<diesel::sql_types::BigInt, _>(batch_size)
+    .execute(conn)
+}
+
+/// Batched DELETE of pods that have been dead longer than the window.
+/// `pod_details` keeps a row per pod ever seen and dead pods are otherwise
+/// never removed, so it grows unbounded with pod churn — and `/pod/info`
+/// returns the whole table including each pod's full manifest JSON, so the
+/// bloat directly degrades both the broker (large serialise + memory spike)
+/// and the frontend. pod_details' PK is (pod_namespace, pod_name), so the
+/// CTE selects and deletes by that pair.
+fn run_dead_pod_batch(conn: &mut PgConnection, days: u32, batch_size: i64) -> QueryResult<usize> {
+    let interval = format!("{} days", days);
+    sql_query(
+        "WITH expired AS (\
+             SELECT pod_namespace, pod_name FROM pod_details \
+             WHERE is_dead = true AND time_stamp < timezone('UTC', NOW()) - $1::interval \
+             ORDER BY pod_namespace, pod_name \
+             LIMIT $2 \
+         ) \
+         DELETE FROM pod_details \
+         WHERE (pod_namespace, pod_name) IN (SELECT pod_namespace, pod_name FROM expired)",
+    )
+    .bind::<diesel::sql_types::Text, _>(interval)
+    .bind::<diesel::sql_types::BigInt, _>(batch_size)
+    .execute(conn)
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -323,6 +387,27 @@ fn retention_days() -> u32 {
         .unwrap_or(DEFAULT_RETENTION_DAYS)
 }
 
+/// pod_traffic window in days; 0 (the default) disables the pass. Same
+/// trim + fall-back-to-default parsing as `retention_days`.
+fn pod_traffic_retention_days() -> u32 {
+    std::env::var("POD_TRAFFIC_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_POD_TRAFFIC_RETENTION_DAYS)
+}
+
+/// True only for an explicit truthy POD_TRAFFIC_PRUNE_DEAD_PODS. The
+/// cascade deletes data, so a typo keeps the default (off) rather than
+/// turning it on.
+fn pod_traffic_prune_dead_pods() -> bool {
+    std::env::var("POD_TRAFFIC_PRUNE_DEAD_PODS").is_ok_and(|v| {
+        matches!(
+            v.trim().to_ascii_lowercase().as_str(),
+            "true" | "1" | "yes" | "on"
+        )
+    })
+}
+
 fn retention_interval() -> Duration {
     let secs = std::env::var("AUDIT_VERDICTS_RETENTION_INTERVAL_SECS")
         .ok()
@@ -429,6 +514,50 @@ mod tests {
         });
     }
 
+    #[test]
+    fn pod_traffic_retention_days_default_off() {
+        // Opt-in: an upgrade must not start deleting flow history that
+        // policy generation reads.
+        with_env("POD_TRAFFIC_RETENTION_DAYS", None, || {
+            assert_eq!(pod_traffic_retention_days(), 0);
+        });
+    }
+
+    #[test]
+    fn pod_traffic_retention_days_explicit_and_trimmed() {
+        with_env("POD_TRAFFIC_RETENTION_DAYS", Some(" 14\n"), || {
+            assert_eq!(pod_traffic_retention_days(), 14);
+        });
+    }
+
+    #[test]
+    fn pod_traffic_retention_days_invalid_stays_off() {
+        with_env("POD_TRAFFIC_RETENTION_DAYS", Some("two-weeks"), || {
+            assert_eq!(
+                pod_traffic_retention_days(),
+                DEFAULT_POD_TRAFFIC_RETENTION_DAYS
+            );
+        });
+    }
+
+    #[test]
+    fn pod_traffic_prune_dead_pods_only_on_truthy() {
+        with_env("POD_TRAFFIC_PRUNE_DEAD_PODS", None, || {
+            assert!(!pod_traffic_prune_dead_pods());
+        });
+        for v in ["true", " TRUE ", "1", "yes", "on"] {
+            with_env("POD_TRAFFIC_PRUNE_DEAD_PODS", Some(v), || {
+                assert!(pod_traffic_prune_dead_pods(), "{v:?} must enable");
+            });
+        }
+        // A typo must not switch on a deleting cascade.
+        for v in ["false", "0", "ture", ""] {
+            with_env("POD_TRAFFIC_PRUNE_DEAD_PODS", Some(v), || {
+                assert!(!pod_traffic_prune_dead_pods(), "{v:?} must stay off");
+            });
+        }
+    }
+
     #[test]
     fn retention_interval_trims_whitespace() {
         with_env(
@@ -560,4 +689,50 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    #[ignore = "requires a database"]
+    fn dead_pod_cascade_keeps_flows_a_live_pod_still_updates() {
+        use crate::schema::pod_details::dsl as d;
+        use crate::schema::pod_traffic::dsl as t;
+        use chrono::{Duration, Utc};
+
+        let now = Utc::now().naive_utc();
+        let month_ago = now - Duration::days(30);
+        let mut conn = crate::test_support::db();
+        conn.test_transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(d::pod_details)
+                .values((
+                    d::pod_namespace.eq("retention-test"),
+                    d::pod_name.eq("retention-test-web-1"),
+                    d::pod_ip.eq("10.99.0.1"),
+                    d::node_name.eq("node-a"),
+                    d::time_stamp.eq(month_ago),
+                    d::is_dead.eq(true),
+                ))
+                .execute(conn)?;
+            // The second flow's key now belongs to a live pod with the
+            // same IP, whose events keep bumping last_seen.
+            for (id, last) in [("stale", month_ago), ("reused", now)] {
+                diesel::insert_into(t::pod_traffic)
+                    .values((
+                        t::uuid.eq(format!("retention-test-{id}")),
+                        t::pod_name.eq("retention-test-web-1"),
+                        t::pod_namespace.eq("retention-test"),
+                        t::pod_ip.eq("10.99.0.1"),
+                        t::traffic_in_out_ip.eq(id),
+                        t::time_stamp.eq(month_ago),
+                        t::last_seen.eq(last),
+                    ))
+                    .execute(conn)?;
+            }
+            while run_dead_pod_traffic_batch(conn, 7, MAX_BATCH_SIZE)? > 0 {}
+            let left = t::pod_traffic
+                .filter(t::uuid.like("retention-test-%"))
+                .select(t::uuid)
+                .load::<String>(conn)?;
+            assert_eq!(left, ["retention-test-reused"]);
+            Ok(())
+        });
+    }
 }
@@ -7,10 +7,10 @@ diesel::table! {
     // `(pod_ip)` declaration here was inconsistent with both and
     // would silently misbehave for any query using diesel's PK-aware
     // helpers (.find(), Identifiable impls, joins).
-    pod_details (pod_name) {
+    pod_details (pod_namespace, pod_name) {
         pod_name -> Varchar,
         pod_ip -> Varchar,
-        pod_namespace -> Nullable<Varchar>,
+        pod_namespace -> Varchar,
         pod_obj -> Nullable<Json>,
         time_stamp -> Timestamp,
         node_name -> Varchar,
@@ -33,11 +33,14 @@ diesel::table! {
         traffic_in_out_port -> Nullable<Varchar>,
         decision -> Nullable<Varchar>,
         time_stamp -> Timestamp,
+        last_seen -> Timestamp,
+        seen_count -> Int8,
+        seq -> Int8,
     }
 }
 
 diesel::table! {
-    pod_syscalls (pod_name) {
+    pod_syscalls (pod_name, pod_namespace) {
         pod_name -> Varchar,
         pod_namespace -> Varchar,
         syscalls -> Varchar,
@@ -46,6 +49,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    pod_syscall_observations (pod_namespace, pod_name, arch, syscall) {
+        pod_namespace -> Varchar,
+        pod_name -> Varchar,
+        arch -> Varchar,
+        syscall -> Varchar,
+        first_seen -> Timestamp,
+        last_seen -> Timestamp,
+        count -> Int8,
+    }
+}
+
 diesel::table! {
     svc_details (svc_ip) {
         svc_ip -> Varchar,
@@ -70,8 +85,46 @@ diesel::table! {
         dst_port -> Int4,
         protocol -> Varchar,
         reason -> Nullable<Varchar>,
-        observed_at -> Timestamp,
+        last_observed_at -> Timestamp,
         verdict -> Varchar,
+        first_observed_at -> Timestamp,
+        hits -> Int8,
+    }
+}
+
+diesel::table! {
+    audit_verdict_flows (verdict_id, traffic_uuid) {
+        verdict_id -> Int8,
+        traffic_uuid -> Varchar,
+    }
+}
+
+diesel::table! {
+    audit_outbox (id) {
+        id -> BigSerial,
+        traffic_uuid -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        created_at -> Timestamp,
+        last_error -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    audit_replay_jobs (id) {
+        id -> Varchar,
+        namespace -> Varchar,
+        policy -> Nullable<Varchar>,
+        since -> Timestamp,
+        until -> Timestamp,
+        state -> Varchar,
+        total -> Int8,
+        processed -> Int8,
+        failed -> Int8,
+        error -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
     }
 }
 
@@ -89,5 +142,9 @@ diesel::allow_tables_to_appear_in_same_query!(
     pod_traffic,
     svc_details,
     pod_syscalls,
+    pod_syscall_observations,
     audit_verdicts,
+    audit_verdict_flows,
+    audit_outbox,
+    audit_replay_jobs,
 );
@@ -0,0 +1,274 @@
+//! `GET /profile/seccomp/{namespace}/{workload}`: an OCI seccomp
+//! profile (the JSON a kubelet `localhost/` profile or
+//! `docker run --security-opt seccomp=` takes) for a workload.
+//!
+//! The profile allow-lists every syscall any member pod has made, on
+//! any architecture, plus the baseline the container runtime needs, and
+//! applies `defaultAction` to the rest. Syscall names are merged into
+//! one rule across architectures: runtimes skip names an architecture
+//! doesn't have (`open` on arm64), so a multi-arch workload gets one
+//! profile that works on every node.
+
+use crate::get::{validate_enum_filter, TimeRange};
+use crate::workload::{workload_members, workload_syscalls, WorkloadSyscall};
+use crate::{Error, ErrorBody};
+use actix_web::{get, web, HttpResponse};
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+/// `defaultAction` values the endpoint emits. ERRNO blocks anything
+/// unseen; LOG only audits it, for a trial run before enforcing. KILL
+/// is left out: a missed syscall would take the workload down.
+pub const SECCOMP_DEFAULT_ACTIONS: &[&str] = &["SCMP_ACT_ERRNO", "SCMP_ACT_LOG"];
+
+/// Syscalls the runtime (runc, crun) makes between installing the
+/// filter and handing control to the entrypoint, plus the ones every
+/// process makes on exit. They happen before the workload is traced,
+/// so they are often missing from what was recorded; without them the
+/// container fails to start.
+const RUNTIME_BASELINE: &[&str] = &[
+    "arch_prctl",
+    "brk",
+    "capget",
+    "capset",
+    "chdir",
+    "close",
+    "dup3",
+    "epoll_ctl",
+    "epoll_pwait",
+    "execve",
+    "execveat",
+    "exit",
+    "exit_group",
+    "fchdir",
+    "fcntl",
+    "fstat",
+    "fstatfs",
+    "futex",
+    "getdents64",
+    "getpid",
+    "getppid",
+    "mmap",
+    "mprotect",
+    "munmap",
+    "nanosleep",
+    "newfstatat",
+    "openat",
+    "prctl",
+    "read",
+    "restart_syscall",
+    "rseq",
+    "rt_sigaction",
+    "rt_sigprocmask",
+    "rt_sigreturn",
+    "sched_yield",
+    "set_robust_list",
+    "set_tid_address",
+    "setgid",
+    "setgroups",
+    "setuid",
+    "sigaltstack",
+    "write",
+];
+
+/// Seccomp arch token for an arch the controller records (Rust's
+/// `std::env::consts::ARCH`). The tokens are the OCI runtime spec's;
+/// note arm64 is `SCMP_ARCH_AARCH64`, not the `SCMP_ARCH_ARM64` the
+/// advisor's `SeccompArchitectures` emits, which runtimes reject.
+pub fn seccomp_arch(arch: &str) -> Option<&'static str> {
+    Some(match arch {
+        "x86_64" => "SCMP_ARCH_X86_64",
+        "x86" => "SCMP_ARCH_X86",
+        "aarch64" => "SCMP_ARCH_AARCH64",
+        "arm" => "SCMP_ARCH_ARM",
+        "riscv64" => "SCMP_ARCH_RISCV64",
+        "s390x" => "SCMP_ARCH_S390X",
+        _ => return None,
+    })
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SeccompQuery {
+    /// `SCMP_ACT_ERRNO` (default) or `SCMP_ACT_LOG`.
+    pub default_action: Option<String>,
+    /// RFC3339; only syscalls the workload made at or after it.
+    pub since: Option<String>,
+    /// RFC3339; only syscalls the workload first made before it.
+    pub until: Option<String>,
+}
+
+/// The `linux.seccomp` object of the OCI runtime spec.
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SeccompProfile {
+    pub default_action: String,
+    pub architectures: Vec<&'static str>,
+    pub syscalls: Vec<SeccompRule>,
+}
+
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct SeccompRule {
+    pub names: Vec<String>,
+    pub action: &'static str,
+}
+
+/// A seccomp profile for a workload, built from its observed syscalls.
+#[utoipa::path(
+    tag = "syscalls",
+    params(SeccompQuery),
+    responses(
+        (status = 200, body = SeccompProfile),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+        (status = 404, description = "No data found", body = ErrorBody),
+        (status = 422, description = "A syscall cannot be put in a profile", body = ErrorBody),
+    )
+)]
+#[get("/profile/seccomp/{namespace}/{workload}")]
+pub async fn get_seccomp_profile(
+    pool: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    query: web::Query<SeccompQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("generate seccomp profile for workload");
+    let (namespace, workload) = path.into_inner();
+    let default_action = match query.default_action.as_deref().filter(|s| !s.is_empty()) {
+        None => SECCOMP_DEFAULT_ACTIONS[0].to_string(),
+        Some(v) => {
+            validate_enum_filter("default_action", v, SECCOMP_DEFAULT_ACTIONS)?;
+            v.to_string()
+        }
+    };
+    let range = TimeRange::parse(query.since.as_deref(), query.until.as_deref())?;
+    let label = format!("{namespace}/{workload}");
+    let rows = web::block(move || {
+        let mut conn = pool.get()?;
+        let members = workload_members(&mut conn, &namespace, &workload)?;
+        if members.is_empty() {
+            return Ok(None);
+        }
+        workload_syscalls(&mut conn, &namespace, &members, range).map(Some)
+    })
+    .await??;
+
+    let rows = rows.ok_or(Error::NotFound)?;
+    let profile = build_seccomp_profile(&rows, default_action)
+        .map_err(|msg| Error::Unprocessable(format!("workload {label}: {msg}")))?;
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+/// Merge the workload's syscalls into one profile. Errors when there is
+/// nothing to allow-list, or no recorded arch has a seccomp token: a
+/// baseline-only profile, or one without architectures, would stop the
+/// workload from running at all.
+pub fn build_seccomp_profile(
+    rows: &[WorkloadSyscall],
+    default_action: String,
+) -> Result<SeccompProfile, String> {
+    let mut architectures = BTreeSet::new();
+    let mut unknown = BTreeSet::new();
+    let mut names = BTreeSet::new();
+    for row in rows {
+        match seccomp_arch(&row.arch) {
+            Some(token) => {
+                architectures.insert(token);
+                names.insert(row.syscall.clone());
+            }
+            None => {
+                unknown.insert(row.arch.as_str());
+            }
+        }
+    }
+    if !unknown.is_empty() {
+        warn!("skipping syscalls recorded on unsupported arches {unknown:?}");
+    }
+    if names.is_empty() {
+        return Err(if unknown.is_empty() {
+            "no syscalls recorded".to_string()
+        } else {
+            format!("no syscalls recorded on a supported arch (saw {unknown:?})")
+        });
+    }
+    names.extend(RUNTIME_BASELINE.iter().map(|s| s.to_string()));
+    Ok(SeccompProfile {
+        default_action,
+        architectures: architectures.into_iter().collect(),
+        syscalls: vec![SeccompRule {
+            names: names.into_iter().collect(),
+            action: "SCMP_ACT_ALLOW",
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn row(arch: &str, syscall: &str) -> WorkloadSyscall {
+        WorkloadSyscall {
+            arch: arch.to_string(),
+            syscall: syscall.to_string(),
+            first_seen: NaiveDateTime::default(),
+            last_seen: NaiveDateTime::default(),
+            count: 1,
+            pod_count: 1,
+        }
+    }
+
+    #[test]
+    fn arches_merge_into_one_rule() {
+        // One profile has to run on both node pools, so arm64 and
+        // x86_64 names share a rule and both arch tokens are listed.
+        let p = build_seccomp_profile(
+            &[
+                row("x86_64", "open"),
+                row("aarch64", "openat"),
+                row("x86_64", "accept4"),
+            ],
+            "SCMP_ACT_LOG".to_string(),
+        )
+        .unwrap();
+        assert_eq!(p.architectures, ["SCMP_ARCH_AARCH64", "SCMP_ARCH_X86_64"]);
+        assert_eq!(p.syscalls.len(), 1);
+        let names = &p.syscalls[0].names;
+        assert!(names.contains(&"open".to_string()) && names.contains(&"accept4".to_string()));
+        assert!(
+            names.windows(2).all(|w| w[0] < w[1]),
+            "names sorted and distinct"
+        );
+        assert_eq!(p.default_action, "SCMP_ACT_LOG");
+    }
+
+    #[test]
+    fn baseline_is_always_allowed() {
+        let p = build_seccomp_profile(&[row("x86_64", "accept4")], "SCMP_ACT_ERRNO".to_string())
+            .unwrap();
+        for name in ["execve", "exit_group", "futex"] {
+            assert!(p.syscalls[0].names.contains(&name.to_string()), "{name}");
+        }
+    }
+
+    #[test]
+    fn nothing_usable_is_an_error() {
+        assert!(build_seccomp_profile(&[], "SCMP_ACT_ERRNO".to_string()).is_err());
+        let err = build_seccomp_profile(&[row("mips", "read")], "SCMP_ACT_ERRNO".to_string())
+            .unwrap_err();
+        assert!(err.contains("mips"), "{err}");
+    }
+
+    #[test]
+    fn profile_uses_oci_field_names() {
+        let p =
+            build_seccomp_profile(&[row("x86_64", "read")], "SCMP_ACT_ERRNO".to_string()).unwrap();
+        let v = serde_json::to_value(&p).unwrap();
+        assert_eq!(v["defaultAction"], "SCMP_ACT_ERRNO");
+        assert_eq!(v["architectures"][0], "SCMP_ARCH_X86_64");
+        assert_eq!(v["syscalls"][0]["action"], "SCMP_ACT_ALLOW");
+    }
+}
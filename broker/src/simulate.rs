@@ -0,0 +1,996 @@
+//! `POST /simulate/networkpolicy`: what a NetworkPolicy would do to the
+//! traffic already recorded, before anyone applies it.
+//!
+//! The body is a `networking.k8s.io/v1` NetworkPolicy, as YAML or JSON.
+//! Every `pod_traffic` row of the policy's namespace in the window is
+//! checked against it with the Kubernetes semantics: the pods picked by
+//! `spec.podSelector` are isolated in each of the policy's types, and a
+//! flow of an isolated direction is allowed when some rule matches both
+//! its peer and its port. Flows of pods the policy doesn't select, or in
+//! a direction it doesn't isolate, are only counted.
+//!
+//! Labels come from `pod_details` (live row first, then newest), the
+//! same resolution as `/pod/traffic?enrich=true`. The broker doesn't
+//! record Namespace objects, so a `namespaceSelector` sees just the
+//! `kubernetes.io/metadata.name` label every namespace carries. A peer
+//! recorded by its Service IP is matched as the pods of that Service's
+//! selector.
+
+use crate::get::{clamp_pod_traffic_limit, TimeRange};
+use crate::networkpolicy::NAMESPACE_NAME_LABEL;
+use crate::policy::{parse_port, policy_protocol, string_map};
+use crate::{schema, Error, ErrorBody, PodDetail, PodTraffic};
+use actix_web::{post, web, HttpResponse};
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::sql_query;
+use diesel::sql_types::{Array, Json, Nullable, Text};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+type DbError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Deserialize, IntoParams)]
+pub struct SimulateQuery {
+    /// RFC3339; only flows seen at or after it.
+    pub since: Option<String>,
+    /// RFC3339; only flows first recorded before it.
+    pub until: Option<String>,
+    /// Most recently seen flows checked; same default and cap as
+    /// `/pod/traffic`.
+    pub limit: Option<i64>,
+}
+
+// ---- Request body ----------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NetworkPolicyDoc {
+    api_version: String,
+    kind: String,
+    #[serde(default)]
+    metadata: MetaDoc,
+    #[serde(default)]
+    spec: SpecDoc,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MetaDoc {
+    name: Option<String>,
+    namespace: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpecDoc {
+    #[serde(default)]
+    pod_selector: LabelSelector,
+    policy_types: Option<Vec<String>>,
+    #[serde(default)]
+    ingress: Vec<IngressRuleDoc>,
+    #[serde(default)]
+    egress: Vec<EgressRuleDoc>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct IngressRuleDoc {
+    #[serde(default)]
+    from: Vec<PeerDoc>,
+    #[serde(default)]
+    ports: Vec<PortDoc>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct EgressRuleDoc {
+    #[serde(default)]
+    to: Vec<PeerDoc>,
+    #[serde(default)]
+    ports: Vec<PortDoc>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PeerDoc {
+    pod_selector: Option<LabelSelector>,
+    namespace_selector: Option<LabelSelector>,
+    ip_block: Option<IpBlockDoc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IpBlockDoc {
+    cidr: String,
+    #[serde(default)]
+    except: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PortDoc {
+    protocol: Option<String>,
+    port: Option<IntOrString>,
+    end_port: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum IntOrString {
+    Int(i64),
+    Str(String),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LabelSelector {
+    #[serde(default)]
+    match_labels: BTreeMap<String, String>,
+    #[serde(default)]
+    match_expressions: Vec<Requirement>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Requirement {
+    key: String,
+    operator: Operator,
+    #[serde(default)]
+    values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+enum Operator {
+    In,
+    NotIn,
+    Exists,
+    DoesNotExist,
+}
+
+impl LabelSelector {
+    /// An empty selector matches everything.
+    fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        self.match_labels
+            .iter()
+            .all(|(k, v)| labels.get(k) == Some(v))
+            && self.match_expressions.iter().all(|r| r.matches(labels))
+    }
+
+    fn validate(&self, field: &str) -> Result<(), String> {
+        for r in &self.match_expressions {
+            let wants_values = matches!(r.operator, Operator::In | Operator::NotIn);
+            if wants_values == r.values.is_empty() {
+                return Err(format!(
+                    "{field}: operator {:?} on key {:?} {}",
+                    r.operator,
+                    r.key,
+                    if wants_values {
+                        "needs values"
+                    } else {
+                        "takes no values"
+                    }
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Requirement {
+    fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        let value = labels.get(&self.key);
+        match self.operator {
+            Operator::In => value.is_some_and(|v| self.values.contains(v)),
+            Operator::NotIn => value.is_none_or(|v| !self.values.contains(v)),
+            Operator::Exists => value.is_some(),
+            Operator::DoesNotExist => value.is_none(),
+        }
+    }
+}
+
+// ---- Compiled policy -------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    fn parse(raw: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid CIDR {raw:?}");
+        let (addr, prefix) = raw.split_once('/').ok_or_else(invalid)?;
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let bits = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|p| *p <= bits)
+            .ok_or_else(invalid)?;
+        Ok(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let (net, ip, bits) = match (self.addr, ip) {
+            (IpAddr::V4(n), IpAddr::V4(i)) => (u32::from(n) as u128, u32::from(i) as u128, 32),
+            (IpAddr::V6(n), IpAddr::V6(i)) => (u128::from(n), u128::from(i), 128),
+            _ => return false,
+        };
+        let shift = bits - u32::from(self.prefix);
+        shift == 128 || (net >> shift) == (ip >> shift)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PeerMatch {
+    IpBlock {
+        cidr: Cidr,
+        except: Vec<Cidr>,
+    },
+    /// `None` namespace selector means the policy's own namespace;
+    /// `None` pod selector means every pod.
+    Pods {
+        pods: Option<LabelSelector>,
+        namespaces: Option<LabelSelector>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum PortMatch {
+    Any,
+    Range(u16, u16),
+    Named(String),
+}
+
+#[derive(Debug, Clone)]
+struct PortRule {
+    protocol: &'static str,
+    port: PortMatch,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Rule {
+    /// Empty matches every peer.
+    peers: Vec<PeerMatch>,
+    /// Empty matches every port.
+    ports: Vec<PortRule>,
+}
+
+/// A validated NetworkPolicy, ready to check flows against.
+#[derive(Debug, Clone)]
+pub struct SimPolicy {
+    namespace: String,
+    name: Option<String>,
+    pod_selector: LabelSelector,
+    /// `None` when the policy doesn't isolate that direction.
+    ingress: Option<Vec<Rule>>,
+    egress: Option<Vec<Rule>>,
+}
+
+impl SimPolicy {
+    /// Parse and validate a request body. Errors are 400 messages.
+    pub fn parse(body: &[u8]) -> Result<Self, String> {
+        let doc: NetworkPolicyDoc =
+            serde_yaml::from_slice(body).map_err(|e| format!("invalid NetworkPolicy: {e}"))?;
+        if doc.api_version != "networking.k8s.io/v1" || doc.kind != "NetworkPolicy" {
+            return Err(format!(
+                "expected a networking.k8s.io/v1 NetworkPolicy, got {}/{}",
+                doc.api_version, doc.kind
+            ));
+        }
+        let namespace = doc
+            .metadata
+            .namespace
+            .filter(|ns| !ns.is_empty())
+            .ok_or("metadata.namespace is required")?;
+        let spec = doc.spec;
+        spec.pod_selector.validate("spec.podSelector")?;
+        // API server defaulting: Ingress always, Egress when there are
+        // egress rules.
+        let types = spec.policy_types.unwrap_or_else(|| {
+            let mut t = vec!["Ingress".to_string()];
+            if !spec.egress.is_empty() {
+                t.push("Egress".to_string());
+            }
+            t
+        });
+        for t in &types {
+            if t != "Ingress" && t != "Egress" {
+                return Err(format!(
+                    "invalid spec.policyTypes entry {t:?}; must be Ingress or Egress"
+                ));
+            }
+        }
+        let ingress = spec
+            .ingress
+            .iter()
+            .enumerate()
+            .map(|(i, r)| compile_rule(&format!("spec.ingress[{i}]"), "from", &r.from, &r.ports))
+            .collect::<Result<Vec<_>, _>>()?;
+        let egress = spec
+            .egress
+            .iter()
+            .enumerate()
+            .map(|(i, r)| compile_rule(&format!("spec.egress[{i}]"), "to", &r.to, &r.ports))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            namespace,
+            name: doc.metadata.name,
+            pod_selector: spec.pod_selector,
+            ingress: types.iter().any(|t| t == "Ingress").then_some(ingress),
+            egress: types.iter().any(|t| t == "Egress").then_some(egress),
+        })
+    }
+
+    /// The verdict for one flow, or `None` when the policy doesn't
+    /// apply to it.
+    fn evaluate(&self, flow: &FlowView) -> Option<Verdict> {
+        let no_labels = BTreeMap::new();
+        let subject_labels = flow.subject.map_or(&no_labels, |s| &s.labels);
+        if !self.pod_selector.matches(subject_labels) {
+            return None;
+        }
+        let (rules, dst) = match flow.direction {
+            Direction::Ingress => (self.ingress.as_ref()?, flow.subject),
+            Direction::Egress => (self.egress.as_ref()?, flow.peer),
+        };
+        let rule = rules.iter().position(|r| {
+            (r.peers.is_empty() || r.peers.iter().any(|p| self.peer_matches(p, flow)))
+                && (r.ports.is_empty() || r.ports.iter().any(|p| port_matches(p, flow, dst)))
+        });
+        Some(match rule {
+            Some(i) => Verdict::Allow(i),
+            None => Verdict::WouldDeny,
+        })
+    }
+
+    fn peer_matches(&self, peer: &PeerMatch, flow: &FlowView) -> bool {
+        match peer {
+            PeerMatch::IpBlock { cidr, except } => flow
+                .peer_ip
+                .is_some_and(|ip| cidr.contains(ip) && !except.iter().any(|e| e.contains(ip))),
+            PeerMatch::Pods { pods, namespaces } => flow.peer.is_some_and(|p| {
+                let ns_ok = match namespaces {
+                    None => p.namespace == self.namespace,
+                    Some(sel) => sel.matches(&namespace_labels(&p.namespace)),
+                };
+                ns_ok && pods.as_ref().is_none_or(|sel| sel.matches(&p.labels))
+            }),
+        }
+    }
+}
+
+fn compile_rule(
+    field: &str,
+    peer_key: &str,
+    peers: &[PeerDoc],
+    ports: &[PortDoc],
+) -> Result<Rule, String> {
+    let peers = peers
+        .iter()
+        .enumerate()
+        .map(|(i, p)| compile_peer(&format!("{field}.{peer_key}[{i}]"), p))
+        .collect::<Result<_, _>>()?;
+    let ports = ports
+        .iter()
+        .enumerate()
+        .map(|(i, p)| compile_port(&format!("{field}.ports[{i}]"), p))
+        .collect::<Result<_, _>>()?;
+    Ok(Rule { peers, ports })
+}
+
+fn compile_peer(field: &str, peer: &PeerDoc) -> Result<PeerMatch, String> {
+    match (&peer.ip_block, &peer.pod_selector, &peer.namespace_selector) {
+        (Some(block), None, None) => {
+            let cidr = Cidr::parse(&block.cidr)?;
+            let except = block
+                .except
+                .iter()
+                .map(|e| Cidr::parse(e))
+                .collect::<Result<_, _>>()?;
+            Ok(PeerMatch::IpBlock { cidr, except })
+        }
+        (Some(_), _, _) => Err(format!(
+            "{field}: ipBlock can't be combined with podSelector or namespaceSelector"
+        )),
+        (None, None, None) => Err(format!(
+            "{field}: needs podSelector, namespaceSelector or ipBlock"
+        )),
+        (None, pods, namespaces) => {
+            if let Some(s) = pods {
+                s.validate(&format!("{field}.podSelector"))?;
+            }
+            if let Some(s) = namespaces {
+                s.validate(&format!("{field}.namespaceSelector"))?;
+            }
+            Ok(PeerMatch::Pods {
+                pods: pods.clone(),
+                namespaces: namespaces.clone(),
+            })
+        }
+    }
+}
+
+fn compile_port(field: &str, port: &PortDoc) -> Result<PortRule, String> {
+    let protocol = match port.protocol.as_deref().unwrap_or("TCP") {
+        "TCP" => "TCP",
+        "UDP" => "UDP",
+        "SCTP" => "SCTP",
+        other => {
+            return Err(format!(
+                "{field}: invalid protocol {other:?}; must be TCP, UDP or SCTP"
+            ))
+        }
+    };
+    let number = |v: i64| {
+        u16::try_from(v)
+            .ok()
+            .filter(|p| *p > 0)
+            .ok_or_else(|| format!("{field}: port {v} is out of range"))
+    };
+    let port = match (&port.port, port.end_port) {
+        (None, None) => PortMatch::Any,
+        (Some(IntOrString::Int(p)), end) => {
+            let start = number(*p)?;
+            let end = end.map(number).transpose()?.unwrap_or(start);
+            if end < start {
+                return Err(format!("{field}: endPort {end} is below port {start}"));
+            }
+            PortMatch::Range(start, end)
+        }
+        (Some(IntOrString::Str(name)), None) if !name.is_empty() => PortMatch::Named(name.clone()),
+        (Some(IntOrString::Str(_)), None) => return Err(format!("{field}: empty port name")),
+        (_, Some(_)) => {
+            return Err(format!("{field}: endPort needs a numeric port"));
+        }
+    };
+    Ok(PortRule { protocol, port })
+}
+
+/// A named port resolves against the destination pod's containers: the
+/// selected pod for Ingress, the peer for Egress.
+fn port_matches(rule: &PortRule, flow: &FlowView, dst: Option<&Endpoint>) -> bool {
+    if rule.protocol != flow.protocol {
+        return false;
+    }
+    match &rule.port {
+        PortMatch::Any => true,
+        PortMatch::Range(start, end) => flow.port.is_some_and(|p| (*start..=*end).contains(&p)),
+        PortMatch::Named(name) => dst
+            .and_then(|d| {
+                d.ports
+                    .iter()
+                    .find(|np| &np.name == name && np.protocol == rule.protocol)
+            })
+            .is_some_and(|np| Some(np.port) == flow.port),
+    }
+}
+
+/// The only Namespace label the broker can vouch for.
+fn namespace_labels(namespace: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([(NAMESPACE_NAME_LABEL.to_string(), namespace.to_string())])
+}
+
+// ---- Flows -----------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Ingress,
+    Egress,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    /// Allowed by the rule at this index of `spec.ingress`/`spec.egress`.
+    Allow(usize),
+    WouldDeny,
+}
+
+/// A pod (or a Service's pods) as far as the policy can see it.
+#[derive(Debug, Clone, Default)]
+struct Endpoint {
+    namespace: String,
+    labels: BTreeMap<String, String>,
+    ports: Vec<NamedPort>,
+}
+
+#[derive(Debug, Clone)]
+struct NamedPort {
+    name: String,
+    port: u16,
+    protocol: &'static str,
+}
+
+impl Endpoint {
+    fn from_pod(namespace: &str, pod_obj: Option<&serde_json::Value>) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            labels: string_map(pod_obj.and_then(|o| o.get("metadata")?.get("labels"))),
+            ports: container_ports(pod_obj),
+        }
+    }
+}
+
+fn container_ports(pod_obj: Option<&serde_json::Value>) -> Vec<NamedPort> {
+    let containers = pod_obj
+        .and_then(|o| o.get("spec")?.get("containers")?.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    containers
+        .iter()
+        .filter_map(|c| c.get("ports")?.as_array())
+        .flatten()
+        .filter_map(|p| {
+            Some(NamedPort {
+                name: p.get("name")?.as_str()?.to_string(),
+                port: u16::try_from(p.get("containerPort")?.as_u64()?).ok()?,
+                protocol: policy_protocol(p.get("protocol").and_then(|v| v.as_str())),
+            })
+        })
+        .collect()
+}
+
+struct FlowView<'a> {
+    direction: Direction,
+    /// The pod that recorded the flow.
+    subject: Option<&'a Endpoint>,
+    peer: Option<&'a Endpoint>,
+    peer_ip: Option<IpAddr>,
+    /// Destination port; `None` when unrecorded.
+    port: Option<u16>,
+    protocol: &'static str,
+}
+
+// ---- Response --------------------------------------------------------
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimulationResult {
+    pub namespace: String,
+    pub name: Option<String>,
+    /// Flows the policy would allow.
+    pub allowed: usize,
+    /// Flows the policy would deny.
+    pub would_deny: usize,
+    /// Flows of pods the policy doesn't select, or in a direction it
+    /// doesn't isolate. Not listed in `flows`.
+    pub unaffected: usize,
+    /// More than `limit` flows were in the window; only the most recent
+    /// were checked.
+    pub truncated: bool,
+    pub flows: Vec<SimulatedFlow>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimulatedFlow {
+    #[serde(flatten)]
+    pub traffic: PodTraffic,
+    /// `Allow` or `WouldDeny`, as in `audit_verdicts`.
+    pub verdict: &'static str,
+    /// Index of the first allowing rule in `spec.ingress` or
+    /// `spec.egress`; absent for `WouldDeny`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<usize>,
+}
+
+/// Dry-run a NetworkPolicy against recorded flows.
+#[utoipa::path(
+    tag = "policies",
+    params(SimulateQuery),
+    request_body(description = "A NetworkPolicy manifest", content(("application/yaml"), ("application/json"))),
+    responses(
+        (status = 200, body = SimulationResult),
+        (status = 400, description = "Invalid query or policy", body = ErrorBody),
+    )
+)]
+#[post("/simulate/networkpolicy")]
+pub async fn simulate_network_policy(
+    pool: web::Data<DbPool>,
+    query: web::Query<SimulateQuery>,
+    body: web::Bytes,
+) -> Result<HttpResponse, Error> {
+    info!("simulate networkpolicy");
+    let range = TimeRange::parse(query.since.as_deref(), query.until.as_deref())?;
+    let policy = SimPolicy::parse(&body)?;
+    let limit = clamp_pod_traffic_limit(query.limit);
+    let result = web::block(move || {
+        let mut conn = pool.get()?;
+        simulate(&mut conn, &policy, range, limit)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Check the namespace's most recent `limit` flows in the window.
+pub fn simulate(
+    conn: &mut PgConnection,
+    policy: &SimPolicy,
+    range: TimeRange,
+    limit: i64,
+) -> Result<SimulationResult, DbError> {
+    let mut flows = namespace_traffic(conn, &policy.namespace, range, limit + 1)?;
+    let truncated = flows.len() as i64 > limit;
+    flows.truncate(limit as usize);
+
+    let names: BTreeSet<&str> = flows.iter().filter_map(|f| f.pod_name.as_deref()).collect();
+    let subjects = subject_pods(conn, &policy.namespace, &names)?;
+    let ips: BTreeSet<&str> = flows
+        .iter()
+        .filter_map(|f| f.traffic_in_out_ip.as_deref())
+        .collect();
+    let peers = resolve_endpoints(conn, &ips.into_iter().collect::<Vec<_>>())?;
+
+    let mut result = SimulationResult {
+        namespace: policy.namespace.clone(),
+        name: policy.name.clone(),
+        allowed: 0,
+        would_deny: 0,
+        unaffected: 0,
+        truncated,
+        flows: Vec::new(),
+    };
+    for traffic in flows {
+        let verdict = flow_view(&traffic, &subjects, &peers).and_then(|v| policy.evaluate(&v));
+        let (verdict, rule) = match verdict {
+            Some(Verdict::Allow(i)) => {
+                result.allowed += 1;
+                ("Allow", Some(i))
+            }
+            Some(Verdict::WouldDeny) => {
+                result.would_deny += 1;
+                ("WouldDeny", None)
+            }
+            None => {
+                result.unaffected += 1;
+                continue;
+            }
+        };
+        result.flows.push(SimulatedFlow {
+            traffic,
+            verdict,
+            rule,
+        });
+    }
+    Ok(result)
+}
+
+fn flow_view<'a>(
+    traffic: &PodTraffic,
+    subjects: &'a HashMap<String, Endpoint>,
+    peers: &'a HashMap<String, Endpoint>,
+) -> Option<FlowView<'a>> {
+    let (direction, port) = match traffic
+        .traffic_type
+        .as_deref()?
+        .trim()
+        .to_ascii_uppercase()
+        .as_str()
+    {
+        "INGRESS" => (Direction::Ingress, traffic.pod_port.as_deref()),
+        "EGRESS" => (Direction::Egress, traffic.traffic_in_out_port.as_deref()),
+        _ => return None,
+    };
+    let peer_ip = traffic.traffic_in_out_ip.as_deref();
+    Some(FlowView {
+        direction,
+        subject: traffic.pod_name.as_deref().and_then(|n| subjects.get(n)),
+        peer: peer_ip.and_then(|ip| peers.get(ip)),
+        peer_ip: peer_ip.and_then(|ip| ip.parse().ok()),
+        port: parse_port(port),
+        protocol: policy_protocol(traffic.ip_protocol.as_deref()),
+    })
+}
+
+/// The namespace's flows active in the window (last seen at or after
+/// `since`, first seen before `until`), most recently seen first.
+fn namespace_traffic(
+    conn: &mut PgConnection,
+    namespace: &str,
+    range: TimeRange,
+    limit: i64,
+) -> Result<Vec<PodTraffic>, DbError> {
+    use schema::pod_traffic::dsl::*;
+    let mut q = pod_traffic.filter(pod_namespace.eq(namespace)).into_boxed();
+    if let Some(t) = range.since {
+        q = q.filter(last_seen.ge(t));
+    }
+    if let Some(t) = range.until {
+        q = q.filter(time_stamp.lt(t));
+    }
+    Ok(q.order((last_seen.desc(), uuid.desc()))
+        .limit(limit)
+        .select(PodTraffic::as_select())
+        .load(conn)?)
+}
+
+fn subject_pods(
+    conn: &mut PgConnection,
+    namespace: &str,
+    names: &BTreeSet<&str>,
+) -> Result<HashMap<String, Endpoint>, DbError> {
+    use schema::pod_details::dsl::*;
+    let rows = pod_details
+        .filter(pod_namespace.eq(namespace))
+        .filter(pod_name.eq_any(names))
+        .select(PodDetail::as_select())
+        .load::<PodDetail>(conn)?;
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let ep = Endpoint::from_pod(namespace, r.pod_obj.as_ref());
+            (r.pod_name, ep)
+        })
+        .collect())
+}
+
+#[derive(QueryableByName)]
+struct EndpointRow {
+    #[diesel(sql_type = Text)]
+    ip: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pod_namespace: Option<String>,
+    #[diesel(sql_type = Nullable<Json>)]
+    pod_obj: Option<serde_json::Value>,
+    #[diesel(sql_type = Nullable<Text>)]
+    svc_namespace: Option<String>,
+    #[diesel(sql_type = Nullable<Json>)]
+    service_spec: Option<serde_json::Value>,
+}
+
+/// Peer IPs to endpoints in one statement. A pod wins over a Service,
+/// as in enrich.rs; a Service stands for its selector's pods. IPs that
+/// resolve to neither only match `ipBlock` peers.
+fn resolve_endpoints(
+    conn: &mut PgConnection,
+    ips: &[&str],
+) -> Result<HashMap<String, Endpoint>, DbError> {
+    if ips.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let rows = sql_query(
+        "SELECT ip.addr AS ip, p.pod_namespace, p.pod_obj, s.svc_namespace, s.service_spec \
+         FROM unnest($1::text[]) AS ip(addr) \
+         LEFT JOIN LATERAL ( \
+             SELECT d.pod_namespace, d.pod_obj FROM pod_details d \
+             WHERE d.pod_ip = ip.addr \
+             ORDER BY d.is_dead, d.time_stamp DESC LIMIT 1 \
+         ) p ON true \
+         LEFT JOIN svc_details s ON s.svc_ip = ip.addr",
+    )
+    .bind::<Array<Text>, _>(ips)
+    .load::<EndpointRow>(conn)?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| {
+            let ep = if let Some(ns) = r.pod_namespace.as_deref().filter(|ns| !ns.is_empty()) {
+                Endpoint::from_pod(ns, r.pod_obj.as_ref())
+            } else {
+                let selector = string_map(
+                    r.service_spec
+                        .as_ref()
+                        .and_then(|s| s.get("spec"))
+                        .and_then(|s| s.get("selector")),
+                );
+                if selector.is_empty() {
+                    return None;
+                }
+                Endpoint {
+                    namespace: r.svc_namespace?,
+                    labels: selector,
+                    ports: Vec::new(),
+                }
+            };
+            Some((r.ip, ep))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+apiVersion: networking.k8s.io/v1
+kind: NetworkPolicy
+metadata:
+  name: web
+  namespace: prod
+spec:
+  podSelector:
+    matchLabels:
+      app: web
+  policyTypes: [Ingress, Egress]
+  ingress:
+  - from:
+    - podSelector:
+        matchExpressions:
+        - {key: role, operator: In, values: [frontend, lb]}
+    ports:
+    - port: http
+  egress:
+  - to:
+    - namespaceSelector:
+        matchLabels:
+          kubernetes.io/metadata.name: data
+      podSelector:
+        matchLabels:
+          app: db
+    ports:
+    - port: 5432
+  - to:
+    - ipBlock:
+        cidr: 10.0.0.0/8
+        except: [10.9.0.0/16]
+    ports:
+    - protocol: UDP
+      port: 53
+      endPort: 54
+"#;
+
+    fn endpoint(ns: &str, labels: &[(&str, &str)]) -> Endpoint {
+        Endpoint {
+            namespace: ns.to_string(),
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ports: Vec::new(),
+        }
+    }
+
+    fn view<'a>(
+        direction: Direction,
+        subject: &'a Endpoint,
+        peer: Option<&'a Endpoint>,
+        ip: &str,
+        port: u16,
+        protocol: &'static str,
+    ) -> FlowView<'a> {
+        FlowView {
+            direction,
+            subject: Some(subject),
+            peer,
+            peer_ip: ip.parse().ok(),
+            port: Some(port),
+            protocol,
+        }
+    }
+
+    #[test]
+    fn ingress_matches_selector_and_named_port() {
+        let policy = SimPolicy::parse(POLICY.as_bytes()).unwrap();
+        let mut web = endpoint("prod", &[("app", "web")]);
+        web.ports.push(NamedPort {
+            name: "http".to_string(),
+            port: 8080,
+            protocol: "TCP",
+        });
+        let fe = endpoint("prod", &[("role", "frontend")]);
+        let other_ns = endpoint("dev", &[("role", "frontend")]);
+        let allowed = view(Direction::Ingress, &web, Some(&fe), "10.1.0.2", 8080, "TCP");
+        assert_eq!(policy.evaluate(&allowed), Some(Verdict::Allow(0)));
+        let wrong_port = view(Direction::Ingress, &web, Some(&fe), "10.1.0.2", 9090, "TCP");
+        assert_eq!(policy.evaluate(&wrong_port), Some(Verdict::WouldDeny));
+        // A podSelector alone only matches the policy's namespace.
+        let foreign = view(
+            Direction::Ingress,
+            &web,
+            Some(&other_ns),
+            "10.2.0.2",
+            8080,
+            "TCP",
+        );
+        assert_eq!(policy.evaluate(&foreign), Some(Verdict::WouldDeny));
+        let unresolved = view(Direction::Ingress, &web, None, "10.1.0.2", 8080, "TCP");
+        assert_eq!(policy.evaluate(&unresolved), Some(Verdict::WouldDeny));
+    }
+
+    #[test]
+    fn egress_matches_namespace_selector_and_ip_block() {
+        let policy = SimPolicy::parse(POLICY.as_bytes()).unwrap();
+        let web = endpoint("prod", &[("app", "web")]);
+        let db = endpoint("data", &[("app", "db")]);
+        let to_db = view(Direction::Egress, &web, Some(&db), "10.9.9.9", 5432, "TCP");
+        assert_eq!(policy.evaluate(&to_db), Some(Verdict::Allow(0)));
+        let dns = view(Direction::Egress, &web, None, "10.96.0.10", 54, "UDP");
+        assert_eq!(policy.evaluate(&dns), Some(Verdict::Allow(1)));
+        let excepted = view(Direction::Egress, &web, None, "10.9.0.10", 53, "UDP");
+        assert_eq!(policy.evaluate(&excepted), Some(Verdict::WouldDeny));
+        let tcp_dns = view(Direction::Egress, &web, None, "10.96.0.10", 53, "TCP");
+        assert_eq!(policy.evaluate(&tcp_dns), Some(Verdict::WouldDeny));
+    }
+
+    #[test]
+    fn unselected_pods_and_unisolated_directions_are_unaffected() {
+        let doc = r#"{"apiVersion":"networking.k8s.io/v1","kind":"NetworkPolicy",
+            "metadata":{"namespace":"prod"},
+            "spec":{"podSelector":{"matchLabels":{"app":"web"}},"ingress":[{}]}}"#;
+        let policy = SimPolicy::parse(doc.as_bytes()).unwrap();
+        let web = endpoint("prod", &[("app", "web")]);
+        let api = endpoint("prod", &[("app", "api")]);
+        // No egress rules, so only Ingress is isolated.
+        let egress = view(Direction::Egress, &web, None, "1.1.1.1", 443, "TCP");
+        assert_eq!(policy.evaluate(&egress), None);
+        let other = view(Direction::Ingress, &api, None, "1.1.1.1", 80, "TCP");
+        assert_eq!(policy.evaluate(&other), None);
+        // An empty rule allows everything.
+        let any = view(Direction::Ingress, &web, None, "1.1.1.1", 80, "TCP");
+        assert_eq!(policy.evaluate(&any), Some(Verdict::Allow(0)));
+    }
+
+    #[test]
+    fn deny_all_with_no_rules() {
+        let doc = "apiVersion: networking.k8s.io/v1\nkind: NetworkPolicy\n\
+                   metadata: {namespace: prod}\n\
+                   spec: {podSelector: {}, policyTypes: [Egress]}\n";
+        let policy = SimPolicy::parse(doc.as_bytes()).unwrap();
+        let pod = endpoint("prod", &[]);
+        let flow = view(Direction::Egress, &pod, None, "1.1.1.1", 443, "TCP");
+        assert_eq!(policy.evaluate(&flow), Some(Verdict::WouldDeny));
+    }
+
+    #[test]
+    fn label_requirements() {
+        let labels: BTreeMap<String, String> = [("tier".to_string(), "web".to_string())].into();
+        let req = |operator, values: &[&str]| Requirement {
+            key: "tier".to_string(),
+            operator,
+            values: values.iter().map(|v| v.to_string()).collect(),
+        };
+        assert!(req(Operator::In, &["web", "api"]).matches(&labels));
+        assert!(!req(Operator::NotIn, &["web"]).matches(&labels));
+        assert!(req(Operator::NotIn, &["web"]).matches(&BTreeMap::new()));
+        assert!(req(Operator::Exists, &[]).matches(&labels));
+        assert!(req(Operator::DoesNotExist, &[]).matches(&BTreeMap::new()));
+    }
+
+    #[test]
+    fn cidr_contains() {
+        let net = Cidr::parse("10.1.0.0/16").unwrap();
+        assert!(net.contains("10.1.255.1".parse().unwrap()));
+        assert!(!net.contains("10.2.0.1".parse().unwrap()));
+        assert!(!net.contains("::1".parse().unwrap()));
+        assert!(Cidr::parse("0.0.0.0/0")
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap()));
+        assert!(Cidr::parse("fd00::/8")
+            .unwrap()
+            .contains("fd12::1".parse().unwrap()));
+        for bad in ["10.0.0.0", "10.0.0.0/33", "nope/8"] {
+            assert!(Cidr::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_policies() {
+        let base = "apiVersion: networking.k8s.io/v1\nkind: NetworkPolicy\n";
+        for (body, want) in [
+            ("apiVersion: v1\nkind: Pod\n", "expected a networking.k8s.io/v1"),
+            (base, "metadata.namespace is required"),
+            (
+                "metadata: {namespace: a}\nspec: {ingress: [{from: [{}]}]}",
+                "spec.ingress[0].from[0]: needs",
+            ),
+            (
+                "metadata: {namespace: a}\nspec: {egress: [{ports: [{protocol: ICMP}]}]}",
+                "invalid protocol",
+            ),
+            (
+                "metadata: {namespace: a}\nspec: {egress: [{ports: [{port: 80, endPort: 79}]}]}",
+                "endPort 79 is below port 80",
+            ),
+            (
+                "metadata: {namespace: a}\nspec: {podSelector: {matchExpressions: [{key: a, operator: Exists, values: [x]}]}}",
+                "takes no values",
+            ),
+        ] {
+            let doc = if body.starts_with("apiVersion") {
+                body.to_string()
+            } else {
+                format!("{base}{body}")
+            };
+            let err = SimPolicy::parse(doc.as_bytes()).unwrap_err();
+            assert!(err.contains(want), "{err} !~ {want}");
+        }
+    }
+}
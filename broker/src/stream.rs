@@ -0,0 +1,568 @@
+//! `GET /stream/traffic` and `GET /stream/verdicts`: new flows and
+//! audit verdicts as Server-Sent Events, so the UI and on-call tooling
+//! can tail them instead of polling `/pod/traffic` and `/audit/verdicts`.
+//!
+//! Ingest and the audit client publish to an in-process broadcast
+//! channel (`EventHub`) once their rows are committed. Each stream
+//! subscribes, filters and writes the rows as `data:` JSON, one event
+//! per row.
+//!
+//! A traffic event id is the row's `pod_traffic.seq`, taken from a
+//! sequence at insert. `time_stamp` would not do: it is the
+//! controller's capture time, so a batch that arrives late commits
+//! rows behind ids a client has already seen. `seq` follows commit
+//! order except across ingest transactions that overlap, which is a
+//! window of one batch insert rather than of controller batching and
+//! clock skew. Verdict event ids are the `/audit/verdicts` keyset
+//! cursor `(last_observed_at, id)` (cursor.rs). A client that
+//! reconnects with `Last-Event-ID` first gets the rows after that
+//! position from Postgres, oldest first, then the live feed. A verdict
+//! that repeats moves forward in that order, so a resumed verdict
+//! stream also replays the rows whose `hits` grew.
+//!
+//! A stream that falls behind the broadcast buffer, or has more than a
+//! page of rows to catch up on, is ended after its last event; the
+//! client's reconnect resumes it from the table. Each broker replica
+//! publishes only the rows it ingested or evaluated itself.
+
+use crate::cursor::{TrafficCursor, VerdictCursor};
+use crate::get::validate_enum_filter;
+use crate::{schema, AuditVerdict, Error, ErrorBody, PodTraffic};
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::web::Bytes;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use diesel::dsl::sql;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::sql_types::{BigInt, Bool, Text, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, info};
+use utoipa::IntoParams;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+type DbError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Rows the broadcast channels buffer for the slowest subscriber.
+const HUB_CAPACITY: usize = 4096;
+/// Rows replayed from the table per connection. A client further
+/// behind is caught up across reconnects.
+const BACKFILL_PAGE: i64 = 5000;
+/// Comment sent on an idle stream, so proxies keep it open and a gone
+/// client is noticed.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// Reconnect delay sent to clients (`retry:`), in milliseconds.
+const RECONNECT_MS: u64 = 1000;
+/// Encoded events buffered per connection before the stream waits on
+/// the client.
+const CONNECTION_BUFFER: usize = 64;
+
+/// The broker's in-process feed of committed rows. Cheap to clone;
+/// every clone publishes to the same subscribers.
+#[derive(Clone)]
+pub struct EventHub {
+    traffic: broadcast::Sender<Arc<TrafficEvent>>,
+    verdicts: broadcast::Sender<Arc<AuditVerdict>>,
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self {
+            traffic: broadcast::channel(HUB_CAPACITY).0,
+            verdicts: broadcast::channel(HUB_CAPACITY).0,
+        }
+    }
+}
+
+impl EventHub {
+    /// Publish newly inserted flows with their `seq` values, in the
+    /// same order. Call after the insert committed.
+    pub fn publish_traffic(&self, rows: &[PodTraffic], seqs: &[i64]) {
+        if self.traffic.receiver_count() == 0 {
+            return;
+        }
+        for (row, &seq) in rows.iter().zip(seqs) {
+            let _ = self.traffic.send(Arc::new(TrafficEvent {
+                seq,
+                row: row.clone(),
+            }));
+        }
+    }
+
+    /// Publish inserted or bumped verdict rows, as stored.
+    pub(crate) fn publish_verdicts(&self, rows: Vec<AuditVerdict>) {
+        if self.verdicts.receiver_count() == 0 {
+            return;
+        }
+        for row in rows {
+            let _ = self.verdicts.send(Arc::new(row));
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct StreamQuery {
+    /// Namespace of the pod (traffic), or of either side (verdicts).
+    pub namespace: Option<String>,
+    /// Pod name; on verdicts, the source or destination pod.
+    pub pod: Option<String>,
+    /// `ALLOW` / `DROP` on traffic, `Allow` / `WouldDeny` on verdicts.
+    pub decision: Option<String>,
+}
+
+/// A row that can go out as an event.
+trait StreamEvent: Serialize + Send + Sync + 'static {
+    const NAME: &'static str;
+    fn event_id(&self) -> String;
+}
+
+/// A stored flow and its `seq`. Serializes as the bare row.
+#[derive(Serialize)]
+#[serde(transparent)]
+struct TrafficEvent {
+    #[serde(skip)]
+    seq: i64,
+    row: PodTraffic,
+}
+
+impl StreamEvent for TrafficEvent {
+    const NAME: &'static str = "traffic";
+    fn event_id(&self) -> String {
+        self.seq.to_string()
+    }
+}
+
+/// Where a resumed traffic stream picks up.
+#[derive(Debug, PartialEq)]
+enum TrafficResume {
+    /// After this `seq`.
+    After(i64),
+    /// An id from a broker that keyed traffic events on `(time_stamp,
+    /// uuid)`: after that flow's `seq`.
+    Cursor(TrafficCursor),
+}
+
+impl TrafficResume {
+    fn parse(raw: &str) -> Result<Self, String> {
+        match raw.parse::<i64>() {
+            Ok(seq) => Ok(Self::After(seq)),
+            Err(_) => TrafficCursor::decode(raw).map(Self::Cursor),
+        }
+    }
+}
+
+impl StreamEvent for AuditVerdict {
+    const NAME: &'static str = "verdict";
+    fn event_id(&self) -> String {
+        VerdictCursor {
+            last_observed_at: self.last_observed_at,
+            id: self.id,
+        }
+        .encode()
+    }
+}
+
+/// Validated `StreamQuery`. Empty values mean no filter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct StreamFilter {
+    namespace: Option<String>,
+    pod: Option<String>,
+    decision: Option<String>,
+}
+
+impl StreamFilter {
+    fn parse(q: StreamQuery, decisions: &[&str]) -> Result<Self, String> {
+        let decision = q.decision.filter(|s| !s.is_empty());
+        if let Some(d) = &decision {
+            validate_enum_filter("decision", d, decisions)?;
+        }
+        Ok(Self {
+            namespace: q.namespace.filter(|s| !s.is_empty()),
+            pod: q.pod.filter(|s| !s.is_empty()),
+            decision,
+        })
+    }
+
+    fn wants(want: &Option<String>, have: Option<&str>) -> bool {
+        want.as_deref().is_none_or(|w| have == Some(w))
+    }
+
+    fn traffic(&self, t: &PodTraffic) -> bool {
+        Self::wants(&self.namespace, t.pod_namespace.as_deref())
+            && Self::wants(&self.pod, t.pod_name.as_deref())
+            && Self::wants(&self.decision, t.decision.as_deref())
+    }
+
+    /// Namespace and pod must match on the same side of the flow.
+    fn verdict(&self, v: &AuditVerdict) -> bool {
+        let side = |ns: Option<&str>, pod: Option<&str>| {
+            Self::wants(&self.namespace, ns) && Self::wants(&self.pod, pod)
+        };
+        (side(v.src_namespace.as_deref(), v.src_pod.as_deref())
+            || side(v.dst_namespace.as_deref(), v.dst_pod.as_deref()))
+            && Self::wants(&self.decision, Some(&v.verdict))
+    }
+}
+
+/// Server-sent events for newly stored flows.
+#[utoipa::path(
+    tag = "traffic",
+    params(StreamQuery),
+    responses(
+        (status = 200, description = "`traffic` events, each one row of `/pod/traffic`", content_type = "text/event-stream", body = String),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+    )
+)]
+#[get("/stream/traffic")]
+pub async fn stream_traffic(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    hub: web::Data<EventHub>,
+    query: web::Query<StreamQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("stream pod traffic");
+    let filter = StreamFilter::parse(query.into_inner(), &["ALLOW", "DROP"])?;
+    let after = last_event_id(&req).map(TrafficResume::parse).transpose()?;
+    // Subscribe before reading the backlog so nothing committed in
+    // between is missed; the pump drops the overlap.
+    let live = hub.traffic.subscribe();
+    let backfill = match after {
+        Some(c) => {
+            let filter = filter.clone();
+            web::block(move || {
+                let mut conn = pool.get()?;
+                traffic_after(&mut conn, &c, &filter)
+            })
+            .await??
+        }
+        None => Vec::new(),
+    };
+    Ok(respond(backfill, live, move |t: &TrafficEvent| {
+        filter.traffic(&t.row)
+    }))
+}
+
+/// Server-sent events for new and updated audit verdicts.
+#[utoipa::path(
+    tag = "audit",
+    params(StreamQuery),
+    responses(
+        (status = 200, description = "`verdict` events, each one row of `/audit/verdicts`", content_type = "text/event-stream", body = String),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+    )
+)]
+#[get("/stream/verdicts")]
+pub async fn stream_verdicts(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    hub: web::Data<EventHub>,
+    query: web::Query<StreamQuery>,
+) -> Result<HttpResponse, Error> {
+    info!("stream audit verdicts");
+    let filter = StreamFilter::parse(query.into_inner(), &["Allow", "WouldDeny"])?;
+    let after = last_event_id(&req).map(VerdictCursor::decode).transpose()?;
+    let live = hub.verdicts.subscribe();
+    let backfill = match after {
+        Some(c) => {
+            let filter = filter.clone();
+            web::block(move || {
+                let mut conn = pool.get()?;
+                verdicts_after(&mut conn, &c, &filter)
+            })
+            .await??
+        }
+        None => Vec::new(),
+    };
+    Ok(respond(backfill, live, move |v: &AuditVerdict| {
+        filter.verdict(v)
+    }))
+}
+
+/// `Last-Event-ID`, if present and non-empty.
+fn last_event_id(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("Last-Event-ID")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn respond<T: StreamEvent>(
+    backfill: Vec<T>,
+    live: broadcast::Receiver<Arc<T>>,
+    filter: impl Fn(&T) -> bool + Send + 'static,
+) -> HttpResponse {
+    let (tx, rx) = mpsc::channel(CONNECTION_BUFFER);
+    spawn_pump(backfill, live, filter, tx);
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        // Stops nginx-style proxies from buffering the stream.
+        .insert_header(("X-Accel-Buffering", "no"))
+        .body(SseBody { rx })
+}
+
+/// Write the backlog, then the live rows that pass `filter`, until the
+/// client goes away.
+fn spawn_pump<T: StreamEvent>(
+    backfill: Vec<T>,
+    mut live: broadcast::Receiver<Arc<T>>,
+    filter: impl Fn(&T) -> bool + Send + 'static,
+    tx: mpsc::Sender<Bytes>,
+) {
+    actix_web::rt::spawn(async move {
+        if tx
+            .send(Bytes::from(format!("retry: {RECONNECT_MS}\n\n")))
+            .await
+            .is_err()
+        {
+            return;
+        }
+        let caught_up = (backfill.len() as i64) < BACKFILL_PAGE;
+        let mut sent = HashSet::with_capacity(backfill.len());
+        for row in &backfill {
+            let id = row.event_id();
+            if tx.send(frame(row, &id)).await.is_err() {
+                return;
+            }
+            sent.insert(id);
+        }
+        if !caught_up {
+            // More to replay than one page; the reconnect picks up
+            // from the last event sent.
+            return;
+        }
+        let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+        keepalive.tick().await;
+        loop {
+            let bytes = tokio::select! {
+                msg = live.recv() => match msg {
+                    Ok(row) => {
+                        if !filter(&row) {
+                            continue;
+                        }
+                        let id = row.event_id();
+                        if sent.contains(&id) {
+                            continue;
+                        }
+                        frame(&*row, &id)
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!(skipped, stream = T::NAME, "stream fell behind; closing so the client resumes");
+                        return;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                _ = keepalive.tick() => Bytes::from_static(b": keepalive\n\n"),
+            };
+            if tx.send(bytes).await.is_err() {
+                return;
+            }
+        }
+    });
+}
+
+/// One SSE event.
+fn frame<T: StreamEvent>(row: &T, id: &str) -> Bytes {
+    let data = serde_json::to_string(row).unwrap_or_default();
+    Bytes::from(format!("id: {id}\nevent: {}\ndata: {data}\n\n", T::NAME))
+}
+
+/// Response body fed by the pump. Dropping it (client gone) closes the
+/// channel, which ends the pump at its next send.
+struct SseBody {
+    rx: mpsc::Receiver<Bytes>,
+}
+
+impl MessageBody for SseBody {
+    type Error = std::convert::Infallible;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        self.get_mut().rx.poll_recv(cx).map(|b| b.map(Ok))
+    }
+}
+
+/// Flows inserted after `after`, oldest first. An old-style cursor
+/// whose flow has since been pruned resumes from the newest row.
+fn traffic_after(
+    conn: &mut PgConnection,
+    after: &TrafficResume,
+    filter: &StreamFilter,
+) -> Result<Vec<TrafficEvent>, DbError> {
+    use schema::pod_traffic::dsl::*;
+    let mut q = match after {
+        TrafficResume::After(s) => pod_traffic.filter(seq.gt(*s)).into_boxed::<Pg>(),
+        TrafficResume::Cursor(c) => pod_traffic
+            .filter(
+                sql::<Bool>(
+                    "pod_traffic.seq > COALESCE(\
+                   (SELECT p.seq FROM pod_traffic p WHERE p.uuid = ",
+                )
+                .bind::<Text, _>(c.uuid.clone())
+                .sql("), (SELECT MAX(p.seq) FROM pod_traffic p), 0)"),
+            )
+            .into_boxed::<Pg>(),
+    };
+    if let Some(ns) = &filter.namespace {
+        q = q.filter(pod_namespace.eq(ns));
+    }
+    if let Some(pod) = &filter.pod {
+        q = q.filter(pod_name.eq(pod));
+    }
+    if let Some(d) = &filter.decision {
+        q = q.filter(decision.eq(d));
+    }
+    let rows: Vec<(i64, PodTraffic)> = q
+        .order(seq.asc())
+        .limit(BACKFILL_PAGE)
+        .select((seq, PodTraffic::as_select()))
+        .load(conn)?;
+    Ok(rows
+        .into_iter()
+        .map(|(s, row)| TrafficEvent { seq: s, row })
+        .collect())
+}
+
+/// Verdicts after `c` in `(last_observed_at, id)` order, oldest first.
+fn verdicts_after(
+    conn: &mut PgConnection,
+    c: &VerdictCursor,
+    filter: &StreamFilter,
+) -> Result<Vec<AuditVerdict>, DbError> {
+    use schema::audit_verdicts::dsl::*;
+    let mut q = audit_verdicts
+        .filter(
+            sql::<Bool>("(audit_verdicts.last_observed_at, audit_verdicts.id) > (")
+                .bind::<Timestamp, _>(c.last_observed_at)
+                .sql(", ")
+                .bind::<BigInt, _>(c.id)
+                .sql(")"),
+        )
+        .into_boxed::<Pg>();
+    match (&filter.namespace, &filter.pod) {
+        (Some(ns), Some(pod)) => {
+            q = q.filter(
+                (src_namespace.eq(ns).and(src_pod.eq(pod)))
+                    .or(dst_namespace.eq(ns).and(dst_pod.eq(pod))),
+            )
+        }
+        (Some(ns), None) => q = q.filter(src_namespace.eq(ns).or(dst_namespace.eq(ns))),
+        (None, Some(pod)) => q = q.filter(src_pod.eq(pod).or(dst_pod.eq(pod))),
+        (None, None) => {}
+    }
+    if let Some(d) = &filter.decision {
+        q = q.filter(verdict.eq(d));
+    }
+    Ok(q.order((last_observed_at.asc(), id.asc()))
+        .limit(BACKFILL_PAGE)
+        .select(AuditVerdict::as_select())
+        .load(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(namespace: &str, pod: &str, decision: &str) -> StreamQuery {
+        StreamQuery {
+            namespace: Some(namespace.to_string()),
+            pod: Some(pod.to_string()),
+            decision: Some(decision.to_string()),
+        }
+    }
+
+    fn verdict(src: (&str, &str), dst: (&str, &str), v: &str) -> AuditVerdict {
+        AuditVerdict {
+            id: 7,
+            policy_uid: "u".to_string(),
+            policy_namespace: "prod".to_string(),
+            policy_name: "p".to_string(),
+            direction: "Egress".to_string(),
+            src_namespace: Some(src.0.to_string()),
+            src_pod: Some(src.1.to_string()),
+            dst_namespace: Some(dst.0.to_string()),
+            dst_pod: Some(dst.1.to_string()),
+            dst_port: 5432,
+            protocol: "TCP".to_string(),
+            reason: None,
+            last_observed_at: chrono::NaiveDateTime::default(),
+            verdict: v.to_string(),
+            first_observed_at: chrono::NaiveDateTime::default(),
+            hits: 1,
+        }
+    }
+
+    #[test]
+    fn filter_rejects_unknown_decisions_and_ignores_empty_values() {
+        assert!(StreamFilter::parse(query("", "", "deny"), &["ALLOW", "DROP"]).is_err());
+        let f = StreamFilter::parse(query("", "", ""), &["ALLOW", "DROP"]).unwrap();
+        assert_eq!(f, StreamFilter::default());
+        assert!(f.traffic(&PodTraffic::default()));
+    }
+
+    #[test]
+    fn traffic_filter_matches_pod_side() {
+        let f = StreamFilter::parse(query("prod", "web-1", "DROP"), &["ALLOW", "DROP"]).unwrap();
+        let mut t = PodTraffic {
+            pod_namespace: Some("prod".to_string()),
+            pod_name: Some("web-1".to_string()),
+            decision: Some("DROP".to_string()),
+            ..Default::default()
+        };
+        assert!(f.traffic(&t));
+        t.decision = Some("ALLOW".to_string());
+        assert!(!f.traffic(&t));
+    }
+
+    #[test]
+    fn verdict_filter_needs_namespace_and_pod_on_one_side() {
+        let f = StreamFilter::parse(query("data", "db-0", "WouldDeny"), &["Allow", "WouldDeny"])
+            .unwrap();
+        assert!(f.verdict(&verdict(("prod", "web-1"), ("data", "db-0"), "WouldDeny")));
+        assert!(!f.verdict(&verdict(("prod", "web-1"), ("data", "db-0"), "Allow")));
+        // Namespace on one side, pod name on the other.
+        assert!(!f.verdict(&verdict(("data", "web-1"), ("prod", "db-0"), "WouldDeny")));
+    }
+
+    #[test]
+    fn frame_carries_seq_id_and_bare_row() {
+        let t = TrafficEvent {
+            seq: 42,
+            row: PodTraffic {
+                uuid: "u-1".to_string(),
+                ..Default::default()
+            },
+        };
+        let id = t.event_id();
+        assert_eq!(TrafficResume::parse(&id).unwrap(), TrafficResume::After(42));
+        let body = String::from_utf8(frame(&t, &id).to_vec()).unwrap();
+        let data = serde_json::to_string(&t.row).unwrap();
+        assert_eq!(body, format!("id: 42\nevent: traffic\ndata: {data}\n\n"));
+    }
+
+    #[test]
+    fn resume_accepts_old_cursor_ids() {
+        let c = TrafficCursor {
+            time_stamp: chrono::NaiveDateTime::default(),
+            uuid: "u-1".to_string(),
+        };
+        assert_eq!(
+            TrafficResume::parse(&c.encode()).unwrap(),
+            TrafficResume::Cursor(c)
+        );
+        assert!(TrafficResume::parse("not-an-id").is_err());
+    }
+}
@@ -1,7 +1,18 @@
-use crate::schema::{audit_verdicts, pod_details, pod_syscalls, pod_traffic, svc_details};
+use crate::schema::{
+    audit_verdicts, pod_details, pod_syscall_observations, pod_syscalls, pod_traffic, svc_details,
+};
 use chrono::NaiveDateTime;
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable, Selectable};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// Controllers type `pod_namespace` as optional on the wire. Decode a
+/// `null` (or a missing field) as '' — the same value the
+/// namespaced-key migration gave pre-namespace rows.
+fn null_as_empty<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(d)?.unwrap_or_default())
+}
 
 #[derive(
     Default,
@@ -14,6 +25,7 @@ use serde::{Deserialize, Serialize};
     Serialize,
     Deserialize,
     Selectable,
+    ToSchema,
 )]
 #[diesel(table_name = pod_traffic)]
 #[diesel(primary_key(uuid))]
@@ -28,7 +40,15 @@ pub struct PodTraffic {
     pub traffic_in_out_ip: Option<String>,
     pub traffic_in_out_port: Option<String>,
     pub decision: Option<String>,
+    /// When the flow was first seen. Repeats don't move it.
     pub time_stamp: NaiveDateTime,
+    /// When the flow was last seen. Absent on the controller wire;
+    /// ingest stamps it from `time_stamp` and repeats bump it.
+    #[serde(default)]
+    pub last_seen: NaiveDateTime,
+    /// Emits folded into this row, first one included.
+    #[serde(default)]
+    pub seen_count: i64,
 }
 
 #[derive(
@@ -41,13 +61,16 @@ pub struct PodTraffic {
     Serialize,
     Deserialize,
     Selectable,
+    ToSchema,
 )]
 #[diesel(table_name = pod_details)]
-#[diesel(primary_key(pod_name))]
+#[diesel(primary_key(pod_namespace, pod_name))]
 pub struct PodDetail {
     pub pod_name: String,
     pub pod_ip: String,
-    pub pod_namespace: Option<String>,
+    /// Part of the key, so never NULL; '' means the namespace is unknown.
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub pod_namespace: String,
     pub pod_obj: Option<serde_json::Value>,
     pub time_stamp: NaiveDateTime,
     pub node_name: String,
@@ -66,6 +89,7 @@ pub struct PodDetail {
     Serialize,
     Deserialize,
     Selectable,
+    ToSchema,
 )]
 #[diesel(table_name = svc_details)]
 #[diesel(primary_key(svc_ip))]
@@ -87,9 +111,10 @@ pub struct SvcDetail {
     Serialize,
     Deserialize,
     Selectable,
+    ToSchema,
 )]
 #[diesel(table_name = pod_syscalls)]
-#[diesel(primary_key(pod_name))]
+#[diesel(primary_key(pod_name, pod_namespace))]
 pub struct PodSyscalls {
     pub pod_name: String,
     pub pod_namespace: String,
@@ -98,17 +123,38 @@ pub struct PodSyscalls {
     pub time_stamp: NaiveDateTime,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 
 pub struct PodInputSyscalls {
     pub pod_name: String,
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub pod_namespace: String,
     pub syscalls: Vec<String>,
     pub arch: String,
     pub time_stamp: NaiveDateTime,
+    /// How many times each syscall was seen since the controller's last
+    /// successful report. Empty from controllers that predate it.
+    #[serde(default)]
+    pub counts: BTreeMap<String, i64>,
+}
+
+/// One syscall of one pod on one architecture, with when it was first
+/// and last reported and how often.
+#[derive(
+    Debug, Clone, PartialEq, Insertable, Queryable, Selectable, Serialize, Deserialize, ToSchema,
+)]
+#[diesel(table_name = pod_syscall_observations)]
+pub struct PodSyscallObservation {
+    pub pod_namespace: String,
+    pub pod_name: String,
+    pub arch: String,
+    pub syscall: String,
+    pub first_seen: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    pub count: i64,
 }
 
-#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
 #[diesel(table_name = audit_verdicts)]
 pub struct AuditVerdict {
     pub id: i64,
@@ -123,6 +169,11 @@ pub struct AuditVerdict {
     pub dst_port: i32,
     pub protocol: String,
     pub reason: Option<String>,
-    pub observed_at: NaiveDateTime,
+    /// Most recent evaluation that produced this verdict.
+    pub last_observed_at: NaiveDateTime,
     pub verdict: String, // "Allow" | "WouldDeny"
+    /// First evaluation that produced this verdict.
+    pub first_observed_at: NaiveDateTime,
+    /// Flows rolled up into this row, each counted once.
+    pub hits: i64,
 }
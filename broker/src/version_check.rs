@@ -38,6 +38,7 @@ use std::time::Duration;
 use tracing::{debug, info, warn};
 
 use actix_web::{get, web, HttpResponse, Responder};
+use utoipa::ToSchema;
 
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 type DbError = Box<dyn std::error::Error + Send + Sync>;
@@ -144,7 +145,7 @@ struct CheckResponse {
 }
 
 /// Wire shape of `GET /version`.
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct VersionInfo {
     broker: String,
     chart: String,
@@ -182,6 +183,13 @@ pub(crate) fn update_available(
         .unwrap_or(false)
 }
 
+/// The running broker version and the latest release.
+#[utoipa::path(
+    tag = "version",
+    responses(
+        (status = 200, body = VersionInfo),
+    )
+)]
 #[get("/version")]
 pub async fn get_version(state: web::Data<VersionCheckState>) -> impl Responder {
     let outcome = state.outcome.read().map(|o| o.clone()).unwrap_or_default();
@@ -0,0 +1,558 @@
+//! Workload-level reads: one answer per workload instead of per pod.
+//!
+//! A workload is `(namespace, pod_identity)` as the controller derives
+//! it in `extract_pod_identity_and_selectors`. Its members are every
+//! pod_details row, live or dead, in that namespace with that identity,
+//! plus any pod carrying the same `workload_selector_labels` as one of
+//! them. A Deployment's selector can't change, so the second rule keeps
+//! pods from before a relabel that moved `pod_identity` (say, adding
+//! `app.kubernetes.io/name` next to `app`) in the same workload.
+//!
+//! Rows are aggregated in Postgres: replicas that all talk to the same
+//! peer on the same port collapse into one row, so a 30-replica
+//! Deployment reads like a single pod that has seen everything its
+//! replicas have.
+
+use crate::api_version::ApiVersion;
+use crate::enrich::maybe_enrich;
+use crate::get::TimeRange;
+use crate::{Error, ErrorBody};
+use actix_web::{get, web, HttpResponse};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::sql_query;
+use diesel::sql_types::{Array, BigInt, Nullable, Text, Timestamp};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+type DbError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Query params for the workload reads.
+#[derive(Deserialize, IntoParams)]
+pub struct WorkloadQuery {
+    /// RFC3339 lower bound (inclusive).
+    pub since: Option<String>,
+    /// RFC3339 upper bound (exclusive).
+    pub until: Option<String>,
+    /// Traffic only: add peer fields to each row (see enrich.rs).
+    #[serde(default)]
+    pub enrich: bool,
+}
+
+/// One distinct flow of a workload: the member-side port and the peer,
+/// merged across every member pod.
+#[derive(Debug, Clone, PartialEq, QueryableByName, Serialize, Deserialize, ToSchema)]
+pub struct WorkloadTraffic {
+    #[diesel(sql_type = Nullable<Text>)]
+    pub traffic_type: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub ip_protocol: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub pod_port: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub traffic_in_out_ip: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub traffic_in_out_port: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub decision: Option<String>,
+    /// Earliest `time_stamp` of the flow on any member.
+    #[diesel(sql_type = Timestamp)]
+    pub first_seen: NaiveDateTime,
+    /// Latest `last_seen` of the flow on any member.
+    #[diesel(sql_type = Timestamp)]
+    pub last_seen: NaiveDateTime,
+    /// Sum of the members' `seen_count`.
+    #[diesel(sql_type = BigInt)]
+    pub seen_count: i64,
+    /// How many member pods recorded the flow.
+    #[diesel(sql_type = BigInt)]
+    pub pod_count: i64,
+}
+
+/// One syscall of a workload on one architecture, merged across every
+/// member pod.
+#[derive(Debug, Clone, PartialEq, QueryableByName, Serialize, Deserialize, ToSchema)]
+pub struct WorkloadSyscall {
+    #[diesel(sql_type = Text)]
+    pub arch: String,
+    #[diesel(sql_type = Text)]
+    pub syscall: String,
+    #[diesel(sql_type = Timestamp)]
+    pub first_seen: NaiveDateTime,
+    #[diesel(sql_type = Timestamp)]
+    pub last_seen: NaiveDateTime,
+    #[diesel(sql_type = BigInt)]
+    pub count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub pod_count: i64,
+}
+
+#[derive(QueryableByName)]
+struct MemberName {
+    #[diesel(sql_type = Text)]
+    pod_name: String,
+}
+
+/// Traffic of every pod of a workload.
+#[utoipa::path(
+    tag = "workloads",
+    params(WorkloadQuery),
+    responses(
+        (status = 200, body = Vec<WorkloadTraffic>),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+    )
+)]
+#[get("/workload/{namespace}/{identity}/traffic")]
+pub async fn get_workload_traffic(
+    pool: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    query: web::Query<WorkloadQuery>,
+    version: ApiVersion,
+) -> Result<HttpResponse, Error> {
+    info!("select workload traffic");
+    let (namespace, identity) = path.into_inner();
+    let range = TimeRange::parse(query.since.as_deref(), query.until.as_deref())?;
+    let enrich = query.enrich;
+    let rows = web::block(move || {
+        let mut conn = pool.get()?;
+        let members = workload_members(&mut conn, &namespace, &identity)?;
+        if members.is_empty() {
+            return Ok(None);
+        }
+        let rows = workload_traffic(&mut conn, &namespace, &members, range)?;
+        maybe_enrich(&mut conn, rows, enrich).map(Some)
+    })
+    .await??;
+
+    match rows {
+        Some(rows) => Ok(HttpResponse::Ok().json(rows)),
+        None => version.no_rows::<WorkloadTraffic>(),
+    }
+}
+
+/// Syscalls of every pod of a workload.
+#[utoipa::path(
+    tag = "workloads",
+    params(WorkloadQuery),
+    responses(
+        (status = 200, body = Vec<WorkloadSyscall>),
+        (status = 400, description = "Invalid query parameter", body = ErrorBody),
+    )
+)]
+#[get("/workload/{namespace}/{identity}/syscalls")]
+pub async fn get_workload_syscalls(
+    pool: web::Data<DbPool>,
+    path: web::Path<(String, String)>,
+    query: web::Query<WorkloadQuery>,
+    version: ApiVersion,
+) -> Result<HttpResponse, Error> {
+    info!("select workload syscalls");
+    let (namespace, identity) = path.into_inner();
+    let range = TimeRange::parse(query.since.as_deref(), query.until.as_deref())?;
+    let rows = web::block(move || {
+        let mut conn = pool.get()?;
+        let members = workload_members(&mut conn, &namespace, &identity)?;
+        if members.is_empty() {
+            return Ok(None);
+        }
+        workload_syscalls(&mut conn, &namespace, &members, range).map(Some)
+    })
+    .await??;
+
+    match rows {
+        Some(rows) => Ok(HttpResponse::Ok().json(rows)),
+        None => version.no_rows::<WorkloadSyscall>(),
+    }
+}
+
+/// Names of the workload's current and past pods, sorted. See the
+/// module doc for the membership rule. The column is JSON, not JSONB,
+/// so the selector comparison casts; an empty selector would match
+/// every selector-less pod and is ignored.
+pub fn workload_members(
+    conn: &mut PgConnection,
+    namespace: &str,
+    identity: &str,
+) -> Result<Vec<String>, DbError> {
+    let rows = sql_query(
+        "SELECT d.pod_name FROM pod_details d \
+         WHERE d.pod_namespace = $1 \
+           AND (d.pod_identity = $2 \
+                OR d.workload_selector_labels::jsonb IN ( \
+                     SELECT s.workload_selector_labels::jsonb FROM pod_details s \
+                     WHERE s.pod_namespace = $1 AND s.pod_identity = $2 \
+                       AND s.workload_selector_labels IS NOT NULL \
+                       AND s.workload_selector_labels::jsonb <> '{}'::jsonb)) \
+         ORDER BY d.pod_name",
+    )
+    .bind::<Text, _>(namespace)
+    .bind::<Text, _>(identity)
+    .load::<MemberName>(conn)?;
+    Ok(rows.into_iter().map(|r| r.pod_name).collect())
+}
+
+/// Distinct flows of the given member pods. `since`/`until` keep the
+/// flows active in the window (`last_seen >= since`, `time_stamp <
+/// until`), as /graph does: a flow that started before `since` and is
+/// still live must reach the generated policies, or they deny it.
+/// Flows stored without a namespace (NULL or '') are included, as on the
+/// namespaced per-pod traffic read.
+pub fn workload_traffic(
+    conn: &mut PgConnection,
+    namespace: &str,
+    members: &[String],
+    range: TimeRange,
+) -> Result<Vec<WorkloadTraffic>, DbError> {
+    Ok(sql_query(
+        "SELECT traffic_type, ip_protocol, pod_port, traffic_in_out_ip, \
+                traffic_in_out_port, decision, \
+                MIN(time_stamp) AS first_seen, MAX(last_seen) AS last_seen, \
+                SUM(seen_count)::BIGINT AS seen_count, \
+                COUNT(DISTINCT pod_name) AS pod_count \
+         FROM pod_traffic \
+         WHERE COALESCE(pod_namespace, '') IN ($1, '') AND pod_name = ANY($2) \
+           AND ($3::timestamp IS NULL OR last_seen >= $3) \
+           AND ($4::timestamp IS NULL OR time_stamp < $4) \
+         GROUP BY traffic_type, ip_protocol, pod_port, traffic_in_out_ip, \
+                  traffic_in_out_port, decision \
+         ORDER BY traffic_type, traffic_in_out_ip, traffic_in_out_port, \
+                  ip_protocol, pod_port, decision",
+    )
+    .bind::<Text, _>(namespace)
+    .bind::<Array<Text>, _>(members)
+    .bind::<Nullable<Timestamp>, _>(range.since)
+    .bind::<Nullable<Timestamp>, _>(range.until)
+    .load::<WorkloadTraffic>(conn)?)
+}
+
+/// Distinct syscalls of the given member pods. `since`/`until` keep the
+/// observations active in the window (`last_seen >= since`,
+/// `first_seen < until`): a seccomp profile built from it must allow
+/// the syscalls the workload has made all along, not only the ones it
+/// started making inside the window. Observations stored with namespace
+/// '' are included, as on the per-pod observations read.
+pub fn workload_syscalls(
+    conn: &mut PgConnection,
+    namespace: &str,
+    members: &[String],
+    range: TimeRange,
+) -> Result<Vec<WorkloadSyscall>, DbError> {
+    Ok(sql_query(
+        "SELECT arch, syscall, MIN(first_seen) AS first_seen, \
+                MAX(last_seen) AS last_seen, SUM(count)::BIGINT AS count, \
+                COUNT(DISTINCT pod_name) AS pod_count \
+         FROM pod_syscall_observations \
+         WHERE pod_namespace IN ($1, '') AND pod_name = ANY($2) \
+           AND ($3::timestamp IS NULL OR last_seen >= $3) \
+           AND ($4::timestamp IS NULL OR first_seen < $4) \
+         GROUP BY arch, syscall \
+         ORDER BY arch, syscall",
+    )
+    .bind::<Text, _>(namespace)
+    .bind::<Array<Text>, _>(members)
+    .bind::<Nullable<Timestamp>, _>(range.since)
+    .bind::<Nullable<Timestamp>, _>(range.until)
+    .load::<WorkloadSyscall>(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Dashboards and the advisor read these rows by field name; pin the
+    // shape so a rename shows up here rather than as empty panels.
+    #[test]
+    fn workload_traffic_wire_shape() {
+        let row = WorkloadTraffic {
+            traffic_type: Some("EGRESS".to_string()),
+            ip_protocol: Some("TCP".to_string()),
+            pod_port: Some("0".to_string()),
+            traffic_in_out_ip: Some("10.0.0.2".to_string()),
+            traffic_in_out_port: Some("443".to_string()),
+            decision: Some("ALLOW".to_string()),
+            first_seen: NaiveDateTime::default(),
+            last_seen: NaiveDateTime::default(),
+            seen_count: 5,
+            pod_count: 2,
+        };
+        let v = serde_json::to_value(&row).unwrap();
+        for field in [
+            "traffic_type",
+            "ip_protocol",
+            "pod_port",
+            "traffic_in_out_ip",
+            "traffic_in_out_port",
+            "decision",
+            "first_seen",
+            "last_seen",
+            "seen_count",
+            "pod_count",
+        ] {
+            assert!(v.get(field).is_some(), "missing {field}: {v}");
+        }
+    }
+
+    fn ts(s: &str) -> NaiveDateTime {
+        chrono::DateTime::parse_from_rfc3339(s).unwrap().naive_utc()
+    }
+
+    fn add_pod(
+        conn: &mut PgConnection,
+        namespace: &str,
+        name: &str,
+        identity: &str,
+        selector: Option<serde_json::Value>,
+    ) -> Result<(), DbError> {
+        use crate::schema::pod_details::dsl as d;
+        diesel::insert_into(d::pod_details)
+            .values((
+                d::pod_namespace.eq(namespace),
+                d::pod_name.eq(name),
+                d::pod_ip.eq("10.0.0.1"),
+                d::node_name.eq("node-a"),
+                d::time_stamp.eq(ts("2026-10-01T00:00:00Z")),
+                d::is_dead.eq(false),
+                d::pod_identity.eq(identity),
+                d::workload_selector_labels.eq(selector),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires a database"]
+    fn members_follow_identity_and_selector() {
+        let mut conn = crate::test_support::db();
+        conn.test_transaction::<_, DbError, _>(|conn| {
+            let ns = "workload-test";
+            let api = Some(serde_json::json!({"app": "api"}));
+            add_pod(conn, ns, "api-1", "api", api.clone())?;
+            // Relabelled since: same selector, different identity.
+            add_pod(conn, ns, "api-old", "api-v1", api.clone())?;
+            add_pod(
+                conn,
+                ns,
+                "web-1",
+                "web",
+                Some(serde_json::json!({"app": "web"})),
+            )?;
+            add_pod(conn, "workload-test-other", "api-1", "api", api)?;
+            // Empty selectors never link pods.
+            add_pod(conn, ns, "job-1", "job", Some(serde_json::json!({})))?;
+            add_pod(conn, ns, "cron-1", "cron", Some(serde_json::json!({})))?;
+
+            assert_eq!(workload_members(conn, ns, "api")?, ["api-1", "api-old"]);
+            assert_eq!(workload_members(conn, ns, "web")?, ["web-1"]);
+            assert_eq!(workload_members(conn, ns, "job")?, ["job-1"]);
+            assert!(workload_members(conn, ns, "missing")?.is_empty());
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[ignore = "requires a database"]
+    fn traffic_merges_member_flows() {
+        use crate::schema::pod_traffic::dsl as t;
+
+        let mut conn = crate::test_support::db();
+        conn.test_transaction::<_, DbError, _>(|conn| {
+            let ns = "workload-test";
+            let rows = [
+                (
+                    "api-1",
+                    "10.0.0.9",
+                    3,
+                    "2026-10-10T04:00:00Z",
+                    "2026-10-11T00:00:00Z",
+                ),
+                (
+                    "api-2",
+                    "10.0.0.9",
+                    4,
+                    "2026-10-10T02:00:00Z",
+                    "2026-10-10T20:00:00Z",
+                ),
+                // Not a member.
+                (
+                    "web-1",
+                    "10.0.0.9",
+                    50,
+                    "2026-10-10T00:00:00Z",
+                    "2026-10-12T00:00:00Z",
+                ),
+                (
+                    "api-2",
+                    "10.0.0.8",
+                    1,
+                    "2026-10-10T05:00:00Z",
+                    "2026-10-10T05:00:00Z",
+                ),
+                // Last seen before `since`.
+                (
+                    "api-1",
+                    "10.0.0.7",
+                    9,
+                    "2026-10-09T00:00:00Z",
+                    "2026-10-09T23:00:00Z",
+                ),
+            ];
+            for (i, (pod, peer, seen, first, last)) in rows.into_iter().enumerate() {
+                diesel::insert_into(t::pod_traffic)
+                    .values((
+                        t::uuid.eq(format!("workload-test-{i}")),
+                        t::pod_name.eq(pod),
+                        t::pod_namespace.eq(ns),
+                        t::pod_ip.eq(format!("10.1.0.{i}")),
+                        t::pod_port.eq("0"),
+                        t::ip_protocol.eq("TCP"),
+                        t::traffic_type.eq("EGRESS"),
+                        t::traffic_in_out_ip.eq(peer),
+                        t::traffic_in_out_port.eq("443"),
+                        t::decision.eq("ALLOW"),
+                        t::time_stamp.eq(ts(first)),
+                        t::last_seen.eq(ts(last)),
+                        t::seen_count.eq(seen),
+                    ))
+                    .execute(conn)?;
+            }
+            // Recorded before namespaces were: still api-2's.
+            diesel::update(t::pod_traffic.find("workload-test-1"))
+                .set(t::pod_namespace.eq(None::<String>))
+                .execute(conn)?;
+            let members = ["api-1".to_string(), "api-2".to_string()];
+            let range = TimeRange {
+                since: Some(ts("2026-10-10T00:00:00Z")),
+                until: None,
+            };
+            let flows = workload_traffic(conn, ns, &members, range)?;
+            let got: Vec<_> = flows
+                .iter()
+                .map(|f| {
+                    (
+                        f.traffic_in_out_ip.as_deref().unwrap(),
+                        f.pod_count,
+                        f.seen_count,
+                        f.first_seen,
+                        f.last_seen,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                got,
+                [
+                    (
+                        "10.0.0.8",
+                        1,
+                        1,
+                        ts("2026-10-10T05:00:00Z"),
+                        ts("2026-10-10T05:00:00Z")
+                    ),
+                    (
+                        "10.0.0.9",
+                        2,
+                        7,
+                        ts("2026-10-10T02:00:00Z"),
+                        ts("2026-10-11T00:00:00Z")
+                    ),
+                ]
+            );
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[ignore = "requires a database"]
+    fn syscalls_merge_members_and_namespace_unknown_rows() {
+        use crate::schema::pod_syscall_observations::dsl as o;
+
+        let mut conn = crate::test_support::db();
+        conn.test_transaction::<_, DbError, _>(|conn| {
+            let ns = "workload-test";
+            let rows = [
+                (
+                    ns,
+                    "api-1",
+                    "read",
+                    10,
+                    "2026-10-10T04:00:00Z",
+                    "2026-10-11T00:00:00Z",
+                ),
+                (
+                    "",
+                    "api-2",
+                    "read",
+                    5,
+                    "2026-10-10T02:00:00Z",
+                    "2026-10-10T20:00:00Z",
+                ),
+                (
+                    ns,
+                    "api-2",
+                    "openat",
+                    2,
+                    "2026-10-10T05:00:00Z",
+                    "2026-10-10T06:00:00Z",
+                ),
+                // Same name, other namespace.
+                (
+                    "workload-test-other",
+                    "api-1",
+                    "read",
+                    99,
+                    "2026-10-01T00:00:00Z",
+                    "2026-10-12T00:00:00Z",
+                ),
+            ];
+            for (pod_ns, pod, syscall, count, first, last) in rows {
+                diesel::insert_into(o::pod_syscall_observations)
+                    .values((
+                        o::pod_namespace.eq(pod_ns),
+                        o::pod_name.eq(pod),
+                        o::arch.eq("x86_64"),
+                        o::syscall.eq(syscall),
+                        o::first_seen.eq(ts(first)),
+                        o::last_seen.eq(ts(last)),
+                        o::count.eq(count),
+                    ))
+                    .execute(conn)?;
+            }
+            let members = ["api-1".to_string(), "api-2".to_string()];
+            let got = workload_syscalls(conn, ns, &members, TimeRange::default())?;
+            let got: Vec<_> = got
+                .iter()
+                .map(|s| {
+                    (
+                        s.syscall.as_str(),
+                        s.pod_count,
+                        s.count,
+                        s.first_seen,
+                        s.last_seen,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                got,
+                [
+                    (
+                        "openat",
+                        1,
+                        2,
+                        ts("2026-10-10T05:00:00Z"),
+                        ts("2026-10-10T06:00:00Z")
+                    ),
+                    (
+                        "read",
+                        2,
+                        15,
+                        ts("2026-10-10T02:00:00Z"),
+                        ts("2026-10-11T00:00:00Z")
+                    ),
+                ]
+            );
+            Ok(())
+        });
+    }
+}
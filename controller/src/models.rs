@@ -97,4 +97,6 @@ pub struct SyscallData {
     pub syscalls: Vec<String>,
     pub arch: String,
     pub time_stamp: NaiveDateTime,
+    /// Calls per syscall since the last successful report.
+    pub counts: BTreeMap<String, u64>,
 }
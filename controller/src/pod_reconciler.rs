@@ -155,8 +155,11 @@ async fn reconcile_pods(
             db_pod.pod_ip,
             node_name
         );
+        // pod_namespace picks the row when the same pod name exists in
+        // several namespaces (pod_details is keyed on the pair).
         let mark_dead_req = serde_json::json!({
             "pod_name": db_pod.pod_name,
+            "pod_namespace": db_pod.pod_namespace,
             "pod_ip": db_pod.pod_ip,
         });
         if let Err(e) = api_post_call(mark_dead_req, "pod/mark_dead").await {
@@ -3,7 +3,7 @@ use dashmap::DashMap;
 use libseccomp::{ScmpArch, ScmpSyscall};
 use moka::future::Cache;
 use serde_json::json;
-use std::collections::HashSet;
+use std::collections::{btree_map, BTreeMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::{debug, error};
@@ -17,11 +17,21 @@ pub mod sycallprobe {
     ));
 }
 
-type SyscallCache = Cache<String, Arc<Mutex<HashSet<String>>>>;
+/// Syscalls of one pod. `seen` is every syscall since the pod was first
+/// observed, the set /pod/syscalls has always carried; `pending` counts
+/// calls since the last successful report.
+#[derive(Default)]
+struct PodSyscallState {
+    seen: HashSet<String>,
+    pending: BTreeMap<String, u64>,
+}
+
+/// Keyed on (namespace, pod name): pod names only are unique within a
+/// namespace.
+type SyscallCache = Cache<(String, String), Arc<Mutex<PodSyscallState>>>;
 
 lazy_static::lazy_static! {
     static ref SYSCALL_CACHE: SyscallCache = Cache::new(10_000);
-    static ref LAST_SENT_CACHE: SyscallCache = Cache::new(10_000);
 }
 
 #[repr(C)]
@@ -61,22 +71,20 @@ pub async fn process_syscall_event(
         .and_then(get_syscall_name)
         .unwrap_or_else(|| format!("{}", syscall_number));
 
-    let syscalls = SYSCALL_CACHE
-        .get_with(pod_name.clone(), async {
-            Arc::new(Mutex::new(HashSet::new()))
+    let pod_namespace = pod_data.status.pod_namespace.clone().unwrap_or_default();
+    let state = SYSCALL_CACHE
+        .get_with((pod_namespace, pod_name.clone()), async {
+            Arc::new(Mutex::new(PodSyscallState::default()))
         })
         .await;
 
-    let mut syscalls_lock = syscalls.lock().await;
+    let mut state_lock = state.lock().await;
 
-    if syscalls_lock.contains(&syscall_name) {
-        debug!(
-            "Skipping duplicate syscall: {} for pod: {}",
-            syscall_name, pod_name
-        );
-    } else {
-        syscalls_lock.insert(syscall_name.clone());
+    if !state_lock.seen.contains(&syscall_name) {
+        debug!("New syscall: {} for pod: {}", syscall_name, pod_name);
+        state_lock.seen.insert(syscall_name.clone());
     }
+    *state_lock.pending.entry(syscall_name).or_insert(0) += 1;
 
     Ok(())
 }
@@ -86,64 +94,50 @@ pub async fn send_syscall_cache_periodically() -> Result<(), Error> {
     let interval_duration = std::time::Duration::from_secs(10);
     for _ in 0.. {
         let mut batch = Vec::new();
-        // Track which (pod, snapshot) pairs to mark as last_sent
-        // AFTER the POST succeeds. The pre-fix code eagerly updated
-        // last_sent inside the loop, before the POST — so a
-        // transient broker failure permanently dropped the batch:
-        // next iteration the diff `syscalls_lock != last_sent_lock`
-        // was false (we'd just made them equal), no retry, broker
-        // never got those syscalls. Stable-state pods (no new
-        // syscalls between iterations) lost data.
-        let mut pending_updates: Vec<(String, HashSet<String>)> = Vec::new();
-
-        for (pod_name, syscalls) in SYSCALL_CACHE.iter() {
-            let syscalls_lock = syscalls.lock().await;
-            let last_sent = LAST_SENT_CACHE
-                .get_with(pod_name.to_string(), async {
-                    Arc::new(Mutex::new(HashSet::new()))
-                })
-                .await;
-            let last_sent_lock = last_sent.lock().await;
-
-            if *syscalls_lock != *last_sent_lock {
-                let snapshot = syscalls_lock.clone();
-                let syscall_names: Vec<String> = snapshot.iter().cloned().collect();
-                let z = json!(SyscallData {
-                    pod_name: pod_name.to_string(),
-                    pod_namespace: "".to_string(), // We will not store the namespace and rather read it from the pod_details table
-                    syscalls: syscall_names,
-                    arch: std::env::consts::ARCH.to_string(),
-                    time_stamp: Utc::now().naive_utc()
-                });
-                batch.push(z);
-                pending_updates.push((pod_name.to_string(), snapshot));
+        // The counts each pod's report carried, subtracted from its
+        // pending counts only AFTER the POST succeeds. Clearing them
+        // before the POST would drop the interval on a transient
+        // broker failure; leaving them means the next pass re-sends
+        // them folded into that interval's counts.
+        let mut sent: Vec<(Arc<Mutex<PodSyscallState>>, BTreeMap<String, u64>)> = Vec::new();
+
+        for (key, state) in SYSCALL_CACHE.iter() {
+            let state_lock = state.lock().await;
+            // A pod that made no calls this interval has nothing new to
+            // report; any new syscall shows up in `pending` first.
+            if state_lock.pending.is_empty() {
+                continue;
             }
+            let (pod_namespace, pod_name) = key.as_ref();
+            let counts = state_lock.pending.clone();
+            let z = json!(SyscallData {
+                pod_name: pod_name.to_string(),
+                pod_namespace: pod_namespace.to_string(),
+                syscalls: state_lock.seen.iter().cloned().collect(),
+                arch: std::env::consts::ARCH.to_string(),
+                time_stamp: Utc::now().naive_utc(),
+                counts: counts.clone(),
+            });
+            batch.push(z);
+            sent.push((Arc::clone(&state), counts));
         }
 
         if !batch.is_empty() {
             debug!("Sending batch of {} syscalls to API", batch.len());
             match api_post_call(json!(batch), "pod/syscalls").await {
                 Ok(()) => {
-                    // POST succeeded — persist the snapshots as
-                    // last_sent so we don't re-send them next pass.
-                    // No race: this loop is the only writer of
-                    // LAST_SENT_CACHE entries. If new syscalls
-                    // arrived between POST and update, the next
-                    // iteration's diff catches them.
-                    for (pod_name, snapshot) in pending_updates {
-                        let last_sent = LAST_SENT_CACHE
-                            .get_with(pod_name, async { Arc::new(Mutex::new(HashSet::new())) })
-                            .await;
-                        *last_sent.lock().await = snapshot;
+                    // Calls counted between the snapshot and now stay
+                    // pending for the next report.
+                    for (state, counts) in sent {
+                        subtract_sent(&mut state.lock().await.pending, counts);
                     }
                 }
                 Err(e) => {
-                    // Don't touch last_sent. Next iteration will see
-                    // the same diff and retry.
+                    // Keep the pending counts; next pass retries.
                     error!(
                         "Failed to post Syscall Event: {}; {} pod batches will retry next pass",
                         e,
-                        pending_updates.len()
+                        sent.len()
                     );
                 }
             }
@@ -154,6 +148,20 @@ pub async fn send_syscall_cache_periodically() -> Result<(), Error> {
     Ok(())
 }
 
+/// Remove reported counts from `pending`, dropping syscalls that reach
+/// zero so an idle pod stops being reported.
+fn subtract_sent(pending: &mut BTreeMap<String, u64>, sent: BTreeMap<String, u64>) {
+    for (name, n) in sent {
+        if let btree_map::Entry::Occupied(mut e) = pending.entry(name) {
+            if *e.get() <= n {
+                e.remove();
+            } else {
+                *e.get_mut() -= n;
+            }
+        }
+    }
+}
+
 fn get_syscall_name(syscall_number: i32) -> Option<String> {
     let arch = if cfg!(target_arch = "x86_64") {
         ScmpArch::X8664
@@ -168,3 +176,29 @@ fn get_syscall_name(syscall_number: i32) -> Option<String> {
     let name = syscall.get_name_by_arch(arch).ok()?;
     Some(name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(pairs: &[(&str, u64)]) -> BTreeMap<String, u64> {
+        pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn subtract_sent_keeps_calls_counted_during_post() {
+        // read was counted 5 times at snapshot and 2 more while the
+        // POST was in flight; only the 2 may be reported next pass.
+        let mut pending = counts(&[("read", 7), ("write", 1)]);
+        subtract_sent(&mut pending, counts(&[("read", 5), ("write", 1)]));
+        assert_eq!(pending, counts(&[("read", 2)]));
+    }
+
+    #[test]
+    fn subtract_sent_empties_idle_pod() {
+        // An empty map is what stops a pod being re-reported every pass.
+        let mut pending = counts(&[("openat", 3)]);
+        subtract_sent(&mut pending, counts(&[("openat", 3)]));
+        assert!(pending.is_empty());
+    }
+}
@@ -3,6 +3,8 @@
 //	GET  /healthz     liveness probe
 //	GET  /readyz      readiness probe (informer caches synced)
 //	POST /evaluate    body: matcher.Flow JSON; returns []matcher.Result
+//	POST /evaluate/batch  body: {"flows": [matcher.Flow]}; returns one
+//	                  []matcher.Result per flow, in request order
 //
 // The server is intentionally thin — informer caches and policy lookup
 // live on the Store; the server just brokers HTTP <-> matcher.
@@ -56,6 +58,7 @@ func (s *Server) Start(ctx context.Context) error {
 	mux.HandleFunc("/healthz", s.handleHealth)
 	mux.HandleFunc("/readyz", s.handleReady)
 	mux.HandleFunc("/evaluate", s.handleEvaluate)
+	mux.HandleFunc("/evaluate/batch", s.handleEvaluateBatch)
 
 	s.srv = &http.Server{
 		Addr:              s.addr,
@@ -122,6 +125,17 @@ func (s *Server) handleEvaluate(w http.ResponseWriter, r *http.Request) {
 		http.Error(w, "invalid flow JSON: "+err.Error(), http.StatusBadRequest)
 		return
 	}
+	results := s.evaluate(flow)
+
+	w.Header().Set("Content-Type", "application/json")
+	w.WriteHeader(http.StatusOK)
+	_ = json.NewEncoder(w).Encode(EvaluateResponse{Results: results})
+}
+
+// evaluate runs one flow against every policy in scope, records the
+// non-NotApplicable verdicts on the status aggregator, and returns them
+// all. Shared by /evaluate and /evaluate/batch.
+func (s *Server) evaluate(flow matcher.Flow) []matcher.Result {
 	if flow.Timestamp.IsZero() {
 		flow.Timestamp = time.Now().UTC()
 	}
@@ -180,8 +194,51 @@ func (s *Server) handleEvaluate(w http.ResponseWriter, r *http.Request) {
 			}).Info("audit policy would deny flow")
 		}
 	}
+	return results
+}
+
+// MaxBatchFlows caps the flows accepted by one /evaluate/batch call. The
+// broker sends far fewer (AUDIT_BATCH_MAX_FLOWS, default 64).
+const MaxBatchFlows = 1000
+
+// EvaluateBatchRequest is the body of POST /evaluate/batch.
+type EvaluateBatchRequest struct {
+	Flows []matcher.Flow `json:"flows"`
+}
+
+// EvaluateBatchResponse is the wire format returned by POST
+// /evaluate/batch. Results[i] holds the verdicts for Flows[i].
+type EvaluateBatchResponse struct {
+	Results [][]matcher.Result `json:"results"`
+}
+
+func (s *Server) handleEvaluateBatch(w http.ResponseWriter, r *http.Request) {
+	if r.Method != http.MethodPost {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	defer func() { _ = r.Body.Close() }()
+	// Same per-flow headroom as /evaluate, times the batch cap.
+	r.Body = http.MaxBytesReader(w, r.Body, MaxBatchFlows*4*1024)
+
+	var req EvaluateBatchRequest
+	if err := json.NewDecoder(r.Body).Decode(&req); err != nil {
+		http.Error(w, "invalid batch JSON: "+err.Error(), http.StatusBadRequest)
+		return
+	}
+	if len(req.Flows) > MaxBatchFlows {
+		http.Error(w, "too many flows in batch", http.StatusRequestEntityTooLarge)
+		return
+	}
+
+	// Non-nil outer slice for the same reason as /evaluate: the broker
+	// rejects `null` where it expects an array.
+	results := make([][]matcher.Result, 0, len(req.Flows))
+	for _, flow := range req.Flows {
+		results = append(results, s.evaluate(flow))
+	}
 
 	w.Header().Set("Content-Type", "application/json")
 	w.WriteHeader(http.StatusOK)
-	_ = json.NewEncoder(w).Encode(EvaluateResponse{Results: results})
+	_ = json.NewEncoder(w).Encode(EvaluateBatchResponse{Results: results})
 }
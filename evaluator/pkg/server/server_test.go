@@ -266,3 +266,63 @@ func TestHandleEvaluate_ClusterPolicyNamespaceGate(t *testing.T) {
 		t.Errorf("expected cluster-scoped WouldDeny verdict in results: %#v", resp.Results)
 	}
 }
+
+func TestHandleEvaluateBatch_ResultsAlignWithFlows(t *testing.T) {
+	s, f := setup(t)
+	f.pods["prod/web-1"] = &corev1.Pod{
+		ObjectMeta: metav1.ObjectMeta{
+			Namespace: "prod", Name: "web-1",
+			Labels: map[string]string{"app": "web"},
+		},
+	}
+	f.policies["prod"] = []*v1alpha1.AuditNetworkPolicy{{
+		ObjectMeta: metav1.ObjectMeta{
+			Namespace: "prod", Name: "web-deny", UID: "uid-1",
+		},
+		Spec: networkingv1.NetworkPolicySpec{
+			PodSelector: metav1.LabelSelector{MatchLabels: map[string]string{"app": "web"}},
+			PolicyTypes: []networkingv1.PolicyType{networkingv1.PolicyTypeIngress},
+		},
+	}}
+
+	// The second flow lands in a namespace with no policies, so its
+	// slot must be an empty array, not null and not dropped.
+	body, _ := json.Marshal(EvaluateBatchRequest{Flows: []matcher.Flow{
+		{DstPodNamespace: "prod", DstPodName: "web-1", DstPort: 8080, Protocol: matcher.ProtocolTCP},
+		{DstPodNamespace: "dev", DstPodName: "api-1", DstPort: 80, Protocol: matcher.ProtocolTCP},
+	}})
+	rec := httptest.NewRecorder()
+	req := httptest.NewRequest(http.MethodPost, "/evaluate/batch", bytes.NewReader(body))
+	s.handleEvaluateBatch(rec, req)
+
+	if rec.Code != http.StatusOK {
+		t.Fatalf("status: want 200, got %d body=%s", rec.Code, rec.Body.String())
+	}
+	if !strings.Contains(rec.Body.String(), `[]`) || strings.Contains(rec.Body.String(), `null`) {
+		t.Fatalf("empty result set must encode as []: %s", rec.Body.String())
+	}
+	var resp EvaluateBatchResponse
+	if err := json.NewDecoder(rec.Body).Decode(&resp); err != nil {
+		t.Fatalf("decode response: %v", err)
+	}
+	if len(resp.Results) != 2 {
+		t.Fatalf("want one result set per flow, got %d", len(resp.Results))
+	}
+	if len(resp.Results[0]) != 1 || resp.Results[0][0].Verdict != matcher.VerdictWouldDeny {
+		t.Errorf("flow 0: want one WouldDeny, got %#v", resp.Results[0])
+	}
+	if len(resp.Results[1]) != 0 {
+		t.Errorf("flow 1: want no results, got %#v", resp.Results[1])
+	}
+}
+
+func TestHandleEvaluateBatch_RejectsOversizedBatch(t *testing.T) {
+	s, _ := setup(t)
+	body, _ := json.Marshal(EvaluateBatchRequest{Flows: make([]matcher.Flow, MaxBatchFlows+1)})
+	rec := httptest.NewRecorder()
+	req := httptest.NewRequest(http.MethodPost, "/evaluate/batch", bytes.NewReader(body))
+	s.handleEvaluateBatch(rec, req)
+	if rec.Code != http.StatusRequestEntityTooLarge {
+		t.Errorf("oversized batch: want 413, got %d", rec.Code)
+	}
+}
@@ -272,7 +272,8 @@ const AuditVerdictsPanel: React.FC<Props> = ({ isOpen, onClose }) => {
                 <thead className="sticky top-0 bg-hubble-dark z-10 border-b border-hubble-border">
                   <tr className="text-left text-xs font-medium text-tertiary uppercase tracking-wide">
                     <th className="px-4 py-2">Verdict</th>
-                    <th className="px-4 py-2">When</th>
+                    <th className="px-4 py-2">Last seen</th>
+                    <th className="px-4 py-2">Hits</th>
                     <th className="px-4 py-2">Policy</th>
                     <th className="px-4 py-2">Dir</th>
                     <th className="px-4 py-2">Source</th>
@@ -292,7 +293,13 @@ const AuditVerdictsPanel: React.FC<Props> = ({ isOpen, onClose }) => {
                           <VerdictBadge verdict={v.verdict as AuditVerdictKind} />
                         </td>
                         <td className="px-4 py-2 text-tertiary whitespace-nowrap">
-                          {formatTimestamp(v.observed_at)}
+                          {formatTimestamp(v.last_observed_at)}
+                        </td>
+                        <td
+                          className="px-4 py-2 text-secondary"
+                          title={`First seen ${formatTimestamp(v.first_observed_at)}`}
+                        >
+                          {v.hits}
                         </td>
                         <td className="px-4 py-2 font-mono text-xs">
                           {policyKey}
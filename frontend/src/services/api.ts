@@ -36,6 +36,7 @@ class BrokerAPIClient {
     namespace?: string;
     verdict?: 'Allow' | 'WouldDeny';
     direction?: 'Ingress' | 'Egress';
+    sort?: 'recent' | 'hits';
     limit?: number;
   } = {}): Promise<AuditVerdict[]> {
     try {
@@ -44,6 +45,7 @@ class BrokerAPIClient {
       if (opts.namespace) params.namespace = opts.namespace;
       if (opts.verdict) params.verdict = opts.verdict;
       if (opts.direction) params.direction = opts.direction;
+      if (opts.sort) params.sort = opts.sort;
       if (opts.limit) params.limit = opts.limit;
       const response = await this.client.get('/audit/verdicts', { params });
       return response.data || [];
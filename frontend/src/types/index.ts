@@ -88,6 +88,9 @@ export interface AuditVerdict {
   dst_port: number;
   protocol: string;
   reason: string | null;
-  observed_at: string; // ISO 8601
+  // One row per distinct verdict; repeats bump hits and last_observed_at.
+  first_observed_at: string; // ISO 8601
+  last_observed_at: string; // ISO 8601
+  hits: number;
   verdict: AuditVerdictKind | string;
 }